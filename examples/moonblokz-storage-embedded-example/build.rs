@@ -1,19 +1,24 @@
 /* Embedded build helper for RP2040 example linker configuration. */
 
+use moonblokz_storage::build_script::emit_linker_script;
+use moonblokz_storage::control_plane::ControlPlaneLayout;
 use std::env;
 use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
 
+fn copy_to_out_dir(out: &PathBuf, name: &str, bytes: &[u8]) {
+    let mut file = File::create(out.join(name)).unwrap_or_else(|_| {
+        panic!("failed to create {} in OUT_DIR", name)
+    });
+    file.write_all(bytes)
+        .unwrap_or_else(|_| panic!("failed to write {}", name));
+}
+
 fn main() {
     let out = PathBuf::from(env::var_os("OUT_DIR").unwrap_or_default());
-    let mut memory_x = File::create(out.join("memory.x")).unwrap_or_else(|_| {
-        panic!("failed to create memory.x in OUT_DIR")
-    });
-    memory_x
-        .write_all(include_bytes!("memory.x"))
-        .unwrap_or_else(|_| panic!("failed to write memory.x"));
-    println!("cargo:rustc-link-search={}", out.display());
+    copy_to_out_dir(&out, "memory.x", include_bytes!("memory.x"));
+    emit_linker_script(&ControlPlaneLayout::DEFAULT)
+        .unwrap_or_else(|_| panic!("failed to write moonblokz-storage.x"));
     println!("cargo:rerun-if-changed=memory.x");
 }
-
@@ -10,3 +10,5 @@ __flash_size = 2097152;
 __storage_flash_size = 524288;
 __storage_flash_offset = ORIGIN(STORAGE) - ORIGIN(BOOT2);
 
+
+INCLUDE moonblokz-storage.x
@@ -20,7 +20,6 @@ use moonblokz_storage::{INIT_PARAMS_SIZE, Rp2040Backend, StorageError, StorageTr
 use panic_halt as _;
 
 const RP2040_FLASH_SIZE: usize = 2 * 1024 * 1024;
-const DATA_STORAGE_START_ADDRESS: usize = 1536 * 1024;
 const EXAMPLE_STORAGE_INDEX: u32 = 0;
 
 fn make_example_block() -> Result<Block, StorageError> {
@@ -65,6 +64,14 @@ fn run_flow(storage: &mut impl StorageTrait) -> Result<bool, StorageError> {
         && expected.payload_type == actual.payload_type)
 }
 
+fn finish_pending_relocation(storage: &mut Rp2040Backend<RP2040_FLASH_SIZE>) -> Result<(), StorageError> {
+    // Completes a region move interrupted by a reset before anything else touches flash.
+    if let Some(target) = storage.pending_relocation()? {
+        storage.relocate(target.data_storage_start_address, target.flash_size)?;
+    }
+    Ok(())
+}
+
 fn delay_ms(ms: u32) {
     // RP2040 default system clock is typically 125 MHz.
     // Approximate CPU cycles per millisecond for simple LED timing.
@@ -77,8 +84,9 @@ fn main() -> ! {
     let p = embassy_rp::init(Default::default());
     let mut led = Output::new(p.PIN_25, Level::Low);
 
-    let result = match Rp2040Backend::<RP2040_FLASH_SIZE>::new(p.FLASH, DATA_STORAGE_START_ADDRESS) {
-        Ok(mut storage) => run_flow(&mut storage),
+    // Storage region comes from the STORAGE memory region in memory.x.
+    let result = match Rp2040Backend::<RP2040_FLASH_SIZE>::from_linker_symbols(p.FLASH) {
+        Ok(mut storage) => finish_pending_relocation(&mut storage).and_then(|_| run_flow(&mut storage)),
         Err(err) => Err(err),
     };
 
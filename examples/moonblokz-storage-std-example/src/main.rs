@@ -88,6 +88,23 @@ fn main() {
                     println!("Error: ControlPlaneIncompatible")
                 }
                 StorageError::InvalidConfiguration => println!("Error: InvalidConfiguration"),
+                StorageError::KeyUnwrapFailed => println!("Error: KeyUnwrapFailed"),
+                StorageError::LayoutMismatch {
+                    field,
+                    stored,
+                    expected,
+                } => println!(
+                    "Error: LayoutMismatch({} stored={} expected={})",
+                    field.name(),
+                    stored,
+                    expected
+                ),
+                StorageError::RelocationInProgress => println!("Error: RelocationInProgress"),
+                StorageError::InitInProgress => println!("Error: InitInProgress"),
+                StorageError::SlotOccupied => println!("Error: SlotOccupied"),
+                StorageError::Conflict { .. } => println!("Error: Conflict"),
+                StorageError::KvInvalidEntry => println!("Error: KvInvalidEntry"),
+                StorageError::KvFull => println!("Error: KvFull"),
                 StorageError::BackendIo { code } => println!("Error: BackendIo(code={})", code),
             }
         }
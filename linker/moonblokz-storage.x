@@ -0,0 +1,24 @@
+/* MoonBlokz storage region symbols for `Rp2040Backend::from_linker_symbols`.
+
+   Declare a page-aligned STORAGE memory region that runs to the end of flash
+   in memory.x, directly after FLASH, then include this fragment:
+
+     MEMORY {
+         BOOT2   : ORIGIN = 0x10000000, LENGTH = 0x100
+         FLASH   : ORIGIN = 0x10000100, LENGTH = 1536K - 0x100
+         STORAGE : ORIGIN = ORIGIN(FLASH) + LENGTH(FLASH), LENGTH = 512K
+         RAM     : ORIGIN = 0x20000000, LENGTH = 264K
+     }
+     INCLUDE moonblokz-storage.x
+
+   The firmware image can never be placed in STORAGE because it is a separate
+   region; the constructor additionally checks the image end at runtime.
+
+   `build_script::emit_linker_script` (feature `build-script`) writes this
+   fragment to OUT_DIR together with a minimum-length check for the configured
+   control-plane replica count. */
+
+__storage_start = ORIGIN(STORAGE);
+__storage_end = ORIGIN(STORAGE) + LENGTH(STORAGE);
+
+ASSERT(__storage_start % 4096 == 0, "moonblokz-storage: STORAGE origin must be 4096-byte aligned");
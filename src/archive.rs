@@ -0,0 +1,653 @@
+/*! Backend-independent archive format for exporting and importing storage contents.
+
+An archive is a byte stream that does not depend on flash geometry or the
+storage start address, so it can move a node's chain between devices or
+back it up over a UART or radio link.
+
+Layout (all integers little-endian):
+- header: `magic:[u8;4]="MBKA"`, `format_version:u8`, `control_plane_version:u8`,
+  `max_block_size:u16`, `hash_size:u8`, `flags:u8` (bit 0: control data present).
+- optional control data: `private_key_size:u8` (0 = key omitted), `private_key`, `own_node_id:u32`,
+  `init_params_size:u8`, `init_params`, `chain_config_len:u16` (0 = unset), `chain_config`.
+- block records: `tag:u8=0x01`, `storage_index:u32`, `len:u16`, `block:[u8;len]`, `hash:[u8;hash_size]`.
+- trailer: `tag:u8=0xFF`, `record_count:u32`, `crc32:u32` over every preceding byte.
+*/
+
+use crate::control_plane::crc32_update;
+use crate::{
+    CONTROL_PLANE_VERSION, ControlPlaneData, INIT_PARAMS_SIZE, StorageError, StorageIndex,
+    StorageTrait,
+};
+use moonblokz_chain_types::{Block, HASH_SIZE, MAX_BLOCK_SIZE, calculate_hash};
+use moonblokz_crypto::PRIVATE_KEY_SIZE;
+use zeroize::Zeroizing;
+
+/// Archive magic bytes.
+pub const ARCHIVE_MAGIC: [u8; 4] = *b"MBKA";
+/// Archive format version written by this crate.
+pub const ARCHIVE_FORMAT_VERSION: u8 = 1;
+
+const FLAG_CONTROL_DATA: u8 = 0x01;
+const TAG_BLOCK: u8 = 0x01;
+const TAG_TRAILER: u8 = 0xFF;
+
+/// Destination for archive bytes, such as a buffer, UART or radio stream.
+pub trait ByteSink {
+    /// Writes all bytes or fails.
+    ///
+    /// Parameters:
+    /// - `bytes`: bytes to append to the stream.
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), StorageError>;
+}
+
+/// Source of archive bytes, such as a buffer, UART or radio stream.
+///
+/// Import reads the archive twice, so the source must be able to start over:
+/// a buffer rewinds in place, a stream can ask the sender to resend.
+pub trait ByteSource {
+    /// Fills `out` completely or fails.
+    ///
+    /// Parameters:
+    /// - `out`: buffer to fill with the next stream bytes.
+    fn read_bytes(&mut self, out: &mut [u8]) -> Result<(), StorageError>;
+
+    /// Restarts the stream so the next `read_bytes` returns the first archive
+    /// byte again.
+    ///
+    /// Parameters:
+    /// - none.
+    fn rewind(&mut self) -> Result<(), StorageError>;
+}
+
+/// `ByteSink` writing into a caller-provided buffer.
+///
+/// Fails with `BackendIo { code: 300 }` when the buffer is full.
+pub struct SliceSink<'a> {
+    buffer: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceSink<'a> {
+    /// Creates an empty sink over `buffer`.
+    ///
+    /// Parameters:
+    /// - `buffer`: output buffer.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::archive::{ByteSink, SliceSink};
+    ///
+    /// let mut buffer = [0u8; 4];
+    /// let mut sink = SliceSink::new(&mut buffer);
+    /// assert!(sink.write_bytes(&[1, 2]).is_ok());
+    /// assert_eq!(sink.written(), &[1, 2]);
+    /// ```
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self { buffer, len: 0 }
+    }
+
+    /// Returns the bytes written so far.
+    ///
+    /// Parameters:
+    /// - none.
+    pub fn written(&self) -> &[u8] {
+        &self.buffer[..self.len]
+    }
+}
+
+impl ByteSink for SliceSink<'_> {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), StorageError> {
+        let end = self.len + bytes.len();
+        if end > self.buffer.len() {
+            return Err(StorageError::BackendIo { code: 300 });
+        }
+        self.buffer[self.len..end].copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// `ByteSource` reading from a byte slice.
+///
+/// Fails with `BackendIo { code: 301 }` when the slice is exhausted.
+pub struct SliceSource<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> SliceSource<'a> {
+    /// Creates a source positioned at the start of `bytes`.
+    ///
+    /// Parameters:
+    /// - `bytes`: archive bytes.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::archive::{ByteSource, SliceSource};
+    ///
+    /// let mut source = SliceSource::new(&[1, 2, 3]);
+    /// let mut out = [0u8; 2];
+    /// assert!(source.read_bytes(&mut out).is_ok());
+    /// assert_eq!(out, [1, 2]);
+    /// ```
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+}
+
+impl ByteSource for SliceSource<'_> {
+    fn read_bytes(&mut self, out: &mut [u8]) -> Result<(), StorageError> {
+        let end = self.position + out.len();
+        if end > self.bytes.len() {
+            return Err(StorageError::BackendIo { code: 301 });
+        }
+        out.copy_from_slice(&self.bytes[self.position..end]);
+        self.position = end;
+        Ok(())
+    }
+
+    fn rewind(&mut self) -> Result<(), StorageError> {
+        self.position = 0;
+        Ok(())
+    }
+}
+
+/// Options controlling archive export.
+#[derive(Clone, Copy, Default)]
+pub struct ExportOptions {
+    /// Includes control data (node id, init params, chain configuration)
+    /// when `true`.
+    pub include_control_data: bool,
+    /// Also writes the private key, in plaintext, into the control data.
+    ///
+    /// Without it the key is omitted and import keeps the key the target
+    /// was provisioned with. Ignored unless `include_control_data` is set.
+    pub include_private_key: bool,
+}
+
+/// Summary of an export or import run.
+#[derive(Clone, Copy, Default)]
+pub struct ArchiveSummary {
+    /// `true` when the archive carried control data.
+    pub control_data: bool,
+    /// Number of block records written or read.
+    pub blocks: u32,
+    /// Number of slots skipped on export because reading them failed.
+    pub damaged: u32,
+}
+
+struct ArchiveWriter<'a> {
+    sink: &'a mut dyn ByteSink,
+    crc: u32,
+}
+
+impl ArchiveWriter<'_> {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), StorageError> {
+        self.crc = crc32_update(self.crc, bytes);
+        self.sink.write_bytes(bytes)
+    }
+}
+
+struct ArchiveReader<'a> {
+    source: &'a mut dyn ByteSource,
+    crc: u32,
+}
+
+impl ArchiveReader<'_> {
+    fn read(&mut self, out: &mut [u8]) -> Result<(), StorageError> {
+        self.source.read_bytes(out)?;
+        self.crc = crc32_update(self.crc, out);
+        Ok(())
+    }
+
+    fn read_u8(&mut self) -> Result<u8, StorageError> {
+        let mut value = [0u8; 1];
+        self.read(&mut value)?;
+        Ok(value[0])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, StorageError> {
+        let mut value = [0u8; 2];
+        self.read(&mut value)?;
+        Ok(u16::from_le_bytes(value))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, StorageError> {
+        let mut value = [0u8; 4];
+        self.read(&mut value)?;
+        Ok(u32::from_le_bytes(value))
+    }
+}
+
+/// Streams storage contents into `sink` as an archive.
+///
+/// Empty slots are skipped. A slot that fails to read (damaged or
+/// unreadable) is left out and counted in `ArchiveSummary::damaged`, so one
+/// bad slot does not block backing up the rest. Backends expose this as
+/// `export_to`.
+///
+/// Parameters:
+/// - `storage`: backend to export.
+/// - `slot_count`: number of slots to scan, starting at index 0.
+/// - `options`: export options.
+/// - `sink`: archive destination.
+pub fn export_archive<S: StorageTrait>(
+    storage: &mut S,
+    slot_count: StorageIndex,
+    options: ExportOptions,
+    sink: &mut dyn ByteSink,
+) -> Result<ArchiveSummary, StorageError> {
+    let control_data = if options.include_control_data {
+        Some(storage.load_control_data()?)
+    } else {
+        None
+    };
+
+    let mut writer = ArchiveWriter { sink, crc: 0xFFFF_FFFF };
+    writer.write(&ARCHIVE_MAGIC)?;
+    writer.write(&[ARCHIVE_FORMAT_VERSION, CONTROL_PLANE_VERSION])?;
+    writer.write(&(MAX_BLOCK_SIZE as u16).to_le_bytes())?;
+    let flags = if control_data.is_some() { FLAG_CONTROL_DATA } else { 0 };
+    writer.write(&[HASH_SIZE as u8, flags])?;
+
+    if let Some(record) = &control_data {
+        if options.include_private_key {
+            writer.write(&[PRIVATE_KEY_SIZE as u8])?;
+            writer.write(&record.private_key)?;
+        } else {
+            writer.write(&[0u8])?;
+        }
+        writer.write(&record.own_node_id.to_le_bytes())?;
+        writer.write(&[INIT_PARAMS_SIZE as u8])?;
+        writer.write(&record.init_params)?;
+        match &record.chain_configuration {
+            Some(block) => {
+                let bytes = block.serialized_bytes();
+                writer.write(&(bytes.len() as u16).to_le_bytes())?;
+                writer.write(bytes)?;
+            }
+            None => writer.write(&0u16.to_le_bytes())?,
+        }
+    }
+
+    let mut summary = ArchiveSummary {
+        control_data: control_data.is_some(),
+        ..ArchiveSummary::default()
+    };
+    let mut storage_index: StorageIndex = 0;
+    while storage_index < slot_count {
+        let block = match storage.read_block(storage_index) {
+            Ok(value) => value,
+            Err(StorageError::BlockAbsent) => {
+                storage_index += 1;
+                continue;
+            }
+            Err(_) => {
+                summary.damaged += 1;
+                storage_index += 1;
+                continue;
+            }
+        };
+        let bytes = block.serialized_bytes();
+        writer.write(&[TAG_BLOCK])?;
+        writer.write(&storage_index.to_le_bytes())?;
+        writer.write(&(bytes.len() as u16).to_le_bytes())?;
+        writer.write(bytes)?;
+        writer.write(&calculate_hash(bytes))?;
+        summary.blocks += 1;
+        storage_index += 1;
+    }
+
+    writer.write(&[TAG_TRAILER])?;
+    writer.write(&summary.blocks.to_le_bytes())?;
+    let crc = !writer.crc;
+    writer.sink.write_bytes(&crc.to_le_bytes())?;
+    Ok(summary)
+}
+
+/// Restores an archive from `source` into `storage`.
+///
+/// The archive is read twice. The first pass checks everything (header,
+/// record hashes and indexes, block encoding, trailer count and checksum)
+/// without touching `storage`; only when it succeeds is `source` rewound and
+/// the second pass applies the archive. A damaged or truncated archive
+/// therefore leaves `storage` as it was.
+///
+/// When the archive carries control data, `storage` is re-initialized from it
+/// (erasing all slots) before blocks are restored; otherwise blocks are
+/// written into the existing storage. An archive exported without the private
+/// key keeps the key `storage` already holds. Backends expose this as
+/// `import_from`.
+///
+/// Errors:
+/// - `ControlPlaneIncompatible`: archive uses a newer control-plane version, a
+///   larger `MAX_BLOCK_SIZE` or a different hash size.
+/// - `ControlPlaneUninitialized`: archive omits the private key and `storage`
+///   holds none.
+/// - `IntegrityFailure`: record hash or trailer checksum mismatch.
+/// - `InvalidIndex`: record index outside `slot_count`.
+/// - `BackendIo { code: 302 }`: bad magic or unsupported format version.
+/// - `BackendIo { code: 303 }`: malformed control data or record.
+///
+/// Parameters:
+/// - `storage`: backend to restore into.
+/// - `slot_count`: number of slots available in `storage`.
+/// - `source`: archive bytes; rewound once between the two passes.
+pub fn import_archive<S: StorageTrait>(
+    storage: &mut S,
+    slot_count: StorageIndex,
+    source: &mut dyn ByteSource,
+) -> Result<ArchiveSummary, StorageError> {
+    read_archive(storage, slot_count, source, false)?;
+    source.rewind()?;
+    read_archive(storage, slot_count, source, true)
+}
+
+fn read_archive<S: StorageTrait>(
+    storage: &mut S,
+    slot_count: StorageIndex,
+    source: &mut dyn ByteSource,
+    apply: bool,
+) -> Result<ArchiveSummary, StorageError> {
+    let mut reader = ArchiveReader { source, crc: 0xFFFF_FFFF };
+    let mut magic = [0u8; 4];
+    reader.read(&mut magic)?;
+    if magic != ARCHIVE_MAGIC || reader.read_u8()? != ARCHIVE_FORMAT_VERSION {
+        return Err(StorageError::BackendIo { code: 302 });
+    }
+    let control_plane_version = reader.read_u8()?;
+    let max_block_size = reader.read_u16()? as usize;
+    let hash_size = reader.read_u8()? as usize;
+    let flags = reader.read_u8()?;
+    if control_plane_version > CONTROL_PLANE_VERSION
+        || max_block_size > MAX_BLOCK_SIZE
+        || hash_size != HASH_SIZE
+    {
+        return Err(StorageError::ControlPlaneIncompatible);
+    }
+
+    let mut summary = ArchiveSummary::default();
+    let mut buffer = [0u8; MAX_BLOCK_SIZE];
+    if flags & FLAG_CONTROL_DATA != 0 {
+        let mut private_key = Zeroizing::new([0u8; PRIVATE_KEY_SIZE]);
+        let mut init_params = [0u8; INIT_PARAMS_SIZE];
+        match reader.read_u8()? as usize {
+            0 => private_key.copy_from_slice(&storage.load_control_data()?.private_key),
+            PRIVATE_KEY_SIZE => reader.read(&mut private_key[..])?,
+            _ => return Err(StorageError::ControlPlaneIncompatible),
+        }
+        let own_node_id = reader.read_u32()?;
+        if reader.read_u8()? as usize != INIT_PARAMS_SIZE {
+            return Err(StorageError::ControlPlaneIncompatible);
+        }
+        reader.read(&mut init_params)?;
+        let chain_config_len = reader.read_u16()? as usize;
+        if chain_config_len > max_block_size {
+            return Err(StorageError::BackendIo { code: 303 });
+        }
+        reader.read(&mut buffer[..chain_config_len])?;
+        let chain_configuration = if chain_config_len > 0 {
+            Some(
+                Block::from_bytes(&buffer[..chain_config_len])
+                    .map_err(|_| StorageError::BackendIo { code: 303 })?,
+            )
+        } else {
+            None
+        };
+
+        if apply {
+            storage.init(*private_key, own_node_id, init_params)?;
+            if let Some(block) = &chain_configuration {
+                storage.set_chain_configuration(block)?;
+            }
+        }
+        summary.control_data = true;
+    }
+
+    loop {
+        match reader.read_u8()? {
+            TAG_BLOCK => {}
+            TAG_TRAILER => break,
+            _ => return Err(StorageError::BackendIo { code: 303 }),
+        }
+        let storage_index = reader.read_u32()?;
+        let len = reader.read_u16()? as usize;
+        if len > max_block_size {
+            return Err(StorageError::BackendIo { code: 303 });
+        }
+        reader.read(&mut buffer[..len])?;
+        let mut hash = [0u8; HASH_SIZE];
+        reader.read(&mut hash)?;
+        if calculate_hash(&buffer[..len]) != hash {
+            return Err(StorageError::IntegrityFailure);
+        }
+        if storage_index >= slot_count {
+            return Err(StorageError::InvalidIndex);
+        }
+        let block =
+            Block::from_bytes(&buffer[..len]).map_err(|_| StorageError::BackendIo { code: 303 })?;
+        if apply {
+            storage.save_block(storage_index, &block)?;
+        }
+        summary.blocks += 1;
+    }
+
+    let record_count = reader.read_u32()?;
+    let computed_crc = !reader.crc;
+    let mut stored_crc = [0u8; 4];
+    reader.source.read_bytes(&mut stored_crc)?;
+    if record_count != summary.blocks || u32::from_le_bytes(stored_crc) != computed_crc {
+        return Err(StorageError::IntegrityFailure);
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MoonblokzStorage;
+
+    #[cfg(feature = "backend-memory")]
+    const TEST_STORAGE_SIZE: usize = 8 * MAX_BLOCK_SIZE;
+    #[cfg(feature = "backend-rp2040")]
+    const TEST_STORAGE_SIZE: usize = (crate::CONTROL_PLANE_COUNT + 2) * 4096;
+    const TEST_SLOT_COUNT: StorageIndex = 2;
+    const TEST_ARCHIVE_CAPACITY: usize = 4 * MAX_BLOCK_SIZE + 512;
+
+    #[cfg(feature = "backend-memory")]
+    fn new_backend() -> MoonblokzStorage<TEST_STORAGE_SIZE> {
+        MoonblokzStorage::<TEST_STORAGE_SIZE>::new()
+    }
+
+    #[cfg(feature = "backend-rp2040")]
+    fn new_backend() -> MoonblokzStorage<TEST_STORAGE_SIZE> {
+        MoonblokzStorage::<TEST_STORAGE_SIZE>::new_for_tests(0).unwrap_or_else(|_| unreachable!())
+    }
+
+    fn block_from_marker(marker: u8) -> Block {
+        let mut bytes = [0u8; MAX_BLOCK_SIZE];
+        bytes[0] = 1;
+        bytes[1] = marker;
+        match Block::from_bytes(&bytes) {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        }
+    }
+
+    fn populated_backend() -> MoonblokzStorage<TEST_STORAGE_SIZE> {
+        let mut storage = new_backend();
+        assert!(storage
+            .init([3u8; PRIVATE_KEY_SIZE], 77, [4u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert!(storage.set_chain_configuration(&block_from_marker(9)).is_ok());
+        assert!(storage.save_block(1, &block_from_marker(5)).is_ok());
+        storage
+    }
+
+    #[test]
+    fn export_import_round_trip_restores_control_data_and_blocks() {
+        let mut source_storage = populated_backend();
+        let mut buffer = [0u8; TEST_ARCHIVE_CAPACITY];
+        let mut sink = SliceSink::new(&mut buffer);
+        let options = ExportOptions { include_control_data: true, include_private_key: true };
+        let exported = export_archive(&mut source_storage, TEST_SLOT_COUNT, options, &mut sink);
+        assert!(matches!(exported, Ok(ArchiveSummary { control_data: true, blocks: 1, damaged: 0 })));
+        let len = sink.written().len();
+
+        let mut target = new_backend();
+        let imported = import_archive(&mut target, TEST_SLOT_COUNT, &mut SliceSource::new(&buffer[..len]));
+        assert!(matches!(imported, Ok(ArchiveSummary { control_data: true, blocks: 1, .. })));
+
+        let loaded = target.load_control_data().unwrap_or_else(|_| unreachable!());
+        assert_eq!(loaded.private_key, [3u8; PRIVATE_KEY_SIZE]);
+        assert_eq!(loaded.own_node_id, 77);
+        assert!(loaded.chain_configuration.is_some());
+        assert!(matches!(target.read_block(0), Err(StorageError::BlockAbsent)));
+        let restored = target.read_block(1).unwrap_or_else(|_| unreachable!());
+        assert_eq!(restored.serialized_bytes(), block_from_marker(5).serialized_bytes());
+    }
+
+    #[test]
+    fn export_without_control_data_omits_it() {
+        let mut storage = populated_backend();
+        let mut buffer = [0u8; TEST_ARCHIVE_CAPACITY];
+        let mut sink = SliceSink::new(&mut buffer);
+        let exported = export_archive(&mut storage, TEST_SLOT_COUNT, ExportOptions::default(), &mut sink);
+        assert!(matches!(exported, Ok(ArchiveSummary { control_data: false, blocks: 1, .. })));
+        assert_eq!(sink.written()[9] & FLAG_CONTROL_DATA, 0);
+    }
+
+    #[test]
+    fn import_rejects_corrupted_trailer_and_truncated_stream() {
+        let mut storage = populated_backend();
+        let mut buffer = [0u8; TEST_ARCHIVE_CAPACITY];
+        let mut sink = SliceSink::new(&mut buffer);
+        assert!(export_archive(&mut storage, TEST_SLOT_COUNT, ExportOptions::default(), &mut sink).is_ok());
+        let len = sink.written().len();
+
+        buffer[len - 1] ^= 0xFF;
+        let mut target = populated_backend();
+        assert!(matches!(
+            import_archive(&mut target, TEST_SLOT_COUNT, &mut SliceSource::new(&buffer[..len])),
+            Err(StorageError::IntegrityFailure)
+        ));
+        assert!(matches!(
+            import_archive(&mut target, TEST_SLOT_COUNT, &mut SliceSource::new(&buffer[..len - 8])),
+            Err(StorageError::BackendIo { code: 301 })
+        ));
+    }
+
+    #[test]
+    fn failed_import_leaves_the_target_untouched() {
+        let mut storage = populated_backend();
+        let mut buffer = [0u8; TEST_ARCHIVE_CAPACITY];
+        let mut sink = SliceSink::new(&mut buffer);
+        let options = ExportOptions { include_control_data: true, include_private_key: true };
+        assert!(export_archive(&mut storage, TEST_SLOT_COUNT, options, &mut sink).is_ok());
+        let len = sink.written().len();
+        buffer[len - 1] ^= 0xFF;
+
+        let mut target = new_backend();
+        assert!(target.init([8u8; PRIVATE_KEY_SIZE], 5, [0u8; INIT_PARAMS_SIZE]).is_ok());
+        assert!(target.save_block(0, &block_from_marker(6)).is_ok());
+        assert!(matches!(
+            import_archive(&mut target, TEST_SLOT_COUNT, &mut SliceSource::new(&buffer[..len])),
+            Err(StorageError::IntegrityFailure)
+        ));
+
+        let loaded = target.load_control_data().unwrap_or_else(|_| unreachable!());
+        assert_eq!(loaded.own_node_id, 5);
+        assert!(loaded.chain_configuration.is_none());
+        let kept = target.read_block(0).unwrap_or_else(|_| unreachable!());
+        assert_eq!(kept.serialized_bytes(), block_from_marker(6).serialized_bytes());
+    }
+
+    #[test]
+    fn export_omits_the_private_key_unless_asked_and_import_keeps_the_targets_key() {
+        let mut storage = populated_backend();
+        let mut buffer = [0u8; TEST_ARCHIVE_CAPACITY];
+        let mut sink = SliceSink::new(&mut buffer);
+        let options = ExportOptions { include_control_data: true, ..ExportOptions::default() };
+        assert!(export_archive(&mut storage, TEST_SLOT_COUNT, options, &mut sink).is_ok());
+        let len = sink.written().len();
+        assert_eq!(sink.written()[10], 0);
+        assert!(!sink.written().windows(PRIVATE_KEY_SIZE).any(|window| window == [3u8; PRIVATE_KEY_SIZE]));
+
+        let mut blank = new_backend();
+        assert!(matches!(
+            import_archive(&mut blank, TEST_SLOT_COUNT, &mut SliceSource::new(&buffer[..len])),
+            Err(StorageError::ControlPlaneUninitialized)
+        ));
+
+        let mut target = new_backend();
+        assert!(target.init([8u8; PRIVATE_KEY_SIZE], 5, [0u8; INIT_PARAMS_SIZE]).is_ok());
+        let imported = import_archive(&mut target, TEST_SLOT_COUNT, &mut SliceSource::new(&buffer[..len]));
+        assert!(matches!(imported, Ok(ArchiveSummary { control_data: true, blocks: 1, .. })));
+        let loaded = target.load_control_data().unwrap_or_else(|_| unreachable!());
+        assert_eq!(loaded.private_key, [8u8; PRIVATE_KEY_SIZE]);
+        assert_eq!(loaded.own_node_id, 77);
+        assert_eq!(loaded.init_params, [4u8; INIT_PARAMS_SIZE]);
+    }
+
+    struct DamagedSlot<S> {
+        inner: S,
+        damaged_index: StorageIndex,
+    }
+
+    impl<S: StorageTrait> StorageTrait for DamagedSlot<S> {
+        fn init(
+            &mut self,
+            private_key: [u8; PRIVATE_KEY_SIZE],
+            own_node_id: u32,
+            init_params: [u8; INIT_PARAMS_SIZE],
+        ) -> Result<(), StorageError> {
+            self.inner.init(private_key, own_node_id, init_params)
+        }
+
+        fn save_block(&mut self, storage_index: StorageIndex, block: &Block) -> Result<(), StorageError> {
+            self.inner.save_block(storage_index, block)
+        }
+
+        fn read_block(&self, storage_index: StorageIndex) -> Result<Block, StorageError> {
+            if storage_index == self.damaged_index {
+                return Err(StorageError::IntegrityFailure);
+            }
+            self.inner.read_block(storage_index)
+        }
+
+        fn set_chain_configuration(&mut self, block: &Block) -> Result<(), StorageError> {
+            self.inner.set_chain_configuration(block)
+        }
+
+        fn load_control_data(&mut self) -> Result<ControlPlaneData, StorageError> {
+            self.inner.load_control_data()
+        }
+    }
+
+    #[test]
+    fn export_skips_and_counts_damaged_slots() {
+        let mut inner = populated_backend();
+        assert!(inner.save_block(0, &block_from_marker(6)).is_ok());
+        let mut storage = DamagedSlot { inner, damaged_index: 0 };
+        let mut buffer = [0u8; TEST_ARCHIVE_CAPACITY];
+        let mut sink = SliceSink::new(&mut buffer);
+        let exported = export_archive(&mut storage, TEST_SLOT_COUNT, ExportOptions::default(), &mut sink);
+        assert!(matches!(exported, Ok(ArchiveSummary { control_data: false, blocks: 1, damaged: 1 })));
+        let len = sink.written().len();
+
+        let mut target = populated_backend();
+        let imported = import_archive(&mut target, TEST_SLOT_COUNT, &mut SliceSource::new(&buffer[..len]));
+        assert!(matches!(imported, Ok(ArchiveSummary { blocks: 1, .. })));
+        assert!(matches!(target.read_block(0), Err(StorageError::BlockAbsent)));
+    }
+
+    #[test]
+    fn import_rejects_bad_magic() {
+        let mut target = new_backend();
+        assert!(matches!(
+            import_archive(&mut target, TEST_SLOT_COUNT, &mut SliceSource::new(b"NOPE\x01")),
+            Err(StorageError::BackendIo { code: 302 })
+        ));
+    }
+}
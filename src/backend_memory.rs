@@ -1,29 +1,44 @@
 /*! In-memory backend module for MoonBlokz storage contract testing/integration. */
 
+use crate::archive::{
+    ArchiveSummary, ByteSink, ByteSource, ExportOptions, export_archive, import_archive,
+};
+use crate::control_plane::{self, CONTROL_PLANE_ENTRY_SIZE, ControlPlaneLayout, DecodedEntry, KeyProtection};
+use crate::counter::{self, COUNTER_PAGES};
+use crate::kv_store::{self, KV_PAGE_SIZE, KvValue};
+use crate::merkle::{EMPTY_LEAF, MerkleTree};
+use crate::sequence_index::{self, SequenceSource};
+use crate::types::WriteCondition;
 use crate::{
-    CONTROL_PLANE_COUNT, CONTROL_PLANE_VERSION, ControlPlaneData, INIT_PARAMS_SIZE, StorageError,
-    StorageIndex, StorageTrait,
+    ControlPlaneData, ControlPlaneHealth, FsckFinding, FsckIssue, FsckOptions, FsckReport, INIT_PARAMS_SIZE,
+    MAX_CONTROL_PLANE_COUNT, NoopObserver, ReplicaStatus, StorageError, StorageIndex, StorageObserver, StorageTrait,
+    WriteOnceSlots, block_hash,
 };
-use moonblokz_chain_types::{Block, MAX_BLOCK_SIZE};
+#[cfg(feature = "slot-meta")]
+use crate::SLOT_META_SIZE;
+use core::ops::Range;
+use moonblokz_chain_types::{Block, HASH_SIZE, MAX_BLOCK_SIZE, calculate_hash};
 use moonblokz_crypto::PRIVATE_KEY_SIZE;
+use zeroize::{Zeroize, Zeroizing};
 
-const VERSION_OFFSET: usize = 0;
-const PRIVATE_KEY_SIZE_OFFSET: usize = VERSION_OFFSET + 1;
-const PRIVATE_KEY_OFFSET: usize = PRIVATE_KEY_SIZE_OFFSET + 1;
-const OWN_NODE_ID_OFFSET: usize = PRIVATE_KEY_OFFSET + PRIVATE_KEY_SIZE;
-const INIT_PARAMS_SIZE_OFFSET: usize = OWN_NODE_ID_OFFSET + 4;
-const INIT_PARAMS_OFFSET: usize = INIT_PARAMS_SIZE_OFFSET + 1;
-const MAX_BLOCK_SIZE_OFFSET: usize = INIT_PARAMS_OFFSET + INIT_PARAMS_SIZE;
-const CHAIN_CONFIG_OFFSET: usize = MAX_BLOCK_SIZE_OFFSET + 2;
-const CONTROL_CRC32_OFFSET: usize = CHAIN_CONFIG_OFFSET + MAX_BLOCK_SIZE;
-const CONTROL_PLANE_ENTRY_SIZE: usize = CONTROL_CRC32_OFFSET + 4;
-const CONTROL_PLANE_RESERVED_BYTES: usize = CONTROL_PLANE_COUNT * CONTROL_PLANE_ENTRY_SIZE;
+/// Bytes one slot takes in the storage array.
+#[cfg(not(feature = "slot-meta"))]
+const SLOT_STRIDE: usize = MAX_BLOCK_SIZE;
+/// Bytes one slot takes in the storage array: block bytes, a metadata
+/// presence byte (`0` means none) and the metadata value.
+#[cfg(feature = "slot-meta")]
+const SLOT_STRIDE: usize = MAX_BLOCK_SIZE + 1 + SLOT_META_SIZE;
 
 /// In-memory backend with compile-time byte capacity.
 ///
 /// Capacity rule:
-/// - Control-plane uses the first `CONTROL_PLANE_COUNT * CONTROL_PLANE_ENTRY_SIZE` bytes.
-/// - Effective block slots are `(STORAGE_SIZE - control_plane_reserved_bytes) / MAX_BLOCK_SIZE`.
+/// - Control-plane uses the first `replica_count * CONTROL_PLANE_ENTRY_SIZE` bytes
+///   (`CONTROL_PLANE_COUNT` replicas unless `with_control_plane_layout` is used).
+/// - Effective block slots are `(STORAGE_SIZE - control_plane_reserved_bytes) / MAX_BLOCK_SIZE`
+///   (`MAX_BLOCK_SIZE + 1 + SLOT_META_SIZE` with feature `slot-meta`).
+/// - A key-value store (`with_kv_store`) takes the last `pages * KV_PAGE_SIZE` bytes
+///   and counters (`with_counters`) take `count * COUNTER_PAGES * KV_PAGE_SIZE` bytes
+///   before it, before the slot count is computed.
 /// - Remainder bytes are intentionally unused.
 /// - Empty slot marker is `slot[0] == 0` (block version byte is zero).
 ///
@@ -60,21 +75,29 @@ const CONTROL_PLANE_RESERVED_BYTES: usize = CONTROL_PLANE_COUNT * CONTROL_PLANE_
 ///         Err(StorageError::ControlPlaneCorrupted) => { /* control-plane issue */ }
 ///         Err(StorageError::ControlPlaneIncompatible) => { /* control-plane incompatibility */ }
 ///         Err(StorageError::InvalidConfiguration) => { /* backend configuration issue */ }
+///         Err(StorageError::KeyUnwrapFailed) => { /* not used in read */ }
+///         Err(StorageError::LayoutMismatch { .. }) => { /* flash geometry changed */ }
+///         Err(StorageError::RelocationInProgress) => { /* not used in read */ }
+///         Err(StorageError::InitInProgress) => { /* not used in read */ }
+///         Err(StorageError::SlotOccupied) => { /* not used in read */ }
+///         Err(StorageError::Conflict { .. }) => { /* not used in read */ }
+///         Err(StorageError::KvInvalidEntry) => { /* not used in read */ }
+///         Err(StorageError::KvFull) => { /* not used in read */ }
 ///         Err(StorageError::BackendIo { .. }) => { /* backend error */ }
 ///     }
 /// }
 /// ```
-pub struct MemoryBackend<const STORAGE_SIZE: usize> {
+pub struct MemoryBackend<const STORAGE_SIZE: usize, O: StorageObserver = NoopObserver> {
     storage: [u8; STORAGE_SIZE],
+    key_protection: KeyProtection,
+    control_plane_layout: ControlPlaneLayout,
+    write_once: WriteOnceSlots,
+    kv_pages: usize,
+    counters: usize,
+    observer: O,
 }
 
 impl<const STORAGE_SIZE: usize> MemoryBackend<STORAGE_SIZE> {
-    const MAX_STORAGE_SLOTS: StorageIndex = if STORAGE_SIZE > CONTROL_PLANE_RESERVED_BYTES {
-        ((STORAGE_SIZE - CONTROL_PLANE_RESERVED_BYTES) / MAX_BLOCK_SIZE) as StorageIndex
-    } else {
-        0
-    };
-
     /// Creates a new memory backend.
     ///
     /// Parameters:
@@ -90,168 +113,585 @@ impl<const STORAGE_SIZE: usize> MemoryBackend<STORAGE_SIZE> {
     pub fn new() -> Self {
         Self {
             storage: [0u8; STORAGE_SIZE],
+            key_protection: KeyProtection::Plaintext,
+            control_plane_layout: ControlPlaneLayout::DEFAULT,
+            write_once: WriteOnceSlots::NONE,
+            kv_pages: 0,
+            counters: 0,
+            observer: NoopObserver,
         }
     }
+}
 
-    fn slot_range(storage_index: StorageIndex) -> Result<(usize, usize), StorageError> {
-        if storage_index >= Self::MAX_STORAGE_SLOTS {
-            return Err(StorageError::InvalidIndex);
+impl<const STORAGE_SIZE: usize, O: StorageObserver> MemoryBackend<STORAGE_SIZE, O> {
+    /// Replaces the backend observer, keeping stored data unchanged.
+    ///
+    /// Parameters:
+    /// - `observer`: event hooks invoked on storage activity.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_storage::{MemoryBackend, NoopObserver};
+    ///
+    /// let _backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new().with_observer(NoopObserver);
+    /// ```
+    pub fn with_observer<P: StorageObserver>(self, observer: P) -> MemoryBackend<STORAGE_SIZE, P> {
+        MemoryBackend {
+            storage: self.storage,
+            key_protection: self.key_protection,
+            control_plane_layout: self.control_plane_layout,
+            write_once: self.write_once,
+            kv_pages: self.kv_pages,
+            counters: self.counters,
+            observer,
         }
+    }
 
-        let slot_start = CONTROL_PLANE_RESERVED_BYTES + storage_index as usize * MAX_BLOCK_SIZE;
-        let slot_end = slot_start + MAX_BLOCK_SIZE;
-        Ok((slot_start, slot_end))
+    /// Sets how the private key is protected in control-plane replicas.
+    ///
+    /// With a wrapping key configured, `load_control_data` transparently
+    /// unwraps the key. Plaintext replicas are only accepted from control-plane
+    /// version 1 and rewritten wrapped; a current plaintext replica fails with
+    /// `KeyUnwrapFailed`, so use `set_key_protection` to wrap an existing key.
+    ///
+    /// Parameters:
+    /// - `protection`: key protection mode (see `KeyProtection::from_secret`).
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_storage::{KeyProtection, MemoryBackend};
+    ///
+    /// let _backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new()
+    ///     .with_key_protection(KeyProtection::Plaintext);
+    /// ```
+    pub fn with_key_protection(mut self, protection: KeyProtection) -> Self {
+        self.key_protection = protection;
+        self
     }
 
-    fn control_plane_entry_offset(replica_index: usize) -> usize {
-        replica_index * CONTROL_PLANE_ENTRY_SIZE
+    /// Sets the control-plane replica count.
+    ///
+    /// Memory has no erase sectors, so replicas always sit at the start of
+    /// the array and `placement` is ignored. Block slots start after the
+    /// last replica; changing the count on a populated backend shifts them,
+    /// so configure it before `init`.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: invalid replica count (see `ControlPlaneLayout::validate`)
+    ///   or the replicas do not fit `STORAGE_SIZE`.
+    ///
+    /// Parameters:
+    /// - `layout`: replica count and placement.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_storage::{ControlPlaneLayout, ControlPlanePlacement, MemoryBackend};
+    ///
+    /// let layout = ControlPlaneLayout::new(5, ControlPlanePlacement::Consecutive);
+    /// let backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 12000 }>::new().with_control_plane_layout(layout);
+    /// assert!(backend.is_ok());
+    /// ```
+    pub fn with_control_plane_layout(mut self, layout: ControlPlaneLayout) -> Result<Self, StorageError> {
+        layout.validate()?;
+        if layout.replica_count * CONTROL_PLANE_ENTRY_SIZE + self.page_area_bytes() > STORAGE_SIZE {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        self.control_plane_layout = layout;
+        Ok(self)
     }
 
-    fn read_control_plane_entry(&self, replica_index: usize) -> [u8; CONTROL_PLANE_ENTRY_SIZE] {
-        let start = Self::control_plane_entry_offset(replica_index);
-        let end = start + CONTROL_PLANE_ENTRY_SIZE;
-        let mut out = [0u8; CONTROL_PLANE_ENTRY_SIZE];
-        out.copy_from_slice(&self.storage[start..end]);
-        out
+    /// Reserves `pages` pages of `KV_PAGE_SIZE` bytes at the end of the array
+    /// for the key-value store (`kv_get`, `kv_put`, `kv_delete`).
+    ///
+    /// The store keeps two mirrored copies of at least two pages, so `pages`
+    /// must be even and at least 4. Block slots shrink to fit, so configure it
+    /// before `init`.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: `pages` is odd or 2, or the pages do not fit `STORAGE_SIZE`.
+    ///
+    /// Parameters:
+    /// - `pages`: key-value pages (0 disables the store).
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_storage::MemoryBackend;
+    ///
+    /// let backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 24000 }>::new().with_kv_store(4);
+    /// assert!(backend.is_ok());
+    /// ```
+    pub fn with_kv_store(mut self, pages: usize) -> Result<Self, StorageError> {
+        let page_area = (pages + self.counters * COUNTER_PAGES) * KV_PAGE_SIZE;
+        if !kv_store::valid_page_count(pages) || self.control_plane_reserved_bytes() + page_area > STORAGE_SIZE {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        self.kv_pages = pages;
+        Ok(self)
     }
 
-    fn write_control_plane_entry(
-        &mut self,
-        replica_index: usize,
-        entry: &[u8; CONTROL_PLANE_ENTRY_SIZE],
-    ) {
-        let start = Self::control_plane_entry_offset(replica_index);
-        let end = start + CONTROL_PLANE_ENTRY_SIZE;
-        self.storage[start..end].copy_from_slice(entry);
+    /// Reserves `count` monotonic counters of `COUNTER_PAGES` pages each
+    /// before the key-value store at the end of the array.
+    ///
+    /// Block slots shrink to fit, so configure it before `init`.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: the counter pages do not fit `STORAGE_SIZE`.
+    ///
+    /// Parameters:
+    /// - `count`: number of counters (0 disables them).
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_storage::MemoryBackend;
+    ///
+    /// let backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 16000 }>::new().with_counters(1);
+    /// assert!(backend.is_ok());
+    /// ```
+    pub fn with_counters(mut self, count: usize) -> Result<Self, StorageError> {
+        let page_area = (self.kv_pages + count * COUNTER_PAGES) * KV_PAGE_SIZE;
+        if self.control_plane_reserved_bytes() + page_area > STORAGE_SIZE {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        self.counters = count;
+        Ok(self)
     }
 
-    fn crc32(bytes: &[u8]) -> u32 {
-        let mut crc = 0xFFFF_FFFFu32;
-        let mut i = 0usize;
-        while i < bytes.len() {
-            crc ^= bytes[i] as u32;
-            let mut bit = 0usize;
-            while bit < 8 {
-                if (crc & 1) != 0 {
-                    crc = (crc >> 1) ^ 0xEDB8_8320;
-                } else {
-                    crc >>= 1;
-                }
-                bit += 1;
-            }
-            i += 1;
+    /// Returns a handle to monotonic counter `id`.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `id` is not below the count passed to `with_counters`.
+    ///
+    /// Parameters:
+    /// - `id`: counter index.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_storage::MemoryBackend;
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 16000 }>::new().with_counters(1).unwrap();
+    /// assert!(matches!(backend.counter(0).and_then(|mut boots| boots.increment()), Ok(1)));
+    /// assert!(matches!(backend.counter(0).and_then(|boots| boots.get()), Ok(1)));
+    /// ```
+    pub fn counter(&mut self, id: usize) -> Result<MemoryCounter<'_>, StorageError> {
+        if id >= self.counters {
+            return Err(StorageError::InvalidIndex);
         }
-        !crc
+        let counter_bytes = COUNTER_PAGES * KV_PAGE_SIZE;
+        let start = self.counter_range().start + id * counter_bytes;
+        Ok(MemoryCounter {
+            pages: &mut self.storage[start..start + counter_bytes],
+        })
     }
 
-    fn serialize_record(record: &ControlPlaneData) -> [u8; CONTROL_PLANE_ENTRY_SIZE] {
-        let mut out = [0u8; CONTROL_PLANE_ENTRY_SIZE];
-        out[VERSION_OFFSET] = CONTROL_PLANE_VERSION;
-        out[PRIVATE_KEY_SIZE_OFFSET] = PRIVATE_KEY_SIZE as u8;
-        out[PRIVATE_KEY_OFFSET..PRIVATE_KEY_OFFSET + PRIVATE_KEY_SIZE]
-            .copy_from_slice(&record.private_key);
-        out[OWN_NODE_ID_OFFSET..OWN_NODE_ID_OFFSET + 4]
-            .copy_from_slice(&record.own_node_id.to_le_bytes());
-        out[INIT_PARAMS_SIZE_OFFSET] = INIT_PARAMS_SIZE as u8;
-        out[INIT_PARAMS_OFFSET..INIT_PARAMS_OFFSET + INIT_PARAMS_SIZE]
-            .copy_from_slice(&record.init_params);
+    /// Returns the value stored for `key`, `None` when it has none.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: no key-value store is configured.
+    ///
+    /// Parameters:
+    /// - `key`: key bytes.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_storage::MemoryBackend;
+    ///
+    /// let backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 24000 }>::new().with_kv_store(4).unwrap();
+    /// assert!(matches!(backend.kv_get(b"tip"), Ok(None)));
+    /// ```
+    pub fn kv_get(&self, key: &[u8]) -> Result<Option<KvValue>, StorageError> {
+        kv_store::get(&self.storage[self.kv_range()?], key)
+    }
 
-        let max_block_size_u16 = MAX_BLOCK_SIZE as u16;
-        out[MAX_BLOCK_SIZE_OFFSET..MAX_BLOCK_SIZE_OFFSET + 2]
-            .copy_from_slice(&max_block_size_u16.to_le_bytes());
+    /// Stores `value` for `key`, replacing any previous value.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: no key-value store is configured.
+    /// - `KvInvalidEntry`: the key is empty or too long, or the value is too long.
+    /// - `KvFull`: the live entries do not fit in one page.
+    ///
+    /// Parameters:
+    /// - `key`: key bytes, 1 to `KV_MAX_KEY_SIZE` long.
+    /// - `value`: value bytes, at most `KV_MAX_VALUE_SIZE` long.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_storage::MemoryBackend;
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 24000 }>::new().with_kv_store(4).unwrap();
+    /// assert!(backend.kv_put(b"boots", &7u32.to_le_bytes()).is_ok());
+    /// assert!(matches!(backend.kv_get(b"boots"), Ok(Some(value)) if value.as_bytes() == 7u32.to_le_bytes()));
+    /// ```
+    pub fn kv_put(&mut self, key: &[u8], value: &[u8]) -> Result<(), StorageError> {
+        let range = self.kv_range()?;
+        kv_store::put(&mut self.storage[range], key, value)
+    }
+
+    /// Removes the value stored for `key`; removing a missing key succeeds.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: no key-value store is configured.
+    /// - `KvInvalidEntry`: the key is empty or too long.
+    /// - `KvFull`: the live entries do not fit in one page.
+    ///
+    /// Parameters:
+    /// - `key`: key bytes.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_storage::MemoryBackend;
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 24000 }>::new().with_kv_store(4).unwrap();
+    /// assert!(backend.kv_put(b"peer", &[1, 2]).is_ok());
+    /// assert!(backend.kv_delete(b"peer").is_ok());
+    /// assert!(matches!(backend.kv_get(b"peer"), Ok(None)));
+    /// ```
+    pub fn kv_delete(&mut self, key: &[u8]) -> Result<(), StorageError> {
+        let range = self.kv_range()?;
+        kv_store::delete(&mut self.storage[range], key)
+    }
 
-        if let Some(chain_configuration) = &record.chain_configuration {
-            let bytes = chain_configuration.as_bytes();
-            out[CHAIN_CONFIG_OFFSET..CHAIN_CONFIG_OFFSET + bytes.len()].copy_from_slice(bytes);
+    fn kv_range(&self) -> Result<Range<usize>, StorageError> {
+        if self.kv_pages == 0 {
+            return Err(StorageError::InvalidConfiguration);
         }
+        Ok(STORAGE_SIZE - self.kv_pages * KV_PAGE_SIZE..STORAGE_SIZE)
+    }
 
-        let crc = Self::crc32(&out[..CONTROL_CRC32_OFFSET]);
-        out[CONTROL_CRC32_OFFSET..CONTROL_CRC32_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
-        out
+    /// Protects `slots` against overwrites: `save_block` to a protected slot
+    /// that holds a block fails with `SlotOccupied`.
+    ///
+    /// Parameters:
+    /// - `slots`: protected storage indices (`WriteOnceSlots::ALL` for every slot).
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::{Block, MAX_BLOCK_SIZE};
+    /// use moonblokz_storage::{MemoryBackend, StorageError, StorageTrait, WriteOnceSlots};
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new().with_write_once(WriteOnceSlots::ALL);
+    /// let mut bytes = [0u8; MAX_BLOCK_SIZE];
+    /// bytes[0] = 1;
+    /// let block = Block::from_bytes(&bytes).unwrap();
+    /// assert!(backend.save_block(0, &block).is_ok());
+    /// assert!(matches!(backend.save_block(0, &block), Err(StorageError::SlotOccupied)));
+    /// ```
+    pub fn with_write_once(mut self, slots: WriteOnceSlots) -> Self {
+        self.write_once = slots;
+        self
     }
 
-    fn deserialize_record(
-        bytes: &[u8; CONTROL_PLANE_ENTRY_SIZE],
-    ) -> Result<ControlPlaneData, StorageError> {
-        if bytes.iter().all(|value| *value == 0) {
-            return Err(StorageError::ControlPlaneUninitialized);
-        }
+    /// Saves `block` to `storage_index` even when the slot is write-once and
+    /// occupied; use it for deliberate history rewrites only.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `storage_index` is outside the slot range.
+    /// - `BackendIo { code: 1 }`: block bytes exceed `MAX_BLOCK_SIZE`.
+    ///
+    /// Parameters:
+    /// - `storage_index`: destination slot index.
+    /// - `block`: block to persist.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::{Block, MAX_BLOCK_SIZE};
+    /// use moonblokz_storage::{MemoryBackend, StorageTrait, WriteOnceSlots};
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new().with_write_once(WriteOnceSlots::ALL);
+    /// let mut bytes = [0u8; MAX_BLOCK_SIZE];
+    /// bytes[0] = 1;
+    /// let block = Block::from_bytes(&bytes).unwrap();
+    /// assert!(backend.save_block(0, &block).is_ok());
+    /// assert!(backend.force_overwrite(0, &block).is_ok());
+    /// ```
+    pub fn force_overwrite(&mut self, storage_index: StorageIndex, block: &Block) -> Result<(), StorageError> {
+        self.write_block(storage_index, block, WriteCondition::Any, false)
+    }
 
-        let mut stored_crc_bytes = [0u8; 4];
-        stored_crc_bytes.copy_from_slice(&bytes[CONTROL_CRC32_OFFSET..CONTROL_CRC32_OFFSET + 4]);
-        let stored_crc = u32::from_le_bytes(stored_crc_bytes);
-        let computed_crc = Self::crc32(&bytes[..CONTROL_CRC32_OFFSET]);
-        if stored_crc != computed_crc {
-            return Err(StorageError::ControlPlaneCorrupted);
+    /// Replaces the block in `storage_index` only if the slot still holds the
+    /// block hashing to `expected_hash`.
+    ///
+    /// Write-once slots still refuse the replacement with `SlotOccupied`.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `storage_index` is outside the slot range.
+    /// - `Conflict`: the slot is empty or holds another block; `current_hash`
+    ///   reports what it holds.
+    /// - `SlotOccupied`: the slot is write-once.
+    /// - `BackendIo { code: 1 }`: block bytes exceed `MAX_BLOCK_SIZE`.
+    ///
+    /// Parameters:
+    /// - `storage_index`: slot to replace.
+    /// - `expected_hash`: `block_hash` of the block the replacement was validated against.
+    /// - `block`: replacement block.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::{Block, MAX_BLOCK_SIZE};
+    /// use moonblokz_storage::{MemoryBackend, StorageError, StorageTrait, block_hash};
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// let mut bytes = [0u8; MAX_BLOCK_SIZE];
+    /// bytes[0] = 1;
+    /// let old = Block::from_bytes(&bytes).unwrap();
+    /// bytes[1] = 2;
+    /// let new = Block::from_bytes(&bytes).unwrap();
+    /// assert!(backend.save_block(0, &old).is_ok());
+    /// assert!(backend.replace_block(0, &block_hash(&old), &new).is_ok());
+    /// assert!(matches!(
+    ///     backend.replace_block(0, &block_hash(&old), &new),
+    ///     Err(StorageError::Conflict { current_hash: Some(hash) }) if hash == block_hash(&new)
+    /// ));
+    /// ```
+    pub fn replace_block(
+        &mut self,
+        storage_index: StorageIndex,
+        expected_hash: &[u8; HASH_SIZE],
+        block: &Block,
+    ) -> Result<(), StorageError> {
+        let write_once = self.write_once.contains(storage_index);
+        self.write_block(storage_index, block, WriteCondition::Holds(*expected_hash), write_once)
+    }
+
+    /// Saves `block` to `storage_index` only if the slot is empty.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `storage_index` is outside the slot range.
+    /// - `Conflict`: the slot holds a block; `current_hash` is its hash.
+    /// - `BackendIo { code: 1 }`: block bytes exceed `MAX_BLOCK_SIZE`.
+    ///
+    /// Parameters:
+    /// - `storage_index`: destination slot index.
+    /// - `block`: block to persist.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::{Block, MAX_BLOCK_SIZE};
+    /// use moonblokz_storage::{MemoryBackend, StorageError};
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// let mut bytes = [0u8; MAX_BLOCK_SIZE];
+    /// bytes[0] = 1;
+    /// let block = Block::from_bytes(&bytes).unwrap();
+    /// assert!(backend.insert_block(2, &block).is_ok());
+    /// assert!(matches!(backend.insert_block(2, &block), Err(StorageError::Conflict { .. })));
+    /// ```
+    pub fn insert_block(&mut self, storage_index: StorageIndex, block: &Block) -> Result<(), StorageError> {
+        self.write_block(storage_index, block, WriteCondition::Empty, false)
+    }
+
+    /// Returns the metadata attached to the block in `storage_index`, `None`
+    /// when none was written since the block was saved.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `storage_index` is outside the slot range.
+    /// - `BlockAbsent`: the slot holds no block.
+    ///
+    /// Parameters:
+    /// - `storage_index`: slot to read.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::{Block, MAX_BLOCK_SIZE};
+    /// use moonblokz_storage::{MemoryBackend, StorageError, StorageTrait};
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// assert!(matches!(backend.read_slot_meta(0), Err(StorageError::BlockAbsent)));
+    /// let mut bytes = [0u8; MAX_BLOCK_SIZE];
+    /// bytes[0] = 1;
+    /// let block = Block::from_bytes(&bytes).unwrap();
+    /// assert!(backend.save_block(0, &block).is_ok());
+    /// assert!(matches!(backend.read_slot_meta(0), Ok(None)));
+    /// ```
+    #[cfg(feature = "slot-meta")]
+    pub fn read_slot_meta(&self, storage_index: StorageIndex) -> Result<Option<[u8; SLOT_META_SIZE]>, StorageError> {
+        let (slot_start, slot_end) = self.slot_range(storage_index)?;
+        if self.storage[slot_start] == 0 {
+            return Err(StorageError::BlockAbsent);
+        }
+        if self.storage[slot_end] == 0 {
+            return Ok(None);
         }
+        let mut meta = [0u8; SLOT_META_SIZE];
+        meta.copy_from_slice(&self.storage[slot_end + 1..slot_end + 1 + SLOT_META_SIZE]);
+        Ok(Some(meta))
+    }
 
-        if bytes[VERSION_OFFSET] != CONTROL_PLANE_VERSION {
-            return Err(StorageError::ControlPlaneIncompatible);
+    /// Attaches `meta` to the block in `storage_index` without touching the
+    /// block. Saving a block to the slot clears its metadata.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `storage_index` is outside the slot range.
+    /// - `BlockAbsent`: the slot holds no block.
+    ///
+    /// Parameters:
+    /// - `storage_index`: slot to annotate.
+    /// - `meta`: metadata value.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::{Block, MAX_BLOCK_SIZE};
+    /// use moonblokz_storage::{MemoryBackend, SLOT_META_SIZE, StorageTrait};
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// let mut bytes = [0u8; MAX_BLOCK_SIZE];
+    /// bytes[0] = 1;
+    /// let block = Block::from_bytes(&bytes).unwrap();
+    /// assert!(backend.save_block(0, &block).is_ok());
+    ///
+    /// let mut meta = [0u8; SLOT_META_SIZE];
+    /// meta[..4].copy_from_slice(&1_700_000_000u32.to_le_bytes());
+    /// assert!(backend.write_slot_meta(0, &meta).is_ok());
+    /// assert!(matches!(backend.read_slot_meta(0), Ok(Some(value)) if value == meta));
+    /// ```
+    #[cfg(feature = "slot-meta")]
+    pub fn write_slot_meta(
+        &mut self,
+        storage_index: StorageIndex,
+        meta: &[u8; SLOT_META_SIZE],
+    ) -> Result<(), StorageError> {
+        let (slot_start, slot_end) = self.slot_range(storage_index)?;
+        if self.storage[slot_start] == 0 {
+            return Err(StorageError::BlockAbsent);
         }
+        self.storage[slot_end] = 1;
+        self.storage[slot_end + 1..slot_end + 1 + SLOT_META_SIZE].copy_from_slice(meta);
+        Ok(())
+    }
 
-        if bytes[PRIVATE_KEY_SIZE_OFFSET] as usize != PRIVATE_KEY_SIZE {
-            return Err(StorageError::ControlPlaneIncompatible);
+    /// Returns the `block_hash` of the block in a slot, `None` when it is empty.
+    fn slot_hash(&self, slot_start: usize, slot_end: usize) -> Option<[u8; HASH_SIZE]> {
+        let slot = &self.storage[slot_start..slot_end];
+        if slot[0] == 0 {
+            return None;
         }
+        // Saved blocks always parse; raw bytes are hashed as a fallback.
+        Some(Block::from_bytes(slot).map_or_else(|_| calculate_hash(slot), |block| block_hash(&block)))
+    }
 
-        if bytes[INIT_PARAMS_SIZE_OFFSET] as usize != INIT_PARAMS_SIZE {
-            return Err(StorageError::ControlPlaneIncompatible);
+    fn write_block(
+        &mut self,
+        storage_index: StorageIndex,
+        block: &Block,
+        condition: WriteCondition,
+        write_once: bool,
+    ) -> Result<(), StorageError> {
+        let (slot_start, slot_end) = self.slot_range(storage_index)?;
+        condition.check(self.slot_hash(slot_start, slot_end), write_once)?;
+
+        let block_bytes = block.as_bytes();
+        if block_bytes.len() > MAX_BLOCK_SIZE {
+            return Err(StorageError::BackendIo { code: 1 });
         }
 
-        let mut max_block_size_bytes = [0u8; 2];
-        max_block_size_bytes
-            .copy_from_slice(&bytes[MAX_BLOCK_SIZE_OFFSET..MAX_BLOCK_SIZE_OFFSET + 2]);
-        let persisted_max_block_size = u16::from_le_bytes(max_block_size_bytes) as usize;
-        if persisted_max_block_size != MAX_BLOCK_SIZE {
-            return Err(StorageError::ControlPlaneIncompatible);
+        // Clears the metadata of the previous block as well.
+        self.storage[slot_start..slot_start + SLOT_STRIDE].fill(0);
+        let write_end = slot_start + block_bytes.len();
+        self.storage[slot_start..write_end].copy_from_slice(block_bytes);
+        self.observer.on_slot_written(storage_index);
+        self.observer.on_slot_changed(storage_index, Some(&block_hash(block)));
+        Ok(())
+    }
+
+    fn control_plane_reserved_bytes(&self) -> usize {
+        self.control_plane_layout.replica_count * CONTROL_PLANE_ENTRY_SIZE
+    }
+
+    /// Bytes taken by counters and the key-value store at the end of the array.
+    fn page_area_bytes(&self) -> usize {
+        (self.kv_pages + self.counters * COUNTER_PAGES) * KV_PAGE_SIZE
+    }
+
+    /// Byte range of all counter pages, just before the key-value store.
+    fn counter_range(&self) -> core::ops::Range<usize> {
+        let start = STORAGE_SIZE - self.page_area_bytes();
+        start..start + self.counters * COUNTER_PAGES * KV_PAGE_SIZE
+    }
+
+    fn max_storage_slots(&self) -> StorageIndex {
+        let reserved = self.control_plane_reserved_bytes() + self.page_area_bytes();
+        (STORAGE_SIZE.saturating_sub(reserved) / SLOT_STRIDE) as StorageIndex
+    }
+
+    fn slot_range(&self, storage_index: StorageIndex) -> Result<(usize, usize), StorageError> {
+        if storage_index >= self.max_storage_slots() {
+            return Err(StorageError::InvalidIndex);
         }
 
-        let mut private_key = [0u8; PRIVATE_KEY_SIZE];
-        private_key.copy_from_slice(&bytes[PRIVATE_KEY_OFFSET..PRIVATE_KEY_OFFSET + PRIVATE_KEY_SIZE]);
+        let slot_start = self.control_plane_reserved_bytes() + storage_index as usize * SLOT_STRIDE;
+        let slot_end = slot_start + MAX_BLOCK_SIZE;
+        Ok((slot_start, slot_end))
+    }
 
-        let mut own_node_id_bytes = [0u8; 4];
-        own_node_id_bytes.copy_from_slice(&bytes[OWN_NODE_ID_OFFSET..OWN_NODE_ID_OFFSET + 4]);
-        let own_node_id = u32::from_le_bytes(own_node_id_bytes);
+    fn control_plane_entry_offset(replica_index: usize) -> usize {
+        replica_index * CONTROL_PLANE_ENTRY_SIZE
+    }
 
-        let mut init_params = [0u8; INIT_PARAMS_SIZE];
-        init_params.copy_from_slice(&bytes[INIT_PARAMS_OFFSET..INIT_PARAMS_OFFSET + INIT_PARAMS_SIZE]);
+    fn read_control_plane_entry(&self, replica_index: usize) -> Zeroizing<[u8; CONTROL_PLANE_ENTRY_SIZE]> {
+        let start = Self::control_plane_entry_offset(replica_index);
+        let end = start + CONTROL_PLANE_ENTRY_SIZE;
+        let mut out = Zeroizing::new([0u8; CONTROL_PLANE_ENTRY_SIZE]);
+        out.copy_from_slice(&self.storage[start..end]);
+        out
+    }
 
-        let chain_configuration = if bytes[CHAIN_CONFIG_OFFSET] == 0 {
-            None
-        } else {
-            let mut value = [0u8; MAX_BLOCK_SIZE];
-            value.copy_from_slice(&bytes[CHAIN_CONFIG_OFFSET..CHAIN_CONFIG_OFFSET + MAX_BLOCK_SIZE]);
-            Some(Block::from_bytes(&value).map_err(|_| StorageError::ControlPlaneCorrupted)?)
-        };
+    fn write_control_plane_entry(
+        &mut self,
+        replica_index: usize,
+        entry: &[u8; CONTROL_PLANE_ENTRY_SIZE],
+    ) {
+        let start = Self::control_plane_entry_offset(replica_index);
+        let end = start + CONTROL_PLANE_ENTRY_SIZE;
+        self.storage[start..end].copy_from_slice(entry);
+    }
 
-        Ok(ControlPlaneData {
-            private_key,
-            own_node_id,
-            init_params,
-            chain_configuration,
-        })
+    fn serialize_record(
+        &self,
+        record: &ControlPlaneData,
+    ) -> Result<Zeroizing<[u8; CONTROL_PLANE_ENTRY_SIZE]>, StorageError> {
+        control_plane::encode_entry(record, &self.key_protection)
+    }
+
+    fn deserialize_record(
+        &self,
+        bytes: &[u8; CONTROL_PLANE_ENTRY_SIZE],
+    ) -> Result<DecodedEntry, StorageError> {
+        control_plane::decode_entry(bytes, &self.key_protection)
     }
 
     fn load_primary_record_and_repair(&mut self) -> Result<ControlPlaneData, StorageError> {
         let mut first_valid_index: Option<usize> = None;
         let mut first_valid_record: Option<ControlPlaneData> = None;
-        let mut invalid_indexes = [usize::MAX; CONTROL_PLANE_COUNT];
+        let replica_count = self.control_plane_layout.replica_count;
+        let mut invalid_indexes = [usize::MAX; MAX_CONTROL_PLANE_COUNT];
         let mut invalid_len = 0usize;
         let mut saw_non_zero = false;
         let mut saw_incompatible = false;
         let mut saw_corrupted = false;
+        let mut saw_unwrap_failure = false;
+        let mut needs_upgrade = false;
 
         let mut index = 0usize;
-        while index < CONTROL_PLANE_COUNT {
+        while index < replica_count {
             let entry = self.read_control_plane_entry(index);
             if entry.iter().any(|value| *value != 0) {
                 saw_non_zero = true;
             }
 
-            match Self::deserialize_record(&entry) {
-                Ok(record) => {
+            match self.deserialize_record(&entry) {
+                Ok(decoded) => {
                     if first_valid_index.is_none() {
                         first_valid_index = Some(index);
-                        first_valid_record = Some(record);
+                        needs_upgrade = decoded.needs_upgrade;
+                        first_valid_record = Some(decoded.record);
                     }
                 }
                 Err(err) => {
@@ -261,6 +701,9 @@ impl<const STORAGE_SIZE: usize> MemoryBackend<STORAGE_SIZE> {
                     if matches!(err, StorageError::ControlPlaneCorrupted) {
                         saw_corrupted = true;
                     }
+                    if matches!(err, StorageError::KeyUnwrapFailed) {
+                        saw_unwrap_failure = true;
+                    }
                     invalid_indexes[invalid_len] = index;
                     invalid_len += 1;
                 }
@@ -274,6 +717,9 @@ impl<const STORAGE_SIZE: usize> MemoryBackend<STORAGE_SIZE> {
                 if !saw_non_zero {
                     return Err(StorageError::ControlPlaneUninitialized);
                 }
+                if saw_unwrap_failure {
+                    return Err(StorageError::KeyUnwrapFailed);
+                }
                 if saw_incompatible {
                     return Err(StorageError::ControlPlaneIncompatible);
                 }
@@ -284,12 +730,18 @@ impl<const STORAGE_SIZE: usize> MemoryBackend<STORAGE_SIZE> {
             }
         };
 
-        let encoded = Self::serialize_record(&record);
+        if needs_upgrade {
+            self.write_record_to_all_replicas(&record)?;
+            return Ok(record);
+        }
+
+        let encoded = self.serialize_record(&record)?;
         let mut repair_index = 0usize;
         while repair_index < invalid_len {
             let target = invalid_indexes[repair_index];
             if Some(target) != first_valid_index {
                 self.write_control_plane_entry(target, &encoded);
+                self.observer.on_replica_repaired(target);
             }
             repair_index += 1;
         }
@@ -297,24 +749,568 @@ impl<const STORAGE_SIZE: usize> MemoryBackend<STORAGE_SIZE> {
         Ok(record)
     }
 
-    fn write_record_to_all_replicas(&mut self, record: &ControlPlaneData) {
-        let encoded = Self::serialize_record(record);
+    /// Streams the backend contents into `sink` as a portable archive.
+    ///
+    /// See `archive` for the format; empty slots are skipped.
+    ///
+    /// Parameters:
+    /// - `options`: export options (`include_control_data` adds control data, `include_private_key`
+    ///   also the plaintext private key).
+    /// - `sink`: archive destination.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_crypto::PRIVATE_KEY_SIZE;
+    /// use moonblokz_storage::archive::{ExportOptions, SliceSink};
+    /// use moonblokz_storage::{INIT_PARAMS_SIZE, MemoryBackend, StorageTrait};
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// assert!(backend.init([1u8; PRIVATE_KEY_SIZE], 7, [0u8; INIT_PARAMS_SIZE]).is_ok());
+    /// let mut buffer = [0u8; 1024];
+    /// let mut sink = SliceSink::new(&mut buffer);
+    /// let options = ExportOptions { include_control_data: true, include_private_key: true };
+    /// assert!(backend.export_to(options, &mut sink).is_ok());
+    /// ```
+    pub fn export_to(
+        &mut self,
+        options: ExportOptions,
+        sink: &mut dyn ByteSink,
+    ) -> Result<ArchiveSummary, StorageError> {
+        export_archive(self, self.max_storage_slots(), options, sink)
+    }
+
+    /// Restores a portable archive from `source`.
+    ///
+    /// The whole archive is verified before anything is written, so `source`
+    /// is read twice; archives carrying control data then re-initialize the
+    /// backend first.
+    ///
+    /// Parameters:
+    /// - `source`: archive bytes.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_crypto::PRIVATE_KEY_SIZE;
+    /// use moonblokz_storage::archive::{ExportOptions, SliceSink, SliceSource};
+    /// use moonblokz_storage::{INIT_PARAMS_SIZE, MemoryBackend, StorageTrait};
+    ///
+    /// let mut source = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// assert!(source.init([1u8; PRIVATE_KEY_SIZE], 7, [0u8; INIT_PARAMS_SIZE]).is_ok());
+    /// let mut buffer = [0u8; 1024];
+    /// let mut sink = SliceSink::new(&mut buffer);
+    /// let options = ExportOptions { include_control_data: true, include_private_key: true };
+    /// assert!(source.export_to(options, &mut sink).is_ok());
+    /// let len = sink.written().len();
+    ///
+    /// let mut target = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// assert!(target.import_from(&mut SliceSource::new(&buffer[..len])).is_ok());
+    /// assert!(target.load_control_data().is_ok());
+    /// ```
+    pub fn import_from(&mut self, source: &mut dyn ByteSource) -> Result<ArchiveSummary, StorageError> {
+        import_archive(self, self.max_storage_slots(), source)
+    }
+
+    /// Returns the lowest storage index holding a block with header `sequence`.
+    ///
+    /// The memory backend keeps no index: every lookup scans the slots, which
+    /// is cheap in RAM and cannot fail. The `Result` matches
+    /// `Rp2040Backend::find_by_sequence`, so callers handle both alike.
+    ///
+    /// Parameters:
+    /// - `sequence`: `BlockHeader::sequence` to look up.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::{Block, MAX_BLOCK_SIZE};
+    /// use moonblokz_storage::{MemoryBackend, StorageTrait};
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// let mut bytes = [0u8; MAX_BLOCK_SIZE];
+    /// bytes[0] = 1;
+    /// bytes[1] = 9;
+    /// let block = Block::from_bytes(&bytes).unwrap();
+    /// assert!(backend.save_block(1, &block).is_ok());
+    /// assert!(matches!(backend.find_by_sequence(block.header().sequence), Ok(Some(1))));
+    /// ```
+    pub fn find_by_sequence(&self, sequence: u32) -> Result<Option<StorageIndex>, StorageError> {
+        sequence_index::find_by_sequence(self, sequence)
+    }
+
+    /// Returns the highest sequence number stored, i.e. the chain tip.
+    ///
+    /// Like `find_by_sequence`, it never returns an error here.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_storage::MemoryBackend;
+    ///
+    /// let backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// assert!(matches!(backend.max_sequence(), Ok(None)));
+    /// ```
+    pub fn max_sequence(&self) -> Result<Option<u32>, StorageError> {
+        sequence_index::max_sequence(self)
+    }
+
+    /// Calls `on_block` with every stored sequence number in `range` and the
+    /// storage index `find_by_sequence` returns for it, in ascending order.
+    ///
+    /// Parameters:
+    /// - `range`: sequence numbers to report.
+    /// - `on_block`: callback receiving `(sequence, storage_index)`.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_storage::MemoryBackend;
+    ///
+    /// let backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// let mut count = 0;
+    /// assert!(backend.range_by_sequence(0..100, &mut |_sequence, _storage_index| count += 1).is_ok());
+    /// assert_eq!(count, 0);
+    /// ```
+    pub fn range_by_sequence(
+        &self,
+        range: Range<u32>,
+        on_block: &mut dyn FnMut(u32, StorageIndex),
+    ) -> Result<(), StorageError> {
+        sequence_index::range_by_sequence(self, range, on_block)
+    }
+
+    /// Loads `tree` with one leaf per block slot: `block_hash` of an occupied
+    /// slot, `EMPTY_LEAF` for an empty or unparsable one.
+    ///
+    /// Load a `MerkleObserver` with this at boot to keep the tree in step
+    /// with later writes.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: the backend has more slots than `LEAVES`.
+    ///
+    /// Parameters:
+    /// - `tree`: tree to load.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::{Block, MAX_BLOCK_SIZE};
+    /// use moonblokz_storage::merkle::MerkleTree;
+    /// use moonblokz_storage::{MemoryBackend, StorageTrait};
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// let mut tree = MerkleTree::<16>::new();
+    /// assert!(backend.build_merkle_tree(&mut tree).is_ok());
+    /// let mut bytes = [0u8; MAX_BLOCK_SIZE];
+    /// bytes[0] = 1;
+    /// let block = Block::from_bytes(&bytes).unwrap();
+    /// assert!(backend.save_block(1, &block).is_ok());
+    /// assert!(tree.record_block(1, &block).is_ok());
+    ///
+    /// let mut rebuilt = MerkleTree::<16>::new();
+    /// assert!(backend.build_merkle_tree(&mut rebuilt).is_ok());
+    /// assert_eq!(rebuilt.root(), tree.root());
+    /// ```
+    pub fn build_merkle_tree<const LEAVES: usize>(&self, tree: &mut MerkleTree<LEAVES>) -> Result<(), StorageError> {
+        tree.load(self.max_storage_slots(), &mut |storage_index| {
+            let (slot_start, slot_end) = self.slot_range(storage_index)?;
+            let slot = &self.storage[slot_start..slot_end];
+            if slot[0] == 0 {
+                return Ok(EMPTY_LEAF);
+            }
+            Ok(Block::from_bytes(slot).map_or(EMPTY_LEAF, |block| block_hash(&block)))
+        })
+    }
+
+    /// Scans all control-plane replicas and block slots for anomalies.
+    ///
+    /// Empty slots are all-zero; an empty slot (first byte zero) with stray
+    /// non-zero bytes is reported as a padding anomaly. In repair mode, invalid
+    /// replicas are rewritten from the primary replica and broken slots are
+    /// zero-filled.
+    ///
+    /// Parameters:
+    /// - `options`: scan options (`repair` enables rewrites).
+    /// - `on_finding`: callback invoked for every detected anomaly.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_crypto::PRIVATE_KEY_SIZE;
+    /// use moonblokz_storage::{FsckOptions, INIT_PARAMS_SIZE, MemoryBackend, StorageTrait};
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// assert!(backend.init([1u8; PRIVATE_KEY_SIZE], 7, [0u8; INIT_PARAMS_SIZE]).is_ok());
+    /// let report = backend.fsck(FsckOptions::default(), &mut |_finding| {});
+    /// assert!(report.is_clean());
+    /// ```
+    pub fn fsck(
+        &mut self,
+        options: FsckOptions,
+        on_finding: &mut dyn FnMut(&FsckFinding),
+    ) -> FsckReport {
+        let mut report = FsckReport::default();
+        self.fsck_control_plane(options, &mut report, on_finding);
+
+        let mut storage_index: StorageIndex = 0;
+        while storage_index < self.max_storage_slots() {
+            report.slots_checked += 1;
+            let (slot_start, slot_end) = match self.slot_range(storage_index) {
+                Ok(value) => value,
+                Err(_) => break,
+            };
+            let slot = &self.storage[slot_start..slot_end];
+            let issue = if slot[0] == 0 {
+                if slot.iter().any(|value| *value != 0) {
+                    Some(FsckIssue::SlotPaddingAnomaly { storage_index })
+                } else {
+                    None
+                }
+            } else if Block::from_bytes(slot).is_err() {
+                Some(FsckIssue::SlotIntegrityFailure { storage_index })
+            } else {
+                report.slots_occupied += 1;
+                None
+            };
+
+            if let Some(issue) = issue {
+                if options.repair {
+                    self.storage[slot_start..slot_end].fill(0);
+                }
+                self.observer.on_slot_changed(storage_index, None);
+                report.record(issue, options.repair, on_finding);
+            }
+            storage_index += 1;
+        }
+
+        report
+    }
+
+    /// Replaces the persisted private key, keeping node id, init params,
+    /// chain configuration and all blocks.
+    ///
+    /// Parameters:
+    /// - `private_key`: new node private key bytes (wiped before returning).
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_crypto::PRIVATE_KEY_SIZE;
+    /// use moonblokz_storage::{INIT_PARAMS_SIZE, MemoryBackend, StorageTrait};
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// assert!(backend.init([1u8; PRIVATE_KEY_SIZE], 7, [0u8; INIT_PARAMS_SIZE]).is_ok());
+    /// assert!(backend.rotate_private_key([2u8; PRIVATE_KEY_SIZE]).is_ok());
+    /// ```
+    pub fn rotate_private_key(&mut self, mut private_key: [u8; PRIVATE_KEY_SIZE]) -> Result<(), StorageError> {
+        let result = self.update_record(|record| record.private_key = private_key);
+        private_key.zeroize();
+        result
+    }
+
+    /// Replaces the persisted own node id, keeping all other control data and blocks.
+    ///
+    /// Parameters:
+    /// - `own_node_id`: new local node identifier.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_crypto::PRIVATE_KEY_SIZE;
+    /// use moonblokz_storage::{INIT_PARAMS_SIZE, MemoryBackend, StorageTrait};
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// assert!(backend.init([1u8; PRIVATE_KEY_SIZE], 7, [0u8; INIT_PARAMS_SIZE]).is_ok());
+    /// assert!(backend.set_own_node_id(8).is_ok());
+    /// ```
+    pub fn set_own_node_id(&mut self, own_node_id: u32) -> Result<(), StorageError> {
+        self.update_record(|record| record.own_node_id = own_node_id)
+    }
+
+    /// Replaces the persisted init params, keeping all other control data and blocks.
+    ///
+    /// Parameters:
+    /// - `init_params`: new free-form control-plane initialization bytes.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_crypto::PRIVATE_KEY_SIZE;
+    /// use moonblokz_storage::{INIT_PARAMS_SIZE, MemoryBackend, StorageTrait};
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// assert!(backend.init([1u8; PRIVATE_KEY_SIZE], 7, [0u8; INIT_PARAMS_SIZE]).is_ok());
+    /// assert!(backend.update_init_params([3u8; INIT_PARAMS_SIZE]).is_ok());
+    /// ```
+    pub fn update_init_params(&mut self, init_params: [u8; INIT_PARAMS_SIZE]) -> Result<(), StorageError> {
+        self.update_record(|record| record.init_params = init_params)
+    }
+
+    /// Rewrites every control-plane replica with the private key protected by
+    /// `protection`, and keeps `protection` for later loads.
+    ///
+    /// The record is loaded with the current protection first, so this is
+    /// the explicit way to wrap a plaintext key, change the wrapping key or
+    /// go back to plaintext. On error the current protection is kept.
+    ///
+    /// Errors:
+    /// - any error of `load_control_data` under the current protection.
+    ///
+    /// Parameters:
+    /// - `protection`: new key protection mode.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_crypto::PRIVATE_KEY_SIZE;
+    /// use moonblokz_storage::{INIT_PARAMS_SIZE, KeyProtection, MemoryBackend, StorageTrait};
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// assert!(backend.init([1u8; PRIVATE_KEY_SIZE], 7, [0u8; INIT_PARAMS_SIZE]).is_ok());
+    /// assert!(backend.set_key_protection(KeyProtection::Plaintext).is_ok());
+    /// ```
+    pub fn set_key_protection(&mut self, protection: KeyProtection) -> Result<(), StorageError> {
+        let record = self.load_primary_record_and_repair()?;
+        let previous = core::mem::replace(&mut self.key_protection, protection);
+        let result = self.write_record_to_all_replicas(&record);
+        if result.is_err() {
+            self.key_protection = previous;
+        }
+        result
+    }
+
+    fn update_record(&mut self, update: impl FnOnce(&mut ControlPlaneData)) -> Result<(), StorageError> {
+        let mut record = self.load_primary_record_and_repair()?;
+        update(&mut record);
+        self.write_record_to_all_replicas(&record)
+    }
+
+    /// Wipes all control-plane replicas and block slots.
+    ///
+    /// The backing array is zeroized with volatile writes, leaving the backend
+    /// in the `ControlPlaneUninitialized` state until the next `init`.
+    ///
+    /// Parameters:
+    /// - `verify`: read every byte back and fail if any is non-zero.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_crypto::PRIVATE_KEY_SIZE;
+    /// use moonblokz_storage::{INIT_PARAMS_SIZE, MemoryBackend, StorageError, StorageTrait};
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// assert!(backend.init([1u8; PRIVATE_KEY_SIZE], 7, [0u8; INIT_PARAMS_SIZE]).is_ok());
+    /// assert!(backend.decommission(true).is_ok());
+    /// assert!(matches!(backend.load_control_data(), Err(StorageError::ControlPlaneUninitialized)));
+    /// ```
+    pub fn decommission(&mut self, verify: bool) -> Result<(), StorageError> {
+        self.storage.zeroize();
+        self.observer.on_slots_reset(self.max_storage_slots());
+        if verify && self.storage.iter().any(|value| *value != 0) {
+            return Err(StorageError::BackendIo { code: 3 });
+        }
+        Ok(())
+    }
+
+    /// Reports the status of every control-plane replica.
+    ///
+    /// Runs the replica selection of `load_control_data`: the first replica
+    /// that decodes is the primary. Unless `read_only` is set, replicas that
+    /// are not `Valid` are rewritten from the primary. This repairs more than
+    /// `load_control_data`, which only rewrites replicas that fail to decode and
+    /// leaves a decodable replica that differs from the primary untouched.
+    /// It never fails here; the `Result` matches `Rp2040Backend`.
+    ///
+    /// Parameters:
+    /// - `read_only`: diagnose only, never write.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_crypto::PRIVATE_KEY_SIZE;
+    /// use moonblokz_storage::{INIT_PARAMS_SIZE, MemoryBackend, StorageTrait};
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// assert!(backend.init([1u8; PRIVATE_KEY_SIZE], 7, [0u8; INIT_PARAMS_SIZE]).is_ok());
+    /// let health = backend.control_plane_health(true).unwrap();
+    /// assert!(health.is_healthy());
+    /// assert_eq!(health.primary_replica, Some(0));
+    /// ```
+    pub fn control_plane_health(&mut self, read_only: bool) -> Result<ControlPlaneHealth, StorageError> {
+        let replica_count = self.control_plane_layout.replica_count;
+        let mut health = ControlPlaneHealth::new(replica_count);
+        let mut primary: Option<Zeroizing<[u8; CONTROL_PLANE_ENTRY_SIZE]>> = None;
+
+        let mut replica_index = 0usize;
+        while replica_index < replica_count {
+            let entry = self.read_control_plane_entry(replica_index);
+            let decoded = self.deserialize_record(&entry).map(|_| ());
+            health.classify(replica_index, entry, decoded, &mut primary);
+            replica_index += 1;
+        }
+
+        if let (Some(primary_entry), false) = (&primary, read_only) {
+            let mut replica_index = 0usize;
+            while replica_index < replica_count {
+                if health.replicas[replica_index] != ReplicaStatus::Valid {
+                    self.write_control_plane_entry(replica_index, primary_entry);
+                    self.observer.on_replica_repaired(replica_index);
+                    health.repaired = true;
+                }
+                replica_index += 1;
+            }
+        }
+        Ok(health)
+    }
+
+    fn fsck_control_plane(
+        &mut self,
+        options: FsckOptions,
+        report: &mut FsckReport,
+        on_finding: &mut dyn FnMut(&FsckFinding),
+    ) {
+        let replica_count = self.control_plane_layout.replica_count;
+        let mut issues: [Option<FsckIssue>; MAX_CONTROL_PLANE_COUNT] = [None; MAX_CONTROL_PLANE_COUNT];
+        let mut primary: Option<Zeroizing<[u8; CONTROL_PLANE_ENTRY_SIZE]>> = None;
+
+        let mut replica_index = 0usize;
+        while replica_index < replica_count {
+            report.replicas_checked += 1;
+            let entry = self.read_control_plane_entry(replica_index);
+            issues[replica_index] = match self.deserialize_record(&entry) {
+                Ok(_) | Err(StorageError::KeyUnwrapFailed) => {
+                    report.replicas_valid += 1;
+                    match &primary {
+                        None => {
+                            primary = Some(entry);
+                            None
+                        }
+                        Some(primary_entry) if **primary_entry != *entry => {
+                            Some(FsckIssue::ReplicaDisagreement { replica_index })
+                        }
+                        Some(_) => None,
+                    }
+                }
+                Err(StorageError::ControlPlaneUninitialized) => {
+                    Some(FsckIssue::ReplicaErased { replica_index })
+                }
+                Err(StorageError::ControlPlaneIncompatible) => {
+                    Some(FsckIssue::ReplicaIncompatible { replica_index })
+                }
+                Err(_) => Some(FsckIssue::ReplicaCorrupted { replica_index }),
+            };
+            replica_index += 1;
+        }
+
+        let all_erased = issues[..replica_count]
+            .iter()
+            .all(|issue| matches!(issue, Some(FsckIssue::ReplicaErased { .. })));
+        if all_erased {
+            return;
+        }
+
+        let mut replica_index = 0usize;
+        while replica_index < replica_count {
+            if let Some(issue) = issues[replica_index] {
+                let repaired = match (&primary, options.repair) {
+                    (Some(primary_entry), true) => {
+                        self.write_control_plane_entry(replica_index, primary_entry);
+                        self.observer.on_replica_repaired(replica_index);
+                        true
+                    }
+                    _ => false,
+                };
+                report.record(issue, repaired, on_finding);
+            }
+            replica_index += 1;
+        }
+    }
+
+    fn write_record_to_all_replicas(&mut self, record: &ControlPlaneData) -> Result<(), StorageError> {
+        let encoded = self.serialize_record(record)?;
         let mut index = 0usize;
-        while index < CONTROL_PLANE_COUNT {
+        while index < self.control_plane_layout.replica_count {
             self.write_control_plane_entry(index, &encoded);
             index += 1;
         }
+        Ok(())
+    }
+}
+
+/// Monotonic counter of a `MemoryBackend`, returned by `MemoryBackend::counter`.
+pub struct MemoryCounter<'a> {
+    pages: &'a mut [u8],
+}
+
+impl MemoryCounter<'_> {
+    /// Returns the counter value, 0 before the first increment.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let boots = backend.counter(0)?.get()?;
+    /// ```
+    pub fn get(&self) -> Result<u64, StorageError> {
+        counter::get(&*self.pages)
+    }
+
+    /// Adds one to the counter and returns the new value.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let nonce = backend.counter(1)?.increment()?;
+    /// ```
+    pub fn increment(&mut self) -> Result<u64, StorageError> {
+        counter::increment(&mut *self.pages)
+    }
+}
+
+impl<const STORAGE_SIZE: usize, O: StorageObserver> SequenceSource for MemoryBackend<STORAGE_SIZE, O> {
+    fn for_each_candidate(
+        &self,
+        visit: &mut dyn FnMut(StorageIndex, u32) -> bool,
+    ) -> Result<(), StorageError> {
+        let mut storage_index: StorageIndex = 0;
+        while storage_index < self.max_storage_slots() {
+            let (slot_start, slot_end) = self.slot_range(storage_index)?;
+            let slot = &self.storage[slot_start..slot_end];
+            if slot[0] != 0 && !visit(storage_index, sequence_index::sequence_from_bytes(slot)) {
+                break;
+            }
+            storage_index += 1;
+        }
+        Ok(())
+    }
+
+    fn holds(&self, storage_index: StorageIndex, sequence: u32) -> Result<bool, StorageError> {
+        let (slot_start, slot_end) = self.slot_range(storage_index)?;
+        let slot = &self.storage[slot_start..slot_end];
+        Ok(slot[0] != 0
+            && matches!(Block::from_bytes(slot), Ok(block) if sequence_index::block_sequence(&block) == sequence))
     }
 }
 
-impl<const STORAGE_SIZE: usize> StorageTrait for MemoryBackend<STORAGE_SIZE> {
+impl<const STORAGE_SIZE: usize, O: StorageObserver> StorageTrait for MemoryBackend<STORAGE_SIZE, O> {
     fn init(
         &mut self,
-        private_key: [u8; PRIVATE_KEY_SIZE],
+        mut private_key: [u8; PRIVATE_KEY_SIZE],
         own_node_id: u32,
         init_params: [u8; INIT_PARAMS_SIZE],
     ) -> Result<(), StorageError> {
-        self.storage.fill(0);
+        self.observer.on_init_started();
+        // Counters must never go down, so they survive re-initialization.
+        let counters = self.counter_range();
+        self.storage[..counters.start].fill(0);
+        self.storage[counters.end..].fill(0);
+        self.observer.on_slots_reset(self.max_storage_slots());
 
         let record = ControlPlaneData {
             private_key,
@@ -322,36 +1318,34 @@ impl<const STORAGE_SIZE: usize> StorageTrait for MemoryBackend<STORAGE_SIZE> {
             init_params,
             chain_configuration: None,
         };
-        self.write_record_to_all_replicas(&record);
+        private_key.zeroize();
+        self.write_record_to_all_replicas(&record)?;
+        self.observer.on_init_finished();
         Ok(())
     }
 
     fn save_block(
         &mut self,
-        storage_index: StorageIndex,
-        block: &Block,
-    ) -> Result<(), StorageError> {
-        let (slot_start, slot_end) = Self::slot_range(storage_index)?;
-
-        let block_bytes = block.as_bytes();
-        if block_bytes.len() > MAX_BLOCK_SIZE {
-            return Err(StorageError::BackendIo { code: 1 });
-        }
-
-        self.storage[slot_start..slot_end].fill(0);
-        let write_end = slot_start + block_bytes.len();
-        self.storage[slot_start..write_end].copy_from_slice(block_bytes);
-        Ok(())
+        storage_index: StorageIndex,
+        block: &Block,
+    ) -> Result<(), StorageError> {
+        let write_once = self.write_once.contains(storage_index);
+        self.write_block(storage_index, block, WriteCondition::Any, write_once)
     }
 
     fn read_block(&self, storage_index: StorageIndex) -> Result<Block, StorageError> {
-        let (slot_start, slot_end) = Self::slot_range(storage_index)?;
+        let (slot_start, slot_end) = self.slot_range(storage_index)?;
         let slot = &self.storage[slot_start..slot_end];
         if slot[0] == 0 {
             return Err(StorageError::BlockAbsent);
         }
 
-        Block::from_bytes(slot).map_err(|_| StorageError::BackendIo { code: 2 })
+        let block = Block::from_bytes(slot).map_err(|_| {
+            self.observer.on_slot_changed(storage_index, None);
+            StorageError::BackendIo { code: 2 }
+        })?;
+        self.observer.on_slot_read(storage_index);
+        Ok(block)
     }
 
     fn set_chain_configuration(&mut self, block: &Block) -> Result<(), StorageError> {
@@ -363,7 +1357,7 @@ impl<const STORAGE_SIZE: usize> StorageTrait for MemoryBackend<STORAGE_SIZE> {
         record.chain_configuration =
             Some(Block::from_bytes(block.as_bytes()).map_err(|_| StorageError::BackendIo { code: 1 })?);
 
-        self.write_record_to_all_replicas(&record);
+        self.write_record_to_all_replicas(&record)?;
         Ok(())
     }
 
@@ -372,19 +1366,57 @@ impl<const STORAGE_SIZE: usize> StorageTrait for MemoryBackend<STORAGE_SIZE> {
 
         Ok(record)
     }
+
+    /// Scans every occupied slot and compares `block_hash` of its block;
+    /// slots that do not parse are skipped. The memory backend keeps no index,
+    /// which is cheap in RAM.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::{Block, MAX_BLOCK_SIZE};
+    /// use moonblokz_storage::{MemoryBackend, StorageTrait, block_hash};
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// let mut bytes = [0u8; MAX_BLOCK_SIZE];
+    /// bytes[0] = 1;
+    /// let block = Block::from_bytes(&bytes).unwrap();
+    /// assert!(backend.save_block(1, &block).is_ok());
+    /// assert!(matches!(backend.find_by_hash(&block_hash(&block)), Ok(Some(1))));
+    /// ```
+    fn find_by_hash(&self, hash: &[u8; HASH_SIZE]) -> Result<Option<StorageIndex>, StorageError> {
+        let mut storage_index: StorageIndex = 0;
+        while storage_index < self.max_storage_slots() {
+            let (slot_start, slot_end) = self.slot_range(storage_index)?;
+            let slot = &self.storage[slot_start..slot_end];
+            if slot[0] != 0 {
+                if let Ok(block) = Block::from_bytes(slot) {
+                    if block_hash(&block) == *hash {
+                        return Ok(Some(storage_index));
+                    }
+                }
+            }
+            storage_index += 1;
+        }
+        Ok(None)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::control_plane::{CRC32_OFFSET, ControlPlanePlacement, VERSION_OFFSET};
+    use crate::merkle::MerkleObserver;
+    use crate::{CONTROL_PLANE_COUNT, CONTROL_PLANE_VERSION};
     use moonblokz_chain_types::HEADER_SIZE;
 
+    const CONTROL_PLANE_RESERVED_BYTES: usize = CONTROL_PLANE_COUNT * CONTROL_PLANE_ENTRY_SIZE;
+
     const TEST_PRIVATE_KEY: [u8; PRIVATE_KEY_SIZE] = [7u8; PRIVATE_KEY_SIZE];
     const TEST_NODE_ID: u32 = 42;
     const TEST_INIT_PARAMS: [u8; INIT_PARAMS_SIZE] = [9u8; INIT_PARAMS_SIZE];
-    const TEST_STORAGE_SIZE_2_SLOTS: usize = CONTROL_PLANE_RESERVED_BYTES + (2 * MAX_BLOCK_SIZE);
-    const TEST_STORAGE_SIZE_3_SLOTS: usize = CONTROL_PLANE_RESERVED_BYTES + (3 * MAX_BLOCK_SIZE);
-    const TEST_STORAGE_SIZE_4_SLOTS: usize = CONTROL_PLANE_RESERVED_BYTES + (4 * MAX_BLOCK_SIZE);
+    const TEST_STORAGE_SIZE_2_SLOTS: usize = CONTROL_PLANE_RESERVED_BYTES + (2 * SLOT_STRIDE);
+    const TEST_STORAGE_SIZE_3_SLOTS: usize = CONTROL_PLANE_RESERVED_BYTES + (3 * SLOT_STRIDE);
+    const TEST_STORAGE_SIZE_4_SLOTS: usize = CONTROL_PLANE_RESERVED_BYTES + (4 * SLOT_STRIDE);
 
     fn block_from_len_and_marker(len: usize, marker: u8) -> Block {
         let mut bytes = [0u8; HEADER_SIZE + 8];
@@ -397,6 +1429,13 @@ mod tests {
         }
     }
 
+    fn block_with_sequence(sequence: u32) -> Block {
+        let mut bytes = [0u8; HEADER_SIZE];
+        bytes[0] = 1;
+        bytes[1..5].copy_from_slice(&sequence.to_le_bytes());
+        Block::from_bytes(&bytes).unwrap_or_else(|_| unreachable!())
+    }
+
     fn expected_slot_bytes(block: &Block) -> [u8; MAX_BLOCK_SIZE] {
         let mut out = [0u8; MAX_BLOCK_SIZE];
         let bytes = block.as_bytes();
@@ -412,6 +1451,113 @@ mod tests {
         backend
     }
 
+    #[derive(Default)]
+    struct CountingObserver {
+        init_started: core::cell::Cell<u32>,
+        init_finished: core::cell::Cell<u32>,
+        slots_written: core::cell::Cell<u32>,
+        slots_read: core::cell::Cell<u32>,
+        replicas_repaired: core::cell::Cell<u32>,
+    }
+
+    impl StorageObserver for CountingObserver {
+        fn on_init_started(&self) {
+            self.init_started.set(self.init_started.get() + 1);
+        }
+
+        fn on_init_finished(&self) {
+            self.init_finished.set(self.init_finished.get() + 1);
+        }
+
+        fn on_slot_written(&self, _storage_index: StorageIndex) {
+            self.slots_written.set(self.slots_written.get() + 1);
+        }
+
+        fn on_slot_read(&self, _storage_index: StorageIndex) {
+            self.slots_read.set(self.slots_read.get() + 1);
+        }
+
+        fn on_replica_repaired(&self, _replica_index: usize) {
+            self.replicas_repaired.set(self.replicas_repaired.get() + 1);
+        }
+    }
+
+    #[test]
+    fn observer_receives_init_write_read_and_repair_events() {
+        let observer = CountingObserver::default();
+        let mut backend = MemoryBackend::<TEST_STORAGE_SIZE_2_SLOTS>::new().with_observer(&observer);
+        assert!(backend
+            .init(TEST_PRIVATE_KEY, TEST_NODE_ID, TEST_INIT_PARAMS)
+            .is_ok());
+        let block = block_from_len_and_marker(HEADER_SIZE, 1);
+        assert!(backend.save_block(0, &block).is_ok());
+        assert!(backend.read_block(0).is_ok());
+        assert!(matches!(backend.read_block(1), Err(StorageError::BlockAbsent)));
+
+        let mut replica = backend.read_control_plane_entry(2);
+        replica[VERSION_OFFSET] ^= 0xFF;
+        backend.write_control_plane_entry(2, &replica);
+        assert!(backend.load_control_data().is_ok());
+
+        assert_eq!(observer.init_started.get(), 1);
+        assert_eq!(observer.init_finished.get(), 1);
+        assert_eq!(observer.slots_written.get(), 1);
+        assert_eq!(observer.slots_read.get(), 1);
+        assert_eq!(observer.replicas_repaired.get(), 1);
+    }
+
+    #[test]
+    fn control_plane_mutations_keep_blocks_and_other_fields() {
+        let mut backend = initialized_backend::<TEST_STORAGE_SIZE_2_SLOTS>();
+        let block = block_from_len_and_marker(HEADER_SIZE, 5);
+        assert!(backend.save_block(1, &block).is_ok());
+
+        assert!(backend.rotate_private_key([8u8; PRIVATE_KEY_SIZE]).is_ok());
+        assert!(backend.set_own_node_id(77).is_ok());
+        assert!(backend.update_init_params([1u8; INIT_PARAMS_SIZE]).is_ok());
+
+        let loaded = match backend.load_control_data() {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        };
+        assert_eq!(loaded.private_key, [8u8; PRIVATE_KEY_SIZE]);
+        assert_eq!(loaded.own_node_id, 77);
+        assert_eq!(loaded.init_params, [1u8; INIT_PARAMS_SIZE]);
+        let stored = match backend.read_block(1) {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        };
+        assert_eq!(stored.as_bytes(), block.as_bytes());
+        let report = backend.fsck(FsckOptions::default(), &mut |_| {});
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn control_plane_mutations_require_initialized_control_plane() {
+        let mut backend = MemoryBackend::<TEST_STORAGE_SIZE_2_SLOTS>::new();
+        assert!(matches!(
+            backend.set_own_node_id(1),
+            Err(StorageError::ControlPlaneUninitialized)
+        ));
+    }
+
+    #[test]
+    fn decommission_wipes_control_plane_and_blocks() {
+        let mut backend = initialized_backend::<TEST_STORAGE_SIZE_2_SLOTS>();
+        assert!(backend.save_block(0, &block_from_len_and_marker(HEADER_SIZE, 4)).is_ok());
+
+        assert!(backend.decommission(true).is_ok());
+        assert!(matches!(
+            backend.load_control_data(),
+            Err(StorageError::ControlPlaneUninitialized)
+        ));
+        assert!(matches!(backend.read_block(0), Err(StorageError::BlockAbsent)));
+        assert!(!backend
+            .storage
+            .windows(PRIVATE_KEY_SIZE)
+            .any(|window| window == &TEST_PRIVATE_KEY[..]));
+    }
+
     #[test]
     fn load_control_data_reports_uninitialized_before_init() {
         let mut backend = MemoryBackend::<TEST_STORAGE_SIZE_2_SLOTS>::new();
@@ -426,8 +1572,8 @@ mod tests {
         let mut backend = initialized_backend::<TEST_STORAGE_SIZE_2_SLOTS>();
         let mut replica = backend.read_control_plane_entry(0);
         replica[VERSION_OFFSET] = CONTROL_PLANE_VERSION.wrapping_add(1);
-        let crc = MemoryBackend::<TEST_STORAGE_SIZE_2_SLOTS>::crc32(&replica[..CONTROL_CRC32_OFFSET]);
-        replica[CONTROL_CRC32_OFFSET..CONTROL_CRC32_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
+        let crc = control_plane::crc32(&replica[..CRC32_OFFSET]);
+        replica[CRC32_OFFSET..CRC32_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
         backend.write_control_plane_entry(0, &replica);
         backend.write_control_plane_entry(1, &replica);
         backend.write_control_plane_entry(2, &replica);
@@ -481,6 +1627,34 @@ mod tests {
         assert!(loaded.chain_configuration.is_some());
     }
 
+    #[test]
+    fn frozen_v1_replicas_upgrade_in_place_on_load() {
+        let mut backend = MemoryBackend::<TEST_STORAGE_SIZE_2_SLOTS>::new();
+        let legacy = control_plane::frozen::v1_entry();
+        let mut index = 0usize;
+        while index < CONTROL_PLANE_COUNT {
+            backend.write_control_plane_entry(index, &legacy);
+            index += 1;
+        }
+
+        let loaded = match backend.load_control_data() {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        };
+        assert_eq!(loaded.private_key, [control_plane::frozen::V1_PRIVATE_KEY_BYTE; PRIVATE_KEY_SIZE]);
+        assert_eq!(loaded.own_node_id, control_plane::frozen::V1_OWN_NODE_ID);
+        assert_eq!(loaded.init_params, [control_plane::frozen::V1_INIT_PARAMS_BYTE; INIT_PARAMS_SIZE]);
+
+        let mut index = 0usize;
+        while index < CONTROL_PLANE_COUNT {
+            let entry = backend.read_control_plane_entry(index);
+            assert_eq!(entry[VERSION_OFFSET], CONTROL_PLANE_VERSION);
+            let decoded = backend.deserialize_record(&entry);
+            assert!(matches!(decoded, Ok(ref value) if !value.needs_upgrade));
+            index += 1;
+        }
+    }
+
     #[test]
     fn load_repairs_corrupted_control_plane_replica() {
         let mut backend = initialized_backend::<TEST_STORAGE_SIZE_2_SLOTS>();
@@ -490,12 +1664,209 @@ mod tests {
 
         let loaded = backend.load_control_data();
         assert!(loaded.is_ok());
-        let repaired = MemoryBackend::<TEST_STORAGE_SIZE_2_SLOTS>::deserialize_record(
-            &backend.read_control_plane_entry(1),
-        );
+        let repaired = backend.deserialize_record(&backend.read_control_plane_entry(1));
         assert!(repaired.is_ok());
     }
 
+    #[test]
+    fn configured_replica_count_moves_slots_and_repairs_last_replica() {
+        let layout = ControlPlaneLayout::new(5, ControlPlanePlacement::Spread);
+        let mut backend = MemoryBackend::<TEST_STORAGE_SIZE_4_SLOTS>::new()
+            .with_control_plane_layout(layout)
+            .unwrap_or_else(|_| unreachable!());
+        assert_eq!(
+            backend.max_storage_slots(),
+            ((TEST_STORAGE_SIZE_4_SLOTS - 5 * CONTROL_PLANE_ENTRY_SIZE) / SLOT_STRIDE) as StorageIndex
+        );
+        assert!(backend
+            .init(TEST_PRIVATE_KEY, TEST_NODE_ID, TEST_INIT_PARAMS)
+            .is_ok());
+        let block = block_from_len_and_marker(HEADER_SIZE, 1);
+        assert!(backend.save_block(0, &block).is_ok());
+
+        let mut replica = backend.read_control_plane_entry(4);
+        replica[VERSION_OFFSET] ^= 0xFF;
+        backend.write_control_plane_entry(4, &replica);
+        assert!(backend.load_control_data().is_ok());
+        assert!(backend.deserialize_record(&backend.read_control_plane_entry(4)).is_ok());
+        assert!(backend.read_block(0).is_ok());
+
+        let report = backend.fsck(FsckOptions::default(), &mut |_| {});
+        assert!(report.is_clean());
+        assert_eq!(report.replicas_checked, 5);
+    }
+
+    #[test]
+    fn with_control_plane_layout_rejects_invalid_counts() {
+        let empty = ControlPlaneLayout::new(0, ControlPlanePlacement::Consecutive);
+        assert!(matches!(
+            MemoryBackend::<TEST_STORAGE_SIZE_2_SLOTS>::new().with_control_plane_layout(empty),
+            Err(StorageError::InvalidConfiguration)
+        ));
+        let too_large = ControlPlaneLayout::new(MAX_CONTROL_PLANE_COUNT, ControlPlanePlacement::Consecutive);
+        assert!(matches!(
+            MemoryBackend::<TEST_STORAGE_SIZE_2_SLOTS>::new().with_control_plane_layout(too_large),
+            Err(StorageError::InvalidConfiguration)
+        ));
+    }
+
+    #[cfg(feature = "key-encryption")]
+    #[test]
+    fn wrapped_key_is_not_stored_in_plaintext_and_loads_transparently() {
+        let protection = KeyProtection::from_secret(b"node secret", None).unwrap_or_else(|_| unreachable!());
+        let mut backend = MemoryBackend::<TEST_STORAGE_SIZE_2_SLOTS>::new().with_key_protection(protection);
+        assert!(backend
+            .init(TEST_PRIVATE_KEY, TEST_NODE_ID, TEST_INIT_PARAMS)
+            .is_ok());
+        assert!(!backend
+            .storage
+            .windows(PRIVATE_KEY_SIZE)
+            .any(|window| window == TEST_PRIVATE_KEY));
+
+        let loaded = match backend.load_control_data() {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        };
+        assert_eq!(loaded.private_key, TEST_PRIVATE_KEY);
+    }
+
+    #[cfg(feature = "key-encryption")]
+    #[test]
+    fn wrong_wrapping_key_reports_key_unwrap_failed() {
+        let protection = KeyProtection::from_secret(b"node secret", None).unwrap_or_else(|_| unreachable!());
+        let mut backend = MemoryBackend::<TEST_STORAGE_SIZE_2_SLOTS>::new().with_key_protection(protection);
+        assert!(backend
+            .init(TEST_PRIVATE_KEY, TEST_NODE_ID, TEST_INIT_PARAMS)
+            .is_ok());
+
+        let wrong = KeyProtection::from_secret(b"other secret", None).unwrap_or_else(|_| unreachable!());
+        let mut backend = backend.with_key_protection(wrong);
+        assert!(matches!(
+            backend.load_control_data(),
+            Err(StorageError::KeyUnwrapFailed)
+        ));
+        let mut backend = backend.with_key_protection(KeyProtection::Plaintext);
+        assert!(matches!(
+            backend.load_control_data(),
+            Err(StorageError::KeyUnwrapFailed)
+        ));
+    }
+
+    #[cfg(feature = "key-encryption")]
+    #[test]
+    fn plaintext_replicas_are_rejected_until_set_key_protection_wraps_them() {
+        let backend = initialized_backend::<TEST_STORAGE_SIZE_2_SLOTS>();
+        let protection = KeyProtection::from_secret(b"node secret", None).unwrap_or_else(|_| unreachable!());
+        let mut backend = backend.with_key_protection(protection.clone());
+        assert!(matches!(
+            backend.load_control_data(),
+            Err(StorageError::KeyUnwrapFailed)
+        ));
+
+        let mut backend = backend.with_key_protection(KeyProtection::Plaintext);
+        assert!(backend.set_key_protection(protection).is_ok());
+        assert!(matches!(backend.load_control_data(), Ok(record) if record.private_key == TEST_PRIVATE_KEY));
+        assert!(!backend
+            .storage
+            .windows(PRIVATE_KEY_SIZE)
+            .any(|window| window == TEST_PRIVATE_KEY));
+
+        let mut backend = backend.with_key_protection(KeyProtection::Plaintext);
+        assert!(matches!(
+            backend.load_control_data(),
+            Err(StorageError::KeyUnwrapFailed)
+        ));
+    }
+
+    #[test]
+    fn fsck_reports_clean_backend() {
+        let mut backend = initialized_backend::<TEST_STORAGE_SIZE_2_SLOTS>();
+        let block = block_from_len_and_marker(HEADER_SIZE, 1);
+        assert!(backend.save_block(0, &block).is_ok());
+
+        let mut findings = 0u32;
+        let report = backend.fsck(FsckOptions::default(), &mut |_| findings += 1);
+        assert!(report.is_clean());
+        assert_eq!(findings, 0);
+        assert_eq!(report.replicas_checked, CONTROL_PLANE_COUNT as u32);
+        assert_eq!(report.replicas_valid, CONTROL_PLANE_COUNT as u32);
+        assert_eq!(report.slots_checked, 2);
+        assert_eq!(report.slots_occupied, 1);
+    }
+
+    #[test]
+    fn fsck_reports_and_repairs_replica_and_slot_anomalies() {
+        let mut backend = initialized_backend::<TEST_STORAGE_SIZE_2_SLOTS>();
+        let mut replica = backend.read_control_plane_entry(1);
+        replica[VERSION_OFFSET] ^= 0xFF;
+        backend.write_control_plane_entry(1, &replica);
+        let (slot_start, _) = backend.slot_range(1).unwrap_or_else(|_| unreachable!());
+        backend.storage[slot_start + 5] = 0xAA;
+
+        let mut issues = [None; 4];
+        let mut count = 0usize;
+        let report = backend.fsck(FsckOptions { repair: false }, &mut |finding| {
+            issues[count] = Some(finding.issue);
+            count += 1;
+        });
+        assert_eq!(report.issues_found, 2);
+        assert_eq!(report.issues_repaired, 0);
+        assert_eq!(issues[0], Some(FsckIssue::ReplicaCorrupted { replica_index: 1 }));
+        assert_eq!(issues[1], Some(FsckIssue::SlotPaddingAnomaly { storage_index: 1 }));
+
+        let report = backend.fsck(FsckOptions { repair: true }, &mut |_| {});
+        assert_eq!(report.issues_found, 2);
+        assert_eq!(report.issues_repaired, 2);
+
+        let report = backend.fsck(FsckOptions::default(), &mut |_| {});
+        assert!(report.is_clean());
+        assert!(matches!(backend.read_block(1), Err(StorageError::BlockAbsent)));
+    }
+
+    #[test]
+    fn control_plane_health_reports_replicas_and_repairs_unless_read_only() {
+        let mut backend = initialized_backend::<TEST_STORAGE_SIZE_2_SLOTS>();
+        let mut replica = backend.read_control_plane_entry(0);
+        replica[CONTROL_PLANE_ENTRY_SIZE - 1] ^= 0xFF;
+        backend.write_control_plane_entry(0, &replica);
+        let other = ControlPlaneData {
+            private_key: [1u8; PRIVATE_KEY_SIZE],
+            own_node_id: 99,
+            init_params: [0u8; INIT_PARAMS_SIZE],
+            chain_configuration: None,
+        };
+        let encoded = backend.serialize_record(&other).unwrap_or_else(|_| unreachable!());
+        backend.write_control_plane_entry(2, &encoded);
+
+        let health = backend.control_plane_health(true).unwrap_or_else(|_| unreachable!());
+        assert_eq!(
+            health.statuses(),
+            &[ReplicaStatus::CrcMismatch, ReplicaStatus::Valid, ReplicaStatus::DiffersFromPrimary]
+        );
+        assert_eq!(health.primary_replica, Some(1));
+        assert!(!health.repaired);
+        assert!(matches!(backend.control_plane_health(true), Ok(ref again) if *again == health));
+
+        let health = backend.control_plane_health(false).unwrap_or_else(|_| unreachable!());
+        assert_eq!(health.primary_replica, Some(1));
+        assert!(health.repaired);
+
+        let health = backend.control_plane_health(true).unwrap_or_else(|_| unreachable!());
+        assert!(health.is_healthy());
+        assert_eq!(health.primary_replica, Some(0));
+        assert!(matches!(backend.load_control_data(), Ok(ref record) if record.own_node_id == TEST_NODE_ID));
+    }
+
+    #[test]
+    fn control_plane_health_of_erased_backend_has_no_primary_and_writes_nothing() {
+        let mut backend = MemoryBackend::<TEST_STORAGE_SIZE_2_SLOTS>::new();
+        let health = backend.control_plane_health(false).unwrap_or_else(|_| unreachable!());
+        assert_eq!(health.statuses(), &[ReplicaStatus::Erased; CONTROL_PLANE_COUNT]);
+        assert_eq!(health.primary_replica, None);
+        assert!(!health.repaired);
+        assert!(backend.storage.iter().all(|byte| *byte == 0));
+    }
+
     #[test]
     fn compile_time_block_storage_size_is_enforced() {
         let mut backend = initialized_backend::<TEST_STORAGE_SIZE_2_SLOTS>();
@@ -603,6 +1974,246 @@ mod tests {
         assert_eq!(read_b.as_bytes(), &expected_slot_bytes(&block_b));
     }
 
+    #[test]
+    fn find_by_hash_returns_lowest_matching_slot() {
+        let mut backend = initialized_backend::<TEST_STORAGE_SIZE_4_SLOTS>();
+        let block_a = block_from_len_and_marker(HEADER_SIZE, 5);
+        let block_b = block_from_len_and_marker(HEADER_SIZE + 2, 6);
+        assert!(matches!(backend.find_by_hash(&block_hash(&block_a)), Ok(None)));
+
+        assert!(backend.save_block(3, &block_a).is_ok());
+        assert!(backend.save_block(1, &block_b).is_ok());
+        assert!(matches!(backend.find_by_hash(&block_hash(&block_a)), Ok(Some(3))));
+        assert!(matches!(backend.find_by_hash(&block_hash(&block_b)), Ok(Some(1))));
+
+        assert!(backend.save_block(2, &block_a).is_ok());
+        assert!(matches!(backend.find_by_hash(&block_hash(&block_a)), Ok(Some(2))));
+        assert!(matches!(backend.find_by_hash(&[0u8; HASH_SIZE]), Ok(None)));
+    }
+
+    #[test]
+    fn sequence_lookups_follow_overwrites() {
+        let mut backend = initialized_backend::<TEST_STORAGE_SIZE_4_SLOTS>();
+        assert!(matches!(backend.max_sequence(), Ok(None)));
+
+        assert!(backend.save_block(2, &block_with_sequence(10)).is_ok());
+        assert!(backend.save_block(0, &block_with_sequence(12)).is_ok());
+        assert!(backend.save_block(3, &block_with_sequence(11)).is_ok());
+        assert!(backend.save_block(1, &block_with_sequence(11)).is_ok());
+        assert!(matches!(backend.find_by_sequence(11), Ok(Some(1))));
+        assert!(matches!(backend.max_sequence(), Ok(Some(12))));
+
+        assert!(backend.save_block(0, &block_with_sequence(4)).is_ok());
+        assert!(matches!(backend.find_by_sequence(12), Ok(None)));
+        assert!(matches!(backend.max_sequence(), Ok(Some(11))));
+
+        let mut seen: [(u32, StorageIndex); 4] = [(0, 0); 4];
+        let mut count = 0usize;
+        assert!(backend
+            .range_by_sequence(5..u32::MAX, &mut |sequence, storage_index| {
+                seen[count] = (sequence, storage_index);
+                count += 1;
+            })
+            .is_ok());
+        assert_eq!(&seen[..count], &[(10, 2), (11, 1)]);
+    }
+
+    #[test]
+    fn merkle_observer_tracks_writes_and_tells_backends_apart() {
+        let merkle = MerkleObserver::<4>::new();
+        let mut ours = MemoryBackend::<TEST_STORAGE_SIZE_4_SLOTS>::new().with_observer(&merkle);
+        assert!(ours.init(TEST_PRIVATE_KEY, TEST_NODE_ID, TEST_INIT_PARAMS).is_ok());
+        assert_eq!(merkle.leaf_count(), 4);
+        let mut theirs = initialized_backend::<TEST_STORAGE_SIZE_4_SLOTS>();
+        let mut empty = MerkleTree::<4>::new();
+        assert!(theirs.build_merkle_tree(&mut empty).is_ok());
+        assert_eq!(merkle.root(), empty.root());
+
+        let mut index: StorageIndex = 0;
+        while index < 4 {
+            let block = block_with_sequence(index + 1);
+            assert!(ours.save_block(index, &block).is_ok());
+            assert!(theirs.save_block(index, &block).is_ok());
+            index += 1;
+        }
+        assert!(ours.force_overwrite(1, &block_with_sequence(7)).is_ok());
+        assert!(theirs.save_block(1, &block_with_sequence(7)).is_ok());
+        assert!(theirs.save_block(2, &block_with_sequence(9)).is_ok());
+
+        let mut rebuilt = MerkleTree::<4>::new();
+        assert!(ours.build_merkle_tree(&mut rebuilt).is_ok());
+        assert_eq!(rebuilt.root(), merkle.root());
+        let mut other = MerkleTree::<4>::new();
+        assert!(theirs.build_merkle_tree(&mut other).is_ok());
+        assert_ne!(other.root(), merkle.root());
+        assert_eq!(merkle.subtree_hash(0..2).ok(), other.subtree_hash(0..2).ok());
+        assert_ne!(merkle.subtree_hash(2..3).ok(), other.subtree_hash(2..3).ok());
+
+        assert!(ours.decommission(true).is_ok());
+        assert_eq!(merkle.root(), empty.root());
+        assert!(merkle.load(|tree| ours.build_merkle_tree(tree)).is_ok());
+        assert_eq!(merkle.root(), empty.root());
+
+        let mut too_small = MerkleTree::<2>::new();
+        assert!(matches!(
+            ours.build_merkle_tree(&mut too_small),
+            Err(StorageError::InvalidConfiguration)
+        ));
+    }
+
+    #[test]
+    fn write_once_slots_reject_overwrites_unless_forced() {
+        let mut backend = initialized_backend::<TEST_STORAGE_SIZE_4_SLOTS>().with_write_once(WriteOnceSlots::range(1, 3));
+        let first = block_with_sequence(1);
+        let second = block_with_sequence(2);
+
+        assert!(backend.save_block(1, &first).is_ok());
+        assert!(matches!(backend.save_block(1, &second), Err(StorageError::SlotOccupied)));
+        assert!(matches!(backend.read_block(1), Ok(block) if block.header().sequence == 1));
+
+        assert!(backend.save_block(0, &first).is_ok());
+        assert!(backend.save_block(0, &second).is_ok());
+        assert!(backend.save_block(3, &first).is_ok());
+        assert!(backend.save_block(3, &second).is_ok());
+
+        assert!(backend.force_overwrite(1, &second).is_ok());
+        assert!(matches!(backend.read_block(1), Ok(block) if block.header().sequence == 2));
+        assert!(matches!(backend.force_overwrite(4, &second), Err(StorageError::InvalidIndex)));
+    }
+
+    #[cfg(feature = "slot-meta")]
+    #[test]
+    fn slot_meta_survives_neighbour_writes_and_is_cleared_by_a_new_block() {
+        let mut backend = initialized_backend::<TEST_STORAGE_SIZE_2_SLOTS>();
+        let meta = [5u8; SLOT_META_SIZE];
+        assert!(matches!(backend.write_slot_meta(0, &meta), Err(StorageError::BlockAbsent)));
+        assert!(matches!(backend.read_slot_meta(2), Err(StorageError::InvalidIndex)));
+
+        assert!(backend.save_block(0, &block_with_sequence(1)).is_ok());
+        assert!(backend.write_slot_meta(0, &meta).is_ok());
+        assert!(backend.save_block(1, &block_with_sequence(2)).is_ok());
+        assert!(matches!(backend.read_slot_meta(0), Ok(Some(value)) if value == meta));
+        assert!(matches!(backend.read_slot_meta(1), Ok(None)));
+        assert!(matches!(backend.read_block(0), Ok(block) if block.header().sequence == 1));
+
+        assert!(backend.save_block(0, &block_with_sequence(3)).is_ok());
+        assert!(matches!(backend.read_slot_meta(0), Ok(None)));
+    }
+
+    #[test]
+    fn kv_store_takes_pages_from_the_end_and_is_wiped_by_init() {
+        const SIZE: usize = TEST_STORAGE_SIZE_2_SLOTS + 4 * KV_PAGE_SIZE;
+        assert!(matches!(
+            MemoryBackend::<SIZE>::new().with_kv_store(1),
+            Err(StorageError::InvalidConfiguration)
+        ));
+        assert!(matches!(
+            MemoryBackend::<SIZE>::new().with_kv_store(2),
+            Err(StorageError::InvalidConfiguration)
+        ));
+        assert!(matches!(
+            MemoryBackend::<SIZE>::new().kv_get(b"tip"),
+            Err(StorageError::InvalidConfiguration)
+        ));
+
+        let mut backend = MemoryBackend::<SIZE>::new()
+            .with_kv_store(4)
+            .unwrap_or_else(|_| unreachable!());
+        assert_eq!(backend.max_storage_slots(), 2);
+        assert!(backend
+            .init(TEST_PRIVATE_KEY, TEST_NODE_ID, TEST_INIT_PARAMS)
+            .is_ok());
+        assert!(backend.save_block(1, &block_with_sequence(1)).is_ok());
+
+        assert!(backend.kv_put(b"tip", &[1, 2, 3]).is_ok());
+        assert!(matches!(backend.kv_get(b"tip"), Ok(Some(value)) if value.as_bytes() == [1, 2, 3]));
+        assert!(matches!(backend.read_block(1), Ok(block) if block.header().sequence == 1));
+        assert!(backend.kv_delete(b"tip").is_ok());
+        assert!(matches!(backend.kv_get(b"tip"), Ok(None)));
+
+        assert!(backend.kv_put(b"tip", &[4]).is_ok());
+        assert!(backend
+            .init(TEST_PRIVATE_KEY, TEST_NODE_ID, TEST_INIT_PARAMS)
+            .is_ok());
+        assert!(matches!(backend.kv_get(b"tip"), Ok(None)));
+    }
+
+    #[test]
+    fn counters_sit_before_the_kv_store_and_survive_init_and_import_until_decommission() {
+        const SIZE: usize = TEST_STORAGE_SIZE_2_SLOTS + 6 * KV_PAGE_SIZE;
+        let mut backend = MemoryBackend::<SIZE>::new()
+            .with_kv_store(4)
+            .and_then(|backend| backend.with_counters(1))
+            .unwrap_or_else(|_| unreachable!());
+        assert_eq!(backend.max_storage_slots(), 2);
+        assert!(matches!(
+            MemoryBackend::<SIZE>::new().with_kv_store(4).and_then(|backend| backend.with_counters(2)),
+            Err(StorageError::InvalidConfiguration)
+        ));
+        assert!(matches!(backend.counter(1), Err(StorageError::InvalidIndex)));
+
+        assert!(backend
+            .init(TEST_PRIVATE_KEY, TEST_NODE_ID, TEST_INIT_PARAMS)
+            .is_ok());
+        assert!(backend.kv_put(b"tip", &[1]).is_ok());
+        let mut round = 1u64;
+        while round <= 20 {
+            assert!(matches!(backend.counter(0).and_then(|mut nonce| nonce.increment()), Ok(value) if value == round));
+            round += 1;
+        }
+        assert!(matches!(backend.counter(0).and_then(|nonce| nonce.get()), Ok(20)));
+        assert!(matches!(backend.kv_get(b"tip"), Ok(Some(value)) if value.as_bytes() == [1]));
+
+        assert!(backend
+            .init(TEST_PRIVATE_KEY, TEST_NODE_ID, TEST_INIT_PARAMS)
+            .is_ok());
+        assert!(matches!(backend.counter(0).and_then(|nonce| nonce.get()), Ok(20)));
+        assert!(matches!(backend.kv_get(b"tip"), Ok(None)));
+
+        let mut archive = [0u8; 1024];
+        let mut sink = crate::archive::SliceSink::new(&mut archive);
+        let options = ExportOptions { include_control_data: true, include_private_key: true };
+        assert!(backend.export_to(options, &mut sink).is_ok());
+        let len = sink.written().len();
+        assert!(backend.import_from(&mut crate::archive::SliceSource::new(&archive[..len])).is_ok());
+        assert!(matches!(backend.counter(0).and_then(|mut nonce| nonce.increment()), Ok(21)));
+
+        assert!(backend.decommission(true).is_ok());
+        assert!(matches!(backend.counter(0).and_then(|nonce| nonce.get()), Ok(0)));
+    }
+
+    #[test]
+    fn replace_and_insert_report_conflicts_with_the_current_hash() {
+        let mut backend = initialized_backend::<TEST_STORAGE_SIZE_4_SLOTS>();
+        let first = block_with_sequence(1);
+        let second = block_with_sequence(2);
+
+        assert!(matches!(
+            backend.replace_block(0, &block_hash(&first), &second),
+            Err(StorageError::Conflict { current_hash: None })
+        ));
+        assert!(backend.insert_block(0, &first).is_ok());
+        assert!(matches!(
+            backend.insert_block(0, &second),
+            Err(StorageError::Conflict { current_hash: Some(hash) }) if hash == block_hash(&first)
+        ));
+        assert!(matches!(
+            backend.replace_block(0, &block_hash(&second), &second),
+            Err(StorageError::Conflict { current_hash: Some(hash) }) if hash == block_hash(&first)
+        ));
+        assert!(matches!(backend.read_block(0), Ok(block) if block.header().sequence == 1));
+
+        assert!(backend.replace_block(0, &block_hash(&first), &second).is_ok());
+        assert!(matches!(backend.read_block(0), Ok(block) if block.header().sequence == 2));
+        assert!(matches!(backend.insert_block(4, &first), Err(StorageError::InvalidIndex)));
+
+        let mut backend = backend.with_write_once(WriteOnceSlots::ALL);
+        assert!(matches!(
+            backend.replace_block(0, &block_hash(&second), &first),
+            Err(StorageError::SlotOccupied)
+        ));
+    }
+
     #[test]
     fn startup_read_cycle_with_mixed_slots_returns_typed_outcomes() {
         let mut backend = initialized_backend::<TEST_STORAGE_SIZE_4_SLOTS>();
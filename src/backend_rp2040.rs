@@ -1,12 +1,34 @@
 /*! RP2040 backend geometry mapping and synchronous flash save/retrieve paths. */
 
+use crate::archive::{
+    ArchiveSummary, ByteSink, ByteSource, ExportOptions, export_archive, import_archive,
+};
+use crate::control_plane::{self, ControlPlaneLayout, ControlPlanePlacement, DecodedEntry, KeyProtection};
+use crate::counter::{self, COUNTER_PAGES};
+use crate::init_marker::{INIT_ERASE_CHUNK_PAGES, INIT_MARKER_SIZE, InitMarker, InitProgress};
+use crate::kv_store::{self, KV_PAGE_SIZE, KvPages, KvValue};
+use crate::merkle::{EMPTY_LEAF, MerkleTree};
+use crate::relocation::{
+    RELOCATION_JOURNAL_SIZE, RelocationJournal, RelocationPhase, RelocationTarget,
+};
+use crate::rp2040_image::RP2040_XIP_BASE;
+use crate::sequence_index::{self, SequenceSource};
+use crate::sequence_log::{self, LogRecord, SEQUENCE_RECORD_SIZE, SEQUENCE_RECORDS_PER_PAGE};
+use crate::slot_meta::{self, SLOT_META_AREA_SIZE, SLOT_META_AREAS_PER_PAGE, SLOT_META_RECORD_SIZE};
+use crate::superblock::{SUPERBLOCK_SIZE, Superblock};
+use crate::types::WriteCondition;
 use crate::{
-    CONTROL_PLANE_COUNT, CONTROL_PLANE_VERSION, ControlPlaneData, INIT_PARAMS_SIZE, StorageError,
-    StorageIndex, StorageTrait,
+    ControlPlaneData, ControlPlaneHealth, FsckFinding, FsckIssue, FsckOptions, FsckReport, INIT_PARAMS_SIZE,
+    MAX_CONTROL_PLANE_COUNT, NoopObserver, ReplicaStatus, StorageError, StorageIndex, StorageObserver,
+    StorageTrait, WriteOnceSlots,
 };
+#[cfg(feature = "slot-meta")]
+use crate::SLOT_META_SIZE;
 use core::cell::RefCell;
+use core::ops::Range;
 use moonblokz_chain_types::{Block, HASH_SIZE, MAX_BLOCK_SIZE, calculate_hash};
 use moonblokz_crypto::PRIVATE_KEY_SIZE;
+use zeroize::{Zeroize, Zeroizing};
 
 #[cfg(all(not(test), target_arch = "arm"))]
 use embassy_rp::flash::{Blocking, Flash};
@@ -20,22 +42,25 @@ pub const FLASH_PAGE_SIZE: usize = 4096;
 /// Default RP2040 full flash size in bytes.
 pub const RP2040_DEFAULT_FLASH_SIZE: usize = 2 * 1024 * 1024;
 /// Slot hash metadata offset (after fixed-size block bytes).
-const SLOT_HASH_OFFSET: usize = MAX_BLOCK_SIZE;
+pub const SLOT_HASH_OFFSET: usize = MAX_BLOCK_SIZE;
 /// Total bytes used by one persisted slot (`block bytes + hash metadata`).
-const SLOT_SIZE_BYTES: usize = MAX_BLOCK_SIZE + HASH_SIZE;
-/// Reserved control-plane bytes (one full page per replica).
-const CONTROL_PLANE_RESERVED_BYTES: usize = CONTROL_PLANE_COUNT * FLASH_PAGE_SIZE;
-
-const CP_VERSION_OFFSET: usize = 0;
-const CP_PRIVATE_KEY_SIZE_OFFSET: usize = CP_VERSION_OFFSET + 1;
-const CP_PRIVATE_KEY_OFFSET: usize = CP_PRIVATE_KEY_SIZE_OFFSET + 1;
-const CP_OWN_NODE_ID_OFFSET: usize = CP_PRIVATE_KEY_OFFSET + PRIVATE_KEY_SIZE;
-const CP_INIT_PARAMS_SIZE_OFFSET: usize = CP_OWN_NODE_ID_OFFSET + 4;
-const CP_INIT_PARAMS_OFFSET: usize = CP_INIT_PARAMS_SIZE_OFFSET + 1;
-const CP_MAX_BLOCK_SIZE_OFFSET: usize = CP_INIT_PARAMS_OFFSET + INIT_PARAMS_SIZE;
-const CP_CHAIN_CONFIG_OFFSET: usize = CP_MAX_BLOCK_SIZE_OFFSET + 2;
-const CP_CRC32_OFFSET: usize = CP_CHAIN_CONFIG_OFFSET + MAX_BLOCK_SIZE;
-const CONTROL_PLANE_ENTRY_SIZE: usize = CP_CRC32_OFFSET + 4;
+pub const SLOT_SIZE_BYTES: usize = MAX_BLOCK_SIZE + HASH_SIZE;
+pub use crate::control_plane::CONTROL_PLANE_ENTRY_SIZE;
+/// Superblock offset inside each control-plane page (the last `SUPERBLOCK_SIZE` bytes).
+///
+/// Fixed to the page end so firmware with a different entry size still finds it.
+pub const SUPERBLOCK_OFFSET: usize = FLASH_PAGE_SIZE - SUPERBLOCK_SIZE;
+
+/// RP2040 flash unique id size in bytes.
+pub const FLASH_UNIQUE_ID_SIZE: usize = 8;
+#[cfg(any(test, not(target_arch = "arm")))]
+const MOCK_FLASH_UNIQUE_ID: [u8; FLASH_UNIQUE_ID_SIZE] = [0xE6, 0x60, 0x38, 0xB7, 0x13, 0x5A, 0x2B, 0x21];
+
+/// Bytes compared or batched at a time where a page buffer is not needed
+/// (one flash program page), keeping page-sized arrays off the stack.
+const FLASH_CHUNK_SIZE: usize = 256;
+/// Sequence log records per `FLASH_CHUNK_SIZE` chunk.
+const SEQUENCE_RECORDS_PER_CHUNK: usize = FLASH_CHUNK_SIZE / SEQUENCE_RECORD_SIZE;
 
 /// Number of block slots per RP2040 flash page.
 pub const BLOCKS_PER_PAGE: usize = FLASH_PAGE_SIZE / SLOT_SIZE_BYTES;
@@ -48,11 +73,27 @@ const _: () = {
     if BLOCKS_PER_PAGE == 0 {
         panic!("MAX_BLOCK_SIZE must allow at least one block in a 4096-byte RP2040 page");
     }
-    if CONTROL_PLANE_ENTRY_SIZE > FLASH_PAGE_SIZE {
-        panic!("control-plane entry must fit in one RP2040 flash page");
+    if CONTROL_PLANE_ENTRY_SIZE > SUPERBLOCK_OFFSET {
+        panic!("control-plane entry and superblock must fit in one RP2040 flash page");
+    }
+    if RELOCATION_JOURNAL_SIZE > FLASH_PAGE_SIZE {
+        panic!("relocation journal must fit in one RP2040 flash page");
+    }
+    if KV_PAGE_SIZE != FLASH_PAGE_SIZE {
+        panic!("key-value pages must be RP2040 flash pages");
     }
 };
 
+/// Raw control-plane entry header fields, read without validation.
+pub struct ControlRecordHeader {
+    /// Persisted control-plane schema version byte.
+    pub version: u8,
+    /// CRC32 stored in the entry.
+    pub stored_crc32: u32,
+    /// CRC32 recomputed over the entry payload.
+    pub computed_crc32: u32,
+}
+
 /// Deterministic RP2040 flash mapping result for a `storage_index`.
 pub struct Rp2040SlotMapping {
     /// Zero-based flash page index.
@@ -63,6 +104,374 @@ pub struct Rp2040SlotMapping {
     pub byte_offset_in_page: usize,
 }
 
+/// Name of the region a backend uses when no partitions are configured.
+pub const MAIN_REGION_NAME: &str = "main";
+
+/// Flash range holding one control plane and its block slots.
+///
+/// A backend owns one region from its start address to the end of flash
+/// unless `with_partitions` splits the flash into several named regions.
+/// Each control-plane replica takes one page at the position given by
+/// `control_plane`; an optional sequence index takes the last
+/// `sequence_index_pages` pages, an optional key-value store the `kv_pages`
+/// pages before it, optional counters `COUNTER_PAGES` pages each before that,
+/// optional slot metadata the `slot_meta_pages` pages before the counters,
+/// and block pages fill the remaining pages in address order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rp2040Region {
+    /// Region name used by `Rp2040Backend::partition`.
+    pub name: &'static str,
+    /// First flash address of the region (page aligned).
+    pub start: usize,
+    /// Flash address one past the region (page aligned).
+    pub end: usize,
+    /// Control-plane replica count and placement.
+    pub control_plane: ControlPlaneLayout,
+    /// Pages reserved for the sequence index at the end of the region (0 when disabled).
+    pub sequence_index_pages: usize,
+    /// Pages reserved for the key-value store before the sequence index (0 when disabled).
+    pub kv_pages: usize,
+    /// Monotonic counters before the key-value store, `COUNTER_PAGES` pages each (0 when disabled).
+    pub counters: usize,
+    /// Pages reserved for slot metadata before the counters (0 when disabled).
+    pub slot_meta_pages: usize,
+    /// Slots `save_block` refuses to overwrite once they hold a block.
+    pub write_once: WriteOnceSlots,
+}
+
+impl Rp2040Region {
+    /// Creates a region descriptor.
+    ///
+    /// Parameters:
+    /// - `name`: region name.
+    /// - `start`: first flash address of the region.
+    /// - `end`: flash address one past the region.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::Rp2040Region;
+    ///
+    /// const TESTNET: Rp2040Region = Rp2040Region::new("testnet", 0x1C_0000, 0x20_0000);
+    /// ```
+    pub const fn new(name: &'static str, start: usize, end: usize) -> Self {
+        Self {
+            name,
+            start,
+            end,
+            control_plane: ControlPlaneLayout::DEFAULT,
+            sequence_index_pages: 0,
+            kv_pages: 0,
+            counters: 0,
+            slot_meta_pages: 0,
+            write_once: WriteOnceSlots::NONE,
+        }
+    }
+
+    /// Returns the region with a different control-plane layout.
+    ///
+    /// Parameters:
+    /// - `layout`: replica count and placement.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::Rp2040Region;
+    /// use moonblokz_storage::{ControlPlaneLayout, ControlPlanePlacement};
+    ///
+    /// const TESTNET: Rp2040Region = Rp2040Region::new("testnet", 0x1C_0000, 0x20_0000)
+    ///     .with_control_plane_layout(ControlPlaneLayout::new(5, ControlPlanePlacement::Spread));
+    /// ```
+    pub const fn with_control_plane_layout(self, layout: ControlPlaneLayout) -> Self {
+        Self {
+            control_plane: layout,
+            ..self
+        }
+    }
+
+    /// Returns the region with `pages` pages reserved for the sequence index.
+    ///
+    /// The pages must hold more records than the region has slots, so a
+    /// rebuilt index always leaves room to append.
+    ///
+    /// Parameters:
+    /// - `pages`: sequence index pages at the end of the region (0 disables it).
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::Rp2040Region;
+    ///
+    /// const MAIN: Rp2040Region = Rp2040Region::new("main", 0x18_0000, 0x20_0000).with_sequence_index(1);
+    /// ```
+    pub const fn with_sequence_index(self, pages: usize) -> Self {
+        Self {
+            sequence_index_pages: pages,
+            ..self
+        }
+    }
+
+    /// Returns the region with `pages` pages reserved for the key-value store;
+    /// see `Rp2040Backend::with_kv_store`.
+    ///
+    /// Parameters:
+    /// - `pages`: key-value pages before the sequence index (0 disables the store).
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::Rp2040Region;
+    ///
+    /// const MAIN: Rp2040Region = Rp2040Region::new("main", 0x18_0000, 0x20_0000).with_kv_store(4);
+    /// ```
+    pub const fn with_kv_store(self, pages: usize) -> Self {
+        Self { kv_pages: pages, ..self }
+    }
+
+    /// Returns the region with `count` monotonic counters; see
+    /// `Rp2040Backend::with_counters`.
+    ///
+    /// Parameters:
+    /// - `count`: counters before the key-value store (0 disables them).
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::Rp2040Region;
+    ///
+    /// const MAIN: Rp2040Region = Rp2040Region::new("main", 0x18_0000, 0x20_0000).with_counters(2);
+    /// ```
+    pub const fn with_counters(self, count: usize) -> Self {
+        Self { counters: count, ..self }
+    }
+
+    /// Returns the region with `pages` pages reserved for slot metadata; see
+    /// `Rp2040Backend::with_slot_meta`.
+    ///
+    /// Parameters:
+    /// - `pages`: slot metadata pages before the counters (0 disables them).
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::Rp2040Region;
+    ///
+    /// const MAIN: Rp2040Region = Rp2040Region::new("main", 0x18_0000, 0x20_0000).with_slot_meta(8);
+    /// ```
+    pub const fn with_slot_meta(self, pages: usize) -> Self {
+        Self {
+            slot_meta_pages: pages,
+            ..self
+        }
+    }
+
+    /// Returns the region with `slots` protected against overwrites; see
+    /// `Rp2040Backend::with_write_once`.
+    ///
+    /// Parameters:
+    /// - `slots`: protected partition-local storage indices.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::WriteOnceSlots;
+    /// use moonblokz_storage::backend_rp2040::Rp2040Region;
+    ///
+    /// const MAIN: Rp2040Region = Rp2040Region::new("main", 0x18_0000, 0x20_0000).with_write_once(WriteOnceSlots::ALL);
+    /// ```
+    pub const fn with_write_once(self, slots: WriteOnceSlots) -> Self {
+        Self {
+            write_once: slots,
+            ..self
+        }
+    }
+
+    /// Returns the number of block pages, i.e. pages holding no control-plane
+    /// replica and no tail area (sequence index, key-value store, counters or
+    /// slot metadata).
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::{FLASH_PAGE_SIZE, Rp2040Region};
+    ///
+    /// let region = Rp2040Region::new("main", 0, 5 * FLASH_PAGE_SIZE);
+    /// assert_eq!(region.block_page_count(), 2);
+    /// ```
+    pub fn block_page_count(&self) -> usize {
+        self.layout_page_count().saturating_sub(self.control_plane.replica_count)
+    }
+
+    /// Returns the number of block slots in the region.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::{BLOCKS_PER_PAGE_INDEX, FLASH_PAGE_SIZE, Rp2040Region};
+    ///
+    /// let region = Rp2040Region::new("main", 0, 5 * FLASH_PAGE_SIZE);
+    /// assert_eq!(region.max_storage_slots(), 2 * BLOCKS_PER_PAGE_INDEX);
+    /// ```
+    pub fn max_storage_slots(&self) -> StorageIndex {
+        (self.block_page_count() * BLOCKS_PER_PAGE) as StorageIndex
+    }
+
+    fn overlaps(&self, other: &Rp2040Region) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+
+    fn page_count(&self) -> usize {
+        self.end.saturating_sub(self.start) / FLASH_PAGE_SIZE
+    }
+
+    /// Pages at the end of the region: slot metadata, counters, key-value
+    /// store and sequence index.
+    fn tail_page_count(&self) -> usize {
+        self.slot_meta_pages + self.counters * COUNTER_PAGES + self.kv_pages + self.sequence_index_pages
+    }
+
+    /// Returns the number of pages shared by control-plane replicas and block
+    /// pages, i.e. the region minus its tail area; zero when the tail does not
+    /// fit.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::{FLASH_PAGE_SIZE, Rp2040Region};
+    ///
+    /// let region = Rp2040Region::new("main", 0, 10 * FLASH_PAGE_SIZE).with_kv_store(4);
+    /// assert_eq!(region.layout_page_count(), 6);
+    /// ```
+    pub fn layout_page_count(&self) -> usize {
+        self.page_count().saturating_sub(self.tail_page_count())
+    }
+
+    /// Layout is valid, leaves at least one block page, any key-value store
+    /// has two mirrored copies of at least two pages, any sequence index can hold a record for
+    /// every slot plus one and any slot metadata pages hold an area for every
+    /// slot.
+    pub(crate) fn has_valid_layout(&self) -> bool {
+        self.control_plane.validate().is_ok()
+            && self.block_page_count() > 0
+            && kv_store::valid_page_count(self.kv_pages)
+            && (self.sequence_index_pages == 0
+                || sequence_log::capacity(self.sequence_index_pages) > self.max_storage_slots() as usize)
+            && (self.slot_meta_pages == 0
+                || slot_meta::capacity(self.slot_meta_pages) >= self.max_storage_slots() as usize)
+    }
+
+    pub(crate) fn control_plane_page_address(&self, replica_index: usize) -> usize {
+        self.start + self.control_plane.replica_unit(self.layout_page_count(), replica_index) * FLASH_PAGE_SIZE
+    }
+
+    pub(crate) fn block_page_address(&self, page_index: usize) -> usize {
+        self.start + self.control_plane.data_unit(self.layout_page_count(), page_index) * FLASH_PAGE_SIZE
+    }
+
+    pub(crate) fn sequence_index_page_address(&self, page_index: usize) -> usize {
+        self.end - (self.sequence_index_pages - page_index) * FLASH_PAGE_SIZE
+    }
+
+    pub(crate) fn kv_page_address(&self, page_index: usize) -> usize {
+        self.end - (self.sequence_index_pages + self.kv_pages - page_index) * FLASH_PAGE_SIZE
+    }
+
+    pub(crate) fn counter_page_address(&self, counter_id: usize, page_index: usize) -> usize {
+        let pages_from_end = self.sequence_index_pages + self.kv_pages + self.counters * COUNTER_PAGES;
+        self.end - (pages_from_end - counter_id * COUNTER_PAGES - page_index) * FLASH_PAGE_SIZE
+    }
+
+    pub(crate) fn slot_meta_page_address(&self, page_index: usize) -> usize {
+        self.end - (self.tail_page_count() - page_index) * FLASH_PAGE_SIZE
+    }
+
+    /// Flash address of the metadata area of `storage_index`.
+    pub(crate) fn slot_meta_address(&self, storage_index: StorageIndex) -> usize {
+        let storage_index = storage_index as usize;
+        self.slot_meta_page_address(storage_index / SLOT_META_AREAS_PER_PAGE)
+            + (storage_index % SLOT_META_AREAS_PER_PAGE) * SLOT_META_AREA_SIZE
+    }
+
+    /// Flash address of sequence log record `position` (0 is the header).
+    pub(crate) fn sequence_log_address(&self, position: usize) -> usize {
+        self.sequence_index_page_address(position / SEQUENCE_RECORDS_PER_PAGE)
+            + (position % SEQUENCE_RECORDS_PER_PAGE) * SEQUENCE_RECORD_SIZE
+    }
+}
+
+/// Returns the superblock describing the compiled RP2040 geometry of `region`.
+///
+/// Parameters:
+/// - `region`: storage region; its end is recorded as the flash size.
+///
+/// Example:
+/// ```ignore
+/// use moonblokz_storage::backend_rp2040::{FLASH_PAGE_SIZE, Rp2040Region, compiled_superblock};
+///
+/// let superblock = compiled_superblock(&Rp2040Region::new("main", 0x18_0000, 2 * 1024 * 1024));
+/// assert_eq!(superblock.flash_page_size as usize, FLASH_PAGE_SIZE);
+/// ```
+pub fn compiled_superblock(region: &Rp2040Region) -> Superblock {
+    Superblock {
+        flash_page_size: FLASH_PAGE_SIZE as u32,
+        slot_size_bytes: SLOT_SIZE_BYTES as u32,
+        blocks_per_page: BLOCKS_PER_PAGE as u32,
+        max_block_size: MAX_BLOCK_SIZE as u32,
+        hash_size: HASH_SIZE as u32,
+        data_storage_start_address: region.start as u32,
+        flash_size: region.end as u32,
+        control_plane_replicas: region.control_plane.replica_count as u32,
+        control_plane_placement: region.control_plane.placement.to_u32(),
+        sequence_index_pages: region.sequence_index_pages as u32,
+        kv_pages: region.kv_pages as u32,
+        counters: region.counters as u32,
+        slot_meta_pages: region.slot_meta_pages as u32,
+    }
+}
+
+/// Validates a linker-provided storage region and returns its flash offset.
+///
+/// Addresses are XIP bus addresses as seen by the running firmware (flash
+/// offset plus `RP2040_XIP_BASE`). The region must be page aligned, end at the
+/// end of flash and start at or after `firmware_end`.
+///
+/// Errors:
+/// - `InvalidConfiguration`: region is empty, misaligned, outside flash, does
+///   not end at the end of flash, or overlaps the firmware image.
+///
+/// Parameters:
+/// - `storage_start`: address of `__storage_start`.
+/// - `storage_end`: address of `__storage_end`.
+/// - `firmware_end`: end address of the firmware image in flash.
+/// - `flash_size`: total flash size in bytes.
+///
+/// Example:
+/// ```ignore
+/// use moonblokz_storage::backend_rp2040::storage_start_from_linker_addresses;
+///
+/// let start = storage_start_from_linker_addresses(0x1018_0000, 0x1020_0000, 0x1004_2000, 2 * 1024 * 1024)?;
+/// assert_eq!(start, 0x18_0000);
+/// ```
+pub fn storage_start_from_linker_addresses(
+    storage_start: usize,
+    storage_end: usize,
+    firmware_end: usize,
+    flash_size: usize,
+) -> Result<usize, StorageError> {
+    if storage_start < RP2040_XIP_BASE
+        || storage_end != RP2040_XIP_BASE + flash_size
+        || storage_start >= storage_end
+        || firmware_end > storage_start
+    {
+        return Err(StorageError::InvalidConfiguration);
+    }
+
+    let data_storage_start_address = storage_start - RP2040_XIP_BASE;
+    if data_storage_start_address % FLASH_PAGE_SIZE != 0 {
+        return Err(StorageError::InvalidConfiguration);
+    }
+    Ok(data_storage_start_address)
+}
+
 /// Maps a `storage_index` to RP2040 page/slot/offset coordinates.
 ///
 /// Parameters:
@@ -76,7 +485,7 @@ pub struct Rp2040SlotMapping {
 /// assert_eq!(mapping.page_index, 1);
 /// assert_eq!(mapping.slot_index, 0);
 /// ```
-fn map_storage_index(storage_index: StorageIndex) -> Rp2040SlotMapping {
+pub(crate) fn map_storage_index(storage_index: StorageIndex) -> Rp2040SlotMapping {
     let page_index = storage_index / BLOCKS_PER_PAGE_INDEX;
     let slot_index = storage_index % BLOCKS_PER_PAGE_INDEX;
     let byte_offset_in_page = slot_index as usize * SLOT_SIZE_BYTES;
@@ -88,27 +497,35 @@ fn map_storage_index(storage_index: StorageIndex) -> Rp2040SlotMapping {
     }
 }
 
+/// Returns whether a block page holds non-erased bytes after `slots_end`,
+/// erasing them in `page` when `repair` is set.
+fn scrub_page_padding(page: &mut [u8; FLASH_PAGE_SIZE], slots_end: usize, repair: bool) -> bool {
+    if page[slots_end..].iter().all(|byte| *byte == 0xFF) {
+        return false;
+    }
+    if repair {
+        page[slots_end..].fill(0xFF);
+    }
+    true
+}
+
 /// RP2040 backend implementation.
-pub struct Rp2040Backend<const RP2040_FLASH_SIZE: usize = RP2040_DEFAULT_FLASH_SIZE> {
+pub struct Rp2040Backend<
+    const RP2040_FLASH_SIZE: usize = RP2040_DEFAULT_FLASH_SIZE,
+    O: StorageObserver = NoopObserver,
+> {
     #[cfg(all(not(test), target_arch = "arm"))]
     flash: RefCell<Flash<'static, FLASH, Blocking, RP2040_FLASH_SIZE>>,
-    data_storage_start_address: usize,
-    max_storage_slots: StorageIndex,
+    region: Rp2040Region,
+    partitions: &'static [Rp2040Region],
     page_buffer: RefCell<[u8; FLASH_PAGE_SIZE]>,
     #[cfg(any(test, not(target_arch = "arm")))]
     flash_mock: RefCell<MockFlash<RP2040_FLASH_SIZE>>,
+    key_protection: KeyProtection,
+    observer: O,
 }
 
 impl<const RP2040_FLASH_SIZE: usize> Rp2040Backend<RP2040_FLASH_SIZE> {
-    fn validate_page_aligned_start_address(
-        data_storage_start_address: usize,
-    ) -> Result<(), StorageError> {
-        if data_storage_start_address % FLASH_PAGE_SIZE != 0 {
-            return Err(StorageError::InvalidConfiguration);
-        }
-        Ok(())
-    }
-
     /// Creates a new RP2040 backend instance.
     ///
     /// Parameters:
@@ -129,16 +546,56 @@ impl<const RP2040_FLASH_SIZE: usize> Rp2040Backend<RP2040_FLASH_SIZE> {
         data_storage_start_address: usize,
     ) -> Result<Self, StorageError> {
         Self::validate_page_aligned_start_address(data_storage_start_address)?;
-        let max_storage_slots = Self::calculate_max_storage_slots(data_storage_start_address);
 
         Ok(Self {
             flash: RefCell::new(Flash::new_blocking(flash_peripheral)),
-            data_storage_start_address,
-            max_storage_slots,
+            region: Rp2040Region::new(MAIN_REGION_NAME, data_storage_start_address, RP2040_FLASH_SIZE),
+            partitions: &[],
             page_buffer: RefCell::new([0xFF; FLASH_PAGE_SIZE]),
+            key_protection: KeyProtection::Plaintext,
+            observer: NoopObserver,
         })
     }
 
+    /// Creates an RP2040 backend over the storage region reserved by the linker script.
+    ///
+    /// The region is taken from the `__storage_start` / `__storage_end` symbols
+    /// (see `linker/moonblokz-storage.x`). The firmware image end is computed
+    /// from the `cortex-m-rt` symbols `__sidata`, `__sdata` and `__edata`; the
+    /// constructor refuses to start if the image reaches into the region.
+    ///
+    /// Parameters:
+    /// - `flash_peripheral`: RP2040 flash peripheral.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use embassy_rp::init;
+    /// use moonblokz_storage::backend_rp2040::Rp2040Backend;
+    ///
+    /// let peripherals = init(Default::default());
+    /// let _backend = Rp2040Backend::<{ 2 * 1024 * 1024 }>::from_linker_symbols(peripherals.FLASH)?;
+    /// ```
+    #[cfg(all(not(test), target_arch = "arm"))]
+    pub fn from_linker_symbols(flash_peripheral: Peri<'static, FLASH>) -> Result<Self, StorageError> {
+        unsafe extern "C" {
+            safe static __storage_start: u8;
+            safe static __storage_end: u8;
+            safe static __sidata: u8;
+            safe static __sdata: u8;
+            safe static __edata: u8;
+        }
+
+        let data_size = core::ptr::addr_of!(__edata) as usize - core::ptr::addr_of!(__sdata) as usize;
+        let firmware_end = core::ptr::addr_of!(__sidata) as usize + data_size;
+        let data_storage_start_address = storage_start_from_linker_addresses(
+            core::ptr::addr_of!(__storage_start) as usize,
+            core::ptr::addr_of!(__storage_end) as usize,
+            firmware_end,
+            RP2040_FLASH_SIZE,
+        )?;
+        Self::new(flash_peripheral, data_storage_start_address)
+    }
+
     /// Creates a host/non-ARM RP2040 backend with an in-memory flash mock.
     ///
     /// Parameters:
@@ -153,13 +610,14 @@ impl<const RP2040_FLASH_SIZE: usize> Rp2040Backend<RP2040_FLASH_SIZE> {
     #[cfg(not(target_arch = "arm"))]
     pub fn new(data_storage_start_address: usize) -> Result<Self, StorageError> {
         Self::validate_page_aligned_start_address(data_storage_start_address)?;
-        let max_storage_slots = Self::calculate_max_storage_slots(data_storage_start_address);
 
         Ok(Self {
-            data_storage_start_address,
-            max_storage_slots,
+            region: Rp2040Region::new(MAIN_REGION_NAME, data_storage_start_address, RP2040_FLASH_SIZE),
+            partitions: &[],
             page_buffer: RefCell::new([0xFF; FLASH_PAGE_SIZE]),
             flash_mock: RefCell::new(MockFlash::new()),
+            key_protection: KeyProtection::Plaintext,
+            observer: NoopObserver,
         })
     }
 
@@ -177,111 +635,659 @@ impl<const RP2040_FLASH_SIZE: usize> Rp2040Backend<RP2040_FLASH_SIZE> {
     #[cfg(test)]
     pub fn new_for_tests(data_storage_start_address: usize) -> Result<Self, StorageError> {
         Self::validate_page_aligned_start_address(data_storage_start_address)?;
-        let max_storage_slots = Self::calculate_max_storage_slots(data_storage_start_address);
 
         Ok(Self {
-            data_storage_start_address,
-            max_storage_slots,
+            region: Rp2040Region::new(MAIN_REGION_NAME, data_storage_start_address, RP2040_FLASH_SIZE),
+            partitions: &[],
             page_buffer: RefCell::new([0xFF; FLASH_PAGE_SIZE]),
             flash_mock: RefCell::new(MockFlash::new()),
+            key_protection: KeyProtection::Plaintext,
+            observer: NoopObserver,
         })
     }
 
-    fn calculate_max_storage_slots(data_storage_start_address: usize) -> StorageIndex {
-        let available_bytes = RP2040_FLASH_SIZE.saturating_sub(data_storage_start_address);
-        let block_storage_bytes = available_bytes.saturating_sub(CONTROL_PLANE_RESERVED_BYTES);
-        let usable_pages = block_storage_bytes / FLASH_PAGE_SIZE;
-        (usable_pages * BLOCKS_PER_PAGE) as StorageIndex
+    /// Creates a host/non-ARM RP2040 backend whose flash mock is loaded from a raw flash image.
+    ///
+    /// Parameters:
+    /// - `data_storage_start_address`: first flash address reserved for block storage.
+    /// - `image`: flash contents starting at flash offset zero; missing tail bytes stay erased.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::Rp2040Backend;
+    ///
+    /// let image = std::fs::read("flash.bin")?;
+    /// let _backend = Rp2040Backend::<{ 2 * 1024 * 1024 }>::from_flash_image(0x18_0000, &image)?;
+    /// ```
+    #[cfg(not(target_arch = "arm"))]
+    pub fn from_flash_image(
+        data_storage_start_address: usize,
+        image: &[u8],
+    ) -> Result<Self, StorageError> {
+        if image.len() > RP2040_FLASH_SIZE {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        let backend = Self::new(data_storage_start_address)?;
+        backend.flash_mock.borrow_mut().data[..image.len()].copy_from_slice(image);
+        Ok(backend)
     }
+}
 
-    fn page_flash_address(&self, mapping: &Rp2040SlotMapping) -> usize {
-        self.data_storage_start_address
-            + CONTROL_PLANE_RESERVED_BYTES
-            + mapping.page_index as usize * FLASH_PAGE_SIZE
+impl<const RP2040_FLASH_SIZE: usize, O: StorageObserver> Rp2040Backend<RP2040_FLASH_SIZE, O> {
+    /// Replaces the backend observer, keeping flash state and geometry unchanged.
+    ///
+    /// Parameters:
+    /// - `observer`: event hooks invoked on storage activity.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::{NoopObserver, backend_rp2040::Rp2040Backend};
+    ///
+    /// let _backend = Rp2040Backend::<{ 8 * 4096 }>::new(0)?.with_observer(NoopObserver);
+    /// ```
+    pub fn with_observer<P: StorageObserver>(self, observer: P) -> Rp2040Backend<RP2040_FLASH_SIZE, P> {
+        Rp2040Backend {
+            #[cfg(all(not(test), target_arch = "arm"))]
+            flash: self.flash,
+            region: self.region,
+            partitions: self.partitions,
+            page_buffer: self.page_buffer,
+            #[cfg(any(test, not(target_arch = "arm")))]
+            flash_mock: self.flash_mock,
+            key_protection: self.key_protection,
+            observer,
+        }
     }
 
-    #[cfg(test)]
-    fn slot_flash_address(&self, mapping: &Rp2040SlotMapping) -> usize {
-        self.page_flash_address(mapping) + mapping.byte_offset_in_page
+    /// Sets how the private key is protected in control-plane replicas.
+    ///
+    /// With a wrapping key configured, `load_control_data` transparently
+    /// unwraps the key. Plaintext replicas are only accepted from control-plane
+    /// version 1 and rewritten wrapped; a current plaintext replica fails with
+    /// `KeyUnwrapFailed`, so use `set_key_protection` to wrap an existing key.
+    ///
+    /// Parameters:
+    /// - `protection`: key protection mode (see `KeyProtection::from_secret`).
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::KeyProtection;
+    /// use moonblokz_storage::backend_rp2040::Rp2040Backend;
+    ///
+    /// let backend = Rp2040Backend::<{ 2 * 1024 * 1024 }>::new(p.FLASH, 0x18_0000)?;
+    /// let unique_id = backend.flash_unique_id()?;
+    /// let protection = KeyProtection::from_secret(b"fleet secret", Some(&unique_id))?;
+    /// let backend = backend.with_key_protection(protection);
+    /// ```
+    pub fn with_key_protection(mut self, protection: KeyProtection) -> Self {
+        self.key_protection = protection;
+        self
     }
 
-    fn write_slot(&self, mapping: &Rp2040SlotMapping, block: &Block) -> Result<(), StorageError> {
-        let page_address = self.page_flash_address(mapping);
-
-        #[cfg(any(test, not(target_arch = "arm")))]
-        {
-            return self.write_slot_with_mock(page_address, mapping, block);
-        }
-
-        #[cfg(all(not(test), target_arch = "arm"))]
-        {
-            return self.write_slot_with_flash(page_address, mapping, block);
-        }
+    /// Protects `slots` against overwrites: `save_block` to a protected slot
+    /// that is not erased fails with `SlotOccupied` before anything is
+    /// written. A damaged slot counts as occupied, so history is never
+    /// replaced silently; repair it with `force_overwrite`.
+    ///
+    /// The policy applies to the backend's own region; partitions set it per
+    /// region with `Rp2040Region::with_write_once`.
+    ///
+    /// Parameters:
+    /// - `slots`: protected storage indices (`WriteOnceSlots::ALL` for every slot).
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::WriteOnceSlots;
+    /// use moonblokz_storage::backend_rp2040::Rp2040Backend;
+    ///
+    /// let backend = Rp2040Backend::<{ 2 * 1024 * 1024 }>::new(p.FLASH, 0x18_0000)?.with_write_once(WriteOnceSlots::ALL);
+    /// ```
+    pub fn with_write_once(mut self, slots: WriteOnceSlots) -> Self {
+        self.region.write_once = slots;
+        self
+    }
 
-        #[allow(unreachable_code)]
-        Err(StorageError::BackendIo { code: 213 })
+    /// Saves `block` to `storage_index` even when the slot is write-once and
+    /// occupied; use it for deliberate history rewrites and repairs only.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `storage_index` is outside the slot range.
+    /// - `BackendIo` when a flash operation fails.
+    ///
+    /// Parameters:
+    /// - `storage_index`: destination slot index.
+    /// - `block`: block to persist.
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.force_overwrite(4, &fork_block)?;
+    /// ```
+    pub fn force_overwrite(&mut self, storage_index: StorageIndex, block: &Block) -> Result<(), StorageError> {
+        self.write_block_in(&self.region, storage_index, block, WriteCondition::Any, false)
     }
 
-    #[cfg(all(not(test), target_arch = "arm"))]
-    fn write_slot_with_flash(
-        &self,
-        page_address: usize,
-        mapping: &Rp2040SlotMapping,
-        block: &Block,
-    ) -> Result<(), StorageError> {
-        let mut flash = self.flash.borrow_mut();
-        let mut page_buffer = self.page_buffer.borrow_mut();
-        flash
-            .blocking_read(page_address as u32, &mut page_buffer[..])
-            .map_err(|_| StorageError::BackendIo { code: 210 })?;
+    /// Replaces the block in `storage_index` only if the slot still holds the
+    /// block hashing to `expected_hash`.
+    ///
+    /// The stored hash is checked against the same page read that the page
+    /// rewrite uses. Write-once slots still refuse the replacement.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `storage_index` is outside the slot range.
+    /// - `Conflict`: the slot is empty or holds another block; `current_hash`
+    ///   reports its stored hash.
+    /// - `SlotOccupied`: the slot is write-once.
+    /// - `BackendIo` when a flash operation fails.
+    ///
+    /// Parameters:
+    /// - `storage_index`: slot to replace.
+    /// - `expected_hash`: `block_hash` of the block the replacement was validated against.
+    /// - `block`: replacement block.
+    ///
+    /// Example:
+    /// ```ignore
+    /// match backend.replace_block(4, &block_hash(&old_tip), &new_tip) {
+    ///     Err(StorageError::Conflict { current_hash }) => resync(current_hash),
+    ///     result => result?,
+    /// }
+    /// ```
+    pub fn replace_block(
+        &mut self,
+        storage_index: StorageIndex,
+        expected_hash: &[u8; HASH_SIZE],
+        block: &Block,
+    ) -> Result<(), StorageError> {
+        let write_once = self.region.write_once.contains(storage_index);
+        self.write_block_in(
+            &self.region,
+            storage_index,
+            block,
+            WriteCondition::Holds(*expected_hash),
+            write_once,
+        )
+    }
 
-        Self::encode_block_to_slot(&mut page_buffer[..], mapping, block)?;
+    /// Saves `block` to `storage_index` only if the slot is erased.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `storage_index` is outside the slot range.
+    /// - `Conflict`: the slot holds a block; `current_hash` is its stored hash.
+    /// - `BackendIo` when a flash operation fails.
+    ///
+    /// Parameters:
+    /// - `storage_index`: destination slot index.
+    /// - `block`: block to persist.
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.insert_block(5, &block)?;
+    /// ```
+    pub fn insert_block(&mut self, storage_index: StorageIndex, block: &Block) -> Result<(), StorageError> {
+        self.write_block_in(&self.region, storage_index, block, WriteCondition::Empty, false)
+    }
 
-        flash
-            .blocking_erase(page_address as u32, (page_address + FLASH_PAGE_SIZE) as u32)
-            .map_err(|_| StorageError::BackendIo { code: 211 })?;
+    /// Returns the metadata attached to the block in `storage_index`, `None`
+    /// when none was written since the block was saved.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: no slot metadata pages are configured
+    ///   (`with_slot_meta`).
+    /// - `InvalidIndex`: `storage_index` is outside the slot range.
+    /// - `BlockAbsent`: the slot holds no block.
+    /// - `BackendIo` when a flash read fails.
+    ///
+    /// Parameters:
+    /// - `storage_index`: slot to read.
+    ///
+    /// Example:
+    /// ```ignore
+    /// if let Some(meta) = backend.read_slot_meta(4)? {
+    ///     let received_at = u32::from_le_bytes([meta[0], meta[1], meta[2], meta[3]]);
+    /// }
+    /// ```
+    #[cfg(feature = "slot-meta")]
+    pub fn read_slot_meta(&self, storage_index: StorageIndex) -> Result<Option<[u8; SLOT_META_SIZE]>, StorageError> {
+        self.read_slot_meta_in(&self.region, storage_index)
+    }
 
-        flash
-            .blocking_write(page_address as u32, &page_buffer[..])
-            .map_err(|_| StorageError::BackendIo { code: 212 })?;
+    /// Attaches `meta` to the block in `storage_index` without touching the
+    /// block or its hash.
+    ///
+    /// The update programs the next erased record of the slot's area in the
+    /// metadata pages (see `slot_meta`), so no erase is needed until all
+    /// `SLOT_META_GENERATIONS` records are used; the update after that
+    /// rewrites the metadata page. Saving a block to the slot clears its
+    /// metadata.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: no slot metadata pages are configured
+    ///   (`with_slot_meta`).
+    /// - `InvalidIndex`: `storage_index` is outside the slot range.
+    /// - `BlockAbsent`: the slot holds no block.
+    /// - `BackendIo` when a flash operation fails.
+    ///
+    /// Parameters:
+    /// - `storage_index`: slot to annotate.
+    /// - `meta`: metadata value.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let mut meta = [0u8; SLOT_META_SIZE];
+    /// meta[..4].copy_from_slice(&received_at.to_le_bytes());
+    /// backend.write_slot_meta(4, &meta)?;
+    /// ```
+    #[cfg(feature = "slot-meta")]
+    pub fn write_slot_meta(
+        &mut self,
+        storage_index: StorageIndex,
+        meta: &[u8; SLOT_META_SIZE],
+    ) -> Result<(), StorageError> {
+        self.write_slot_meta_in(&self.region, storage_index, meta)
+    }
 
-        Ok(())
+    /// Returns the value stored for `key` in the key-value store, `None` when
+    /// it has none.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: no key-value store is configured.
+    /// - `BackendIo` when a flash read fails.
+    ///
+    /// Parameters:
+    /// - `key`: key bytes.
+    ///
+    /// Example:
+    /// ```ignore
+    /// if let Some(value) = backend.kv_get(b"boots")? {
+    ///     let boots = u32::from_le_bytes(value.as_bytes().try_into().unwrap_or([0; 4]));
+    /// }
+    /// ```
+    pub fn kv_get(&self, key: &[u8]) -> Result<Option<KvValue>, StorageError> {
+        kv_store::get(&self.region_kv_pages(&self.region)?, key)
     }
 
-    #[cfg(any(test, not(target_arch = "arm")))]
-    fn write_slot_with_mock(
-        &self,
-        page_address: usize,
-        mapping: &Rp2040SlotMapping,
-        block: &Block,
-    ) -> Result<(), StorageError> {
-        let mut flash_mock = self.flash_mock.borrow_mut();
-        let mut page_buffer = self.page_buffer.borrow_mut();
-        flash_mock
-            .read(page_address as u32, &mut page_buffer[..])
-            .map_err(|code| StorageError::BackendIo { code })?;
+    /// Stores `value` for `key` in the key-value store, replacing any previous value.
+    ///
+    /// The record is programmed after the last one of the active page; a full
+    /// page is compacted into the next page, which becomes active once its
+    /// header is written (see `kv_store`).
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: no key-value store is configured.
+    /// - `KvInvalidEntry`: the key is empty or too long, or the value is too long.
+    /// - `KvFull`: the live entries do not fit in one page.
+    /// - `BackendIo` when a flash operation fails.
+    ///
+    /// Parameters:
+    /// - `key`: key bytes, 1 to `KV_MAX_KEY_SIZE` long.
+    /// - `value`: value bytes, at most `KV_MAX_VALUE_SIZE` long.
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.kv_put(b"boots", &boots.to_le_bytes())?;
+    /// ```
+    pub fn kv_put(&mut self, key: &[u8], value: &[u8]) -> Result<(), StorageError> {
+        kv_store::put(&mut self.region_kv_pages(&self.region)?, key, value)
+    }
 
-        Self::encode_block_to_slot(&mut page_buffer[..], mapping, block)?;
+    /// Removes the value stored for `key`; removing a missing key succeeds.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: no key-value store is configured.
+    /// - `KvInvalidEntry`: the key is empty or too long.
+    /// - `KvFull`: the live entries do not fit in one page.
+    /// - `BackendIo` when a flash operation fails.
+    ///
+    /// Parameters:
+    /// - `key`: key bytes.
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.kv_delete(b"peer")?;
+    /// ```
+    pub fn kv_delete(&mut self, key: &[u8]) -> Result<(), StorageError> {
+        kv_store::delete(&mut self.region_kv_pages(&self.region)?, key)
+    }
 
-        flash_mock
-            .erase(page_address as u32, (page_address + FLASH_PAGE_SIZE) as u32)
-            .map_err(|code| StorageError::BackendIo { code })?;
+    /// Returns a handle to monotonic counter `id`.
+    ///
+    /// An increment clears one bit of the counter's active page, so it
+    /// programs a single byte; a page is erased only once every
+    /// `COUNTER_STEPS_PER_PAGE` increments (see the `counter` module).
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `id` is not below the count passed to `with_counters`.
+    ///
+    /// Parameters:
+    /// - `id`: counter index.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let boots = backend.counter(0)?.increment()?;
+    /// let nonce = backend.counter(1)?.get()?;
+    /// ```
+    pub fn counter(&self, id: usize) -> Result<Rp2040Counter<'_, RP2040_FLASH_SIZE, O>, StorageError> {
+        Rp2040Counter::new(self, &self.region, id)
+    }
 
-        flash_mock
-            .write(page_address as u32, &page_buffer[..])
-            .map_err(|code| StorageError::BackendIo { code })?;
+    /// Sets the control-plane replica count and placement of the storage region.
+    ///
+    /// With `Spread` or `StartAndEnd` placement one bad sector cluster no
+    /// longer takes out several replicas. The layout is recorded in every
+    /// superblock, so a region written with another layout reports
+    /// `LayoutMismatch` instead of being misread. Configure it before `init`;
+    /// partitions carry their own layout in the partition table.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: invalid replica count, no block page left,
+    ///   or partitions are configured.
+    ///
+    /// Parameters:
+    /// - `layout`: replica count and placement.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::Rp2040Backend;
+    /// use moonblokz_storage::{ControlPlaneLayout, ControlPlanePlacement};
+    ///
+    /// let layout = ControlPlaneLayout::new(5, ControlPlanePlacement::Spread);
+    /// let backend = Rp2040Backend::<{ 2 * 1024 * 1024 }>::new(p.FLASH, 0x18_0000)?.with_control_plane_layout(layout)?;
+    /// ```
+    pub fn with_control_plane_layout(mut self, layout: ControlPlaneLayout) -> Result<Self, StorageError> {
+        let region = self.region.with_control_plane_layout(layout);
+        if !region.has_valid_layout() || !self.partitions.is_empty() {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        self.region = region;
+        Ok(self)
+    }
+
+    /// Reserves the last `pages` pages of the storage region for a persisted
+    /// sequence index.
+    ///
+    /// The index speeds up `find_by_sequence`, `max_sequence`,
+    /// `range_by_sequence` and `find_by_hash`, which otherwise read every slot.
+    /// Its page count is recorded in every superblock, so configure it before
+    /// `init`; partitions set it per region with
+    /// `Rp2040Region::with_sequence_index`.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: no block page left, the pages cannot hold a
+    ///   record for every slot plus one, or partitions are configured.
+    ///
+    /// Parameters:
+    /// - `pages`: sequence index pages (0 disables the index).
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::Rp2040Backend;
+    ///
+    /// let backend = Rp2040Backend::<{ 2 * 1024 * 1024 }>::new(p.FLASH, 0x18_0000)?.with_sequence_index(1)?;
+    /// ```
+    pub fn with_sequence_index(mut self, pages: usize) -> Result<Self, StorageError> {
+        let region = self.region.with_sequence_index(pages);
+        if !region.has_valid_layout() || !self.partitions.is_empty() {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        self.region = region;
+        Ok(self)
+    }
+
+    /// Reserves `pages` pages before any sequence index for the key-value
+    /// store (`kv_get`, `kv_put`, `kv_delete`).
+    ///
+    /// The store keeps two mirrored copies of at least two pages, so `pages`
+    /// must be even and at least 4. Its page count is recorded in every
+    /// superblock and `init` erases the pages, so configure it before `init`;
+    /// partitions set it per region with `Rp2040Region::with_kv_store`.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: `pages` is odd or 2, no block page left, or
+    ///   partitions are configured.
+    ///
+    /// Parameters:
+    /// - `pages`: key-value pages (0 disables the store).
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::Rp2040Backend;
+    ///
+    /// let backend = Rp2040Backend::<{ 2 * 1024 * 1024 }>::new(p.FLASH, 0x18_0000)?.with_kv_store(4)?;
+    /// ```
+    pub fn with_kv_store(mut self, pages: usize) -> Result<Self, StorageError> {
+        let region = self.region.with_kv_store(pages);
+        if !region.has_valid_layout() || !self.partitions.is_empty() {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        self.region = region;
+        Ok(self)
+    }
+
+    /// Reserves `count` monotonic counters of `COUNTER_PAGES` pages each
+    /// before the key-value store (`counter`).
+    ///
+    /// The counter count is recorded in every superblock, so configure it
+    /// before `init`; partitions set it per region with
+    /// `Rp2040Region::with_counters`. `init` and `import_from` leave the pages
+    /// alone, so with an unchanged layout the counters keep counting up across
+    /// re-provisioning; only `decommission` clears them.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: no block page left, or partitions are configured.
+    ///
+    /// Parameters:
+    /// - `count`: number of counters (0 disables them).
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::Rp2040Backend;
+    ///
+    /// let backend = Rp2040Backend::<{ 2 * 1024 * 1024 }>::new(p.FLASH, 0x18_0000)?.with_counters(2)?;
+    /// ```
+    pub fn with_counters(mut self, count: usize) -> Result<Self, StorageError> {
+        let region = self.region.with_counters(count);
+        if !region.has_valid_layout() || !self.partitions.is_empty() {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        self.region = region;
+        Ok(self)
+    }
+
+    /// Reserves `pages` pages before the counters for slot metadata
+    /// (`read_slot_meta`, `write_slot_meta`; see `slot_meta`).
+    ///
+    /// Every page holds the metadata areas of `SLOT_META_AREAS_PER_PAGE`
+    /// slots, so the pages must cover every block slot left after them. The
+    /// page count is recorded in every superblock and `init` erases the
+    /// pages, so configure it before `init`; partitions set it per region
+    /// with `Rp2040Region::with_slot_meta`. The pages are reserved with or
+    /// without the `slot-meta` feature, which only gates the metadata calls.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: no block page left, the pages cannot hold an
+    ///   area for every slot, or partitions are configured.
+    ///
+    /// Parameters:
+    /// - `pages`: slot metadata pages (0 disables them).
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::Rp2040Backend;
+    ///
+    /// let backend = Rp2040Backend::<{ 2 * 1024 * 1024 }>::new(p.FLASH, 0x18_0000)?.with_slot_meta(8)?;
+    /// ```
+    pub fn with_slot_meta(mut self, pages: usize) -> Result<Self, StorageError> {
+        let region = self.region.with_slot_meta(pages);
+        if !region.has_valid_layout() || !self.partitions.is_empty() {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        self.region = region;
+        Ok(self)
+    }
+
+    /// Splits the storage area into named partitions.
+    ///
+    /// Every region gets its own control-plane replicas, superblock and slot
+    /// space; `partition` returns a `StorageTrait` handle for one of them. The
+    /// backend's own `StorageTrait` implementation, `fsck`, export/import and
+    /// the control-data updates act on the first region.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: no regions, a region is misaligned, starts
+    ///   below the backend start address, ends past the flash, has an invalid
+    ///   control-plane layout, no block page, a too small sequence index, a
+    ///   one-page key-value store or too few slot metadata pages, overlaps
+    ///   another region, or reuses a name.
+    ///
+    /// Parameters:
+    /// - `regions`: partition table, first region first.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::{Rp2040Backend, Rp2040Region};
+    ///
+    /// const PARTITIONS: [Rp2040Region; 2] = [
+    ///     Rp2040Region::new("main", 0x18_0000, 0x1E_0000),
+    ///     Rp2040Region::new("testnet", 0x1E_0000, 0x20_0000),
+    /// ];
+    /// let backend = Rp2040Backend::<{ 2 * 1024 * 1024 }>::new(p.FLASH, 0x18_0000)?.with_partitions(&PARTITIONS)?;
+    /// ```
+    pub fn with_partitions(mut self, regions: &'static [Rp2040Region]) -> Result<Self, StorageError> {
+        if regions.is_empty() {
+            return Err(StorageError::InvalidConfiguration);
+        }
+
+        let mut index = 0usize;
+        while index < regions.len() {
+            let region = &regions[index];
+            if region.start % FLASH_PAGE_SIZE != 0
+                || region.end % FLASH_PAGE_SIZE != 0
+                || region.start < self.region.start
+                || region.end > RP2040_FLASH_SIZE
+                || !region.has_valid_layout()
+            {
+                return Err(StorageError::InvalidConfiguration);
+            }
+            let mut other = 0usize;
+            while other < index {
+                if region.overlaps(&regions[other]) || region.name == regions[other].name {
+                    return Err(StorageError::InvalidConfiguration);
+                }
+                other += 1;
+            }
+            index += 1;
+        }
+
+        self.region = regions[0];
+        self.partitions = regions;
+        Ok(self)
+    }
+
+    /// Returns a `StorageTrait` handle for the partition called `name`.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: no partition with that name is configured.
+    ///
+    /// Parameters:
+    /// - `name`: region name from the `with_partitions` table.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::StorageTrait;
+    ///
+    /// let mut testnet = backend.partition("testnet")?;
+    /// testnet.init(private_key, 7, init_params)?;
+    /// ```
+    pub fn partition(&self, name: &str) -> Result<Rp2040Partition<'_, RP2040_FLASH_SIZE, O>, StorageError> {
+        let mut index = 0usize;
+        while index < self.partitions.len() {
+            if self.partitions[index].name == name {
+                return Ok(Rp2040Partition {
+                    backend: self,
+                    region: self.partitions[index],
+                });
+            }
+            index += 1;
+        }
+        Err(StorageError::InvalidConfiguration)
+    }
+
+    /// Returns the configured partition table (empty when unpartitioned).
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// for region in backend.partitions() {
+    ///     let _slots = region.max_storage_slots();
+    /// }
+    /// ```
+    pub fn partitions(&self) -> &'static [Rp2040Region] {
+        self.partitions
+    }
+
+    /// Reads the 64-bit unique id of the RP2040 flash chip.
+    ///
+    /// Host builds return a fixed id so wrapped images stay reproducible.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let unique_id = backend.flash_unique_id()?;
+    /// ```
+    pub fn flash_unique_id(&self) -> Result<[u8; FLASH_UNIQUE_ID_SIZE], StorageError> {
+        let mut unique_id = [0u8; FLASH_UNIQUE_ID_SIZE];
+
+        #[cfg(any(test, not(target_arch = "arm")))]
+        {
+            unique_id.copy_from_slice(&MOCK_FLASH_UNIQUE_ID);
+            return Ok(unique_id);
+        }
+
+        #[cfg(all(not(test), target_arch = "arm"))]
+        {
+            let mut flash = self.flash.borrow_mut();
+            return flash
+                .blocking_unique_id(&mut unique_id)
+                .map(|_| unique_id)
+                .map_err(|_| StorageError::BackendIo { code: 214 });
+        }
 
+        #[allow(unreachable_code)]
+        Err(StorageError::BackendIo { code: 214 })
+    }
+
+    fn validate_page_aligned_start_address(
+        data_storage_start_address: usize,
+    ) -> Result<(), StorageError> {
+        if data_storage_start_address % FLASH_PAGE_SIZE != 0 {
+            return Err(StorageError::InvalidConfiguration);
+        }
         Ok(())
     }
 
-    fn encode_block_to_slot(
+    fn page_flash_address(region: &Rp2040Region, mapping: &Rp2040SlotMapping) -> usize {
+        region.block_page_address(mapping.page_index as usize)
+    }
+
+    #[cfg(test)]
+    fn slot_flash_address(&self, mapping: &Rp2040SlotMapping) -> usize {
+        Self::page_flash_address(&self.region, mapping) + mapping.byte_offset_in_page
+    }
+
+    pub(crate) fn encode_block_to_slot(
         page_buffer: &mut [u8],
         mapping: &Rp2040SlotMapping,
         block: &Block,
     ) -> Result<(), StorageError> {
         let slot_start = mapping.byte_offset_in_page;
         let slot_end = slot_start + SLOT_SIZE_BYTES;
-        page_buffer[slot_start..slot_end].fill(0);
+        let hash_end = slot_start + SLOT_HASH_OFFSET + HASH_SIZE;
+        page_buffer[slot_start..hash_end].fill(0);
+        // A new block starts without metadata.
+        page_buffer[hash_end..slot_end].fill(0xFF);
 
         let block_bytes = block.serialized_bytes();
         if block_bytes.len() > MAX_BLOCK_SIZE {
@@ -291,15 +1297,14 @@ impl<const RP2040_FLASH_SIZE: usize> Rp2040Backend<RP2040_FLASH_SIZE> {
         page_buffer[slot_start..data_end].copy_from_slice(block_bytes);
 
         let hash_start = slot_start + SLOT_HASH_OFFSET;
-        let hash_end = hash_start + HASH_SIZE;
         let computed_hash = calculate_hash(&page_buffer[slot_start..slot_start + MAX_BLOCK_SIZE]);
         page_buffer[hash_start..hash_end].copy_from_slice(&computed_hash);
 
         Ok(())
     }
 
-    fn read_slot(&self, mapping: &Rp2040SlotMapping) -> Result<Block, StorageError> {
-        let page_address = self.page_flash_address(mapping);
+    fn read_slot(&self, region: &Rp2040Region, mapping: &Rp2040SlotMapping) -> Result<Block, StorageError> {
+        let page_address = Self::page_flash_address(region, mapping);
 
         #[cfg(any(test, not(target_arch = "arm")))]
         {
@@ -349,8 +1354,22 @@ impl<const RP2040_FLASH_SIZE: usize> Rp2040Backend<RP2040_FLASH_SIZE> {
         Self::decode_slot_block(&page_buffer[slot_start..slot_end])
     }
 
-    fn decode_slot_block(slot_bytes: &[u8]) -> Result<Block, StorageError> {
-        if slot_bytes.iter().all(|byte| *byte == 0xFF) {
+    /// Decodes one persisted slot and verifies its stored hash.
+    ///
+    /// Parameters:
+    /// - `slot_bytes`: raw slot bytes, at least `SLOT_SIZE_BYTES` long.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::StorageError;
+    /// use moonblokz_storage::backend_rp2040::{Rp2040Backend, SLOT_SIZE_BYTES};
+    ///
+    /// let erased = [0xFFu8; SLOT_SIZE_BYTES];
+    /// let result = Rp2040Backend::<{ 2 * 1024 * 1024 }>::decode_slot_block(&erased);
+    /// assert!(matches!(result, Err(StorageError::BlockAbsent)));
+    /// ```
+    pub fn decode_slot_block(slot_bytes: &[u8]) -> Result<Block, StorageError> {
+        if slot_bytes[..SLOT_HASH_OFFSET + HASH_SIZE].iter().all(|byte| *byte == 0xFF) {
             return Err(StorageError::BlockAbsent);
         }
 
@@ -367,6 +1386,23 @@ impl<const RP2040_FLASH_SIZE: usize> Rp2040Backend<RP2040_FLASH_SIZE> {
         Block::from_bytes(&slot_bytes[..MAX_BLOCK_SIZE]).map_err(|_| StorageError::IntegrityFailure)
     }
 
+    /// Returns the full host flash-mock contents.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::Rp2040Backend;
+    ///
+    /// let backend = Rp2040Backend::<{ 8 * 4096 }>::new(0)?;
+    /// assert_eq!(backend.flash_image().len(), 8 * 4096);
+    /// ```
+    #[cfg(any(test, not(target_arch = "arm")))]
+    pub fn flash_image(&self) -> core::cell::Ref<'_, [u8]> {
+        core::cell::Ref::map(self.flash_mock.borrow(), |flash_mock| &flash_mock.data[..])
+    }
+
     #[cfg(test)]
     fn with_corrupted_mock_slot_byte(&self, storage_index: StorageIndex, byte_index: usize) {
         let mapping = map_storage_index(storage_index);
@@ -382,16 +1418,16 @@ impl<const RP2040_FLASH_SIZE: usize> Rp2040Backend<RP2040_FLASH_SIZE> {
         self.flash_mock.borrow_mut().data[slot_start..slot_end].copy_from_slice(slot_bytes);
     }
 
-    fn control_plane_page_address(&self, replica_index: usize) -> usize {
-        self.data_storage_start_address + replica_index * FLASH_PAGE_SIZE
+    fn read_page(&self, page_address: usize, out: &mut [u8; FLASH_PAGE_SIZE]) -> Result<(), StorageError> {
+        self.read_flash(page_address, out)
     }
 
-    fn read_page(&self, page_address: usize, out: &mut [u8; FLASH_PAGE_SIZE]) -> Result<(), StorageError> {
+    fn read_flash(&self, address: usize, out: &mut [u8]) -> Result<(), StorageError> {
         #[cfg(any(test, not(target_arch = "arm")))]
         {
             let flash_mock = self.flash_mock.borrow();
             return flash_mock
-                .read(page_address as u32, out)
+                .read(address as u32, out)
                 .map_err(|code| StorageError::BackendIo { code });
         }
 
@@ -399,7 +1435,7 @@ impl<const RP2040_FLASH_SIZE: usize> Rp2040Backend<RP2040_FLASH_SIZE> {
         {
             let mut flash = self.flash.borrow_mut();
             return flash
-                .blocking_read(page_address as u32, out)
+                .blocking_read(address as u32, out)
                 .map_err(|_| StorageError::BackendIo { code: 210 });
         }
 
@@ -412,17 +1448,21 @@ impl<const RP2040_FLASH_SIZE: usize> Rp2040Backend<RP2040_FLASH_SIZE> {
         #[cfg(any(test, not(target_arch = "arm")))]
         {
             let mut flash_mock = self.flash_mock.borrow_mut();
-            return flash_mock
+            flash_mock
                 .erase(page_address as u32, page_end as u32)
-                .map_err(|code| StorageError::BackendIo { code });
+                .map_err(|code| StorageError::BackendIo { code })?;
+            self.observer.on_page_erased(page_address);
+            return Ok(());
         }
 
         #[cfg(all(not(test), target_arch = "arm"))]
         {
             let mut flash = self.flash.borrow_mut();
-            return flash
+            flash
                 .blocking_erase(page_address as u32, page_end as u32)
-                .map_err(|_| StorageError::BackendIo { code: 211 });
+                .map_err(|_| StorageError::BackendIo { code: 211 })?;
+            self.observer.on_page_erased(page_address);
+            return Ok(());
         }
 
         #[allow(unreachable_code)]
@@ -430,11 +1470,17 @@ impl<const RP2040_FLASH_SIZE: usize> Rp2040Backend<RP2040_FLASH_SIZE> {
     }
 
     fn write_page(&self, page_address: usize, page: &[u8; FLASH_PAGE_SIZE]) -> Result<(), StorageError> {
+        self.write_flash(page_address, page)
+    }
+
+    /// Programs `bytes` at `address` without erasing; the target bytes must be
+    /// erased or already hold the same value.
+    fn write_flash(&self, address: usize, bytes: &[u8]) -> Result<(), StorageError> {
         #[cfg(any(test, not(target_arch = "arm")))]
         {
             let mut flash_mock = self.flash_mock.borrow_mut();
             return flash_mock
-                .write(page_address as u32, page)
+                .write(address as u32, bytes)
                 .map_err(|code| StorageError::BackendIo { code });
         }
 
@@ -442,7 +1488,7 @@ impl<const RP2040_FLASH_SIZE: usize> Rp2040Backend<RP2040_FLASH_SIZE> {
         {
             let mut flash = self.flash.borrow_mut();
             return flash
-                .blocking_write(page_address as u32, page)
+                .blocking_write(address as u32, bytes)
                 .map_err(|_| StorageError::BackendIo { code: 212 });
         }
 
@@ -450,707 +1496,4389 @@ impl<const RP2040_FLASH_SIZE: usize> Rp2040Backend<RP2040_FLASH_SIZE> {
         Err(StorageError::BackendIo { code: 212 })
     }
 
-    fn crc32(bytes: &[u8]) -> u32 {
-        let mut crc = 0xFFFF_FFFFu32;
-        let mut i = 0usize;
-        while i < bytes.len() {
-            crc ^= bytes[i] as u32;
-            let mut bit = 0usize;
-            while bit < 8 {
-                if (crc & 1) != 0 {
-                    crc = (crc >> 1) ^ 0xEDB8_8320;
-                } else {
-                    crc >>= 1;
-                }
-                bit += 1;
+    /// Returns `true` when the `len` bytes at `address` all equal `value`.
+    fn flash_is_filled(&self, address: usize, len: usize, value: u8) -> Result<bool, StorageError> {
+        let mut chunk = [0u8; FLASH_CHUNK_SIZE];
+        let mut offset = 0usize;
+        while offset < len {
+            let size = (len - offset).min(FLASH_CHUNK_SIZE);
+            self.read_flash(address + offset, &mut chunk[..size])?;
+            if chunk[..size].iter().any(|byte| *byte != value) {
+                return Ok(false);
             }
-            i += 1;
+            offset += size;
         }
-        !crc
+        Ok(true)
     }
 
-    fn serialize_control_record(record: &ControlPlaneData) -> [u8; CONTROL_PLANE_ENTRY_SIZE] {
-        let mut out = [0u8; CONTROL_PLANE_ENTRY_SIZE];
-        out[CP_VERSION_OFFSET] = CONTROL_PLANE_VERSION;
-        out[CP_PRIVATE_KEY_SIZE_OFFSET] = PRIVATE_KEY_SIZE as u8;
-        out[CP_PRIVATE_KEY_OFFSET..CP_PRIVATE_KEY_OFFSET + PRIVATE_KEY_SIZE]
-            .copy_from_slice(&record.private_key);
-        out[CP_OWN_NODE_ID_OFFSET..CP_OWN_NODE_ID_OFFSET + 4]
-            .copy_from_slice(&record.own_node_id.to_le_bytes());
-        out[CP_INIT_PARAMS_SIZE_OFFSET] = INIT_PARAMS_SIZE as u8;
-        out[CP_INIT_PARAMS_OFFSET..CP_INIT_PARAMS_OFFSET + INIT_PARAMS_SIZE]
-            .copy_from_slice(&record.init_params);
-
-        let max_block_size = MAX_BLOCK_SIZE as u16;
-        out[CP_MAX_BLOCK_SIZE_OFFSET..CP_MAX_BLOCK_SIZE_OFFSET + 2]
-            .copy_from_slice(&max_block_size.to_le_bytes());
-
-        if let Some(chain_configuration) = &record.chain_configuration {
-            let bytes = chain_configuration.as_bytes();
-            out[CP_CHAIN_CONFIG_OFFSET..CP_CHAIN_CONFIG_OFFSET + bytes.len()].copy_from_slice(bytes);
+    /// Returns `true` when the flash bytes at `address` equal `expected`.
+    fn flash_matches(&self, address: usize, expected: &[u8]) -> Result<bool, StorageError> {
+        let mut chunk = [0u8; FLASH_CHUNK_SIZE];
+        let mut offset = 0usize;
+        while offset < expected.len() {
+            let size = (expected.len() - offset).min(FLASH_CHUNK_SIZE);
+            self.read_flash(address + offset, &mut chunk[..size])?;
+            if chunk[..size] != expected[offset..offset + size] {
+                return Ok(false);
+            }
+            offset += size;
         }
+        Ok(true)
+    }
 
-        let crc = Self::crc32(&out[..CP_CRC32_OFFSET]);
-        out[CP_CRC32_OFFSET..CP_CRC32_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
-        out
+    /// Reads version and CRC fields of a control-plane entry without validating it.
+    ///
+    /// Parameters:
+    /// - `bytes`: raw control-plane entry bytes.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::{CONTROL_PLANE_ENTRY_SIZE, Rp2040Backend};
+    ///
+    /// let header = Rp2040Backend::<{ 2 * 1024 * 1024 }>::control_record_header(&[0xFF; CONTROL_PLANE_ENTRY_SIZE]);
+    /// assert_eq!(header.version, 0xFF);
+    /// ```
+    pub fn control_record_header(bytes: &[u8; CONTROL_PLANE_ENTRY_SIZE]) -> ControlRecordHeader {
+        let (version, stored_crc32, computed_crc32) = control_plane::entry_header(bytes);
+        ControlRecordHeader {
+            version,
+            stored_crc32,
+            computed_crc32,
+        }
     }
 
-    fn deserialize_control_record(
+    /// Decodes and validates one control-plane entry.
+    ///
+    /// Wrapped private keys cannot be opened here and report `KeyUnwrapFailed`.
+    ///
+    /// Parameters:
+    /// - `bytes`: raw control-plane entry bytes.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::StorageError;
+    /// use moonblokz_storage::backend_rp2040::{CONTROL_PLANE_ENTRY_SIZE, Rp2040Backend};
+    ///
+    /// let result = Rp2040Backend::<{ 2 * 1024 * 1024 }>::deserialize_control_record(&[0xFF; CONTROL_PLANE_ENTRY_SIZE]);
+    /// assert!(matches!(result, Err(StorageError::ControlPlaneUninitialized)));
+    /// ```
+    pub fn deserialize_control_record(
         bytes: &[u8; CONTROL_PLANE_ENTRY_SIZE],
     ) -> Result<ControlPlaneData, StorageError> {
-        let all_zero = bytes.iter().all(|v| *v == 0);
-        let all_ff = bytes.iter().all(|v| *v == 0xFF);
-        if all_zero || all_ff {
-            return Err(StorageError::ControlPlaneUninitialized);
-        }
+        control_plane::decode_entry(bytes, &KeyProtection::Plaintext).map(|decoded| decoded.record)
+    }
 
-        let mut crc_bytes = [0u8; 4];
-        crc_bytes.copy_from_slice(&bytes[CP_CRC32_OFFSET..CP_CRC32_OFFSET + 4]);
-        let stored_crc = u32::from_le_bytes(crc_bytes);
-        let computed_crc = Self::crc32(&bytes[..CP_CRC32_OFFSET]);
-        if stored_crc != computed_crc {
-            return Err(StorageError::ControlPlaneCorrupted);
-        }
+    fn read_control_record_from_replica(
+        &self,
+        region: &Rp2040Region,
+        replica_index: usize,
+    ) -> Result<DecodedEntry, StorageError> {
+        let (entry, superblock) = self.read_replica_entry(region, replica_index)?;
+        let mut decoded = control_plane::decode_entry(&entry, &self.key_protection)?;
+        decoded.needs_upgrade |= superblock.is_none();
+        Ok(decoded)
+    }
 
-        if bytes[CP_VERSION_OFFSET] != CONTROL_PLANE_VERSION
-            || bytes[CP_PRIVATE_KEY_SIZE_OFFSET] as usize != PRIVATE_KEY_SIZE
-            || bytes[CP_INIT_PARAMS_SIZE_OFFSET] as usize != INIT_PARAMS_SIZE
-        {
-            return Err(StorageError::ControlPlaneIncompatible);
+    /// Reads the entry and superblock of a control-plane replica and checks
+    /// the superblock against the compiled geometry. The superblock is `None`
+    /// when the page has none.
+    fn read_replica_entry(
+        &self,
+        region: &Rp2040Region,
+        replica_index: usize,
+    ) -> Result<(Zeroizing<[u8; CONTROL_PLANE_ENTRY_SIZE]>, Option<Superblock>), StorageError> {
+        let page_address = region.control_plane_page_address(replica_index);
+        let mut stored = [0u8; SUPERBLOCK_SIZE];
+        self.read_flash(page_address + SUPERBLOCK_OFFSET, &mut stored)?;
+        let superblock = Superblock::decode(&stored)?;
+        if let Some(value) = &superblock {
+            value.check(&compiled_superblock(region))?;
         }
 
-        let mut max_block_size_bytes = [0u8; 2];
-        max_block_size_bytes
-            .copy_from_slice(&bytes[CP_MAX_BLOCK_SIZE_OFFSET..CP_MAX_BLOCK_SIZE_OFFSET + 2]);
-        let persisted_max_block_size = u16::from_le_bytes(max_block_size_bytes) as usize;
-        if persisted_max_block_size != MAX_BLOCK_SIZE {
-            return Err(StorageError::ControlPlaneIncompatible);
-        }
+        let mut entry = Zeroizing::new([0u8; CONTROL_PLANE_ENTRY_SIZE]);
+        self.read_flash(page_address, &mut entry[..])?;
+        Ok((entry, superblock))
+    }
 
-        let mut private_key = [0u8; PRIVATE_KEY_SIZE];
-        private_key.copy_from_slice(&bytes[CP_PRIVATE_KEY_OFFSET..CP_PRIVATE_KEY_OFFSET + PRIVATE_KEY_SIZE]);
+    /// Returns the superblock this backend writes next to every control-plane replica.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let superblock = backend.superblock();
+    /// assert_eq!(superblock.data_storage_start_address, 0x18_0000);
+    /// ```
+    pub fn superblock(&self) -> Superblock {
+        compiled_superblock(&self.region)
+    }
 
-        let mut own_node_id_bytes = [0u8; 4];
-        own_node_id_bytes.copy_from_slice(&bytes[CP_OWN_NODE_ID_OFFSET..CP_OWN_NODE_ID_OFFSET + 4]);
-        let own_node_id = u32::from_le_bytes(own_node_id_bytes);
+    fn write_control_record_to_replica(
+        &self,
+        region: &Rp2040Region,
+        replica_index: usize,
+        record: &ControlPlaneData,
+    ) -> Result<(), StorageError> {
+        let encoded = control_plane::encode_entry(record, &self.key_protection)?;
+        self.write_control_entry_to_replica(region, replica_index, &encoded)
+    }
 
-        let mut init_params = [0u8; INIT_PARAMS_SIZE];
-        init_params.copy_from_slice(&bytes[CP_INIT_PARAMS_OFFSET..CP_INIT_PARAMS_OFFSET + INIT_PARAMS_SIZE]);
+    fn write_control_entry_to_replica(
+        &self,
+        region: &Rp2040Region,
+        replica_index: usize,
+        entry: &[u8; CONTROL_PLANE_ENTRY_SIZE],
+    ) -> Result<(), StorageError> {
+        let page_address = region.control_plane_page_address(replica_index);
+        let mut page = Zeroizing::new([0u8; FLASH_PAGE_SIZE]);
+        page[..CONTROL_PLANE_ENTRY_SIZE].copy_from_slice(entry);
+        page[SUPERBLOCK_OFFSET..].copy_from_slice(&compiled_superblock(region).encode());
+        self.erase_page(page_address)?;
+        self.write_page(page_address, &page)
+    }
 
-        let chain_configuration = if bytes[CP_CHAIN_CONFIG_OFFSET] == 0 {
-            None
-        } else {
-            let mut cfg = [0u8; MAX_BLOCK_SIZE];
-            cfg.copy_from_slice(&bytes[CP_CHAIN_CONFIG_OFFSET..CP_CHAIN_CONFIG_OFFSET + MAX_BLOCK_SIZE]);
-            Some(Block::from_bytes(&cfg).map_err(|_| StorageError::ControlPlaneCorrupted)?)
-        };
+    /// Streams the backend contents into `sink` as a portable archive.
+    ///
+    /// The archive is independent of flash geometry and start address; see
+    /// `archive` for the format. Empty slots are skipped.
+    ///
+    /// Parameters:
+    /// - `options`: export options (`include_control_data` adds control data, `include_private_key`
+    ///   also the plaintext private key).
+    /// - `sink`: archive destination, for example a UART writer.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::archive::ExportOptions;
+    /// use moonblokz_storage::backend_rp2040::Rp2040Backend;
+    ///
+    /// let mut backend = Rp2040Backend::<{ 8 * 4096 }>::new(0)?;
+    /// let summary = backend.export_to(ExportOptions::default(), &mut uart_sink)?;
+    /// ```
+    pub fn export_to(
+        &mut self,
+        options: ExportOptions,
+        sink: &mut dyn ByteSink,
+    ) -> Result<ArchiveSummary, StorageError> {
+        let slot_count = self.region.max_storage_slots();
+        export_archive(self, slot_count, options, sink)
+    }
 
-        Ok(ControlPlaneData {
-            private_key,
-            own_node_id,
-            init_params,
-            chain_configuration,
-        })
+    /// Restores a portable archive from `source`.
+    ///
+    /// The whole archive is verified before anything is written, so `source`
+    /// is read twice; archives carrying control data then re-initialize the
+    /// backend first.
+    ///
+    /// Parameters:
+    /// - `source`: archive bytes, for example a UART reader.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::Rp2040Backend;
+    ///
+    /// let mut backend = Rp2040Backend::<{ 8 * 4096 }>::new(0)?;
+    /// let summary = backend.import_from(&mut uart_source)?;
+    /// ```
+    pub fn import_from(&mut self, source: &mut dyn ByteSource) -> Result<ArchiveSummary, StorageError> {
+        let slot_count = self.region.max_storage_slots();
+        import_archive(self, slot_count, source)
     }
 
-    fn read_control_record_from_replica(&self, replica_index: usize) -> Result<ControlPlaneData, StorageError> {
-        let page_address = self.control_plane_page_address(replica_index);
-        let mut page = [0u8; FLASH_PAGE_SIZE];
-        self.read_page(page_address, &mut page)?;
+    /// Loads `tree` with one leaf per block slot: the stored hash of an
+    /// intact slot, `EMPTY_LEAF` for an empty or damaged one.
+    ///
+    /// The leaves are the hashes `save_block` already stores next to every
+    /// slot; each block page is read once and each slot verified. Load a
+    /// `MerkleObserver` with this at boot to keep the tree in step with later
+    /// writes.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: the region has more slots than `LEAVES`.
+    /// - `BackendIo` when a page read fails.
+    ///
+    /// Parameters:
+    /// - `tree`: tree to load.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::merkle::MerkleObserver;
+    ///
+    /// let merkle = MerkleObserver::<256>::new();
+    /// let mut backend = Rp2040Backend::<{ 2 * 1024 * 1024 }>::new(p.FLASH, 0x18_0000)?.with_observer(&merkle);
+    /// merkle.load(|tree| backend.build_merkle_tree(tree))?;
+    /// backend.save_block(4, &block)?;
+    /// let root = merkle.root();
+    /// ```
+    pub fn build_merkle_tree<const LEAVES: usize>(&self, tree: &mut MerkleTree<LEAVES>) -> Result<(), StorageError> {
+        self.build_merkle_tree_in(&self.region, tree)
+    }
+
+    /// Returns the lowest storage index holding a block with header
+    /// `sequence`.
+    ///
+    /// With a built sequence index (`with_sequence_index`) only the slots its
+    /// records point at are read; otherwise the sequence bytes of every slot
+    /// are scanned. Hits are verified against the slot either way.
+    ///
+    /// Errors:
+    /// - `BackendIo` when a flash read fails.
+    ///
+    /// Parameters:
+    /// - `sequence`: `BlockHeader::sequence` to look up.
+    ///
+    /// Example:
+    /// ```ignore
+    /// if let Some(storage_index) = backend.find_by_sequence(1200)? {
+    ///     let block = backend.read_block(storage_index)?;
+    /// }
+    /// ```
+    pub fn find_by_sequence(&self, sequence: u32) -> Result<Option<StorageIndex>, StorageError> {
+        sequence_index::find_by_sequence(&self.sequences_in(&self.region)?, sequence)
+    }
 
-        let mut entry = [0u8; CONTROL_PLANE_ENTRY_SIZE];
-        entry.copy_from_slice(&page[..CONTROL_PLANE_ENTRY_SIZE]);
-        Self::deserialize_control_record(&entry)
+    /// Returns the highest sequence number stored, i.e. the chain tip.
+    ///
+    /// Errors:
+    /// - `BackendIo` when a flash read fails.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let tip = backend.max_sequence()?;
+    /// ```
+    pub fn max_sequence(&self) -> Result<Option<u32>, StorageError> {
+        sequence_index::max_sequence(&self.sequences_in(&self.region)?)
     }
 
-    fn write_control_record_to_replica(
+    /// Calls `on_block` with every stored sequence number in `range` and the
+    /// storage index `find_by_sequence` returns for it, in ascending order.
+    ///
+    /// Errors:
+    /// - `BackendIo` when a flash read fails.
+    ///
+    /// Parameters:
+    /// - `range`: sequence numbers to report.
+    /// - `on_block`: callback receiving `(sequence, storage_index)`.
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.range_by_sequence(100..200, &mut |sequence, storage_index| {
+    ///     defmt::info!("block {} in slot {}", sequence, storage_index);
+    /// })?;
+    /// ```
+    pub fn range_by_sequence(
         &self,
-        replica_index: usize,
-        record: &ControlPlaneData,
+        range: Range<u32>,
+        on_block: &mut dyn FnMut(u32, StorageIndex),
     ) -> Result<(), StorageError> {
-        let page_address = self.control_plane_page_address(replica_index);
-        let mut page = [0u8; FLASH_PAGE_SIZE];
-        page.fill(0);
-        let encoded = Self::serialize_control_record(record);
-        page[..CONTROL_PLANE_ENTRY_SIZE].copy_from_slice(&encoded);
-        self.erase_page(page_address)?;
-        self.write_page(page_address, &page)
+        sequence_index::range_by_sequence(&self.sequences_in(&self.region)?, range, on_block)
     }
 
-    fn load_primary_control_record_and_repair(&self) -> Result<ControlPlaneData, StorageError> {
-        let mut first_valid_record: Option<ControlPlaneData> = None;
-        let mut first_valid_index: Option<usize> = None;
-        let mut invalid = [usize::MAX; CONTROL_PLANE_COUNT];
-        let mut invalid_len = 0usize;
-        let mut saw_non_uninitialized = false;
-        let mut saw_incompatible = false;
+    /// Rebuilds the sequence index from a scan of every slot.
+    ///
+    /// The index is rebuilt automatically when its log fills up and after
+    /// `relocate`. Call this when lookups fall back to scanning because the
+    /// index was lost, for example after an interrupted rebuild or when slots
+    /// were written by firmware without the index.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: the region has no sequence index.
+    /// - `BackendIo` when a flash operation fails.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.rebuild_sequence_index()?;
+    /// ```
+    pub fn rebuild_sequence_index(&mut self) -> Result<(), StorageError> {
+        if self.region.sequence_index_pages == 0 {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        let mut page = self.page_buffer.borrow_mut();
+        self.rebuild_sequence_log(&self.region, &mut page)
+    }
 
-        let mut i = 0usize;
-        while i < CONTROL_PLANE_COUNT {
-            match self.read_control_record_from_replica(i) {
-                Ok(record) => {
-                    if first_valid_record.is_none() {
-                        first_valid_index = Some(i);
-                        first_valid_record = Some(record);
+    /// Scans all control-plane replicas and block slots for anomalies.
+    ///
+    /// Slot classification:
+    /// - fully erased: empty.
+    /// - hash area still erased while block bytes are programmed: half-written.
+    /// - hash mismatch or unparsable block: integrity failure.
+    ///
+    /// Replica pages must be zero after the entry and block pages must stay
+    /// erased after the last slot; other bytes there are padding anomalies. In
+    /// repair mode, invalid replicas are rewritten from the primary replica and
+    /// broken slots are erased back to `0xFF`.
+    ///
+    /// Parameters:
+    /// - `options`: scan options (`repair` enables rewrites).
+    /// - `on_finding`: callback invoked for every detected anomaly.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::FsckOptions;
+    /// use moonblokz_storage::backend_rp2040::Rp2040Backend;
+    ///
+    /// let mut backend = Rp2040Backend::<{ 8 * 4096 }>::new(0)?;
+    /// let report = backend.fsck(FsckOptions { repair: true }, &mut |_finding| {})?;
+    /// assert!(report.is_clean());
+    /// ```
+    pub fn fsck(
+        &mut self,
+        options: FsckOptions,
+        on_finding: &mut dyn FnMut(&FsckFinding),
+    ) -> Result<FsckReport, StorageError> {
+        self.ensure_no_pending_operation(&self.region)?;
+
+        let mut report = FsckReport::default();
+        self.fsck_control_plane(options, &mut report, on_finding)?;
+
+        let slots_end = BLOCKS_PER_PAGE * SLOT_SIZE_BYTES;
+        let page_count = self.region.max_storage_slots() / BLOCKS_PER_PAGE_INDEX;
+        let mut page = self.page_buffer.borrow_mut();
+        let mut page_index: StorageIndex = 0;
+        while page_index < page_count {
+            let mapping = map_storage_index(page_index * BLOCKS_PER_PAGE_INDEX);
+            let page_address = Self::page_flash_address(&self.region, &mapping);
+            self.read_page(page_address, &mut page)?;
+            let mut page_dirty = false;
+
+            let mut slot_index = 0usize;
+            while slot_index < BLOCKS_PER_PAGE {
+                report.slots_checked += 1;
+                let storage_index = page_index * BLOCKS_PER_PAGE_INDEX + slot_index as StorageIndex;
+                let slot_start = slot_index * SLOT_SIZE_BYTES;
+                let slot = &page[slot_start..slot_start + SLOT_SIZE_BYTES];
+                let hash_erased = slot[SLOT_HASH_OFFSET..SLOT_HASH_OFFSET + HASH_SIZE]
+                    .iter()
+                    .all(|byte| *byte == 0xFF);
+
+                let issue = match Self::decode_slot_block(slot) {
+                    Ok(_) => {
+                        report.slots_occupied += 1;
+                        None
                     }
+                    Err(StorageError::BlockAbsent) => None,
+                    Err(_) if hash_erased => Some(FsckIssue::SlotHalfWritten { storage_index }),
+                    Err(_) => Some(FsckIssue::SlotIntegrityFailure { storage_index }),
+                };
+
+                if let Some(issue) = issue {
+                    if options.repair {
+                        page[slot_start..slot_start + SLOT_SIZE_BYTES].fill(0xFF);
+                        page_dirty = true;
+                    }
+                    self.observer.on_slot_changed(storage_index, None);
+                    report.record(issue, options.repair, on_finding);
                 }
-                Err(StorageError::ControlPlaneUninitialized) => {
-                    invalid[invalid_len] = i;
-                    invalid_len += 1;
-                }
-                Err(StorageError::ControlPlaneIncompatible) => {
-                    saw_non_uninitialized = true;
-                    saw_incompatible = true;
-                    invalid[invalid_len] = i;
-                    invalid_len += 1;
-                }
-                Err(StorageError::ControlPlaneCorrupted) => {
-                    saw_non_uninitialized = true;
-                    invalid[invalid_len] = i;
-                    invalid_len += 1;
-                }
-                Err(err) => return Err(err),
+                slot_index += 1;
             }
-            i += 1;
-        }
 
-        let record = match first_valid_record {
-            Some(value) => value,
-            None => {
-                if saw_incompatible {
-                    return Err(StorageError::ControlPlaneIncompatible);
-                }
-                if saw_non_uninitialized {
-                    return Err(StorageError::ControlPlaneCorrupted);
-                }
-                return Err(StorageError::ControlPlaneUninitialized);
+            if scrub_page_padding(&mut page, slots_end, options.repair) {
+                page_dirty |= options.repair;
+                report.record(
+                    FsckIssue::PagePaddingAnomaly { page_address },
+                    options.repair,
+                    on_finding,
+                );
             }
-        };
 
-        let mut j = 0usize;
-        while j < invalid_len {
-            let target = invalid[j];
-            if Some(target) != first_valid_index {
-                self.write_control_record_to_replica(target, &record)?;
+            if page_dirty {
+                self.erase_page(page_address)?;
+                self.write_page(page_address, &page)?;
             }
-            j += 1;
+            page_index += 1;
         }
 
-        Ok(record)
+        Ok(report)
     }
-}
 
-impl<const RP2040_FLASH_SIZE: usize> StorageTrait for Rp2040Backend<RP2040_FLASH_SIZE> {
-    fn init(
-        &mut self,
-        private_key: [u8; PRIVATE_KEY_SIZE],
-        own_node_id: u32,
-        init_params: [u8; INIT_PARAMS_SIZE],
-    ) -> Result<(), StorageError> {
-        Self::validate_page_aligned_start_address(self.data_storage_start_address)?;
-        let first_page = self.data_storage_start_address / FLASH_PAGE_SIZE;
-        let page_count = (RP2040_FLASH_SIZE.saturating_sub(self.data_storage_start_address)) / FLASH_PAGE_SIZE;
-        let mut page = 0usize;
-        while page < page_count {
-            let page_address = (first_page + page) * FLASH_PAGE_SIZE;
-            self.erase_page(page_address)?;
-            page += 1;
-        }
+    /// Replaces the persisted private key, keeping node id, init params,
+    /// chain configuration and all block pages.
+    ///
+    /// Replicas are rewritten one at a time and each is read back before the
+    /// next is touched, so an interruption leaves at least one valid replica.
+    ///
+    /// Parameters:
+    /// - `private_key`: new node private key bytes (wiped before returning).
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.rotate_private_key(new_private_key)?;
+    /// ```
+    pub fn rotate_private_key(&mut self, mut private_key: [u8; PRIVATE_KEY_SIZE]) -> Result<(), StorageError> {
+        let result = self.update_control_record(|record| record.private_key = private_key);
+        private_key.zeroize();
+        result
+    }
 
-        let record = ControlPlaneData {
-            private_key,
-            own_node_id,
-            init_params,
-            chain_configuration: None,
-        };
+    /// Replaces the persisted own node id, keeping all other control data and block pages.
+    ///
+    /// Parameters:
+    /// - `own_node_id`: new local node identifier.
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.set_own_node_id(8)?;
+    /// ```
+    pub fn set_own_node_id(&mut self, own_node_id: u32) -> Result<(), StorageError> {
+        self.update_control_record(|record| record.own_node_id = own_node_id)
+    }
 
-        let mut replica_index = 0usize;
-        while replica_index < CONTROL_PLANE_COUNT {
-            self.write_control_record_to_replica(replica_index, &record)?;
-            replica_index += 1;
-        }
+    /// Replaces the persisted init params, keeping all other control data and block pages.
+    ///
+    /// Parameters:
+    /// - `init_params`: new free-form control-plane initialization bytes.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::INIT_PARAMS_SIZE;
+    ///
+    /// backend.update_init_params([3u8; INIT_PARAMS_SIZE])?;
+    /// ```
+    pub fn update_init_params(&mut self, init_params: [u8; INIT_PARAMS_SIZE]) -> Result<(), StorageError> {
+        self.update_control_record(|record| record.init_params = init_params)
+    }
+
+    /// Rewrites the control-plane replicas of every partition with the
+    /// private key protected by `protection`, and keeps `protection` for
+    /// later loads.
+    ///
+    /// Each record is loaded with the current protection first, so this is
+    /// the explicit way to wrap a plaintext key, change the wrapping key or
+    /// go back to plaintext. Partitions that already load with `protection`
+    /// and uninitialized ones are skipped, so after an interruption calling
+    /// this again with the same `protection` finishes the remaining ones.
+    /// Replicas are rewritten one at a time and read back, as in
+    /// `rotate_private_key`.
+    ///
+    /// Errors:
+    /// - any error of `load_control_data` under the current protection.
+    ///
+    /// Parameters:
+    /// - `protection`: new key protection mode.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let unique_id = backend.flash_unique_id()?;
+    /// backend.set_key_protection(KeyProtection::from_secret(b"fleet secret", Some(&unique_id))?)?;
+    /// ```
+    pub fn set_key_protection(&mut self, protection: KeyProtection) -> Result<(), StorageError> {
+        let mut protection = protection;
+        let region_count = self.regions().len();
+        let mut region_index = 0usize;
+        while region_index < region_count {
+            let region = self.regions()[region_index];
+            region_index += 1;
+
+            core::mem::swap(&mut self.key_protection, &mut protection);
+            let converted = self.load_primary_control_record_and_repair(&region);
+            core::mem::swap(&mut self.key_protection, &mut protection);
+            if converted.is_ok() {
+                continue;
+            }
+            let record = match self.load_primary_control_record_and_repair(&region) {
+                Ok(record) => record,
+                Err(StorageError::ControlPlaneUninitialized) => continue,
+                Err(err) => return Err(err),
+            };
 
+            core::mem::swap(&mut self.key_protection, &mut protection);
+            let result = self.rewrite_control_record(&region, &record);
+            core::mem::swap(&mut self.key_protection, &mut protection);
+            result?;
+        }
+        self.key_protection = protection;
         Ok(())
     }
 
-    fn save_block(
+    fn update_control_record(
         &mut self,
-        storage_index: StorageIndex,
-        block: &Block,
+        update: impl FnOnce(&mut ControlPlaneData),
     ) -> Result<(), StorageError> {
-        if storage_index >= self.max_storage_slots {
-            return Err(StorageError::InvalidIndex);
-        }
-
-        let mapping = map_storage_index(storage_index);
-        self.write_slot(&mapping, block)
+        let mut record = self.load_primary_control_record_and_repair(&self.region)?;
+        update(&mut record);
+        self.rewrite_control_record(&self.region, &record)
     }
 
-    fn read_block(&self, storage_index: StorageIndex) -> Result<Block, StorageError> {
-        if storage_index >= self.max_storage_slots {
-            return Err(StorageError::InvalidIndex);
+    /// Writes `record` to every replica of `region`, reading each back before
+    /// the next is touched.
+    fn rewrite_control_record(&self, region: &Rp2040Region, record: &ControlPlaneData) -> Result<(), StorageError> {
+        let mut replica_index = 0usize;
+        while replica_index < region.control_plane.replica_count {
+            self.write_control_record_to_replica(region, replica_index, record)?;
+            self.read_control_record_from_replica(region, replica_index)?;
+            replica_index += 1;
+        }
+        Ok(())
+    }
+
+    /// Erases all control-plane replicas and block pages.
+    ///
+    /// Every page from the storage start address to the end of flash is erased
+    /// (every partition when `with_partitions` is configured), leaving the
+    /// device in the `ControlPlaneUninitialized` state until the next `init`.
+    /// Use before retiring or handing over hardware.
+    ///
+    /// Errors:
+    /// - `BackendIo { code: 215 }`: `verify` found a byte that is not `0xFF`.
+    ///
+    /// Parameters:
+    /// - `verify`: read every page back and check that it is fully erased.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::Rp2040Backend;
+    ///
+    /// let mut backend = Rp2040Backend::<{ 2 * 1024 * 1024 }>::new(p.FLASH, 0x18_0000)?;
+    /// backend.decommission(true)?;
+    /// ```
+    pub fn decommission(&mut self, verify: bool) -> Result<(), StorageError> {
+        Self::validate_page_aligned_start_address(self.region.start)?;
+        let regions = self.regions();
+        let mut region_index = 0usize;
+        while region_index < regions.len() {
+            self.erase_storage_region(&regions[region_index])?;
+            region_index += 1;
+        }
+        self.observer.on_slots_reset(self.region.max_storage_slots());
+        if !verify {
+            return Ok(());
+        }
+
+        let mut region_index = 0usize;
+        while region_index < regions.len() {
+            let mut page_address = regions[region_index].start;
+            while page_address + FLASH_PAGE_SIZE <= regions[region_index].end {
+                if !self.flash_is_filled(page_address, FLASH_PAGE_SIZE, 0xFF)? {
+                    return Err(StorageError::BackendIo { code: 215 });
+                }
+                page_address += FLASH_PAGE_SIZE;
+            }
+            region_index += 1;
+        }
+        Ok(())
+    }
+
+    fn erase_storage_region(&self, region: &Rp2040Region) -> Result<(), StorageError> {
+        let mut page_address = region.start;
+        while page_address + FLASH_PAGE_SIZE <= region.end {
+            self.erase_page(page_address)?;
+            page_address += FLASH_PAGE_SIZE;
+        }
+        Ok(())
+    }
+
+    /// Configured partitions, or the backend's own region when unpartitioned.
+    fn regions(&self) -> &[Rp2040Region] {
+        if self.partitions.is_empty() {
+            core::slice::from_ref(&self.region)
+        } else {
+            self.partitions
+        }
+    }
+
+    /// Initializes the control plane like `init`, reporting erase progress.
+    ///
+    /// Every replica is first replaced by an init marker carrying the new
+    /// control-plane entry; block pages are then erased in chunks of
+    /// `INIT_ERASE_CHUNK_PAGES`, with `on_progress` called after each chunk.
+    /// If power is lost before the control plane is written, the region
+    /// reports `InitInProgress` until `resume_init` (or another `init`)
+    /// completes it. A power loss while the first marker is written leaves
+    /// the previous contents in place.
+    ///
+    /// Parameters:
+    /// - `private_key`: node private key bytes.
+    /// - `own_node_id`: local node identifier.
+    /// - `init_params`: free-form control-plane initialization bytes.
+    /// - `on_progress`: callback receiving the erase progress.
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.init_with_progress(private_key, own_node_id, init_params, &mut |progress| {
+    ///     defmt::info!("erased {}/{} pages", progress.pages_erased, progress.pages_total);
+    /// })?;
+    /// ```
+    pub fn init_with_progress(
+        &mut self,
+        private_key: [u8; PRIVATE_KEY_SIZE],
+        own_node_id: u32,
+        init_params: [u8; INIT_PARAMS_SIZE],
+        on_progress: &mut dyn FnMut(InitProgress),
+    ) -> Result<(), StorageError> {
+        self.init_in(&self.region, private_key, own_node_id, init_params, on_progress)
+    }
+
+    /// Returns the progress of an unfinished `init` in this region, if any.
+    ///
+    /// Call at startup before `load_control_data`, which reports
+    /// `InitInProgress` while a marker is present.
+    ///
+    /// Errors:
+    /// - `ControlPlaneIncompatible`: marker was written in a newer format version.
+    /// - `InvalidConfiguration`: marker was written for a different region or layout.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// if backend.pending_init()?.is_some() {
+    ///     backend.resume_init(&mut |_progress| {})?;
+    /// }
+    /// let control_data = backend.load_control_data()?;
+    /// ```
+    pub fn pending_init(&self) -> Result<Option<InitProgress>, StorageError> {
+        Ok(self.read_init_marker(&self.region)?.map(|marker| marker.progress()))
+    }
+
+    /// Finishes an `init` interrupted by a reset, continuing from its last
+    /// checkpoint. Does nothing when no init is pending.
+    ///
+    /// The control data passed to the interrupted `init` is taken from the
+    /// marker, so the caller does not need to supply it again. Partitions
+    /// other than the first finish an interrupted `init` by running it again.
+    ///
+    /// Errors:
+    /// - `ControlPlaneIncompatible` / `InvalidConfiguration`: as for `pending_init`.
+    ///
+    /// Parameters:
+    /// - `on_progress`: callback receiving the erase progress.
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.resume_init(&mut |progress| {
+    ///     defmt::info!("erased {}/{} pages", progress.pages_erased, progress.pages_total);
+    /// })?;
+    /// ```
+    pub fn resume_init(&mut self, on_progress: &mut dyn FnMut(InitProgress)) -> Result<(), StorageError> {
+        match self.read_init_marker(&self.region)? {
+            Some(marker) => {
+                self.observer.on_init_started();
+                self.finish_init(&self.region, marker, on_progress)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the destination of a relocation journaled in this region, if any.
+    ///
+    /// Call at startup before `load_control_data`, which reports
+    /// `RelocationInProgress` while a journal is present.
+    ///
+    /// Errors:
+    /// - `ControlPlaneIncompatible`: journal was written in a newer format version.
+    /// - `InvalidConfiguration`: journal was written for a different old region.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// if let Some(target) = backend.pending_relocation()? {
+    ///     backend.relocate(target.data_storage_start_address, target.flash_size)?;
+    /// }
+    /// let control_data = backend.load_control_data()?;
+    /// ```
+    pub fn pending_relocation(&self) -> Result<Option<RelocationTarget>, StorageError> {
+        Ok(self.read_relocation_journal(&self.region)?.map(|(journal, _)| journal.target()))
+    }
+
+    /// Moves the control plane and all block pages to a higher start address.
+    ///
+    /// Page `n` of the old region is copied to page `n` of the new one, so
+    /// every `storage_index` keeps its block. Pages are copied highest first,
+    /// which overwrites a source page only after it was copied. Progress is
+    /// journaled in the first two control-plane pages of the old region; after
+    /// a power loss `pending_relocation` returns the target and calling
+    /// `relocate` again resumes the copy. On success this instance uses the
+    /// new region. The first old page keeps a forwarding journal, so firmware
+    /// still built for the old address gets `RelocationInProgress` instead of
+    /// an empty region until it calls `relocate` as well.
+    ///
+    /// `new_flash_size` may be smaller than `RP2040_FLASH_SIZE`; this instance
+    /// keeps working, while firmware built for the old flash size reports
+    /// `LayoutMismatch`. A sequence index keeps its page count and is rebuilt
+    /// at the end of the new region. A key-value store and counters are not
+    /// copied, so they require the region end to stay in place.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: new start is misaligned or not above the
+    ///   current start, the new region does not fit this flash, holds no
+    ///   block page or is too small for its sequence index, an occupied slot
+    ///   would fall outside it, a key-value store, counters or slot metadata
+    ///   would move, a journaled relocation has a different target,
+    ///   partitions are configured, or the control plane is not at least two
+    ///   `Consecutive` replicas.
+    ///
+    /// Parameters:
+    /// - `new_data_storage_start_address`: first flash address of the new region.
+    /// - `new_flash_size`: flash size the new region is laid out for.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::backend_rp2040::Rp2040Backend;
+    ///
+    /// let mut backend = Rp2040Backend::<{ 2 * 1024 * 1024 }>::new(p.FLASH, 0x18_0000)?;
+    /// backend.relocate(0x1A_0000, 2 * 1024 * 1024)?;
+    /// ```
+    pub fn relocate(
+        &mut self,
+        new_data_storage_start_address: usize,
+        new_flash_size: usize,
+    ) -> Result<(), StorageError> {
+        let (mut journal, mut latest_slot) = match self.read_relocation_journal(&self.region)? {
+            Some((journal, slot)) => {
+                let target = journal.target();
+                if target.data_storage_start_address != new_data_storage_start_address
+                    || target.flash_size != new_flash_size
+                {
+                    return Err(StorageError::InvalidConfiguration);
+                }
+                (journal, slot)
+            }
+            None => (
+                self.begin_relocation(new_data_storage_start_address, new_flash_size)?,
+                0,
+            ),
+        };
+
+        if journal.phase == RelocationPhase::Blocks {
+            self.relocate_block_pages(&mut journal, &mut latest_slot)?;
+            // Both journal pages get this phase: the new control plane may
+            // overwrite the second one.
+            journal.phase = RelocationPhase::ControlPlane;
+            journal.remaining_pages = 0;
+            self.write_relocation_journal(1 - latest_slot, &mut journal)?;
+            self.write_relocation_journal(latest_slot, &mut journal)?;
+        }
+
+        if journal.phase == RelocationPhase::ControlPlane {
+            self.write_relocated_control_plane(&journal)?;
+            journal.phase = RelocationPhase::Done;
+            journal.entry.zeroize();
+            self.write_relocation_journal(0, &mut journal)?;
+        }
+
+        self.region = self.relocated_region(new_data_storage_start_address, new_flash_size);
+        self.rebuild_sequence_log(&self.region, &mut self.page_buffer.borrow_mut())?;
+        self.report_slots()
+    }
+
+    /// Region `relocate` moves the storage region to; the layout is kept.
+    fn relocated_region(&self, new_data_storage_start_address: usize, new_flash_size: usize) -> Rp2040Region {
+        Rp2040Region::new(self.region.name, new_data_storage_start_address, new_flash_size)
+            .with_control_plane_layout(self.region.control_plane)
+            .with_sequence_index(self.region.sequence_index_pages)
+            .with_kv_store(self.region.kv_pages)
+            .with_counters(self.region.counters)
+            .with_slot_meta(self.region.slot_meta_pages)
+            .with_write_once(self.region.write_once)
+    }
+
+    fn begin_relocation(
+        &self,
+        new_data_storage_start_address: usize,
+        new_flash_size: usize,
+    ) -> Result<RelocationJournal, StorageError> {
+        let new_region = self.relocated_region(new_data_storage_start_address, new_flash_size);
+        let new_page_count = new_region.block_page_count();
+        // The journal alternates between the first two replica pages, and
+        // block page `n` must stay at the same offset after the replicas.
+        if new_data_storage_start_address % FLASH_PAGE_SIZE != 0
+            || new_flash_size % FLASH_PAGE_SIZE != 0
+            || new_data_storage_start_address <= self.region.start
+            || new_flash_size > RP2040_FLASH_SIZE
+            || !new_region.has_valid_layout()
+            || (self.region.kv_pages + self.region.counters + self.region.slot_meta_pages > 0
+                && new_flash_size != self.region.end)
+            || !self.partitions.is_empty()
+            || self.region.control_plane.replica_count < 2
+            || self.region.control_plane.placement != ControlPlanePlacement::Consecutive
+        {
+            return Err(StorageError::InvalidConfiguration);
+        }
+
+        // Repairs stale replicas, so replica 0 holds the entry the journal carries.
+        self.load_primary_control_record_and_repair(&self.region)?;
+
+        let old_page_count = self.region.block_page_count();
+        {
+            let mut page = self.page_buffer.borrow_mut();
+            let mut page_index = new_page_count;
+            while page_index < old_page_count {
+                self.read_page(self.region.block_page_address(page_index), &mut page)?;
+                let mut slot_start = 0usize;
+                while slot_start < BLOCKS_PER_PAGE * SLOT_SIZE_BYTES {
+                    if Self::decode_slot_block(&page[slot_start..slot_start + SLOT_SIZE_BYTES]).is_ok() {
+                        return Err(StorageError::InvalidConfiguration);
+                    }
+                    slot_start += SLOT_SIZE_BYTES;
+                }
+                page_index += 1;
+            }
+        }
+
+        let mut entry = Zeroizing::new([0u8; CONTROL_PLANE_ENTRY_SIZE]);
+        self.read_flash(self.region.control_plane_page_address(0), &mut entry[..])?;
+
+        let mut journal = RelocationJournal {
+            phase: RelocationPhase::Blocks,
+            sequence: 0,
+            old_start: self.region.start as u32,
+            old_flash_size: self.region.end as u32,
+            new_start: new_data_storage_start_address as u32,
+            new_flash_size: new_flash_size as u32,
+            remaining_pages: new_page_count as u32,
+            entry,
+        };
+        self.write_relocation_journal(0, &mut journal)?;
+        Ok(journal)
+    }
+
+    fn relocate_block_pages(
+        &self,
+        journal: &mut RelocationJournal,
+        latest_slot: &mut usize,
+    ) -> Result<(), StorageError> {
+        let new_region = self.relocated_region(journal.new_start as usize, journal.new_flash_size as usize);
+        let mut source = self.page_buffer.borrow_mut();
+
+        let mut page_index = journal.remaining_pages as usize;
+        while page_index > 0 {
+            page_index -= 1;
+            let target_address = new_region.block_page_address(page_index);
+            self.read_page(self.region.block_page_address(page_index), &mut source)?;
+            if self.flash_matches(target_address, &source[..])? {
+                continue;
+            }
+
+            // Journal this page as the resume point before touching it; its
+            // source is only overwritten by a lower page.
+            if journal.remaining_pages as usize != page_index + 1 {
+                journal.remaining_pages = (page_index + 1) as u32;
+                *latest_slot = 1 - *latest_slot;
+                self.write_relocation_journal(*latest_slot, journal)?;
+            }
+            self.erase_page(target_address)?;
+            if source.iter().any(|byte| *byte != 0xFF) {
+                self.write_page(target_address, &source)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_relocated_control_plane(&self, journal: &RelocationJournal) -> Result<(), StorageError> {
+        let old_start = journal.old_start as usize;
+        let new_start = journal.new_start as usize;
+        let new_region = self.relocated_region(new_start, journal.new_flash_size as usize);
+
+        // The index pages lie above every copied block page; `relocate`
+        // rebuilds the log once the new region is in use.
+        self.erase_sequence_log(&new_region)?;
+
+        let mut replica_index = 0usize;
+        while replica_index < new_region.control_plane.replica_count {
+            self.write_control_entry_to_replica(&new_region, replica_index, &journal.entry)?;
+            replica_index += 1;
+        }
+
+        // Leftover old pages below the new region, except the forwarding journal.
+        let mut page_address = old_start + FLASH_PAGE_SIZE;
+        while page_address < new_start {
+            self.erase_page(page_address)?;
+            page_address += FLASH_PAGE_SIZE;
+        }
+        Ok(())
+    }
+
+    fn write_relocation_journal(&self, slot: usize, journal: &mut RelocationJournal) -> Result<(), StorageError> {
+        journal.sequence = journal.sequence.wrapping_add(1);
+        let page_address = journal.old_start as usize + slot * FLASH_PAGE_SIZE;
+        self.erase_page(page_address)?;
+        self.write_flash(page_address, &journal.encode()[..])
+    }
+
+    /// Returns the newest valid journal in the first two control-plane pages
+    /// together with the page (0 or 1) holding it.
+    fn read_relocation_journal(
+        &self,
+        region: &Rp2040Region,
+    ) -> Result<Option<(RelocationJournal, usize)>, StorageError> {
+        let mut bytes = Zeroizing::new([0u8; RELOCATION_JOURNAL_SIZE]);
+        let mut latest: Option<(RelocationJournal, usize)> = None;
+
+        let mut slot = 0usize;
+        while slot < region.control_plane.replica_count.min(2) {
+            self.read_flash(region.control_plane_page_address(slot), &mut bytes[..])?;
+            if let Some(journal) = RelocationJournal::decode(&bytes)? {
+                let newer = match &latest {
+                    Some((current, _)) => journal.sequence > current.sequence,
+                    None => true,
+                };
+                if newer {
+                    latest = Some((journal, slot));
+                }
+            }
+            slot += 1;
+        }
+
+        let foreign = match &latest {
+            Some((journal, _)) => {
+                journal.old_start as usize != region.start || journal.old_flash_size as usize != region.end
+            }
+            None => false,
+        };
+        if foreign {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        Ok(latest)
+    }
+
+    /// Reports the status of every control-plane replica.
+    ///
+    /// Runs the replica selection of `load_control_data`: the first replica
+    /// that decodes is the primary. Unless `read_only` is set, replicas that
+    /// are not `Valid` are rewritten from the primary. This repairs more than
+    /// `load_control_data`, which only rewrites replicas that fail to decode and
+    /// leaves a decodable replica that differs from the primary untouched.
+    ///
+    /// Errors:
+    /// - `LayoutMismatch`: a replica superblock records another geometry.
+    /// - `RelocationInProgress`: an unfinished relocation journal is present.
+    /// - `InitInProgress`: an unfinished `init` marker is present.
+    ///
+    /// Parameters:
+    /// - `read_only`: diagnose only, never write.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::ReplicaStatus;
+    ///
+    /// let health = backend.control_plane_health(true)?;
+    /// for (replica_index, status) in health.statuses().iter().enumerate() {
+    ///     if *status != ReplicaStatus::Valid {
+    ///         defmt::warn!("control-plane replica {} is {}", replica_index, status.code());
+    ///     }
+    /// }
+    /// ```
+    pub fn control_plane_health(&mut self, read_only: bool) -> Result<ControlPlaneHealth, StorageError> {
+        self.ensure_no_pending_operation(&self.region)?;
+
+        let replica_count = self.region.control_plane.replica_count;
+        let mut health = ControlPlaneHealth::new(replica_count);
+        let mut primary: Option<Zeroizing<[u8; CONTROL_PLANE_ENTRY_SIZE]>> = None;
+
+        let mut replica_index = 0usize;
+        while replica_index < replica_count {
+            let (entry, _) = self.read_replica_entry(&self.region, replica_index)?;
+            let decoded = control_plane::decode_entry(&entry, &self.key_protection).map(|_| ());
+            health.classify(replica_index, entry, decoded, &mut primary);
+            replica_index += 1;
+        }
+
+        if let (Some(primary_entry), false) = (&primary, read_only) {
+            let mut replica_index = 0usize;
+            while replica_index < replica_count {
+                if health.replicas[replica_index] != ReplicaStatus::Valid {
+                    self.write_control_entry_to_replica(&self.region, replica_index, primary_entry)?;
+                    self.observer.on_replica_repaired(replica_index);
+                    health.repaired = true;
+                }
+                replica_index += 1;
+            }
+        }
+        Ok(health)
+    }
+
+    fn fsck_control_plane(
+        &mut self,
+        options: FsckOptions,
+        report: &mut FsckReport,
+        on_finding: &mut dyn FnMut(&FsckFinding),
+    ) -> Result<(), StorageError> {
+        let replica_count = self.region.control_plane.replica_count;
+        let mut issues: [Option<FsckIssue>; MAX_CONTROL_PLANE_COUNT] = [None; MAX_CONTROL_PLANE_COUNT];
+        let mut primary: Option<Zeroizing<[u8; CONTROL_PLANE_ENTRY_SIZE]>> = None;
+
+        let mut replica_index = 0usize;
+        while replica_index < replica_count {
+            report.replicas_checked += 1;
+            let (entry, superblock) = self.read_replica_entry(&self.region, replica_index)?;
+
+            issues[replica_index] = match control_plane::decode_entry(&entry, &self.key_protection) {
+                Ok(_) | Err(StorageError::KeyUnwrapFailed) => {
+                    report.replicas_valid += 1;
+                    match &primary {
+                        None => {
+                            primary = Some(entry);
+                            None
+                        }
+                        Some(primary_entry) if **primary_entry != *entry => {
+                            Some(FsckIssue::ReplicaDisagreement { replica_index })
+                        }
+                        Some(_) => None,
+                    }
+                }
+                Err(StorageError::ControlPlaneUninitialized) => {
+                    Some(FsckIssue::ReplicaErased { replica_index })
+                }
+                Err(StorageError::ControlPlaneIncompatible) => {
+                    Some(FsckIssue::ReplicaIncompatible { replica_index })
+                }
+                Err(StorageError::ControlPlaneCorrupted) => {
+                    Some(FsckIssue::ReplicaCorrupted { replica_index })
+                }
+                Err(err) => return Err(err),
+            };
+
+            if issues[replica_index].is_none()
+                && !self.flash_is_filled(
+                    self.region.control_plane_page_address(replica_index) + CONTROL_PLANE_ENTRY_SIZE,
+                    SUPERBLOCK_OFFSET - CONTROL_PLANE_ENTRY_SIZE,
+                    0,
+                )?
+            {
+                issues[replica_index] = Some(FsckIssue::ReplicaPaddingAnomaly { replica_index });
+            }
+            if issues[replica_index].is_none() && superblock.is_none() {
+                issues[replica_index] = Some(FsckIssue::SuperblockMissing { replica_index });
+            }
+            replica_index += 1;
+        }
+
+        let all_erased = issues[..replica_count]
+            .iter()
+            .all(|issue| matches!(issue, Some(FsckIssue::ReplicaErased { .. })));
+        if all_erased {
+            return Ok(());
+        }
+
+        let mut replica_index = 0usize;
+        while replica_index < replica_count {
+            if let Some(issue) = issues[replica_index] {
+                let repaired = match (&primary, options.repair) {
+                    (Some(primary_entry), true) => {
+                        self.write_control_entry_to_replica(&self.region, replica_index, primary_entry)?;
+                        self.observer.on_replica_repaired(replica_index);
+                        true
+                    }
+                    _ => false,
+                };
+                report.record(issue, repaired, on_finding);
+            }
+            replica_index += 1;
+        }
+
+        Ok(())
+    }
+
+    fn load_primary_control_record_and_repair(
+        &self,
+        region: &Rp2040Region,
+    ) -> Result<ControlPlaneData, StorageError> {
+        self.ensure_no_pending_operation(region)?;
+
+        let mut first_valid_record: Option<ControlPlaneData> = None;
+        let mut first_valid_index: Option<usize> = None;
+        let mut invalid = [usize::MAX; MAX_CONTROL_PLANE_COUNT];
+        let mut invalid_len = 0usize;
+        let mut saw_non_uninitialized = false;
+        let mut saw_incompatible = false;
+        let mut saw_unwrap_failure = false;
+        let mut needs_upgrade = false;
+
+        let mut i = 0usize;
+        while i < region.control_plane.replica_count {
+            match self.read_control_record_from_replica(region, i) {
+                Ok(decoded) => {
+                    if first_valid_record.is_none() {
+                        first_valid_index = Some(i);
+                        needs_upgrade = decoded.needs_upgrade;
+                        first_valid_record = Some(decoded.record);
+                    }
+                }
+                Err(StorageError::KeyUnwrapFailed) => {
+                    saw_non_uninitialized = true;
+                    saw_unwrap_failure = true;
+                    invalid[invalid_len] = i;
+                    invalid_len += 1;
+                }
+                Err(StorageError::ControlPlaneUninitialized) => {
+                    invalid[invalid_len] = i;
+                    invalid_len += 1;
+                }
+                Err(StorageError::ControlPlaneIncompatible) => {
+                    saw_non_uninitialized = true;
+                    saw_incompatible = true;
+                    invalid[invalid_len] = i;
+                    invalid_len += 1;
+                }
+                Err(StorageError::ControlPlaneCorrupted) => {
+                    saw_non_uninitialized = true;
+                    invalid[invalid_len] = i;
+                    invalid_len += 1;
+                }
+                Err(err) => return Err(err),
+            }
+            i += 1;
+        }
+
+        let record = match first_valid_record {
+            Some(value) => value,
+            None => {
+                if saw_unwrap_failure {
+                    return Err(StorageError::KeyUnwrapFailed);
+                }
+                if saw_incompatible {
+                    return Err(StorageError::ControlPlaneIncompatible);
+                }
+                if saw_non_uninitialized {
+                    return Err(StorageError::ControlPlaneCorrupted);
+                }
+                return Err(StorageError::ControlPlaneUninitialized);
+            }
+        };
+
+        if needs_upgrade {
+            let mut replica_index = 0usize;
+            while replica_index < region.control_plane.replica_count {
+                self.write_control_record_to_replica(region, replica_index, &record)?;
+                replica_index += 1;
+            }
+            return Ok(record);
+        }
+
+        let mut j = 0usize;
+        while j < invalid_len {
+            let target = invalid[j];
+            if Some(target) != first_valid_index {
+                self.write_control_record_to_replica(region, target, &record)?;
+                self.observer.on_replica_repaired(target);
+            }
+            j += 1;
+        }
+
+        Ok(record)
+    }
+
+    fn init_in(
+        &self,
+        region: &Rp2040Region,
+        mut private_key: [u8; PRIVATE_KEY_SIZE],
+        own_node_id: u32,
+        init_params: [u8; INIT_PARAMS_SIZE],
+        on_progress: &mut dyn FnMut(InitProgress),
+    ) -> Result<(), StorageError> {
+        let record = ControlPlaneData {
+            private_key,
+            own_node_id,
+            init_params,
+            chain_configuration: None,
+        };
+        private_key.zeroize();
+
+        Self::validate_page_aligned_start_address(region.start)?;
+        self.observer.on_init_started();
+        let marker = InitMarker {
+            sequence: 0,
+            region_start: region.start as u32,
+            region_end: region.end as u32,
+            pages_erased: 0,
+            pages_total: region.block_page_count() as u32,
+            entry: control_plane::encode_entry(&record, &self.key_protection)?,
+        };
+
+        // Every replica carries the marker before the first block page is erased.
+        let mut replica_index = 0usize;
+        while replica_index < region.control_plane.replica_count {
+            self.write_init_marker(region, replica_index, &marker)?;
+            replica_index += 1;
+        }
+        self.finish_init(region, marker, on_progress)
+    }
+
+    /// Erases the block pages not yet covered by `marker`, then replaces the
+    /// markers with the control-plane entry they carry.
+    fn finish_init(
+        &self,
+        region: &Rp2040Region,
+        mut marker: InitMarker,
+        on_progress: &mut dyn FnMut(InitProgress),
+    ) -> Result<(), StorageError> {
+        let replica_count = region.control_plane.replica_count;
+        let pages_total = marker.pages_total as usize;
+        let mut page_index = marker.pages_erased as usize;
+        while page_index < pages_total {
+            self.erase_page(region.block_page_address(page_index))?;
+            page_index += 1;
+            if page_index % INIT_ERASE_CHUNK_PAGES != 0 && page_index != pages_total {
+                continue;
+            }
+
+            marker.pages_erased = page_index as u32;
+            on_progress(marker.progress());
+            // A single replica cannot take a checkpoint without erasing the
+            // only marker; resuming then repeats the erases instead.
+            if page_index < pages_total && replica_count >= 2 {
+                marker.sequence += 1;
+                self.write_init_marker(region, marker.sequence as usize % replica_count, &marker)?;
+            }
+        }
+
+        self.erase_sequence_log(region)?;
+        self.write_sequence_log_header(region)?;
+        let mut page_index = 0usize;
+        while page_index < region.kv_pages {
+            self.erase_page(region.kv_page_address(page_index))?;
+            page_index += 1;
+        }
+        let mut page_index = 0usize;
+        while page_index < region.slot_meta_pages {
+            self.erase_page(region.slot_meta_page_address(page_index))?;
+            page_index += 1;
+        }
+
+        let mut replica_index = 0usize;
+        while replica_index < replica_count {
+            self.write_control_entry_to_replica(region, replica_index, &marker.entry)?;
+            replica_index += 1;
+        }
+        if *region == self.region {
+            self.observer.on_slots_reset(region.max_storage_slots());
+        }
+        self.observer.on_init_finished();
+        Ok(())
+    }
+
+    fn write_init_marker(
+        &self,
+        region: &Rp2040Region,
+        replica_index: usize,
+        marker: &InitMarker,
+    ) -> Result<(), StorageError> {
+        let page_address = region.control_plane_page_address(replica_index);
+        self.erase_page(page_address)?;
+        self.write_flash(page_address, &marker.encode()[..])
+    }
+
+    /// Returns the newest init marker in the control-plane replicas of `region`.
+    fn read_init_marker(&self, region: &Rp2040Region) -> Result<Option<InitMarker>, StorageError> {
+        let mut bytes = Zeroizing::new([0u8; INIT_MARKER_SIZE]);
+        let mut latest: Option<InitMarker> = None;
+
+        let mut replica_index = 0usize;
+        while replica_index < region.control_plane.replica_count {
+            self.read_flash(region.control_plane_page_address(replica_index), &mut bytes[..])?;
+            if let Some(marker) = InitMarker::decode(&bytes)? {
+                let newer = match &latest {
+                    Some(current) => marker.sequence > current.sequence,
+                    None => true,
+                };
+                if newer {
+                    latest = Some(marker);
+                }
+            }
+            replica_index += 1;
+        }
+
+        let foreign = match &latest {
+            Some(marker) => {
+                marker.region_start as usize != region.start
+                    || marker.region_end as usize != region.end
+                    || marker.pages_total as usize != region.block_page_count()
+            }
+            None => false,
+        };
+        if foreign {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        Ok(latest)
+    }
+
+    /// Fails while a relocation journal or an init marker is present in `region`.
+    fn ensure_no_pending_operation(&self, region: &Rp2040Region) -> Result<(), StorageError> {
+        if self.read_relocation_journal(region)?.is_some() {
+            return Err(StorageError::RelocationInProgress);
+        }
+        if self.read_init_marker(region)?.is_some() {
+            return Err(StorageError::InitInProgress);
+        }
+        Ok(())
+    }
+
+    fn save_block_in(
+        &self,
+        region: &Rp2040Region,
+        storage_index: StorageIndex,
+        block: &Block,
+    ) -> Result<(), StorageError> {
+        let write_once = region.write_once.contains(storage_index);
+        self.write_block_in(region, storage_index, block, WriteCondition::Any, write_once)
+    }
+
+    /// Rewrites the page holding `storage_index` once `condition` holds for
+    /// the slot contents read into the page buffer. The check and the write
+    /// use the same read, so nothing else can change the slot in between.
+    fn write_block_in(
+        &self,
+        region: &Rp2040Region,
+        storage_index: StorageIndex,
+        block: &Block,
+        condition: WriteCondition,
+        write_once: bool,
+    ) -> Result<(), StorageError> {
+        if storage_index >= region.max_storage_slots() {
+            return Err(StorageError::InvalidIndex);
+        }
+
+        let mapping = map_storage_index(storage_index);
+        let page_address = Self::page_flash_address(region, &mapping);
+        let mut page = self.page_buffer.borrow_mut();
+        self.read_page(page_address, &mut page)?;
+
+        let slot_start = mapping.byte_offset_in_page;
+        let slot = &page[slot_start..slot_start + SLOT_HASH_OFFSET + HASH_SIZE];
+        // A damaged slot still counts as occupied and reports its stored hash.
+        let current = if slot.iter().all(|byte| *byte == 0xFF) {
+            None
+        } else {
+            let mut stored_hash = [0u8; HASH_SIZE];
+            stored_hash.copy_from_slice(&slot[SLOT_HASH_OFFSET..SLOT_HASH_OFFSET + HASH_SIZE]);
+            Some(stored_hash)
+        };
+        condition.check(current, write_once)?;
+
+        Self::encode_block_to_slot(&mut page[..], &mapping, block)?;
+        let hash_start = slot_start + SLOT_HASH_OFFSET;
+        let mut stored_hash = [0u8; HASH_SIZE];
+        stored_hash.copy_from_slice(&page[hash_start..hash_start + HASH_SIZE]);
+        // The record goes first, so a built log covers every intact slot.
+        let rebuilt = self.append_sequence_record(
+            region,
+            storage_index,
+            sequence_index::block_sequence(block),
+            sequence_log::hash_tag(&stored_hash),
+            &mut page,
+        )?;
+        if rebuilt {
+            // Rebuilding the full log scanned the slots through `page`.
+            self.read_page(page_address, &mut page)?;
+            Self::encode_block_to_slot(&mut page[..], &mapping, block)?;
+        }
+        self.clear_slot_meta(region, storage_index)?;
+        self.erase_page(page_address)?;
+        self.write_page(page_address, &page)?;
+        self.observer.on_slot_written(storage_index);
+        self.report_slot_changed(region, storage_index, Some(&stored_hash));
+        Ok(())
+    }
+
+    /// Reports a slot change to the observer when `region` is the backend's own region.
+    fn report_slot_changed(
+        &self,
+        region: &Rp2040Region,
+        storage_index: StorageIndex,
+        block_hash: Option<&[u8; HASH_SIZE]>,
+    ) {
+        if *region == self.region {
+            self.observer.on_slot_changed(storage_index, block_hash);
+        }
+    }
+
+    /// Reports the slot count and every intact slot of the backend's own
+    /// region to the observer.
+    fn report_slots(&self) -> Result<(), StorageError> {
+        let region = &self.region;
+        self.observer.on_slots_reset(region.max_storage_slots());
+        let mut page = self.page_buffer.borrow_mut();
+        let mut storage_index: StorageIndex = 0;
+        while storage_index < region.max_storage_slots() {
+            let mapping = map_storage_index(storage_index);
+            let slot_start = mapping.byte_offset_in_page;
+            if slot_start == 0 {
+                self.read_page(Self::page_flash_address(region, &mapping), &mut page)?;
+            }
+            let slot = &page[slot_start..slot_start + SLOT_SIZE_BYTES];
+            if Self::decode_slot_block(slot).is_ok() {
+                let mut stored_hash = [0u8; HASH_SIZE];
+                stored_hash.copy_from_slice(&slot[SLOT_HASH_OFFSET..SLOT_HASH_OFFSET + HASH_SIZE]);
+                self.observer.on_slot_changed(storage_index, Some(&stored_hash));
+            }
+            storage_index += 1;
+        }
+        Ok(())
+    }
+
+    /// Reads the metadata area of a slot, failing on an empty slot.
+    #[cfg(feature = "slot-meta")]
+    fn read_slot_meta_area(
+        &self,
+        region: &Rp2040Region,
+        storage_index: StorageIndex,
+    ) -> Result<[u8; SLOT_META_AREA_SIZE], StorageError> {
+        if region.slot_meta_pages == 0 {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        if storage_index >= region.max_storage_slots() {
+            return Err(StorageError::InvalidIndex);
+        }
+        let mapping = map_storage_index(storage_index);
+        let slot_address = Self::page_flash_address(region, &mapping) + mapping.byte_offset_in_page;
+        let mut hash = [0u8; HASH_SIZE];
+        self.read_flash(slot_address + SLOT_HASH_OFFSET, &mut hash)?;
+        // Blocks are programmed together with their hash, so an erased hash means no block.
+        if hash.iter().all(|byte| *byte == 0xFF) {
+            return Err(StorageError::BlockAbsent);
+        }
+        let mut area = [0xFFu8; SLOT_META_AREA_SIZE];
+        self.read_flash(region.slot_meta_address(storage_index), &mut area)?;
+        Ok(area)
+    }
+
+    /// Drops the metadata of `storage_index` before a block write, without
+    /// erasing: a `cleared` record in the next free position, or zeros over
+    /// the whole area when none is free.
+    fn clear_slot_meta(&self, region: &Rp2040Region, storage_index: StorageIndex) -> Result<(), StorageError> {
+        if region.slot_meta_pages == 0 {
+            return Ok(());
+        }
+        let area_address = region.slot_meta_address(storage_index);
+        let mut area = [0xFFu8; SLOT_META_AREA_SIZE];
+        self.read_flash(area_address, &mut area)?;
+        if slot_meta::latest(&area).is_none() {
+            return Ok(());
+        }
+        match slot_meta::next_free(&area) {
+            Some(position) => self.write_flash(
+                area_address + position * SLOT_META_RECORD_SIZE,
+                &slot_meta::encode_record(None),
+            ),
+            None => self.write_flash(area_address, &[0u8; SLOT_META_AREA_SIZE]),
+        }
+    }
+
+    fn region_kv_pages<'a>(
+        &'a self,
+        region: &'a Rp2040Region,
+    ) -> Result<RegionKvPages<'a, RP2040_FLASH_SIZE, O>, StorageError> {
+        if region.kv_pages == 0 {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        Ok(RegionKvPages { backend: self, region })
+    }
+
+    #[cfg(feature = "slot-meta")]
+    fn read_slot_meta_in(
+        &self,
+        region: &Rp2040Region,
+        storage_index: StorageIndex,
+    ) -> Result<Option<[u8; SLOT_META_SIZE]>, StorageError> {
+        let area = self.read_slot_meta_area(region, storage_index)?;
+        Ok(slot_meta::latest(&area))
+    }
+
+    #[cfg(feature = "slot-meta")]
+    fn write_slot_meta_in(
+        &self,
+        region: &Rp2040Region,
+        storage_index: StorageIndex,
+        meta: &[u8; SLOT_META_SIZE],
+    ) -> Result<(), StorageError> {
+        let area = self.read_slot_meta_area(region, storage_index)?;
+        let area_address = region.slot_meta_address(storage_index);
+        let record = slot_meta::encode_record(Some(meta));
+        if let Some(position) = slot_meta::next_free(&area) {
+            return self.write_flash(area_address + position * SLOT_META_RECORD_SIZE, &record);
+        }
+
+        let page_address = area_address - area_address % FLASH_PAGE_SIZE;
+        let area_start = area_address % FLASH_PAGE_SIZE;
+        let mut page = self.page_buffer.borrow_mut();
+        self.read_page(page_address, &mut page)?;
+        page[area_start..area_start + SLOT_META_AREA_SIZE].fill(0xFF);
+        page[area_start..area_start + SLOT_META_RECORD_SIZE].copy_from_slice(&record);
+        self.erase_page(page_address)?;
+        self.write_page(page_address, &page)
+    }
+
+    fn find_by_hash_in(
+        &self,
+        region: &Rp2040Region,
+        hash: &[u8; HASH_SIZE],
+    ) -> Result<Option<StorageIndex>, StorageError> {
+        if self.sequence_log_built(region)? {
+            return self.find_by_hash_in_log(region, hash);
+        }
+
+        let page_count = region.max_storage_slots() / BLOCKS_PER_PAGE_INDEX;
+        let mut page = self.page_buffer.borrow_mut();
+        let mut page_index: StorageIndex = 0;
+        while page_index < page_count {
+            let mapping = map_storage_index(page_index * BLOCKS_PER_PAGE_INDEX);
+            self.read_page(Self::page_flash_address(region, &mapping), &mut page)?;
+
+            let mut slot_index = 0usize;
+            while slot_index < BLOCKS_PER_PAGE {
+                let slot_start = slot_index * SLOT_SIZE_BYTES;
+                let slot = &page[slot_start..slot_start + SLOT_SIZE_BYTES];
+                if slot[SLOT_HASH_OFFSET..SLOT_HASH_OFFSET + HASH_SIZE] == hash[..]
+                    && Self::decode_slot_block(slot).is_ok()
+                {
+                    return Ok(Some(page_index * BLOCKS_PER_PAGE_INDEX + slot_index as StorageIndex));
+                }
+                slot_index += 1;
+            }
+            page_index += 1;
+        }
+        Ok(None)
+    }
+
+    /// Confirms the log records tagged like `hash` against their slots.
+    fn find_by_hash_in_log(
+        &self,
+        region: &Rp2040Region,
+        hash: &[u8; HASH_SIZE],
+    ) -> Result<Option<StorageIndex>, StorageError> {
+        let tag = sequence_log::hash_tag(hash);
+        let mut found: Option<StorageIndex> = None;
+        let mut failure: Option<StorageError> = None;
+        self.for_each_log_record(region, &mut |storage_index, _, hash_tag| {
+            if hash_tag != tag || found.is_some_and(|index| index <= storage_index) {
+                return true;
+            }
+            match self.slot_holds_hash(region, storage_index, hash) {
+                Ok(true) => found = Some(storage_index),
+                Ok(false) => {}
+                Err(error) => {
+                    failure = Some(error);
+                    return false;
+                }
+            }
+            true
+        })?;
+
+        match failure {
+            Some(error) => Err(error),
+            None => Ok(found),
+        }
+    }
+
+    /// Returns `true` when slot `storage_index` holds an intact block stored with `hash`.
+    fn slot_holds_hash(
+        &self,
+        region: &Rp2040Region,
+        storage_index: StorageIndex,
+        hash: &[u8; HASH_SIZE],
+    ) -> Result<bool, StorageError> {
+        if storage_index >= region.max_storage_slots() {
+            return Ok(false);
+        }
+        let mapping = map_storage_index(storage_index);
+        let mut slot = [0u8; SLOT_SIZE_BYTES];
+        self.read_flash(Self::page_flash_address(region, &mapping) + mapping.byte_offset_in_page, &mut slot)?;
+        Ok(slot[SLOT_HASH_OFFSET..SLOT_HASH_OFFSET + HASH_SIZE] == hash[..] && Self::decode_slot_block(&slot).is_ok())
+    }
+
+    /// Calls `visit` with `(storage_index, sequence, hash_tag)` of every
+    /// intact record of a built sequence log until it returns `false`.
+    ///
+    /// Records are read a chunk at a time, so `visit` may read slots through
+    /// `page_buffer`.
+    fn for_each_log_record(
+        &self,
+        region: &Rp2040Region,
+        visit: &mut dyn FnMut(StorageIndex, u32, u32) -> bool,
+    ) -> Result<(), StorageError> {
+        let mut chunk = [0u8; FLASH_CHUNK_SIZE];
+        let capacity = sequence_log::capacity(region.sequence_index_pages);
+        // Position 0 is the header.
+        let mut position = 1usize;
+        while position <= capacity {
+            let record_start = (position % SEQUENCE_RECORDS_PER_CHUNK) * SEQUENCE_RECORD_SIZE;
+            if position == 1 || record_start == 0 {
+                let chunk_position = position - position % SEQUENCE_RECORDS_PER_CHUNK;
+                self.read_flash(region.sequence_log_address(chunk_position), &mut chunk)?;
+            }
+            match sequence_log::decode_record(&chunk[record_start..record_start + SEQUENCE_RECORD_SIZE]) {
+                LogRecord::Erased => return Ok(()),
+                LogRecord::Damaged => {}
+                LogRecord::Entry {
+                    storage_index,
+                    sequence,
+                    hash_tag,
+                } => {
+                    if !visit(storage_index, sequence, hash_tag) {
+                        return Ok(());
+                    }
+                }
+            }
+            position += 1;
+        }
+        Ok(())
+    }
+
+    fn build_merkle_tree_in<const LEAVES: usize>(
+        &self,
+        region: &Rp2040Region,
+        tree: &mut MerkleTree<LEAVES>,
+    ) -> Result<(), StorageError> {
+        let mut page = self.page_buffer.borrow_mut();
+        let mut loaded_page: Option<StorageIndex> = None;
+        tree.load(region.max_storage_slots(), &mut |storage_index| {
+            let mapping = map_storage_index(storage_index);
+            if loaded_page != Some(mapping.page_index) {
+                self.read_page(Self::page_flash_address(region, &mapping), &mut page)?;
+                loaded_page = Some(mapping.page_index);
+            }
+            let slot_start = mapping.byte_offset_in_page;
+            let slot = &page[slot_start..slot_start + SLOT_SIZE_BYTES];
+            if Self::decode_slot_block(slot).is_err() {
+                return Ok(EMPTY_LEAF);
+            }
+            let mut leaf = [0u8; HASH_SIZE];
+            leaf.copy_from_slice(&slot[SLOT_HASH_OFFSET..SLOT_HASH_OFFSET + HASH_SIZE]);
+            Ok(leaf)
+        })
+    }
+
+    fn sequences_in<'a>(
+        &'a self,
+        region: &'a Rp2040Region,
+    ) -> Result<RegionSequences<'a, RP2040_FLASH_SIZE, O>, StorageError> {
+        Ok(RegionSequences {
+            backend: self,
+            region,
+            log_built: self.sequence_log_built(region)?,
+        })
+    }
+
+    fn read_sequence_log_position(
+        &self,
+        region: &Rp2040Region,
+        position: usize,
+    ) -> Result<[u8; SEQUENCE_RECORD_SIZE], StorageError> {
+        let mut bytes = [0u8; SEQUENCE_RECORD_SIZE];
+        self.read_flash(region.sequence_log_address(position), &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Returns `true` when `region` has a sequence index whose log is built.
+    fn sequence_log_built(&self, region: &Rp2040Region) -> Result<bool, StorageError> {
+        if region.sequence_index_pages == 0 {
+            return Ok(false);
+        }
+        Ok(sequence_log::is_header(&self.read_sequence_log_position(region, 0)?))
+    }
+
+    /// Returns the first erased record position, or `None` when the log is full.
+    fn sequence_log_end(&self, region: &Rp2040Region) -> Result<Option<usize>, StorageError> {
+        // Erased positions form a suffix of the log, so binary search for it.
+        let capacity = sequence_log::capacity(region.sequence_index_pages);
+        let mut low = 1usize;
+        let mut high = capacity + 1;
+        while low < high {
+            let middle = low + (high - low) / 2;
+            match sequence_log::decode_record(&self.read_sequence_log_position(region, middle)?) {
+                LogRecord::Erased => high = middle,
+                _ => low = middle + 1,
+            }
+        }
+        Ok(if low > capacity { None } else { Some(low) })
+    }
+
+    /// Appends a record to a built log, rebuilding it first through `scratch`
+    /// when it is full. Returns `true` when it rebuilt, i.e. `scratch` was
+    /// overwritten.
+    fn append_sequence_record(
+        &self,
+        region: &Rp2040Region,
+        storage_index: StorageIndex,
+        sequence: u32,
+        hash_tag: u32,
+        scratch: &mut [u8; FLASH_PAGE_SIZE],
+    ) -> Result<bool, StorageError> {
+        if !self.sequence_log_built(region)? {
+            return Ok(false);
+        }
+        let (position, rebuilt) = match self.sequence_log_end(region)? {
+            Some(value) => (value, false),
+            None => {
+                self.rebuild_sequence_log(region, scratch)?;
+                // `has_valid_layout` leaves room for one record past every slot.
+                (self.sequence_log_end(region)?.ok_or(StorageError::InvalidConfiguration)?, true)
+            }
+        };
+        self.write_flash(
+            region.sequence_log_address(position),
+            &sequence_log::encode_record(storage_index, sequence, hash_tag),
+        )?;
+        Ok(rebuilt)
+    }
+
+    fn erase_sequence_log(&self, region: &Rp2040Region) -> Result<(), StorageError> {
+        let mut page_index = 0usize;
+        while page_index < region.sequence_index_pages {
+            self.erase_page(region.sequence_index_page_address(page_index))?;
+            page_index += 1;
+        }
+        Ok(())
+    }
+
+    fn write_sequence_log_header(&self, region: &Rp2040Region) -> Result<(), StorageError> {
+        if region.sequence_index_pages == 0 {
+            return Ok(());
+        }
+        self.write_flash(region.sequence_log_address(0), &sequence_log::encode_header())
+    }
+
+    /// Rebuilds the sequence log of `region` from a scan of its slots, read
+    /// through `slot_page`. The header is written last, so an interrupted
+    /// rebuild leaves the log unbuilt.
+    fn rebuild_sequence_log(
+        &self,
+        region: &Rp2040Region,
+        slot_page: &mut [u8; FLASH_PAGE_SIZE],
+    ) -> Result<(), StorageError> {
+        if region.sequence_index_pages == 0 {
+            return Ok(());
+        }
+        self.erase_sequence_log(region)?;
+
+        // Records are programmed a chunk at a time; chunks never cross a page.
+        let mut chunk = [0xFFu8; FLASH_CHUNK_SIZE];
+        let mut position = 1usize;
+        let page_count = region.max_storage_slots() / BLOCKS_PER_PAGE_INDEX;
+        let mut page_index: StorageIndex = 0;
+        while page_index < page_count {
+            let mapping = map_storage_index(page_index * BLOCKS_PER_PAGE_INDEX);
+            self.read_page(Self::page_flash_address(region, &mapping), slot_page)?;
+
+            let mut slot_index = 0usize;
+            while slot_index < BLOCKS_PER_PAGE {
+                let slot_start = slot_index * SLOT_SIZE_BYTES;
+                let slot = &slot_page[slot_start..slot_start + SLOT_SIZE_BYTES];
+                if let Ok(block) = Self::decode_slot_block(slot) {
+                    let mut stored_hash = [0u8; HASH_SIZE];
+                    stored_hash.copy_from_slice(&slot[SLOT_HASH_OFFSET..SLOT_HASH_OFFSET + HASH_SIZE]);
+                    let record_start = (position % SEQUENCE_RECORDS_PER_CHUNK) * SEQUENCE_RECORD_SIZE;
+                    chunk[record_start..record_start + SEQUENCE_RECORD_SIZE].copy_from_slice(
+                        &sequence_log::encode_record(
+                            page_index * BLOCKS_PER_PAGE_INDEX + slot_index as StorageIndex,
+                            sequence_index::block_sequence(&block),
+                            sequence_log::hash_tag(&stored_hash),
+                        ),
+                    );
+                    position += 1;
+                    if position % SEQUENCE_RECORDS_PER_CHUNK == 0 {
+                        let chunk_position = position - SEQUENCE_RECORDS_PER_CHUNK;
+                        self.write_flash(region.sequence_log_address(chunk_position), &chunk)?;
+                        chunk.fill(0xFF);
+                    }
+                }
+                slot_index += 1;
+            }
+            page_index += 1;
+        }
+
+        if chunk.iter().any(|byte| *byte != 0xFF) {
+            let chunk_position = position - position % SEQUENCE_RECORDS_PER_CHUNK;
+            self.write_flash(region.sequence_log_address(chunk_position), &chunk)?;
+        }
+        self.write_sequence_log_header(region)
+    }
+
+    fn read_block_in(&self, region: &Rp2040Region, storage_index: StorageIndex) -> Result<Block, StorageError> {
+        if storage_index >= region.max_storage_slots() {
+            return Err(StorageError::InvalidIndex);
+        }
+
+        let mapping = map_storage_index(storage_index);
+        match self.read_slot(region, &mapping) {
+            Ok(block) => {
+                self.observer.on_slot_read(storage_index);
+                Ok(block)
+            }
+            Err(StorageError::IntegrityFailure) => {
+                self.observer.on_integrity_failure(storage_index);
+                self.report_slot_changed(region, storage_index, None);
+                Err(StorageError::IntegrityFailure)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn set_chain_configuration_in(&self, region: &Rp2040Region, block: &Block) -> Result<(), StorageError> {
+        let mut record = self.load_primary_control_record_and_repair(region)?;
+        if record.chain_configuration.is_some() {
+            return Err(StorageError::ChainConfigurationAlreadySet);
+        }
+
+        record.chain_configuration = Some(
+            Block::from_bytes(block.as_bytes()).map_err(|_| StorageError::BackendIo { code: 213 })?,
+        );
+
+        let mut replica_index = 0usize;
+        while replica_index < region.control_plane.replica_count {
+            self.write_control_record_to_replica(region, replica_index, &record)?;
+            replica_index += 1;
+        }
+
+        Ok(())
+    }
+}
+
+impl<const RP2040_FLASH_SIZE: usize, O: StorageObserver> StorageTrait
+    for Rp2040Backend<RP2040_FLASH_SIZE, O>
+{
+    fn init(
+        &mut self,
+        private_key: [u8; PRIVATE_KEY_SIZE],
+        own_node_id: u32,
+        init_params: [u8; INIT_PARAMS_SIZE],
+    ) -> Result<(), StorageError> {
+        self.init_in(&self.region, private_key, own_node_id, init_params, &mut |_| {})
+    }
+
+    fn save_block(
+        &mut self,
+        storage_index: StorageIndex,
+        block: &Block,
+    ) -> Result<(), StorageError> {
+        self.save_block_in(&self.region, storage_index, block)
+    }
+
+    fn read_block(&self, storage_index: StorageIndex) -> Result<Block, StorageError> {
+        self.read_block_in(&self.region, storage_index)
+    }
+
+    fn set_chain_configuration(&mut self, block: &Block) -> Result<(), StorageError> {
+        self.set_chain_configuration_in(&self.region, block)
+    }
+
+    fn load_control_data(&mut self) -> Result<ControlPlaneData, StorageError> {
+        let record = self.load_primary_control_record_and_repair(&self.region)?;
+        Ok(record)
+    }
+
+    /// With a built sequence index (`with_sequence_index`) only the slots
+    /// whose log record carries the hash's tag are read. Without one, each
+    /// block page is read once and the stored slot hashes are compared. Either
+    /// way a match is verified against the slot and damaged slots are skipped.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::{StorageTrait, block_hash};
+    /// use moonblokz_storage::backend_rp2040::Rp2040Backend;
+    ///
+    /// let mut backend = Rp2040Backend::<{ 8 * 4096 }>::new(0)?.with_sequence_index(1)?;
+    /// backend.save_block(4, &block)?;
+    /// assert_eq!(backend.find_by_hash(&block_hash(&block))?, Some(4));
+    /// ```
+    fn find_by_hash(&self, hash: &[u8; HASH_SIZE]) -> Result<Option<StorageIndex>, StorageError> {
+        self.find_by_hash_in(&self.region, hash)
+    }
+}
+
+/// Monotonic counter of an RP2040 region, returned by `Rp2040Backend::counter`
+/// and `Rp2040Partition::counter`.
+pub struct Rp2040Counter<
+    'a,
+    const RP2040_FLASH_SIZE: usize = RP2040_DEFAULT_FLASH_SIZE,
+    O: StorageObserver = NoopObserver,
+> {
+    backend: &'a Rp2040Backend<RP2040_FLASH_SIZE, O>,
+    region: &'a Rp2040Region,
+    id: usize,
+}
+
+impl<'a, const RP2040_FLASH_SIZE: usize, O: StorageObserver> Rp2040Counter<'a, RP2040_FLASH_SIZE, O> {
+    fn new(
+        backend: &'a Rp2040Backend<RP2040_FLASH_SIZE, O>,
+        region: &'a Rp2040Region,
+        id: usize,
+    ) -> Result<Self, StorageError> {
+        if id >= region.counters {
+            return Err(StorageError::InvalidIndex);
+        }
+        Ok(Self { backend, region, id })
+    }
+
+    /// Returns the counter value, 0 before the first increment.
+    ///
+    /// Errors:
+    /// - `BackendIo` when a flash read fails.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let boots = backend.counter(0)?.get()?;
+    /// ```
+    pub fn get(&self) -> Result<u64, StorageError> {
+        counter::get(self)
+    }
+
+    /// Adds one to the counter and returns the new value.
+    ///
+    /// A power loss during the call leaves the old or the new value, never a
+    /// lower one.
+    ///
+    /// Errors:
+    /// - `BackendIo` when a flash operation fails.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let nonce = backend.counter(1)?.increment()?;
+    /// ```
+    pub fn increment(&mut self) -> Result<u64, StorageError> {
+        counter::increment(self)
+    }
+}
+
+impl<const RP2040_FLASH_SIZE: usize, O: StorageObserver> KvPages for Rp2040Counter<'_, RP2040_FLASH_SIZE, O> {
+    fn page_count(&self) -> usize {
+        COUNTER_PAGES
+    }
+
+    fn read(&self, page: usize, offset: usize, out: &mut [u8]) -> Result<(), StorageError> {
+        self.backend.read_flash(self.region.counter_page_address(self.id, page) + offset, out)
+    }
+
+    fn program(&mut self, page: usize, offset: usize, bytes: &[u8]) -> Result<(), StorageError> {
+        self.backend.write_flash(self.region.counter_page_address(self.id, page) + offset, bytes)
+    }
+
+    fn erase(&mut self, page: usize) -> Result<(), StorageError> {
+        self.backend.erase_page(self.region.counter_page_address(self.id, page))
+    }
+}
+
+/// Key-value pages of one region.
+struct RegionKvPages<'a, const RP2040_FLASH_SIZE: usize, O: StorageObserver> {
+    backend: &'a Rp2040Backend<RP2040_FLASH_SIZE, O>,
+    region: &'a Rp2040Region,
+}
+
+impl<const RP2040_FLASH_SIZE: usize, O: StorageObserver> KvPages for RegionKvPages<'_, RP2040_FLASH_SIZE, O> {
+    fn page_count(&self) -> usize {
+        self.region.kv_pages
+    }
+
+    fn read(&self, page: usize, offset: usize, out: &mut [u8]) -> Result<(), StorageError> {
+        self.backend.read_flash(self.region.kv_page_address(page) + offset, out)
+    }
+
+    fn program(&mut self, page: usize, offset: usize, bytes: &[u8]) -> Result<(), StorageError> {
+        self.backend.write_flash(self.region.kv_page_address(page) + offset, bytes)
+    }
+
+    fn erase(&mut self, page: usize) -> Result<(), StorageError> {
+        self.backend.erase_page(self.region.kv_page_address(page))
+    }
+}
+
+/// Sequence candidates of one region: log records when its sequence log is
+/// built, otherwise the header bytes of every programmed slot.
+struct RegionSequences<'a, const RP2040_FLASH_SIZE: usize, O: StorageObserver> {
+    backend: &'a Rp2040Backend<RP2040_FLASH_SIZE, O>,
+    region: &'a Rp2040Region,
+    log_built: bool,
+}
+
+impl<const RP2040_FLASH_SIZE: usize, O: StorageObserver> SequenceSource
+    for RegionSequences<'_, RP2040_FLASH_SIZE, O>
+{
+    fn for_each_candidate(
+        &self,
+        visit: &mut dyn FnMut(StorageIndex, u32) -> bool,
+    ) -> Result<(), StorageError> {
+        if self.log_built {
+            return self
+                .backend
+                .for_each_log_record(self.region, &mut |storage_index, sequence, _| visit(storage_index, sequence));
+        }
+
+        let mut page = self.backend.page_buffer.borrow_mut();
+        let page_count = self.region.max_storage_slots() / BLOCKS_PER_PAGE_INDEX;
+        let mut page_index: StorageIndex = 0;
+        while page_index < page_count {
+            let mapping = map_storage_index(page_index * BLOCKS_PER_PAGE_INDEX);
+            self.backend
+                .read_page(Rp2040Backend::<RP2040_FLASH_SIZE, O>::page_flash_address(self.region, &mapping), &mut page)?;
+            let mut slot_index = 0usize;
+            while slot_index < BLOCKS_PER_PAGE {
+                let slot = &page[slot_index * SLOT_SIZE_BYTES..(slot_index + 1) * SLOT_SIZE_BYTES];
+                if slot.iter().any(|byte| *byte != 0xFF)
+                    && !visit(
+                        page_index * BLOCKS_PER_PAGE_INDEX + slot_index as StorageIndex,
+                        sequence_index::sequence_from_bytes(slot),
+                    )
+                {
+                    return Ok(());
+                }
+                slot_index += 1;
+            }
+            page_index += 1;
+        }
+        Ok(())
+    }
+
+    /// Reads only the slot, since `for_each_candidate` holds `page_buffer`.
+    fn holds(&self, storage_index: StorageIndex, sequence: u32) -> Result<bool, StorageError> {
+        if storage_index >= self.region.max_storage_slots() {
+            return Ok(false);
         }
-
         let mapping = map_storage_index(storage_index);
-        self.read_slot(&mapping)
+        let page_address = Rp2040Backend::<RP2040_FLASH_SIZE, O>::page_flash_address(self.region, &mapping);
+        let mut slot = [0u8; SLOT_SIZE_BYTES];
+        self.backend.read_flash(page_address + mapping.byte_offset_in_page, &mut slot)?;
+        Ok(matches!(
+            Rp2040Backend::<RP2040_FLASH_SIZE, O>::decode_slot_block(&slot),
+            Ok(block) if sequence_index::block_sequence(&block) == sequence
+        ))
+    }
+}
+
+/// `StorageTrait` handle for one partition of a partitioned `Rp2040Backend`.
+///
+/// Handles borrow the backend, so several partitions can be used side by side
+/// over the same flash peripheral. Storage indexes are partition-local.
+pub struct Rp2040Partition<
+    'a,
+    const RP2040_FLASH_SIZE: usize = RP2040_DEFAULT_FLASH_SIZE,
+    O: StorageObserver = NoopObserver,
+> {
+    backend: &'a Rp2040Backend<RP2040_FLASH_SIZE, O>,
+    region: Rp2040Region,
+}
+
+impl<const RP2040_FLASH_SIZE: usize, O: StorageObserver> Rp2040Partition<'_, RP2040_FLASH_SIZE, O> {
+    /// Returns the flash region of this partition.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let testnet = backend.partition("testnet")?;
+    /// assert_eq!(testnet.region().name, "testnet");
+    /// ```
+    pub fn region(&self) -> Rp2040Region {
+        self.region
+    }
+
+    /// Partition-local `Rp2040Backend::force_overwrite`.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `storage_index` is outside the partition.
+    /// - `BackendIo` when a flash operation fails.
+    ///
+    /// Parameters:
+    /// - `storage_index`: partition-local destination slot index.
+    /// - `block`: block to persist.
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.partition("main")?.force_overwrite(4, &fork_block)?;
+    /// ```
+    pub fn force_overwrite(&mut self, storage_index: StorageIndex, block: &Block) -> Result<(), StorageError> {
+        self.backend
+            .write_block_in(&self.region, storage_index, block, WriteCondition::Any, false)
+    }
+
+    /// Partition-local `Rp2040Backend::replace_block`.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `storage_index` is outside the partition.
+    /// - `Conflict`: the slot does not hold the expected block.
+    /// - `SlotOccupied`: the slot is write-once.
+    /// - `BackendIo` when a flash operation fails.
+    ///
+    /// Parameters:
+    /// - `storage_index`: partition-local slot index.
+    /// - `expected_hash`: `block_hash` of the block the replacement was validated against.
+    /// - `block`: replacement block.
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.partition("main")?.replace_block(4, &block_hash(&old_tip), &new_tip)?;
+    /// ```
+    pub fn replace_block(
+        &mut self,
+        storage_index: StorageIndex,
+        expected_hash: &[u8; HASH_SIZE],
+        block: &Block,
+    ) -> Result<(), StorageError> {
+        let write_once = self.region.write_once.contains(storage_index);
+        self.backend.write_block_in(
+            &self.region,
+            storage_index,
+            block,
+            WriteCondition::Holds(*expected_hash),
+            write_once,
+        )
+    }
+
+    /// Partition-local `Rp2040Backend::insert_block`.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `storage_index` is outside the partition.
+    /// - `Conflict`: the slot holds a block.
+    /// - `BackendIo` when a flash operation fails.
+    ///
+    /// Parameters:
+    /// - `storage_index`: partition-local destination slot index.
+    /// - `block`: block to persist.
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.partition("main")?.insert_block(5, &block)?;
+    /// ```
+    pub fn insert_block(&mut self, storage_index: StorageIndex, block: &Block) -> Result<(), StorageError> {
+        self.backend
+            .write_block_in(&self.region, storage_index, block, WriteCondition::Empty, false)
+    }
+
+    /// Partition-local `Rp2040Backend::kv_get`.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: the partition has no key-value store.
+    /// - `BackendIo` when a flash read fails.
+    ///
+    /// Parameters:
+    /// - `key`: key bytes.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let peer = backend.partition("main")?.kv_get(b"peer")?;
+    /// ```
+    pub fn kv_get(&self, key: &[u8]) -> Result<Option<KvValue>, StorageError> {
+        kv_store::get(&self.backend.region_kv_pages(&self.region)?, key)
+    }
+
+    /// Partition-local `Rp2040Backend::kv_put`.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: the partition has no key-value store.
+    /// - `KvInvalidEntry`: the key is empty or too long, or the value is too long.
+    /// - `KvFull`: the live entries do not fit in one page.
+    /// - `BackendIo` when a flash operation fails.
+    ///
+    /// Parameters:
+    /// - `key`: key bytes, 1 to `KV_MAX_KEY_SIZE` long.
+    /// - `value`: value bytes, at most `KV_MAX_VALUE_SIZE` long.
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.partition("main")?.kv_put(b"peer", &peer_id)?;
+    /// ```
+    pub fn kv_put(&mut self, key: &[u8], value: &[u8]) -> Result<(), StorageError> {
+        kv_store::put(&mut self.backend.region_kv_pages(&self.region)?, key, value)
+    }
+
+    /// Partition-local `Rp2040Backend::kv_delete`.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: the partition has no key-value store.
+    /// - `KvInvalidEntry`: the key is empty or too long.
+    /// - `KvFull`: the live entries do not fit in one page.
+    /// - `BackendIo` when a flash operation fails.
+    ///
+    /// Parameters:
+    /// - `key`: key bytes.
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.partition("main")?.kv_delete(b"peer")?;
+    /// ```
+    pub fn kv_delete(&mut self, key: &[u8]) -> Result<(), StorageError> {
+        kv_store::delete(&mut self.backend.region_kv_pages(&self.region)?, key)
+    }
+
+    /// Partition-local `Rp2040Backend::counter`.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `id` is not below the partition's counter count.
+    ///
+    /// Parameters:
+    /// - `id`: counter index.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let nonce = backend.partition("main")?.counter(0)?.increment()?;
+    /// ```
+    pub fn counter(&self, id: usize) -> Result<Rp2040Counter<'_, RP2040_FLASH_SIZE, O>, StorageError> {
+        Rp2040Counter::new(self.backend, &self.region, id)
+    }
+
+    /// Partition-local `Rp2040Backend::read_slot_meta`.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: the partition has no slot metadata pages.
+    /// - `InvalidIndex`: `storage_index` is outside the partition.
+    /// - `BlockAbsent`: the slot holds no block.
+    /// - `BackendIo` when a flash read fails.
+    ///
+    /// Parameters:
+    /// - `storage_index`: partition-local slot index.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let meta = backend.partition("main")?.read_slot_meta(4)?;
+    /// ```
+    #[cfg(feature = "slot-meta")]
+    pub fn read_slot_meta(&self, storage_index: StorageIndex) -> Result<Option<[u8; SLOT_META_SIZE]>, StorageError> {
+        self.backend.read_slot_meta_in(&self.region, storage_index)
+    }
+
+    /// Partition-local `Rp2040Backend::write_slot_meta`.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: the partition has no slot metadata pages.
+    /// - `InvalidIndex`: `storage_index` is outside the partition.
+    /// - `BlockAbsent`: the slot holds no block.
+    /// - `BackendIo` when a flash operation fails.
+    ///
+    /// Parameters:
+    /// - `storage_index`: partition-local slot index.
+    /// - `meta`: metadata value.
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.partition("main")?.write_slot_meta(4, &meta)?;
+    /// ```
+    #[cfg(feature = "slot-meta")]
+    pub fn write_slot_meta(
+        &mut self,
+        storage_index: StorageIndex,
+        meta: &[u8; SLOT_META_SIZE],
+    ) -> Result<(), StorageError> {
+        self.backend.write_slot_meta_in(&self.region, storage_index, meta)
+    }
+
+    /// Loads `tree` with the slots of this partition; see
+    /// `Rp2040Backend::build_merkle_tree`. Writes to partitions other than the
+    /// backend's own region are not reported to its observer, so reload the
+    /// tree after them.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: the partition has more slots than `LEAVES`.
+    /// - `BackendIo` when a page read fails.
+    ///
+    /// Parameters:
+    /// - `tree`: tree to load.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let mut tree = MerkleTree::<64>::new();
+    /// backend.partition("testnet")?.build_merkle_tree(&mut tree)?;
+    /// ```
+    pub fn build_merkle_tree<const LEAVES: usize>(&self, tree: &mut MerkleTree<LEAVES>) -> Result<(), StorageError> {
+        self.backend.build_merkle_tree_in(&self.region, tree)
+    }
+
+    /// Partition-local `Rp2040Backend::find_by_sequence`.
+    ///
+    /// Errors:
+    /// - `BackendIo` when a flash read fails.
+    ///
+    /// Parameters:
+    /// - `sequence`: `BlockHeader::sequence` to look up.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let found = backend.partition("testnet")?.find_by_sequence(1200)?;
+    /// ```
+    pub fn find_by_sequence(&self, sequence: u32) -> Result<Option<StorageIndex>, StorageError> {
+        sequence_index::find_by_sequence(&self.backend.sequences_in(&self.region)?, sequence)
+    }
+
+    /// Partition-local `Rp2040Backend::max_sequence`.
+    ///
+    /// Errors:
+    /// - `BackendIo` when a flash read fails.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let tip = backend.partition("testnet")?.max_sequence()?;
+    /// ```
+    pub fn max_sequence(&self) -> Result<Option<u32>, StorageError> {
+        sequence_index::max_sequence(&self.backend.sequences_in(&self.region)?)
+    }
+
+    /// Partition-local `Rp2040Backend::range_by_sequence`.
+    ///
+    /// Errors:
+    /// - `BackendIo` when a flash read fails.
+    ///
+    /// Parameters:
+    /// - `range`: sequence numbers to report.
+    /// - `on_block`: callback receiving `(sequence, storage_index)`.
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.partition("testnet")?.range_by_sequence(0..10, &mut |_sequence, _storage_index| {})?;
+    /// ```
+    pub fn range_by_sequence(
+        &self,
+        range: Range<u32>,
+        on_block: &mut dyn FnMut(u32, StorageIndex),
+    ) -> Result<(), StorageError> {
+        sequence_index::range_by_sequence(&self.backend.sequences_in(&self.region)?, range, on_block)
+    }
+
+    /// Partition-local `Rp2040Backend::rebuild_sequence_index`.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: the partition has no sequence index.
+    /// - `BackendIo` when a flash operation fails.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.partition("testnet")?.rebuild_sequence_index()?;
+    /// ```
+    pub fn rebuild_sequence_index(&mut self) -> Result<(), StorageError> {
+        if self.region.sequence_index_pages == 0 {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        let mut page = self.backend.page_buffer.borrow_mut();
+        self.backend.rebuild_sequence_log(&self.region, &mut page)
+    }
+}
+
+impl<const RP2040_FLASH_SIZE: usize, O: StorageObserver> StorageTrait
+    for Rp2040Partition<'_, RP2040_FLASH_SIZE, O>
+{
+    fn init(
+        &mut self,
+        private_key: [u8; PRIVATE_KEY_SIZE],
+        own_node_id: u32,
+        init_params: [u8; INIT_PARAMS_SIZE],
+    ) -> Result<(), StorageError> {
+        self.backend.init_in(&self.region, private_key, own_node_id, init_params, &mut |_| {})
+    }
+
+    fn save_block(
+        &mut self,
+        storage_index: StorageIndex,
+        block: &Block,
+    ) -> Result<(), StorageError> {
+        self.backend.save_block_in(&self.region, storage_index, block)
+    }
+
+    fn read_block(&self, storage_index: StorageIndex) -> Result<Block, StorageError> {
+        self.backend.read_block_in(&self.region, storage_index)
+    }
+
+    fn set_chain_configuration(&mut self, block: &Block) -> Result<(), StorageError> {
+        self.backend.set_chain_configuration_in(&self.region, block)
+    }
+
+    fn load_control_data(&mut self) -> Result<ControlPlaneData, StorageError> {
+        self.backend.load_primary_control_record_and_repair(&self.region)
+    }
+
+    /// Searches this partition, using its sequence index when it has one; see
+    /// `Rp2040Backend::find_by_hash`.
+    fn find_by_hash(&self, hash: &[u8; HASH_SIZE]) -> Result<Option<StorageIndex>, StorageError> {
+        self.backend.find_by_hash_in(&self.region, hash)
+    }
+}
+
+#[cfg(any(test, not(target_arch = "arm")))]
+struct MockFlash<const SIZE: usize> {
+    #[cfg(not(all(feature = "cli", not(test))))]
+    data: [u8; SIZE],
+    /// Heap-backed for the CLI tools so multi-MiB images stay off the stack.
+    #[cfg(all(feature = "cli", not(test)))]
+    data: std::boxed::Box<[u8]>,
+    /// Erase/write operations allowed before a simulated power loss.
+    #[cfg(test)]
+    power_loss_after: Option<usize>,
+}
+
+#[cfg(any(test, not(target_arch = "arm")))]
+impl<const SIZE: usize> MockFlash<SIZE> {
+    fn new() -> Self {
+        Self {
+            #[cfg(not(all(feature = "cli", not(test))))]
+            data: [0xFF; SIZE],
+            #[cfg(all(feature = "cli", not(test)))]
+            data: std::vec![0xFF; SIZE].into_boxed_slice(),
+            #[cfg(test)]
+            power_loss_after: None,
+        }
+    }
+
+    fn consume_power(&mut self) -> Result<(), u16> {
+        #[cfg(test)]
+        if let Some(remaining) = self.power_loss_after {
+            if remaining == 0 {
+                return Err(233);
+            }
+            self.power_loss_after = Some(remaining - 1);
+        }
+        Ok(())
+    }
+
+    fn read(&self, from: u32, out: &mut [u8]) -> Result<(), u16> {
+        let from_index = from as usize;
+        let to_index = from_index + out.len();
+        if to_index > SIZE {
+            return Err(230);
+        }
+        out.copy_from_slice(&self.data[from_index..to_index]);
+        Ok(())
+    }
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), u16> {
+        let from_index = from as usize;
+        let to_index = to as usize;
+        if from_index > to_index || to_index > SIZE {
+            return Err(231);
+        }
+        self.consume_power()?;
+        self.data[from_index..to_index].fill(0xFF);
+        Ok(())
+    }
+
+    fn write(&mut self, from: u32, bytes: &[u8]) -> Result<(), u16> {
+        let from_index = from as usize;
+        let to_index = from_index + bytes.len();
+        if to_index > SIZE {
+            return Err(232);
+        }
+        self.consume_power()?;
+        // NOR programming only clears bits; setting one needs an erase.
+        for (stored, byte) in self.data[from_index..to_index].iter_mut().zip(bytes) {
+            *stored &= *byte;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::counter::COUNTER_STEPS_PER_PAGE;
+    use crate::superblock::{LayoutField, SUPERBLOCK_VERSION};
+    use crate::merkle::MerkleObserver;
+    use crate::{CONTROL_PLANE_COUNT, CONTROL_PLANE_VERSION, block_hash};
+
+    const TEST_FLASH_ONE_BLOCK_PAGE: usize = (CONTROL_PLANE_COUNT + 1) * FLASH_PAGE_SIZE;
+    const TEST_FLASH_TWO_BLOCK_PAGES: usize = (CONTROL_PLANE_COUNT + 2) * FLASH_PAGE_SIZE;
+    const TEST_FLASH_THREE_BLOCK_PAGES: usize = (CONTROL_PLANE_COUNT + 3) * FLASH_PAGE_SIZE;
+    const TEST_FLASH_FOUR_BLOCK_PAGES: usize = (CONTROL_PLANE_COUNT + 4) * FLASH_PAGE_SIZE;
+    const TEST_FLASH_EIGHT_BLOCK_PAGES: usize = (CONTROL_PLANE_COUNT + 8) * FLASH_PAGE_SIZE;
+    const TEST_FLASH_TWENTY_BLOCK_PAGES: usize = (CONTROL_PLANE_COUNT + 20) * FLASH_PAGE_SIZE;
+
+    fn block_from_marker(marker: u8) -> Block {
+        let mut bytes = [0u8; MAX_BLOCK_SIZE];
+        bytes[0] = marker;
+
+        let parse_result = Block::from_bytes(&bytes);
+        assert!(parse_result.is_ok());
+        match parse_result {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        }
+    }
+
+    fn block_with_sequence(sequence: u32) -> Block {
+        let mut bytes = [0u8; MAX_BLOCK_SIZE];
+        bytes[0] = 1;
+        bytes[1..5].copy_from_slice(&sequence.to_le_bytes());
+        Block::from_bytes(&bytes).unwrap_or_else(|_| unreachable!())
+    }
+
+    fn collect_sequences<const SIZE: usize>(
+        backend: &Rp2040Backend<SIZE>,
+        range: Range<u32>,
+        out: &mut [(u32, StorageIndex); 4],
+    ) -> usize {
+        let mut count = 0usize;
+        assert!(backend
+            .range_by_sequence(range, &mut |sequence, storage_index| {
+                out[count] = (sequence, storage_index);
+                count += 1;
+            })
+            .is_ok());
+        count
+    }
+
+    #[test]
+    fn new_calculates_max_slots_from_storage_geometry() {
+        let backend = Rp2040Backend::<TEST_FLASH_THREE_BLOCK_PAGES>::new_for_tests(FLASH_PAGE_SIZE).unwrap_or_else(|_| unreachable!());
+        assert_eq!(
+            backend.region.max_storage_slots(),
+            (2 * BLOCKS_PER_PAGE) as StorageIndex
+        );
+    }
+
+    #[test]
+    fn new_for_tests_returns_error_on_misaligned_start_address() {
+        let backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(1);
+        assert!(matches!(backend, Err(StorageError::InvalidConfiguration)));
+    }
+
+    #[test]
+    fn first_page_mapping_is_deterministic() {
+        let mapping = map_storage_index(0);
+        assert_eq!(mapping.page_index, 0);
+        assert_eq!(mapping.slot_index, 0);
+        assert_eq!(mapping.byte_offset_in_page, 0);
+    }
+
+    #[test]
+    fn last_slot_in_page_maps_to_expected_offset() {
+        let index = (BLOCKS_PER_PAGE - 1) as StorageIndex;
+        let mapping = map_storage_index(index);
+
+        assert_eq!(mapping.page_index, 0);
+        assert_eq!(mapping.slot_index, (BLOCKS_PER_PAGE - 1) as StorageIndex);
+        assert_eq!(
+            mapping.byte_offset_in_page,
+            (BLOCKS_PER_PAGE - 1) * SLOT_SIZE_BYTES
+        );
+    }
+
+    #[test]
+    fn page_boundary_transition_maps_correctly() {
+        let first_next_page = BLOCKS_PER_PAGE as StorageIndex;
+        let mapping = map_storage_index(first_next_page);
+
+        assert_eq!(mapping.page_index, 1);
+        assert_eq!(mapping.slot_index, 0);
+        assert_eq!(mapping.byte_offset_in_page, 0);
+    }
+
+    #[test]
+    fn high_index_mapping_stays_consistent() {
+        let high_index = StorageIndex::MAX;
+        let mapping = map_storage_index(high_index);
+
+        let expected_page = high_index / BLOCKS_PER_PAGE_INDEX;
+        let expected_slot = high_index % BLOCKS_PER_PAGE_INDEX;
+
+        assert_eq!(mapping.page_index, expected_page);
+        assert_eq!(mapping.slot_index, expected_slot);
+        assert_eq!(
+            mapping.byte_offset_in_page,
+            expected_slot as usize * SLOT_SIZE_BYTES
+        );
+    }
+
+    #[test]
+    fn save_block_succeeds_for_valid_index() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_TWO_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        let block = block_from_marker(7);
+
+        let save_result = backend.save_block(1, &block);
+        assert!(save_result.is_ok());
+
+        let read_result = backend.read_block(1);
+        assert!(read_result.is_ok());
+        let read_block = match read_result {
+            Ok(value) => value,
+            Err(_) => return,
+        };
+        assert_eq!(read_block.as_bytes(), block.as_bytes());
+    }
+
+    #[test]
+    fn save_block_rejects_invalid_index() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        let block = block_from_marker(8);
+
+        let save_result = backend.save_block(BLOCKS_PER_PAGE as StorageIndex, &block);
+        assert!(matches!(save_result, Err(StorageError::InvalidIndex)));
+    }
+
+    #[test]
+    fn save_block_succeeds_at_last_valid_index() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        let block = block_from_marker(9);
+        let last_valid_index = backend.region.max_storage_slots() - 1;
+
+        let save_result = backend.save_block(last_valid_index, &block);
+        assert!(save_result.is_ok());
+    }
+
+    #[test]
+    fn storage_start_address_reduces_capacity() {
+        let backend = Rp2040Backend::<TEST_FLASH_THREE_BLOCK_PAGES>::new_for_tests(2 * FLASH_PAGE_SIZE).unwrap_or_else(|_| unreachable!());
+        assert_eq!(backend.region.max_storage_slots(), BLOCKS_PER_PAGE as StorageIndex);
+        assert_eq!(backend.region.start, 2 * FLASH_PAGE_SIZE);
+    }
+
+    #[test]
+    fn slot_flash_address_uses_storage_start_address() {
+        let backend = Rp2040Backend::<TEST_FLASH_FOUR_BLOCK_PAGES>::new_for_tests(FLASH_PAGE_SIZE).unwrap_or_else(|_| unreachable!());
+        let mapping = map_storage_index(BLOCKS_PER_PAGE as StorageIndex);
+        let address = backend.slot_flash_address(&mapping);
+
+        assert_eq!(address, (CONTROL_PLANE_COUNT + 2) * FLASH_PAGE_SIZE);
+    }
+
+    #[test]
+    fn read_block_reports_absent_for_empty_slot() {
+        let backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        let read_result = backend.read_block(0);
+        assert!(matches!(read_result, Err(StorageError::BlockAbsent)));
+    }
+
+    #[test]
+    fn read_block_rejects_invalid_index() {
+        let backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        let read_result = backend.read_block(BLOCKS_PER_PAGE as StorageIndex);
+        assert!(matches!(read_result, Err(StorageError::InvalidIndex)));
+    }
+
+    #[test]
+    fn read_block_detects_hash_mismatch() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        let block = block_from_marker(11);
+        assert!(backend.save_block(0, &block).is_ok());
+
+        // Corrupt first byte of stored hash in slot.
+        let hash_byte_index = SLOT_HASH_OFFSET;
+        backend.with_corrupted_mock_slot_byte(0, hash_byte_index);
+
+        let read_result = backend.read_block(0);
+        assert!(matches!(read_result, Err(StorageError::IntegrityFailure)));
+    }
+
+    #[test]
+    fn read_block_succeeds_at_last_valid_index() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        let block = block_from_marker(12);
+        let last_valid_index = backend.region.max_storage_slots() - 1;
+        assert!(backend.save_block(last_valid_index, &block).is_ok());
+
+        let read_result = backend.read_block(last_valid_index);
+        assert!(read_result.is_ok());
+    }
+
+    #[test]
+    fn read_block_detects_partially_written_slot_data() {
+        let backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        let mut raw_slot = [0xFFu8; SLOT_SIZE_BYTES];
+        raw_slot[0] = 1;
+        raw_slot[1] = 2;
+        backend.write_mock_slot_raw(0, &raw_slot);
+
+        let read_result = backend.read_block(0);
+        assert!(matches!(read_result, Err(StorageError::IntegrityFailure)));
+    }
+
+    #[test]
+    fn read_block_detects_malformed_slot_with_matching_hash() {
+        let backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        let mut raw_slot = [0u8; SLOT_SIZE_BYTES];
+        let computed_hash = calculate_hash(&raw_slot[..MAX_BLOCK_SIZE]);
+        raw_slot[SLOT_HASH_OFFSET..SLOT_HASH_OFFSET + HASH_SIZE].copy_from_slice(&computed_hash);
+        backend.write_mock_slot_raw(0, &raw_slot);
+
+        let read_result = backend.read_block(0);
+        assert!(matches!(read_result, Err(StorageError::IntegrityFailure)));
+    }
+
+    #[test]
+    fn startup_read_cycle_reports_typed_outcomes_for_mixed_slot_states() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_TWO_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        let block = block_from_marker(13);
+        assert!(backend.save_block(0, &block).is_ok());
+
+        let mut partial_slot = [0xFFu8; SLOT_SIZE_BYTES];
+        partial_slot[0] = 1;
+        backend.write_mock_slot_raw(1, &partial_slot);
+
+        let slot0 = backend.read_block(0);
+        let slot1 = backend.read_block(1);
+        let slot2 = backend.read_block(2);
+
+        assert!(matches!(slot0, Ok(_)));
+        assert!(matches!(slot1, Err(StorageError::IntegrityFailure)));
+        assert!(matches!(slot2, Err(StorageError::BlockAbsent)));
+    }
+
+    #[test]
+    fn integration_startup_ingest_query_flow_with_valid_dataset() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_THREE_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        let block_a = block_from_marker(21);
+        let block_b = block_from_marker(22);
+        let block_c = block_from_marker(23);
+
+        // Ingest flow: accepted blocks are saved at deterministic indices.
+        assert!(backend.save_block(0, &block_a).is_ok());
+        assert!(backend.save_block(2, &block_b).is_ok());
+        assert!(backend.save_block(5, &block_c).is_ok());
+
+        // Startup flow: chain logic reads a contiguous range and gets typed outcomes.
+        let startup_scan = [
+            backend.read_block(0),
+            backend.read_block(1),
+            backend.read_block(2),
+            backend.read_block(3),
+            backend.read_block(4),
+            backend.read_block(5),
+        ];
+
+        assert!(matches!(startup_scan[0], Ok(_)));
+        assert!(matches!(startup_scan[1], Err(StorageError::BlockAbsent)));
+        assert!(matches!(startup_scan[2], Ok(_)));
+        assert!(matches!(startup_scan[3], Err(StorageError::BlockAbsent)));
+        assert!(matches!(startup_scan[4], Err(StorageError::BlockAbsent)));
+        assert!(matches!(startup_scan[5], Ok(_)));
+
+        // Query flow: retrieval returns exact stored blocks for populated indices.
+        let read_a = backend.read_block(0);
+        let read_b = backend.read_block(2);
+        let read_c = backend.read_block(5);
+        assert!(read_a.is_ok());
+        assert!(read_b.is_ok());
+        assert!(read_c.is_ok());
+        let read_a = match read_a {
+            Ok(value) => value,
+            Err(_) => return,
+        };
+        let read_b = match read_b {
+            Ok(value) => value,
+            Err(_) => return,
+        };
+        let read_c = match read_c {
+            Ok(value) => value,
+            Err(_) => return,
+        };
+        assert_eq!(read_a.as_bytes(), block_a.as_bytes());
+        assert_eq!(read_b.as_bytes(), block_b.as_bytes());
+        assert_eq!(read_c.as_bytes(), block_c.as_bytes());
+    }
+
+    #[test]
+    fn integration_startup_and_query_flow_reports_integrity_on_corrupted_dataset() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_TWO_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        let block_ok = block_from_marker(31);
+        let block_corrupted = block_from_marker(32);
+
+        // Ingest flow.
+        assert!(backend.save_block(0, &block_ok).is_ok());
+        assert!(backend.save_block(1, &block_corrupted).is_ok());
+
+        // Corrupt one persisted slot and inject one partial slot.
+        backend.with_corrupted_mock_slot_byte(1, SLOT_HASH_OFFSET);
+        let mut partial_slot = [0xFFu8; SLOT_SIZE_BYTES];
+        partial_slot[0] = 1;
+        backend.write_mock_slot_raw(2, &partial_slot);
+
+        // Startup flow over mixed health states.
+        assert!(matches!(backend.read_block(0), Ok(_)));
+        assert!(matches!(
+            backend.read_block(1),
+            Err(StorageError::IntegrityFailure)
+        ));
+        assert!(matches!(
+            backend.read_block(2),
+            Err(StorageError::IntegrityFailure)
+        ));
+        assert!(matches!(
+            backend.read_block(3),
+            Err(StorageError::BlockAbsent)
+        ));
+
+        // Query flow: contract consistency for invalid index remains intact.
+        let invalid_index = backend.region.max_storage_slots();
+        assert!(matches!(
+            backend.read_block(invalid_index),
+            Err(StorageError::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    fn find_by_hash_matches_stored_slot_hashes_and_skips_corrupted_slots() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_TWO_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        let block_a = block_from_marker(33);
+        let block_b = block_from_marker(34);
+        assert!(matches!(backend.find_by_hash(&block_hash(&block_a)), Ok(None)));
+
+        let last_index = 2 * BLOCKS_PER_PAGE_INDEX - 1;
+        assert!(backend.save_block(last_index, &block_a).is_ok());
+        assert!(backend.save_block(1, &block_b).is_ok());
+        assert!(matches!(backend.find_by_hash(&block_hash(&block_a)), Ok(Some(index)) if index == last_index));
+        assert!(matches!(backend.find_by_hash(&block_hash(&block_b)), Ok(Some(1))));
+
+        // A slot whose stored hash matches but whose bytes do not is skipped.
+        assert!(backend.save_block(0, &block_a).is_ok());
+        backend.with_corrupted_mock_slot_byte(0, 5);
+        assert!(matches!(backend.find_by_hash(&block_hash(&block_a)), Ok(Some(index)) if index == last_index));
+        assert!(matches!(backend.find_by_hash(&[0u8; HASH_SIZE]), Ok(None)));
+    }
+
+    #[test]
+    fn find_by_hash_follows_the_sequence_log_when_it_is_built() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_THREE_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_sequence_index(1))
+            .unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        let block_a = block_from_marker(33);
+        let block_b = block_from_marker(34);
+        assert!(backend.save_block(2, &block_a).is_ok());
+        assert!(backend.save_block(1, &block_a).is_ok());
+        assert!(matches!(backend.find_by_hash(&block_hash(&block_a)), Ok(Some(1))));
+
+        // The record of an overwritten block stays in the log and is rejected by its slot.
+        assert!(backend.save_block(1, &block_b).is_ok());
+        assert!(matches!(backend.find_by_hash(&block_hash(&block_a)), Ok(Some(2))));
+        assert!(matches!(backend.find_by_hash(&block_hash(&block_b)), Ok(Some(1))));
+
+        // A slot written behind the log's back is only found once the log is rebuilt.
+        let mut page = [0xFFu8; FLASH_PAGE_SIZE];
+        assert!(Rp2040Backend::<TEST_FLASH_THREE_BLOCK_PAGES>::encode_block_to_slot(
+            &mut page,
+            &map_storage_index(0),
+            &block_b
+        )
+        .is_ok());
+        let mut raw_slot = [0u8; SLOT_SIZE_BYTES];
+        raw_slot.copy_from_slice(&page[..SLOT_SIZE_BYTES]);
+        backend.write_mock_slot_raw(0, &raw_slot);
+        assert!(matches!(backend.find_by_hash(&block_hash(&block_b)), Ok(Some(1))));
+        assert!(backend.rebuild_sequence_index().is_ok());
+        assert!(matches!(backend.find_by_hash(&block_hash(&block_b)), Ok(Some(0))));
+    }
+
+    #[test]
+    fn merkle_tree_uses_stored_slot_hashes_and_empties_damaged_slots() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_TWO_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        let slot_count = 2 * BLOCKS_PER_PAGE_INDEX;
+        let mut tracked = MerkleTree::<16>::new();
+        assert!(backend.build_merkle_tree(&mut tracked).is_ok());
+        assert_eq!(tracked.leaf_count(), slot_count);
+
+        let block_a = block_from_marker(33);
+        let block_b = block_from_marker(34);
+        assert!(backend.save_block(0, &block_a).is_ok());
+        assert!(tracked.record_block(0, &block_a).is_ok());
+        assert!(backend.save_block(slot_count - 1, &block_b).is_ok());
+        assert!(tracked.record_block(slot_count - 1, &block_b).is_ok());
+
+        let mut rebuilt = MerkleTree::<16>::new();
+        assert!(backend.build_merkle_tree(&mut rebuilt).is_ok());
+        assert_eq!(rebuilt.root(), tracked.root());
+        assert!(matches!(rebuilt.leaf(0), Ok(leaf) if leaf == block_hash(&block_a)));
+
+        backend.with_corrupted_mock_slot_byte(0, 5);
+        assert!(backend.build_merkle_tree(&mut rebuilt).is_ok());
+        assert!(matches!(rebuilt.leaf(0), Ok(leaf) if leaf == EMPTY_LEAF));
+        assert!(tracked.set_leaf(0, EMPTY_LEAF).is_ok());
+        assert_eq!(rebuilt.root(), tracked.root());
+
+        let mut too_small = MerkleTree::<1>::new();
+        assert!(matches!(
+            backend.build_merkle_tree(&mut too_small),
+            Err(StorageError::InvalidConfiguration)
+        ));
+    }
+
+    #[test]
+    fn merkle_observer_follows_writes_damage_relocation_and_decommission() {
+        let merkle = MerkleObserver::<16>::new();
+        let mut backend = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(0)
+            .map(|backend| backend.with_observer(&merkle))
+            .unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 5, [2u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert_eq!(merkle.leaf_count(), 8 * BLOCKS_PER_PAGE_INDEX);
+
+        let last_index = 6 * BLOCKS_PER_PAGE_INDEX - 1;
+        assert!(backend.save_block(0, &block_from_marker(1)).is_ok());
+        assert!(backend.save_block(1, &block_from_marker(3)).is_ok());
+        assert!(backend.save_block(last_index, &block_from_marker(2)).is_ok());
+        let mut rebuilt = MerkleTree::<16>::new();
+        assert!(backend.build_merkle_tree(&mut rebuilt).is_ok());
+        assert_eq!(merkle.root(), rebuilt.root());
+
+        backend.with_corrupted_mock_slot_byte(1, 5);
+        assert!(matches!(backend.read_block(1), Err(StorageError::IntegrityFailure)));
+        assert!(backend.build_merkle_tree(&mut rebuilt).is_ok());
+        assert_eq!(merkle.root(), rebuilt.root());
+
+        assert!(backend.relocate(2 * FLASH_PAGE_SIZE, TEST_FLASH_EIGHT_BLOCK_PAGES).is_ok());
+        assert_eq!(merkle.leaf_count(), 6 * BLOCKS_PER_PAGE_INDEX);
+        assert!(backend.build_merkle_tree(&mut rebuilt).is_ok());
+        assert_eq!(merkle.root(), rebuilt.root());
+
+        assert!(backend.decommission(false).is_ok());
+        assert!(backend.build_merkle_tree(&mut rebuilt).is_ok());
+        assert_eq!(merkle.root(), rebuilt.root());
+    }
+
+    #[test]
+    fn write_once_slots_reject_occupied_and_damaged_slots_unless_forced() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_TWO_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_sequence_index(1))
+            .map(|backend| backend.with_write_once(WriteOnceSlots::ALL))
+            .unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert!(backend.save_block(0, &block_with_sequence(1)).is_ok());
+        let before = backend.flash_mock.borrow().data;
+
+        assert!(matches!(
+            backend.save_block(0, &block_with_sequence(2)),
+            Err(StorageError::SlotOccupied)
+        ));
+        assert!(backend.flash_mock.borrow().data == before);
+
+        backend.with_corrupted_mock_slot_byte(0, 5);
+        assert!(matches!(
+            backend.save_block(0, &block_with_sequence(2)),
+            Err(StorageError::SlotOccupied)
+        ));
+        assert!(backend.force_overwrite(0, &block_with_sequence(2)).is_ok());
+        assert!(matches!(backend.read_block(0), Ok(block) if block.header().sequence == 2));
+        assert!(matches!(backend.find_by_sequence(2), Ok(Some(0))));
+        assert!(backend.save_block(1, &block_with_sequence(3)).is_ok());
+    }
+
+    #[test]
+    fn partition_regions_carry_their_own_write_once_policy() {
+        let regions = [
+            Rp2040Region::new("main", 0, 6 * FLASH_PAGE_SIZE).with_write_once(WriteOnceSlots::range(0, 1)),
+            Rp2040Region::new("testnet", 6 * FLASH_PAGE_SIZE, TEST_FLASH_EIGHT_BLOCK_PAGES),
+        ];
+        let backend = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_partitions(&regions))
+            .unwrap_or_else(|_| unreachable!());
+
+        let mut main = backend.partition("main").unwrap_or_else(|_| unreachable!());
+        assert!(main.save_block(0, &block_from_marker(1)).is_ok());
+        assert!(matches!(main.save_block(0, &block_from_marker(2)), Err(StorageError::SlotOccupied)));
+        assert!(main.save_block(1, &block_from_marker(1)).is_ok());
+        assert!(main.save_block(1, &block_from_marker(2)).is_ok());
+        assert!(main.force_overwrite(0, &block_from_marker(2)).is_ok());
+
+        let mut testnet = backend.partition("testnet").unwrap_or_else(|_| unreachable!());
+        assert!(testnet.save_block(0, &block_from_marker(1)).is_ok());
+        assert!(testnet.save_block(0, &block_from_marker(2)).is_ok());
+    }
+
+    #[test]
+    fn replace_and_insert_check_the_stored_hash_before_rewriting_the_page() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_TWO_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_sequence_index(1))
+            .unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        let first = block_with_sequence(1);
+        let second = block_with_sequence(2);
+
+        assert!(matches!(
+            backend.replace_block(0, &block_hash(&first), &second),
+            Err(StorageError::Conflict { current_hash: None })
+        ));
+        assert!(backend.insert_block(0, &first).is_ok());
+        let before = backend.flash_mock.borrow().data;
+        assert!(matches!(
+            backend.insert_block(0, &second),
+            Err(StorageError::Conflict { current_hash: Some(hash) }) if hash == block_hash(&first)
+        ));
+        assert!(matches!(
+            backend.replace_block(0, &block_hash(&second), &second),
+            Err(StorageError::Conflict { current_hash: Some(hash) }) if hash == block_hash(&first)
+        ));
+        assert!(backend.flash_mock.borrow().data == before);
+
+        assert!(backend.replace_block(0, &block_hash(&first), &second).is_ok());
+        assert!(matches!(backend.read_block(0), Ok(block) if block.header().sequence == 2));
+        assert!(matches!(backend.find_by_sequence(2), Ok(Some(0))));
+        assert!(matches!(
+            backend.insert_block(2 * BLOCKS_PER_PAGE_INDEX, &first),
+            Err(StorageError::InvalidIndex)
+        ));
+
+        let mut backend = backend.with_write_once(WriteOnceSlots::ALL);
+        assert!(matches!(
+            backend.replace_block(0, &block_hash(&second), &first),
+            Err(StorageError::SlotOccupied)
+        ));
+    }
+
+    #[test]
+    fn partition_replace_block_uses_partition_local_slots() {
+        let regions = [
+            Rp2040Region::new("main", 0, 6 * FLASH_PAGE_SIZE),
+            Rp2040Region::new("testnet", 6 * FLASH_PAGE_SIZE, TEST_FLASH_EIGHT_BLOCK_PAGES),
+        ];
+        let backend = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_partitions(&regions))
+            .unwrap_or_else(|_| unreachable!());
+
+        let mut main = backend.partition("main").unwrap_or_else(|_| unreachable!());
+        assert!(main.insert_block(0, &block_from_marker(1)).is_ok());
+        let mut testnet = backend.partition("testnet").unwrap_or_else(|_| unreachable!());
+        assert!(testnet.insert_block(0, &block_from_marker(2)).is_ok());
+        assert!(matches!(
+            testnet.replace_block(0, &block_hash(&block_from_marker(1)), &block_from_marker(3)),
+            Err(StorageError::Conflict { current_hash: Some(hash) }) if hash == block_hash(&block_from_marker(2))
+        ));
+        assert!(main
+            .replace_block(0, &block_hash(&block_from_marker(1)), &block_from_marker(3))
+            .is_ok());
+    }
+
+    #[cfg(feature = "slot-meta")]
+    #[test]
+    fn slot_meta_updates_program_records_and_erase_only_when_the_area_is_full() {
+        let plain = Rp2040Backend::<TEST_FLASH_THREE_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        assert!(matches!(plain.read_slot_meta(0), Err(StorageError::InvalidConfiguration)));
+
+        let observer = CountingObserver::default();
+        let mut backend = Rp2040Backend::<TEST_FLASH_THREE_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_slot_meta(1))
+            .unwrap_or_else(|_| unreachable!())
+            .with_observer(&observer);
+        assert_eq!(backend.region.block_page_count(), 2);
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert_eq!(backend.superblock().slot_meta_pages, 1);
+        assert!(matches!(backend.write_slot_meta(0, &[1; SLOT_META_SIZE]), Err(StorageError::BlockAbsent)));
+        assert!(backend.save_block(0, &block_with_sequence(1)).is_ok());
+        assert!(backend.save_block(1, &block_with_sequence(2)).is_ok());
+        assert!(matches!(backend.read_slot_meta(0), Ok(None)));
+        assert!(backend.write_slot_meta(1, &[0x11; SLOT_META_SIZE]).is_ok());
+        let erases = observer.pages_erased.get();
+
+        let mut generation = 0usize;
+        while generation < slot_meta::SLOT_META_GENERATIONS {
+            let meta = [generation as u8; SLOT_META_SIZE];
+            assert!(backend.write_slot_meta(0, &meta).is_ok());
+            assert!(matches!(backend.read_slot_meta(0), Ok(Some(value)) if value == meta));
+            generation += 1;
+        }
+        assert_eq!(observer.pages_erased.get(), erases);
+
+        // The full area rewrites the metadata page, keeping the other slots.
+        assert!(backend.write_slot_meta(0, &[0xAA; SLOT_META_SIZE]).is_ok());
+        assert_eq!(observer.pages_erased.get(), erases + 1);
+        assert!(matches!(backend.read_slot_meta(0), Ok(Some(value)) if value == [0xAA; SLOT_META_SIZE]));
+        assert!(matches!(backend.read_slot_meta(1), Ok(Some(value)) if value == [0x11; SLOT_META_SIZE]));
+        assert!(matches!(backend.read_block(0), Ok(block) if block.header().sequence == 1));
+
+        // A block write programs a cleared record and only erases its block page.
+        assert!(backend.force_overwrite(0, &block_with_sequence(3)).is_ok());
+        assert_eq!(observer.pages_erased.get(), erases + 2);
+        assert!(matches!(backend.read_slot_meta(0), Ok(None)));
+        assert!(matches!(backend.read_slot_meta(1), Ok(Some(value)) if value == [0x11; SLOT_META_SIZE]));
+
+        // With every position used the block write zeroes the area instead.
+        assert!(backend.write_slot_meta(0, &[0xBB; SLOT_META_SIZE]).is_ok());
+        assert!(backend.write_slot_meta(0, &[0xCC; SLOT_META_SIZE]).is_ok());
+        assert!(backend.force_overwrite(0, &block_with_sequence(4)).is_ok());
+        assert_eq!(observer.pages_erased.get(), erases + 3);
+        assert!(matches!(backend.read_slot_meta(0), Ok(None)));
+        assert!(backend.write_slot_meta(0, &[0xDD; SLOT_META_SIZE]).is_ok());
+        assert_eq!(observer.pages_erased.get(), erases + 4);
+        assert!(matches!(backend.read_slot_meta(0), Ok(Some(value)) if value == [0xDD; SLOT_META_SIZE]));
+        assert!(matches!(backend.read_slot_meta(1), Ok(Some(value)) if value == [0x11; SLOT_META_SIZE]));
+        assert!(matches!(
+            backend.read_slot_meta(backend.region.max_storage_slots()),
+            Err(StorageError::InvalidIndex)
+        ));
+    }
+
+    #[test]
+    fn slot_meta_pages_are_part_of_the_layout_and_must_cover_every_slot() {
+        let backend = Rp2040Backend::<TEST_FLASH_TWENTY_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_slot_meta(1))
+            .unwrap_or_else(|_| unreachable!());
+        assert_eq!(backend.region.slot_meta_page_address(0), TEST_FLASH_TWENTY_BLOCK_PAGES - FLASH_PAGE_SIZE);
+        assert_eq!(
+            backend.region.slot_meta_address(1),
+            TEST_FLASH_TWENTY_BLOCK_PAGES - FLASH_PAGE_SIZE + SLOT_META_AREA_SIZE
+        );
+
+        let region = Rp2040Region::new("main", 0, (CONTROL_PLANE_COUNT + SLOT_META_AREAS_PER_PAGE) * FLASH_PAGE_SIZE);
+        assert!(!region.with_slot_meta(1).has_valid_layout());
+        assert!(region.with_slot_meta(2).has_valid_layout());
+
+        let mut written = Rp2040Backend::<TEST_FLASH_TWENTY_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_slot_meta(1))
+            .unwrap_or_else(|_| unreachable!());
+        assert!(written
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        let mut reader = Rp2040Backend::<TEST_FLASH_TWENTY_BLOCK_PAGES>::from_flash_image(0, &written.flash_image())
+            .unwrap_or_else(|_| unreachable!());
+        assert!(matches!(
+            reader.load_control_data(),
+            Err(StorageError::LayoutMismatch {
+                field: LayoutField::SlotMetaPages,
+                stored: 1,
+                expected: 0,
+            })
+        ));
+    }
+
+    #[test]
+    fn control_plane_load_reports_uninitialized_before_init() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        assert!(matches!(
+            backend.load_control_data(),
+            Err(StorageError::ControlPlaneUninitialized)
+        ));
+    }
+
+    #[test]
+    fn control_plane_init_and_load_round_trip() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        let private_key = [7u8; PRIVATE_KEY_SIZE];
+        let init_params = [9u8; INIT_PARAMS_SIZE];
+        assert!(backend.init(private_key, 42, init_params).is_ok());
+
+        let loaded = backend.load_control_data();
+        assert!(loaded.is_ok());
+        let loaded = match loaded {
+            Ok(value) => value,
+            Err(_) => return,
+        };
+
+        assert_eq!(loaded.private_key, private_key);
+        assert_eq!(loaded.own_node_id, 42);
+        assert_eq!(loaded.init_params, init_params);
+        assert!(loaded.chain_configuration.is_none());
+    }
+
+    #[test]
+    fn control_plane_set_chain_configuration_is_set_once() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [2u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        let cfg_block = block_from_marker(55);
+
+        assert!(backend.set_chain_configuration(&cfg_block).is_ok());
+        assert!(matches!(
+            backend.set_chain_configuration(&cfg_block),
+            Err(StorageError::ChainConfigurationAlreadySet)
+        ));
+
+        let loaded = backend.load_control_data();
+        assert!(loaded.is_ok());
+        let loaded = match loaded {
+            Ok(value) => value,
+            Err(_) => return,
+        };
+        assert!(loaded.chain_configuration.is_some());
+    }
+
+    #[test]
+    fn control_plane_load_repairs_corrupted_replica() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 2, [3u8; INIT_PARAMS_SIZE])
+            .is_ok());
+
+        // Corrupt first replica header byte.
+        let replica0_addr = backend.region.control_plane_page_address(0);
+        backend.flash_mock.borrow_mut().data[replica0_addr] ^= 0xFF;
+
+        assert!(backend.load_control_data().is_ok());
+
+        // Re-read replica page and check CRC-valid deserialization.
+        let mut repaired_page = [0u8; FLASH_PAGE_SIZE];
+        assert!(backend.read_page(replica0_addr, &mut repaired_page).is_ok());
+        let mut repaired_entry = [0u8; CONTROL_PLANE_ENTRY_SIZE];
+        repaired_entry.copy_from_slice(&repaired_page[..CONTROL_PLANE_ENTRY_SIZE]);
+        let repaired = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::deserialize_control_record(&repaired_entry);
+        assert!(repaired.is_ok());
+    }
+
+    #[cfg(feature = "key-encryption")]
+    #[test]
+    fn wrapped_key_bound_to_unique_id_round_trips_without_plaintext_in_flash() {
+        let backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        let unique_id = backend.flash_unique_id().unwrap_or_else(|_| unreachable!());
+        let protection = KeyProtection::from_secret(b"fleet", Some(&unique_id)).unwrap_or_else(|_| unreachable!());
+        let mut backend = backend.with_key_protection(protection);
+        let private_key = [0x5Au8; PRIVATE_KEY_SIZE];
+        assert!(backend.init(private_key, 3, [0u8; INIT_PARAMS_SIZE]).is_ok());
+
+        assert!(!backend
+            .flash_image()
+            .windows(PRIVATE_KEY_SIZE)
+            .any(|window| window == &private_key[..]));
+        let loaded = match backend.load_control_data() {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        };
+        assert_eq!(loaded.private_key, private_key);
+
+        let mut backend = backend.with_key_protection(KeyProtection::Plaintext);
+        assert!(matches!(
+            backend.load_control_data(),
+            Err(StorageError::KeyUnwrapFailed)
+        ));
     }
 
-    fn set_chain_configuration(&mut self, block: &Block) -> Result<(), StorageError> {
-        let mut record = self.load_primary_control_record_and_repair()?;
-        if record.chain_configuration.is_some() {
-            return Err(StorageError::ChainConfigurationAlreadySet);
+    #[cfg(feature = "key-encryption")]
+    #[test]
+    fn set_key_protection_wraps_plaintext_partitions_that_loading_rejects() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_partitions(&TEST_PARTITIONS))
+            .unwrap_or_else(|_| unreachable!());
+        assert!(backend.init([0x5Au8; PRIVATE_KEY_SIZE], 3, [0u8; INIT_PARAMS_SIZE]).is_ok());
+        let partition_init = backend
+            .partition("testnet")
+            .and_then(|mut testnet| testnet.init([0x6Bu8; PRIVATE_KEY_SIZE], 4, [0u8; INIT_PARAMS_SIZE]));
+        assert!(partition_init.is_ok());
+
+        let protection = KeyProtection::from_secret(b"fleet", None).unwrap_or_else(|_| unreachable!());
+        let mut backend = backend.with_key_protection(protection.clone());
+        assert!(matches!(
+            backend.load_control_data(),
+            Err(StorageError::KeyUnwrapFailed)
+        ));
+
+        let mut backend = backend.with_key_protection(KeyProtection::Plaintext);
+        assert!(backend.set_key_protection(protection.clone()).is_ok());
+        assert!(matches!(backend.load_control_data(), Ok(record) if record.own_node_id == 3));
+        let testnet = backend.partition("testnet").and_then(|mut testnet| testnet.load_control_data());
+        assert!(matches!(testnet, Ok(record) if record.private_key == [0x6Bu8; PRIVATE_KEY_SIZE]));
+        assert!(!backend
+            .flash_image()
+            .windows(PRIVATE_KEY_SIZE)
+            .any(|window| window == &[0x6Bu8; PRIVATE_KEY_SIZE][..]));
+        // Already converted partitions are skipped on a repeated call.
+        assert!(backend.set_key_protection(protection).is_ok());
+    }
+
+    #[derive(Default)]
+    struct CountingObserver {
+        inits_started: core::cell::Cell<u32>,
+        inits_finished: core::cell::Cell<u32>,
+        pages_erased: core::cell::Cell<u32>,
+        slots_written: core::cell::Cell<u32>,
+        integrity_failures: core::cell::Cell<u32>,
+        last_integrity_failure: core::cell::Cell<Option<StorageIndex>>,
+    }
+
+    impl StorageObserver for CountingObserver {
+        fn on_init_started(&self) {
+            self.inits_started.set(self.inits_started.get() + 1);
+        }
+
+        fn on_init_finished(&self) {
+            self.inits_finished.set(self.inits_finished.get() + 1);
+        }
+
+        fn on_slot_written(&self, _storage_index: StorageIndex) {
+            self.slots_written.set(self.slots_written.get() + 1);
+        }
+
+        fn on_integrity_failure(&self, storage_index: StorageIndex) {
+            self.integrity_failures.set(self.integrity_failures.get() + 1);
+            self.last_integrity_failure.set(Some(storage_index));
+        }
+
+        fn on_page_erased(&self, _page_address: usize) {
+            self.pages_erased.set(self.pages_erased.get() + 1);
         }
+    }
 
-        record.chain_configuration = Some(
-            Block::from_bytes(block.as_bytes()).map_err(|_| StorageError::BackendIo { code: 213 })?,
-        );
+    #[test]
+    fn observer_receives_erase_write_and_integrity_events() {
+        let observer = CountingObserver::default();
+        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0)
+            .unwrap_or_else(|_| unreachable!())
+            .with_observer(&observer);
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        // Init erases each replica page for its marker, every block page, then each replica page again.
+        let init_erases = (CONTROL_PLANE_COUNT + 1 + CONTROL_PLANE_COUNT) as u32;
+        assert_eq!(observer.pages_erased.get(), init_erases);
+
+        let block = block_from_marker(14);
+        assert!(backend.save_block(0, &block).is_ok());
+        assert_eq!(observer.slots_written.get(), 1);
+        assert_eq!(observer.pages_erased.get(), init_erases + 1);
+
+        backend.with_corrupted_mock_slot_byte(0, SLOT_HASH_OFFSET);
+        assert!(matches!(
+            backend.read_block(0),
+            Err(StorageError::IntegrityFailure)
+        ));
+        assert_eq!(observer.integrity_failures.get(), 1);
+        assert_eq!(observer.last_integrity_failure.get(), Some(0));
+    }
+
+    #[test]
+    fn fsck_reports_clean_initialized_backend() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert!(backend.save_block(0, &block_from_marker(31)).is_ok());
+
+        let report = backend.fsck(FsckOptions::default(), &mut |_| {}).unwrap_or_else(|_| unreachable!());
+        assert!(report.is_clean());
+        assert_eq!(report.replicas_valid, CONTROL_PLANE_COUNT as u32);
+        assert_eq!(report.slots_checked, BLOCKS_PER_PAGE as u32);
+        assert_eq!(report.slots_occupied, 1);
+    }
+
+    #[test]
+    fn fsck_detects_and_repairs_half_written_slot_and_corrupted_replica() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert!(backend.save_block(0, &block_from_marker(32)).is_ok());
+        let mut partial_slot = [0xFFu8; SLOT_SIZE_BYTES];
+        partial_slot[0] = 1;
+        backend.write_mock_slot_raw(1, &partial_slot);
+        let replica2_addr = backend.region.control_plane_page_address(2);
+        backend.flash_mock.borrow_mut().data[replica2_addr + 1] ^= 0xFF;
+
+        let mut issues = [None; 4];
+        let mut count = 0usize;
+        let report = backend
+            .fsck(FsckOptions { repair: false }, &mut |finding| {
+                issues[count] = Some(finding.issue);
+                count += 1;
+            })
+            .unwrap_or_else(|_| unreachable!());
+        assert_eq!(report.issues_found, 2);
+        assert_eq!(report.issues_repaired, 0);
+        assert_eq!(issues[0], Some(FsckIssue::ReplicaCorrupted { replica_index: 2 }));
+        assert_eq!(issues[1], Some(FsckIssue::SlotHalfWritten { storage_index: 1 }));
+
+        let report = backend.fsck(FsckOptions { repair: true }, &mut |_| {});
+        assert!(matches!(report, Ok(FsckReport { issues_found: 2, issues_repaired: 2, .. })));
+
+        let report = backend.fsck(FsckOptions::default(), &mut |_| {});
+        assert!(matches!(report, Ok(ref value) if value.is_clean()));
+        assert!(backend.read_block(0).is_ok());
+        assert!(matches!(backend.read_block(1), Err(StorageError::BlockAbsent)));
+    }
+
+    #[test]
+    fn page_padding_scrub_flags_and_repairs_stray_tail_bytes() {
+        // The compiled slots fill the page exactly, so check a geometry whose
+        // slots end 64 bytes before the page does.
+        let slots_end = FLASH_PAGE_SIZE - 64;
+        let mut page = [0xFFu8; FLASH_PAGE_SIZE];
+        page[..slots_end].fill(0x5A);
+        assert!(!scrub_page_padding(&mut page, slots_end, true));
+
+        page[FLASH_PAGE_SIZE - 1] = 0;
+        assert!(scrub_page_padding(&mut page, slots_end, false));
+        assert_eq!(page[FLASH_PAGE_SIZE - 1], 0);
+        assert!(scrub_page_padding(&mut page, slots_end, true));
+        assert!(page[slots_end..].iter().all(|byte| *byte == 0xFF));
+        assert!(page[..slots_end].iter().all(|byte| *byte == 0x5A));
+        assert!(!scrub_page_padding(&mut page, slots_end, false));
+        assert!(!scrub_page_padding(&mut page, FLASH_PAGE_SIZE, false));
+    }
 
+    #[test]
+    fn frozen_v1_flash_image_loads_and_upgrades_in_place() {
+        let mut image = [0xFFu8; TEST_FLASH_ONE_BLOCK_PAGE];
+        let legacy = control_plane::frozen::v1_entry();
         let mut replica_index = 0usize;
         while replica_index < CONTROL_PLANE_COUNT {
-            self.write_control_record_to_replica(replica_index, &record)?;
+            let page_start = replica_index * FLASH_PAGE_SIZE;
+            image[page_start..page_start + FLASH_PAGE_SIZE].fill(0);
+            image[page_start..page_start + CONTROL_PLANE_ENTRY_SIZE].copy_from_slice(&legacy);
             replica_index += 1;
         }
+        let block = block_from_marker(2);
+        let block_page = CONTROL_PLANE_COUNT * FLASH_PAGE_SIZE;
+        assert!(Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::encode_block_to_slot(
+            &mut image[block_page..block_page + FLASH_PAGE_SIZE],
+            &map_storage_index(0),
+            &block,
+        )
+        .is_ok());
+
+        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::from_flash_image(0, &image)
+            .unwrap_or_else(|_| unreachable!());
+        let loaded = match backend.load_control_data() {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        };
+        assert_eq!(loaded.private_key, [control_plane::frozen::V1_PRIVATE_KEY_BYTE; PRIVATE_KEY_SIZE]);
+        assert_eq!(loaded.own_node_id, control_plane::frozen::V1_OWN_NODE_ID);
+        assert_eq!(loaded.init_params, [control_plane::frozen::V1_INIT_PARAMS_BYTE; INIT_PARAMS_SIZE]);
+        assert!(backend.read_block(0).is_ok());
 
-        Ok(())
+        let mut replica_index = 0usize;
+        while replica_index < CONTROL_PLANE_COUNT {
+            let decoded = backend.read_control_record_from_replica(&backend.region, replica_index);
+            assert!(matches!(decoded, Ok(ref value) if !value.needs_upgrade));
+            assert_eq!(backend.flash_image()[replica_index * FLASH_PAGE_SIZE], CONTROL_PLANE_VERSION);
+            replica_index += 1;
+        }
     }
 
-    fn load_control_data(&mut self) -> Result<ControlPlaneData, StorageError> {
-        let record = self.load_primary_control_record_and_repair()?;
-        Ok(record)
+    #[test]
+    fn load_reports_layout_mismatch_for_different_flash_size() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        let image = backend.flash_image();
+
+        let mut larger = Rp2040Backend::<TEST_FLASH_TWO_BLOCK_PAGES>::from_flash_image(0, &image)
+            .unwrap_or_else(|_| unreachable!());
+        assert!(matches!(
+            larger.load_control_data(),
+            Err(StorageError::LayoutMismatch {
+                field: LayoutField::FlashSize,
+                stored,
+                expected,
+            }) if stored as usize == TEST_FLASH_ONE_BLOCK_PAGE && expected as usize == TEST_FLASH_TWO_BLOCK_PAGES
+        ));
+        assert!(matches!(
+            larger.fsck(FsckOptions { repair: true }, &mut |_| {}),
+            Err(StorageError::LayoutMismatch { .. })
+        ));
     }
-}
 
-#[cfg(any(test, not(target_arch = "arm")))]
-struct MockFlash<const SIZE: usize> {
-    data: [u8; SIZE],
-}
+    #[test]
+    fn fsck_reports_and_restores_missing_superblock() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        let page_address = FLASH_PAGE_SIZE;
+        backend.flash_mock.borrow_mut().data[page_address + SUPERBLOCK_OFFSET..page_address + FLASH_PAGE_SIZE].fill(0);
 
-#[cfg(any(test, not(target_arch = "arm")))]
-impl<const SIZE: usize> MockFlash<SIZE> {
-    fn new() -> Self {
-        Self { data: [0xFF; SIZE] }
+        let mut issue = None;
+        let report = backend.fsck(FsckOptions { repair: true }, &mut |finding| issue = Some(finding.issue));
+        assert!(matches!(report, Ok(FsckReport { issues_found: 1, issues_repaired: 1, .. })));
+        assert_eq!(issue, Some(FsckIssue::SuperblockMissing { replica_index: 1 }));
+
+        let mut stored = [0u8; SUPERBLOCK_SIZE];
+        stored.copy_from_slice(&backend.flash_image()[page_address + SUPERBLOCK_OFFSET..page_address + FLASH_PAGE_SIZE]);
+        assert!(matches!(Superblock::decode(&stored), Ok(Some(value)) if value == backend.superblock()));
     }
 
-    fn read(&self, from: u32, out: &mut [u8]) -> Result<(), u16> {
-        let from_index = from as usize;
-        let to_index = from_index + out.len();
-        if to_index > SIZE {
-            return Err(230);
-        }
-        out.copy_from_slice(&self.data[from_index..to_index]);
-        Ok(())
+    #[test]
+    fn start_and_end_layout_keeps_replicas_at_both_ends_and_repairs_them() {
+        let layout = ControlPlaneLayout::new(3, ControlPlanePlacement::StartAndEnd);
+        let mut backend = Rp2040Backend::<TEST_FLASH_TWO_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_control_plane_layout(layout))
+            .unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert!(backend.save_block(BLOCKS_PER_PAGE_INDEX, &block_from_marker(7)).is_ok());
+
+        let last_page = 4 * FLASH_PAGE_SIZE;
+        assert_eq!(backend.region.control_plane_page_address(2), last_page);
+        assert_eq!(backend.flash_image()[last_page], CONTROL_PLANE_VERSION);
+        assert!(backend.flash_image()[2 * FLASH_PAGE_SIZE..3 * FLASH_PAGE_SIZE]
+            .iter()
+            .all(|byte| *byte == 0xFF));
+        assert!(!backend.flash_image()[3 * FLASH_PAGE_SIZE..last_page]
+            .iter()
+            .all(|byte| *byte == 0xFF));
+
+        backend.flash_mock.borrow_mut().data[last_page..].fill(0xFF);
+        assert!(backend.load_control_data().is_ok());
+        assert!(backend.read_control_record_from_replica(&backend.region, 2).is_ok());
+        assert!(backend.read_block(BLOCKS_PER_PAGE_INDEX).is_ok());
     }
 
-    fn erase(&mut self, from: u32, to: u32) -> Result<(), u16> {
-        let from_index = from as usize;
-        let to_index = to as usize;
-        if from_index > to_index || to_index > SIZE {
-            return Err(231);
-        }
-        self.data[from_index..to_index].fill(0xFF);
-        Ok(())
+    #[test]
+    fn region_written_with_another_layout_reports_layout_mismatch() {
+        let layout = ControlPlaneLayout::new(5, ControlPlanePlacement::Spread);
+        let mut backend = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_control_plane_layout(layout))
+            .unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert_eq!(backend.region.max_storage_slots(), 6 * BLOCKS_PER_PAGE_INDEX);
+        let report = backend.fsck(FsckOptions { repair: false }, &mut |_| {});
+        assert!(matches!(report, Ok(FsckReport { replicas_checked: 5, issues_found: 0, .. })));
+
+        let image = backend.flash_image();
+        let mut default_layout = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::from_flash_image(0, &image)
+            .unwrap_or_else(|_| unreachable!());
+        assert!(matches!(
+            default_layout.load_control_data(),
+            Err(StorageError::LayoutMismatch {
+                field: LayoutField::ControlPlaneReplicas,
+                stored: 5,
+                expected,
+            }) if expected as usize == CONTROL_PLANE_COUNT
+        ));
     }
 
-    fn write(&mut self, from: u32, bytes: &[u8]) -> Result<(), u16> {
-        let from_index = from as usize;
-        let to_index = from_index + bytes.len();
-        if to_index > SIZE {
-            return Err(232);
+    #[test]
+    fn with_control_plane_layout_rejects_invalid_layouts() {
+        const PARTITIONS: [Rp2040Region; 1] = [Rp2040Region::new("main", 0, TEST_FLASH_ONE_BLOCK_PAGE)];
+        let new_backend =
+            || Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+
+        let invalid = [
+            ControlPlaneLayout::new(0, ControlPlanePlacement::Consecutive),
+            ControlPlaneLayout::new(MAX_CONTROL_PLANE_COUNT + 1, ControlPlanePlacement::Spread),
+            ControlPlaneLayout::new(CONTROL_PLANE_COUNT + 1, ControlPlanePlacement::StartAndEnd),
+        ];
+        let mut index = 0usize;
+        while index < invalid.len() {
+            assert!(matches!(
+                new_backend().with_control_plane_layout(invalid[index]),
+                Err(StorageError::InvalidConfiguration)
+            ));
+            index += 1;
         }
-        self.data[from_index..to_index].copy_from_slice(bytes);
-        Ok(())
+        assert!(matches!(
+            new_backend()
+                .with_partitions(&PARTITIONS)
+                .and_then(|backend| backend.with_control_plane_layout(ControlPlaneLayout::DEFAULT)),
+            Err(StorageError::InvalidConfiguration)
+        ));
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::CONTROL_PLANE_COUNT;
 
-    const TEST_FLASH_ONE_BLOCK_PAGE: usize = (CONTROL_PLANE_COUNT + 1) * FLASH_PAGE_SIZE;
-    const TEST_FLASH_TWO_BLOCK_PAGES: usize = (CONTROL_PLANE_COUNT + 2) * FLASH_PAGE_SIZE;
-    const TEST_FLASH_THREE_BLOCK_PAGES: usize = (CONTROL_PLANE_COUNT + 3) * FLASH_PAGE_SIZE;
-    const TEST_FLASH_FOUR_BLOCK_PAGES: usize = (CONTROL_PLANE_COUNT + 4) * FLASH_PAGE_SIZE;
-    const TEST_FLASH_EIGHT_BLOCK_PAGES: usize = (CONTROL_PLANE_COUNT + 8) * FLASH_PAGE_SIZE;
-
-    fn block_from_marker(marker: u8) -> Block {
-        let mut bytes = [0u8; MAX_BLOCK_SIZE];
-        bytes[0] = marker;
+    #[test]
+    fn relocation_requires_two_consecutive_replicas() {
+        let layouts = [
+            ControlPlaneLayout::new(1, ControlPlanePlacement::Consecutive),
+            ControlPlaneLayout::new(3, ControlPlanePlacement::Spread),
+        ];
+        let mut index = 0usize;
+        while index < layouts.len() {
+            let mut backend = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(0)
+                .and_then(|backend| backend.with_control_plane_layout(layouts[index]))
+                .unwrap_or_else(|_| unreachable!());
+            assert!(backend
+                .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+                .is_ok());
+            assert!(matches!(
+                backend.relocate(FLASH_PAGE_SIZE, TEST_FLASH_EIGHT_BLOCK_PAGES),
+                Err(StorageError::InvalidConfiguration)
+            ));
+            index += 1;
+        }
+    }
 
-        let parse_result = Block::from_bytes(&bytes);
-        assert!(parse_result.is_ok());
-        match parse_result {
-            Ok(value) => value,
-            Err(_) => unreachable!(),
+    #[test]
+    fn unknown_superblock_version_is_refused_and_left_in_place() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        {
+            let mut flash = backend.flash_mock.borrow_mut();
+            let mut replica_index = 0usize;
+            while replica_index < CONTROL_PLANE_COUNT {
+                flash.data[replica_index * FLASH_PAGE_SIZE + SUPERBLOCK_OFFSET + 4] = SUPERBLOCK_VERSION + 1;
+                replica_index += 1;
+            }
         }
+        let mut before = [0u8; TEST_FLASH_ONE_BLOCK_PAGE];
+        before.copy_from_slice(&backend.flash_image());
+
+        assert!(matches!(
+            backend.load_control_data(),
+            Err(StorageError::ControlPlaneIncompatible)
+        ));
+        assert!(backend.flash_image()[..] == before[..]);
     }
 
     #[test]
-    fn new_calculates_max_slots_from_storage_geometry() {
-        let backend = Rp2040Backend::<TEST_FLASH_THREE_BLOCK_PAGES>::new_for_tests(FLASH_PAGE_SIZE).unwrap_or_else(|_| unreachable!());
+    fn control_plane_health_reports_replicas_and_repairs_unless_read_only() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        {
+            let mut flash = backend.flash_mock.borrow_mut();
+            flash.data[FLASH_PAGE_SIZE..2 * FLASH_PAGE_SIZE].fill(0xFF);
+            flash.data[2 * FLASH_PAGE_SIZE + CONTROL_PLANE_ENTRY_SIZE - 1] ^= 0xFF;
+        }
+        let before = backend.flash_mock.borrow().data;
+
+        let health = backend.control_plane_health(true);
+        assert!(matches!(health, Ok(ref value) if !value.repaired && value.primary_replica == Some(0)));
+        let health = health.unwrap_or_else(|_| unreachable!());
         assert_eq!(
-            backend.max_storage_slots,
-            (2 * BLOCKS_PER_PAGE) as StorageIndex
+            health.statuses(),
+            &[ReplicaStatus::Valid, ReplicaStatus::Erased, ReplicaStatus::CrcMismatch]
         );
+        assert!(backend.flash_mock.borrow().data == before);
+
+        assert!(matches!(backend.control_plane_health(false), Ok(ref value) if value.repaired));
+        assert!(matches!(backend.control_plane_health(true), Ok(ref value) if value.is_healthy()));
+        let report = backend.fsck(FsckOptions { repair: false }, &mut |_| {});
+        assert!(matches!(report, Ok(ref value) if value.is_clean()));
     }
 
     #[test]
-    fn new_for_tests_returns_error_on_misaligned_start_address() {
-        let backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(1);
-        assert!(matches!(backend, Err(StorageError::InvalidConfiguration)));
+    fn init_with_progress_reports_every_chunk() {
+        let mut backend =
+            Rp2040Backend::<TEST_FLASH_TWENTY_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        let mut reports = [0usize; 4];
+        let mut report_count = 0usize;
+        let result = backend.init_with_progress([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE], &mut |progress| {
+            assert_eq!(progress.pages_total, 20);
+            reports[report_count] = progress.pages_erased;
+            report_count += 1;
+        });
+        assert!(result.is_ok());
+        assert_eq!(&reports[..report_count], &[INIT_ERASE_CHUNK_PAGES, 20]);
+        assert!(matches!(backend.pending_init(), Ok(None)));
+        assert!(backend.load_control_data().is_ok());
     }
 
     #[test]
-    fn first_page_mapping_is_deterministic() {
-        let mapping = map_storage_index(0);
-        assert_eq!(mapping.page_index, 0);
-        assert_eq!(mapping.slot_index, 0);
-        assert_eq!(mapping.byte_offset_in_page, 0);
+    fn init_resumes_after_power_loss_at_every_step() {
+        let layouts = [
+            ControlPlaneLayout::DEFAULT,
+            ControlPlaneLayout::new(1, ControlPlanePlacement::Consecutive),
+        ];
+        let last_index = 20 * BLOCKS_PER_PAGE_INDEX - 1;
+        let mut layout_index = 0usize;
+        while layout_index < layouts.len() {
+            let layout = layouts[layout_index];
+            let open = |image: &[u8]| {
+                Rp2040Backend::<TEST_FLASH_TWENTY_BLOCK_PAGES>::from_flash_image(0, image)
+                    .and_then(|backend| backend.with_control_plane_layout(layout))
+                    .unwrap_or_else(|_| unreachable!())
+            };
+            let mut budget = 0usize;
+            loop {
+                let mut backend = open(&[]);
+                assert!(backend
+                    .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+                    .is_ok());
+                assert!(backend.save_block(last_index, &block_from_marker(4)).is_ok());
+
+                backend.flash_mock.borrow_mut().power_loss_after = Some(budget);
+                let interrupted = backend.init_with_progress([2u8; PRIVATE_KEY_SIZE], 2, [0u8; INIT_PARAMS_SIZE], &mut |_| {});
+                backend.flash_mock.borrow_mut().power_loss_after = None;
+                if interrupted.is_ok() {
+                    break;
+                }
+                assert!(matches!(interrupted, Err(StorageError::BackendIo { code: 233 })));
+
+                let mut rebooted = open(&backend.flash_image());
+                match rebooted.pending_init() {
+                    Ok(Some(progress)) => {
+                        assert_eq!(progress.pages_total, 20);
+                        assert!(matches!(rebooted.load_control_data(), Err(StorageError::InitInProgress)));
+                        assert!(rebooted.resume_init(&mut |_| {}).is_ok());
+                        assert!(matches!(rebooted.pending_init(), Ok(None)));
+                    }
+                    Ok(None) => {}
+                    Err(_) => unreachable!(),
+                }
+
+                // Interrupted before the first marker (old node) or after the
+                // last one (new node); never a mix of old blocks and new identity.
+                // A single replica is briefly erased while it is rewritten.
+                match rebooted.load_control_data() {
+                    Ok(loaded) if loaded.own_node_id == 2 => {
+                        assert_eq!(loaded.private_key, [2u8; PRIVATE_KEY_SIZE]);
+                        assert!(matches!(rebooted.read_block(last_index), Err(StorageError::BlockAbsent)));
+                    }
+                    Ok(_) => assert!(rebooted.read_block(last_index).is_ok()),
+                    Err(StorageError::ControlPlaneUninitialized) => assert_eq!(layout.replica_count, 1),
+                    Err(_) => unreachable!(),
+                }
+                budget += 1;
+            }
+            layout_index += 1;
+        }
     }
 
     #[test]
-    fn last_slot_in_page_maps_to_expected_offset() {
-        let index = (BLOCKS_PER_PAGE - 1) as StorageIndex;
-        let mapping = map_storage_index(index);
+    fn resume_init_reports_init_started_and_finished() {
+        let mut backend =
+            Rp2040Backend::<TEST_FLASH_TWENTY_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        backend.flash_mock.borrow_mut().power_loss_after = Some(CONTROL_PLANE_COUNT * 2 + 4);
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_err());
+
+        let observer = CountingObserver::default();
+        let mut rebooted = Rp2040Backend::<TEST_FLASH_TWENTY_BLOCK_PAGES>::from_flash_image(0, &backend.flash_image())
+            .unwrap_or_else(|_| unreachable!())
+            .with_observer(&observer);
+        assert!(matches!(rebooted.pending_init(), Ok(Some(_))));
+        assert!(rebooted.resume_init(&mut |_| {}).is_ok());
+        assert_eq!(observer.inits_started.get(), 1);
+        assert_eq!(observer.inits_finished.get(), 1);
+
+        assert!(rebooted.resume_init(&mut |_| {}).is_ok());
+        assert_eq!(observer.inits_started.get(), 1);
+    }
 
-        assert_eq!(mapping.page_index, 0);
-        assert_eq!(mapping.slot_index, (BLOCKS_PER_PAGE - 1) as StorageIndex);
-        assert_eq!(
-            mapping.byte_offset_in_page,
-            (BLOCKS_PER_PAGE - 1) * SLOT_SIZE_BYTES
-        );
+    #[test]
+    fn control_plane_mutations_keep_blocks_and_other_fields() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert!(backend.save_block(0, &block_from_marker(6)).is_ok());
+
+        assert!(backend.rotate_private_key([2u8; PRIVATE_KEY_SIZE]).is_ok());
+        assert!(backend.set_own_node_id(9).is_ok());
+        assert!(backend.update_init_params([4u8; INIT_PARAMS_SIZE]).is_ok());
+
+        let loaded = match backend.load_control_data() {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        };
+        assert_eq!(loaded.private_key, [2u8; PRIVATE_KEY_SIZE]);
+        assert_eq!(loaded.own_node_id, 9);
+        assert_eq!(loaded.init_params, [4u8; INIT_PARAMS_SIZE]);
+        assert!(backend.read_block(0).is_ok());
+        assert!(!backend
+            .flash_image()
+            .windows(PRIVATE_KEY_SIZE)
+            .any(|window| window == &[1u8; PRIVATE_KEY_SIZE][..]));
     }
 
     #[test]
-    fn page_boundary_transition_maps_correctly() {
-        let first_next_page = BLOCKS_PER_PAGE as StorageIndex;
-        let mapping = map_storage_index(first_next_page);
+    fn decommission_erases_region_and_reports_uninitialized() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_TWO_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert!(backend.save_block(0, &block_from_marker(3)).is_ok());
 
-        assert_eq!(mapping.page_index, 1);
-        assert_eq!(mapping.slot_index, 0);
-        assert_eq!(mapping.byte_offset_in_page, 0);
+        assert!(backend.decommission(true).is_ok());
+        assert!(backend.flash_image().iter().all(|byte| *byte == 0xFF));
+        assert!(matches!(
+            backend.load_control_data(),
+            Err(StorageError::ControlPlaneUninitialized)
+        ));
+        assert!(matches!(backend.read_block(0), Err(StorageError::BlockAbsent)));
+    }
+
+    fn relocation_fixture(last_index: StorageIndex) -> Rp2040Backend<TEST_FLASH_EIGHT_BLOCK_PAGES> {
+        let mut backend =
+            Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 5, [2u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert!(backend.save_block(0, &block_from_marker(1)).is_ok());
+        assert!(backend.save_block(last_index, &block_from_marker(2)).is_ok());
+        backend
+    }
+
+    fn assert_relocated_region(image: &[u8], new_start: usize, last_index: StorageIndex) {
+        let mut moved = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::from_flash_image(new_start, image)
+            .unwrap_or_else(|_| unreachable!());
+        assert!(matches!(moved.pending_relocation(), Ok(None)));
+        assert!(matches!(moved.load_control_data(), Ok(record) if record.own_node_id == 5));
+        assert!(matches!(moved.read_block(0), Ok(block) if block.as_bytes()[0] == 1));
+        assert!(matches!(moved.read_block(last_index), Ok(block) if block.as_bytes()[0] == 2));
     }
 
     #[test]
-    fn high_index_mapping_stays_consistent() {
-        let high_index = StorageIndex::MAX;
-        let mapping = map_storage_index(high_index);
+    fn relocate_moves_control_plane_and_blocks_keeping_storage_indexes() {
+        let new_start = 2 * FLASH_PAGE_SIZE;
+        let last_index = 6 * BLOCKS_PER_PAGE_INDEX - 1;
+        let mut backend = relocation_fixture(last_index);
+
+        assert!(backend.relocate(new_start, TEST_FLASH_EIGHT_BLOCK_PAGES).is_ok());
+        assert_eq!(backend.superblock().data_storage_start_address as usize, new_start);
+        assert!(matches!(backend.load_control_data(), Ok(record) if record.own_node_id == 5));
+        assert!(matches!(backend.read_block(last_index), Ok(block) if block.as_bytes()[0] == 2));
+        assert!(matches!(backend.read_block(last_index + 1), Err(StorageError::InvalidIndex)));
+        assert!(backend.flash_image()[FLASH_PAGE_SIZE..new_start]
+            .iter()
+            .all(|byte| *byte == 0xFF));
+        assert_relocated_region(&backend.flash_image(), new_start, last_index);
+
+        let mut stale = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::from_flash_image(0, &backend.flash_image())
+            .unwrap_or_else(|_| unreachable!());
+        assert!(matches!(
+            stale.pending_relocation(),
+            Ok(Some(target)) if target.data_storage_start_address == new_start
+        ));
+        assert!(matches!(
+            stale.load_control_data(),
+            Err(StorageError::RelocationInProgress)
+        ));
+        assert!(stale.relocate(new_start, TEST_FLASH_EIGHT_BLOCK_PAGES).is_ok());
+        assert!(matches!(stale.read_block(0), Ok(block) if block.as_bytes()[0] == 1));
+    }
 
-        let expected_page = high_index / BLOCKS_PER_PAGE_INDEX;
-        let expected_slot = high_index % BLOCKS_PER_PAGE_INDEX;
+    #[test]
+    fn relocate_rejects_invalid_targets_and_slots_outside_the_new_region() {
+        let mut backend = relocation_fixture(8 * BLOCKS_PER_PAGE_INDEX - 1);
 
-        assert_eq!(mapping.page_index, expected_page);
-        assert_eq!(mapping.slot_index, expected_slot);
-        assert_eq!(
-            mapping.byte_offset_in_page,
-            expected_slot as usize * SLOT_SIZE_BYTES
-        );
+        assert!(matches!(
+            backend.relocate(2 * FLASH_PAGE_SIZE, TEST_FLASH_EIGHT_BLOCK_PAGES),
+            Err(StorageError::InvalidConfiguration)
+        ));
+        assert!(matches!(
+            backend.relocate(FLASH_PAGE_SIZE + 1, TEST_FLASH_EIGHT_BLOCK_PAGES),
+            Err(StorageError::InvalidConfiguration)
+        ));
+        assert!(matches!(
+            backend.relocate(FLASH_PAGE_SIZE, TEST_FLASH_EIGHT_BLOCK_PAGES + FLASH_PAGE_SIZE),
+            Err(StorageError::InvalidConfiguration)
+        ));
+        assert!(matches!(backend.pending_relocation(), Ok(None)));
+        assert!(backend.load_control_data().is_ok());
     }
 
     #[test]
-    fn save_block_succeeds_for_valid_index() {
-        let mut backend = Rp2040Backend::<TEST_FLASH_TWO_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
-        let block = block_from_marker(7);
+    fn relocate_to_smaller_flash_size_reports_layout_mismatch_to_old_firmware() {
+        let mut backend = relocation_fixture(BLOCKS_PER_PAGE_INDEX);
+        let new_flash_size = TEST_FLASH_EIGHT_BLOCK_PAGES - 2 * FLASH_PAGE_SIZE;
 
-        let save_result = backend.save_block(1, &block);
-        assert!(save_result.is_ok());
+        assert!(backend.relocate(FLASH_PAGE_SIZE, new_flash_size).is_ok());
+        assert!(backend.load_control_data().is_ok());
 
-        let read_result = backend.read_block(1);
-        assert!(read_result.is_ok());
-        let read_block = match read_result {
-            Ok(value) => value,
-            Err(_) => return,
-        };
-        assert_eq!(read_block.as_bytes(), block.as_bytes());
+        let mut old_firmware =
+            Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::from_flash_image(FLASH_PAGE_SIZE, &backend.flash_image())
+                .unwrap_or_else(|_| unreachable!());
+        assert!(matches!(
+            old_firmware.load_control_data(),
+            Err(StorageError::LayoutMismatch { field: LayoutField::FlashSize, .. })
+        ));
+
+        let mut moved = Rp2040Backend::<{ TEST_FLASH_EIGHT_BLOCK_PAGES - 2 * FLASH_PAGE_SIZE }>::from_flash_image(
+            FLASH_PAGE_SIZE,
+            &backend.flash_image()[..new_flash_size],
+        )
+        .unwrap_or_else(|_| unreachable!());
+        assert!(moved.load_control_data().is_ok());
+        assert!(matches!(moved.read_block(BLOCKS_PER_PAGE_INDEX), Ok(block) if block.as_bytes()[0] == 2));
+    }
+
+    #[test]
+    fn relocate_resumes_after_power_loss_at_every_step() {
+        let mut shift_pages = 1usize;
+        while shift_pages <= 4 {
+            let new_start = shift_pages * FLASH_PAGE_SIZE;
+            let last_index = (8 - shift_pages) as StorageIndex * BLOCKS_PER_PAGE_INDEX - 1;
+            let mut budget = 0usize;
+            loop {
+                let mut backend = relocation_fixture(last_index);
+                backend.flash_mock.borrow_mut().power_loss_after = Some(budget);
+                let interrupted = backend.relocate(new_start, TEST_FLASH_EIGHT_BLOCK_PAGES);
+                backend.flash_mock.borrow_mut().power_loss_after = None;
+                if interrupted.is_ok() {
+                    break;
+                }
+                assert!(matches!(interrupted, Err(StorageError::BackendIo { code: 233 })));
+
+                // Reboot on firmware still built for the old start address.
+                let mut rebooted =
+                    Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::from_flash_image(0, &backend.flash_image())
+                        .unwrap_or_else(|_| unreachable!());
+                match rebooted.pending_relocation() {
+                    Ok(Some(target)) => {
+                        assert!(matches!(
+                            rebooted.load_control_data(),
+                            Err(StorageError::RelocationInProgress)
+                        ));
+                        assert!(rebooted
+                            .relocate(target.data_storage_start_address, target.flash_size)
+                            .is_ok());
+                    }
+                    Ok(None) => {
+                        // Interrupted before the first journal or after the last one.
+                        if rebooted.load_control_data().is_ok() {
+                            assert!(rebooted.relocate(new_start, TEST_FLASH_EIGHT_BLOCK_PAGES).is_ok());
+                        }
+                    }
+                    Err(_) => unreachable!(),
+                }
+                assert_relocated_region(&rebooted.flash_image(), new_start, last_index);
+                budget += 1;
+            }
+            shift_pages += 1;
+        }
     }
 
+    const TEST_PARTITIONS: [Rp2040Region; 2] = [
+        Rp2040Region::new("main", 0, 6 * FLASH_PAGE_SIZE),
+        Rp2040Region::new("testnet", 6 * FLASH_PAGE_SIZE, TEST_FLASH_EIGHT_BLOCK_PAGES),
+    ];
+
     #[test]
-    fn save_block_rejects_invalid_index() {
-        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
-        let block = block_from_marker(8);
+    fn partitions_keep_independent_control_planes_and_slots() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_partitions(&TEST_PARTITIONS))
+            .unwrap_or_else(|_| unreachable!());
 
-        let save_result = backend.save_block(BLOCKS_PER_PAGE as StorageIndex, &block);
-        assert!(matches!(save_result, Err(StorageError::InvalidIndex)));
-    }
+        {
+            let main = backend.partition("main");
+            assert!(main.is_ok());
+            let mut main = main.unwrap_or_else(|_| unreachable!());
+            assert!(main.init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE]).is_ok());
+            assert!(main.save_block(0, &block_from_marker(1)).is_ok());
+
+            let testnet = backend.partition("testnet");
+            assert!(testnet.is_ok());
+            let mut testnet = testnet.unwrap_or_else(|_| unreachable!());
+            assert!(testnet.init([2u8; PRIVATE_KEY_SIZE], 2, [0u8; INIT_PARAMS_SIZE]).is_ok());
+            assert!(testnet.save_block(0, &block_from_marker(2)).is_ok());
+            assert_eq!(testnet.region().max_storage_slots(), 2 * BLOCKS_PER_PAGE_INDEX);
+            assert!(matches!(
+                testnet.save_block(2 * BLOCKS_PER_PAGE_INDEX, &block_from_marker(3)),
+                Err(StorageError::InvalidIndex)
+            ));
+
+            assert!(matches!(main.load_control_data(), Ok(record) if record.own_node_id == 1));
+            assert!(matches!(testnet.load_control_data(), Ok(record) if record.own_node_id == 2));
+            assert!(matches!(main.read_block(0), Ok(block) if block.as_bytes()[0] == 1));
+            assert!(matches!(testnet.read_block(0), Ok(block) if block.as_bytes()[0] == 2));
+            assert!(matches!(main.find_by_hash(&block_hash(&block_from_marker(2))), Ok(None)));
+            assert!(matches!(testnet.find_by_hash(&block_hash(&block_from_marker(2))), Ok(Some(0))));
+        }
 
-    #[test]
-    fn save_block_succeeds_at_last_valid_index() {
-        let mut backend = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
-        let block = block_from_marker(9);
-        let last_valid_index = backend.max_storage_slots - 1;
+        assert!(matches!(backend.load_control_data(), Ok(record) if record.own_node_id == 1));
+        assert_eq!(backend.superblock().flash_size as usize, 6 * FLASH_PAGE_SIZE);
 
-        let save_result = backend.save_block(last_valid_index, &block);
-        assert!(save_result.is_ok());
+        assert!(backend.decommission(true).is_ok());
+        let testnet = backend.partition("testnet");
+        assert!(testnet.is_ok());
+        let mut testnet = testnet.unwrap_or_else(|_| unreachable!());
+        assert!(matches!(
+            testnet.load_control_data(),
+            Err(StorageError::ControlPlaneUninitialized)
+        ));
     }
 
     #[test]
-    fn storage_start_address_reduces_capacity() {
-        let backend = Rp2040Backend::<TEST_FLASH_THREE_BLOCK_PAGES>::new_for_tests(2 * FLASH_PAGE_SIZE).unwrap_or_else(|_| unreachable!());
-        assert_eq!(backend.max_storage_slots, BLOCKS_PER_PAGE as StorageIndex);
-        assert_eq!(backend.data_storage_start_address, 2 * FLASH_PAGE_SIZE);
+    fn with_partitions_rejects_invalid_tables() {
+        const OVERLAPPING: [Rp2040Region; 2] = [
+            Rp2040Region::new("main", 0, 6 * FLASH_PAGE_SIZE),
+            Rp2040Region::new("testnet", 5 * FLASH_PAGE_SIZE, TEST_FLASH_EIGHT_BLOCK_PAGES),
+        ];
+        const DUPLICATE_NAME: [Rp2040Region; 2] = [
+            Rp2040Region::new("main", 0, 6 * FLASH_PAGE_SIZE),
+            Rp2040Region::new("main", 6 * FLASH_PAGE_SIZE, TEST_FLASH_EIGHT_BLOCK_PAGES),
+        ];
+        const NO_BLOCK_PAGE: [Rp2040Region; 1] = [Rp2040Region::new("main", 0, CONTROL_PLANE_COUNT * FLASH_PAGE_SIZE)];
+        const PAST_FLASH_END: [Rp2040Region; 1] =
+            [Rp2040Region::new("main", 0, TEST_FLASH_EIGHT_BLOCK_PAGES + FLASH_PAGE_SIZE)];
+        const BELOW_START: [Rp2040Region; 1] = [Rp2040Region::new("main", 0, 6 * FLASH_PAGE_SIZE)];
+
+        let tables: [&'static [Rp2040Region]; 5] = [&[], &OVERLAPPING, &DUPLICATE_NAME, &NO_BLOCK_PAGE, &PAST_FLASH_END];
+        let mut index = 0usize;
+        while index < tables.len() {
+            let result = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(0)
+                .and_then(|backend| backend.with_partitions(tables[index]));
+            assert!(matches!(result, Err(StorageError::InvalidConfiguration)));
+            index += 1;
+        }
+        let below_start = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(FLASH_PAGE_SIZE)
+            .and_then(|backend| backend.with_partitions(&BELOW_START));
+        assert!(matches!(below_start, Err(StorageError::InvalidConfiguration)));
+
+        let mut backend = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_partitions(&TEST_PARTITIONS))
+            .unwrap_or_else(|_| unreachable!());
+        assert!(matches!(backend.partition("orphans"), Err(StorageError::InvalidConfiguration)));
+        assert!(backend.init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE]).is_ok());
+        assert!(matches!(
+            backend.relocate(FLASH_PAGE_SIZE, 7 * FLASH_PAGE_SIZE),
+            Err(StorageError::InvalidConfiguration)
+        ));
     }
 
     #[test]
-    fn slot_flash_address_uses_storage_start_address() {
-        let backend = Rp2040Backend::<TEST_FLASH_FOUR_BLOCK_PAGES>::new_for_tests(FLASH_PAGE_SIZE).unwrap_or_else(|_| unreachable!());
-        let mapping = map_storage_index(BLOCKS_PER_PAGE as StorageIndex);
-        let address = backend.slot_flash_address(&mapping);
-
-        assert_eq!(address, (CONTROL_PLANE_COUNT + 2) * FLASH_PAGE_SIZE);
+    fn linker_region_is_accepted_when_it_follows_the_firmware() {
+        let flash_size = 2 * 1024 * 1024;
+        let start = storage_start_from_linker_addresses(
+            RP2040_XIP_BASE + 0x18_0000,
+            RP2040_XIP_BASE + flash_size,
+            RP2040_XIP_BASE + 0x18_0000,
+            flash_size,
+        );
+        assert!(matches!(start, Ok(0x18_0000)));
     }
 
     #[test]
-    fn read_block_reports_absent_for_empty_slot() {
-        let backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
-        let read_result = backend.read_block(0);
-        assert!(matches!(read_result, Err(StorageError::BlockAbsent)));
+    fn linker_region_is_rejected_when_invalid_or_overlapping_firmware() {
+        let flash_size = 2 * 1024 * 1024;
+        let flash_end = RP2040_XIP_BASE + flash_size;
+        let start = RP2040_XIP_BASE + 0x18_0000;
+        let cases = [
+            (start, flash_end, start + 1),
+            (start + 1, flash_end, RP2040_XIP_BASE),
+            (start, flash_end - FLASH_PAGE_SIZE, RP2040_XIP_BASE),
+            (0x18_0000, flash_end, 0),
+            (flash_end, flash_end, RP2040_XIP_BASE),
+        ];
+        let mut index = 0usize;
+        while index < cases.len() {
+            let (storage_start, storage_end, firmware_end) = cases[index];
+            assert!(matches!(
+                storage_start_from_linker_addresses(storage_start, storage_end, firmware_end, flash_size),
+                Err(StorageError::InvalidConfiguration)
+            ));
+            index += 1;
+        }
     }
 
     #[test]
-    fn read_block_rejects_invalid_index() {
-        let backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
-        let read_result = backend.read_block(BLOCKS_PER_PAGE as StorageIndex);
-        assert!(matches!(read_result, Err(StorageError::InvalidIndex)));
+    fn sequence_index_finds_blocks_and_follows_overwrites() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_THREE_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_sequence_index(1))
+            .unwrap_or_else(|_| unreachable!());
+        assert_eq!(backend.region.max_storage_slots(), 2 * BLOCKS_PER_PAGE_INDEX);
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert!(matches!(backend.sequence_log_built(&backend.region), Ok(true)));
+        assert!(matches!(backend.max_sequence(), Ok(None)));
+
+        let last_index = 2 * BLOCKS_PER_PAGE_INDEX - 1;
+        assert!(backend.save_block(0, &block_with_sequence(5)).is_ok());
+        assert!(backend.save_block(last_index, &block_with_sequence(9)).is_ok());
+        assert!(matches!(backend.find_by_sequence(5), Ok(Some(0))));
+        assert!(matches!(backend.find_by_sequence(9), Ok(Some(index)) if index == last_index));
+        assert!(matches!(backend.find_by_sequence(7), Ok(None)));
+        assert!(matches!(backend.max_sequence(), Ok(Some(9))));
+
+        assert!(backend.save_block(last_index, &block_with_sequence(3)).is_ok());
+        assert!(matches!(backend.find_by_sequence(9), Ok(None)));
+        assert!(matches!(backend.max_sequence(), Ok(Some(5))));
+        let mut seen: [(u32, StorageIndex); 4] = [(0, 0); 4];
+        let count = collect_sequences(&backend, 0..u32::MAX, &mut seen);
+        assert_eq!(&seen[..count], &[(3, last_index), (5, 0)]);
+        let count = collect_sequences(&backend, 4..u32::MAX, &mut seen);
+        assert_eq!(&seen[..count], &[(5, 0)]);
     }
 
     #[test]
-    fn read_block_detects_hash_mismatch() {
-        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
-        let block = block_from_marker(11);
-        assert!(backend.save_block(0, &block).is_ok());
-
-        // Corrupt first byte of stored hash in slot.
-        let hash_byte_index = SLOT_HASH_OFFSET;
-        backend.with_corrupted_mock_slot_byte(0, hash_byte_index);
-
-        let read_result = backend.read_block(0);
-        assert!(matches!(read_result, Err(StorageError::IntegrityFailure)));
+    fn lost_sequence_index_falls_back_to_scan_until_rebuilt() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_THREE_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_sequence_index(1))
+            .unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert!(backend.save_block(0, &block_with_sequence(4)).is_ok());
+
+        let index_page = backend.region.sequence_index_page_address(0);
+        backend.flash_mock.borrow_mut().data[index_page..index_page + FLASH_PAGE_SIZE].fill(0xFF);
+        assert!(matches!(backend.sequence_log_built(&backend.region), Ok(false)));
+        assert!(backend.save_block(1, &block_with_sequence(6)).is_ok());
+        assert!(matches!(backend.find_by_sequence(4), Ok(Some(0))));
+        assert!(matches!(backend.max_sequence(), Ok(Some(6))));
+
+        assert!(backend.rebuild_sequence_index().is_ok());
+        assert!(matches!(backend.sequence_log_built(&backend.region), Ok(true)));
+        assert!(matches!(backend.sequence_log_end(&backend.region), Ok(Some(3))));
+        assert!(matches!(backend.find_by_sequence(6), Ok(Some(1))));
+
+        let mut plain = Rp2040Backend::<TEST_FLASH_THREE_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+        assert!(matches!(plain.rebuild_sequence_index(), Err(StorageError::InvalidConfiguration)));
     }
 
     #[test]
-    fn read_block_succeeds_at_last_valid_index() {
-        let mut backend = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
-        let block = block_from_marker(12);
-        let last_valid_index = backend.max_storage_slots - 1;
-        assert!(backend.save_block(last_valid_index, &block).is_ok());
-
-        let read_result = backend.read_block(last_valid_index);
-        assert!(read_result.is_ok());
-    }
+    fn full_sequence_log_is_rebuilt_on_save() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_TWO_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_sequence_index(1))
+            .unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
 
-    #[test]
-    fn read_block_detects_partially_written_slot_data() {
-        let backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
-        let mut raw_slot = [0xFFu8; SLOT_SIZE_BYTES];
-        raw_slot[0] = 1;
-        raw_slot[1] = 2;
-        backend.write_mock_slot_raw(0, &raw_slot);
+        let capacity = sequence_log::capacity(1) as u32;
+        let mut sequence = 0u32;
+        while sequence < capacity {
+            assert!(backend.save_block(0, &block_with_sequence(sequence)).is_ok());
+            sequence += 1;
+        }
+        assert!(matches!(backend.sequence_log_end(&backend.region), Ok(None)));
 
-        let read_result = backend.read_block(0);
-        assert!(matches!(read_result, Err(StorageError::IntegrityFailure)));
+        assert!(backend.save_block(0, &block_with_sequence(capacity)).is_ok());
+        assert!(matches!(backend.sequence_log_end(&backend.region), Ok(Some(3))));
+        assert!(matches!(backend.find_by_sequence(capacity), Ok(Some(0))));
+        assert!(matches!(backend.find_by_sequence(capacity - 1), Ok(None)));
     }
 
     #[test]
-    fn read_block_detects_malformed_slot_with_matching_hash() {
-        let backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
-        let mut raw_slot = [0u8; SLOT_SIZE_BYTES];
-        let computed_hash = calculate_hash(&raw_slot[..MAX_BLOCK_SIZE]);
-        raw_slot[SLOT_HASH_OFFSET..SLOT_HASH_OFFSET + HASH_SIZE].copy_from_slice(&computed_hash);
-        backend.write_mock_slot_raw(0, &raw_slot);
+    fn power_loss_after_sequence_record_keeps_previous_block() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_TWO_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_sequence_index(1))
+            .unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert!(backend.save_block(0, &block_with_sequence(5)).is_ok());
 
-        let read_result = backend.read_block(0);
-        assert!(matches!(read_result, Err(StorageError::IntegrityFailure)));
+        backend.flash_mock.borrow_mut().power_loss_after = Some(1);
+        assert!(matches!(
+            backend.save_block(0, &block_with_sequence(7)),
+            Err(StorageError::BackendIo { code: 233 })
+        ));
+        backend.flash_mock.borrow_mut().power_loss_after = None;
+
+        assert!(matches!(backend.sequence_log_end(&backend.region), Ok(Some(3))));
+        assert!(matches!(backend.find_by_sequence(7), Ok(None)));
+        assert!(matches!(backend.find_by_sequence(5), Ok(Some(0))));
+        assert!(matches!(backend.max_sequence(), Ok(Some(5))));
     }
 
     #[test]
-    fn startup_read_cycle_reports_typed_outcomes_for_mixed_slot_states() {
-        let mut backend = Rp2040Backend::<TEST_FLASH_TWO_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
-        let block = block_from_marker(13);
-        assert!(backend.save_block(0, &block).is_ok());
-
-        let mut partial_slot = [0xFFu8; SLOT_SIZE_BYTES];
-        partial_slot[0] = 1;
-        backend.write_mock_slot_raw(1, &partial_slot);
+    fn sequence_index_pages_are_part_of_the_layout() {
+        assert!(matches!(
+            Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).and_then(|backend| backend.with_sequence_index(1)),
+            Err(StorageError::InvalidConfiguration)
+        ));
 
-        let slot0 = backend.read_block(0);
-        let slot1 = backend.read_block(1);
-        let slot2 = backend.read_block(2);
+        let mut backend = Rp2040Backend::<TEST_FLASH_TWO_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_sequence_index(1))
+            .unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert_eq!(backend.superblock().sequence_index_pages, 1);
 
-        assert!(matches!(slot0, Ok(_)));
-        assert!(matches!(slot1, Err(StorageError::IntegrityFailure)));
-        assert!(matches!(slot2, Err(StorageError::BlockAbsent)));
+        let image = backend.flash_image();
+        let mut without_index = Rp2040Backend::<TEST_FLASH_TWO_BLOCK_PAGES>::from_flash_image(0, &image)
+            .unwrap_or_else(|_| unreachable!());
+        assert!(matches!(
+            without_index.load_control_data(),
+            Err(StorageError::LayoutMismatch {
+                field: LayoutField::SequenceIndexPages,
+                stored: 1,
+                expected: 0,
+            })
+        ));
     }
 
     #[test]
-    fn integration_startup_ingest_query_flow_with_valid_dataset() {
-        let mut backend = Rp2040Backend::<TEST_FLASH_THREE_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
-        let block_a = block_from_marker(21);
-        let block_b = block_from_marker(22);
-        let block_c = block_from_marker(23);
-
-        // Ingest flow: accepted blocks are saved at deterministic indices.
-        assert!(backend.save_block(0, &block_a).is_ok());
-        assert!(backend.save_block(2, &block_b).is_ok());
-        assert!(backend.save_block(5, &block_c).is_ok());
-
-        // Startup flow: chain logic reads a contiguous range and gets typed outcomes.
-        let startup_scan = [
-            backend.read_block(0),
-            backend.read_block(1),
-            backend.read_block(2),
-            backend.read_block(3),
-            backend.read_block(4),
-            backend.read_block(5),
+    fn partitions_keep_separate_sequence_indexes() {
+        let regions = [
+            Rp2040Region::new("main", 0, 6 * FLASH_PAGE_SIZE).with_sequence_index(1),
+            Rp2040Region::new("testnet", 6 * FLASH_PAGE_SIZE, TEST_FLASH_EIGHT_BLOCK_PAGES),
         ];
-
-        assert!(matches!(startup_scan[0], Ok(_)));
-        assert!(matches!(startup_scan[1], Err(StorageError::BlockAbsent)));
-        assert!(matches!(startup_scan[2], Ok(_)));
-        assert!(matches!(startup_scan[3], Err(StorageError::BlockAbsent)));
-        assert!(matches!(startup_scan[4], Err(StorageError::BlockAbsent)));
-        assert!(matches!(startup_scan[5], Ok(_)));
-
-        // Query flow: retrieval returns exact stored blocks for populated indices.
-        let read_a = backend.read_block(0);
-        let read_b = backend.read_block(2);
-        let read_c = backend.read_block(5);
-        assert!(read_a.is_ok());
-        assert!(read_b.is_ok());
-        assert!(read_c.is_ok());
-        let read_a = match read_a {
-            Ok(value) => value,
-            Err(_) => return,
-        };
-        let read_b = match read_b {
-            Ok(value) => value,
-            Err(_) => return,
-        };
-        let read_c = match read_c {
-            Ok(value) => value,
-            Err(_) => return,
-        };
-        assert_eq!(read_a.as_bytes(), block_a.as_bytes());
-        assert_eq!(read_b.as_bytes(), block_b.as_bytes());
-        assert_eq!(read_c.as_bytes(), block_c.as_bytes());
+        let backend = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_partitions(&regions))
+            .unwrap_or_else(|_| unreachable!());
+
+        let mut main = backend.partition("main").unwrap_or_else(|_| unreachable!());
+        assert!(main.init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE]).is_ok());
+        assert!(main.save_block(1, &block_with_sequence(8)).is_ok());
+        let mut testnet = backend.partition("testnet").unwrap_or_else(|_| unreachable!());
+        assert!(testnet.init([2u8; PRIVATE_KEY_SIZE], 2, [0u8; INIT_PARAMS_SIZE]).is_ok());
+        assert!(testnet.save_block(0, &block_with_sequence(8)).is_ok());
+
+        assert!(matches!(main.find_by_sequence(8), Ok(Some(1))));
+        assert!(matches!(testnet.find_by_sequence(8), Ok(Some(0))));
+        assert!(matches!(main.max_sequence(), Ok(Some(8))));
+        assert!(matches!(testnet.rebuild_sequence_index(), Err(StorageError::InvalidConfiguration)));
     }
 
     #[test]
-    fn integration_startup_and_query_flow_reports_integrity_on_corrupted_dataset() {
-        let mut backend = Rp2040Backend::<TEST_FLASH_TWO_BLOCK_PAGES>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
-        let block_ok = block_from_marker(31);
-        let block_corrupted = block_from_marker(32);
-
-        // Ingest flow.
-        assert!(backend.save_block(0, &block_ok).is_ok());
-        assert!(backend.save_block(1, &block_corrupted).is_ok());
-
-        // Corrupt one persisted slot and inject one partial slot.
-        backend.with_corrupted_mock_slot_byte(1, SLOT_HASH_OFFSET);
-        let mut partial_slot = [0xFFu8; SLOT_SIZE_BYTES];
-        partial_slot[0] = 1;
-        backend.write_mock_slot_raw(2, &partial_slot);
-
-        // Startup flow over mixed health states.
-        assert!(matches!(backend.read_block(0), Ok(_)));
+    fn kv_store_persists_across_reload_and_is_wiped_by_init() {
         assert!(matches!(
-            backend.read_block(1),
-            Err(StorageError::IntegrityFailure)
+            Rp2040Backend::<TEST_FLASH_FOUR_BLOCK_PAGES>::new_for_tests(0).and_then(|backend| backend.with_kv_store(1)),
+            Err(StorageError::InvalidConfiguration)
         ));
         assert!(matches!(
-            backend.read_block(2),
-            Err(StorageError::IntegrityFailure)
+            Rp2040Backend::<TEST_FLASH_FOUR_BLOCK_PAGES>::new_for_tests(0).and_then(|backend| backend.with_kv_store(2)),
+            Err(StorageError::InvalidConfiguration)
         ));
         assert!(matches!(
-            backend.read_block(3),
-            Err(StorageError::BlockAbsent)
+            Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(0).and_then(|backend| backend.with_kv_store(5)),
+            Err(StorageError::InvalidConfiguration)
         ));
-
-        // Query flow: contract consistency for invalid index remains intact.
-        let invalid_index = backend.max_storage_slots;
         assert!(matches!(
-            backend.read_block(invalid_index),
-            Err(StorageError::InvalidIndex)
+            Rp2040Backend::<TEST_FLASH_FOUR_BLOCK_PAGES>::new_for_tests(0).and_then(|backend| backend.with_kv_store(4)),
+            Err(StorageError::InvalidConfiguration)
         ));
-    }
 
-    #[test]
-    fn control_plane_load_reports_uninitialized_before_init() {
-        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
-        assert!(matches!(
-            backend.load_control_data(),
-            Err(StorageError::ControlPlaneUninitialized)
-        ));
+        let mut backend = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_kv_store(4))
+            .unwrap_or_else(|_| unreachable!());
+        assert_eq!(backend.region.max_storage_slots(), 4 * BLOCKS_PER_PAGE_INDEX);
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert_eq!(backend.superblock().kv_pages, 4);
+        assert!(matches!(backend.kv_get(b"boots"), Ok(None)));
+        assert!(backend.kv_put(b"boots", &3u32.to_le_bytes()).is_ok());
+        assert!(backend.kv_put(b"peer", &[7, 8]).is_ok());
+        assert!(backend.save_block(1, &block_with_sequence(4)).is_ok());
+        assert!(backend.kv_delete(b"peer").is_ok());
+
+        let image = backend.flash_image();
+        let mut reloaded = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::from_flash_image(0, &image)
+            .and_then(|backend| backend.with_kv_store(4))
+            .unwrap_or_else(|_| unreachable!());
+        assert!(reloaded.load_control_data().is_ok());
+        assert!(matches!(reloaded.kv_get(b"boots"), Ok(Some(value)) if value.as_bytes() == 3u32.to_le_bytes()));
+        assert!(matches!(reloaded.kv_get(b"peer"), Ok(None)));
+        assert!(matches!(reloaded.read_block(1), Ok(block) if block.header().sequence == 4));
+
+        assert!(reloaded
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert!(matches!(reloaded.kv_get(b"boots"), Ok(None)));
     }
 
     #[test]
-    fn control_plane_init_and_load_round_trip() {
-        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
-        let private_key = [7u8; PRIVATE_KEY_SIZE];
-        let init_params = [9u8; INIT_PARAMS_SIZE];
-        assert!(backend.init(private_key, 42, init_params).is_ok());
-
-        let loaded = backend.load_control_data();
-        assert!(loaded.is_ok());
-        let loaded = match loaded {
-            Ok(value) => value,
-            Err(_) => return,
-        };
-
-        assert_eq!(loaded.private_key, private_key);
-        assert_eq!(loaded.own_node_id, 42);
-        assert_eq!(loaded.init_params, init_params);
-        assert!(loaded.chain_configuration.is_none());
+    fn kv_put_keeps_a_value_after_power_loss_at_every_step() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_EIGHT_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_kv_store(4))
+            .unwrap_or_else(|_| unreachable!());
+        assert!(backend
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert!(backend.kv_put(b"keep", &[9; 64]).is_ok());
+
+        // Enough rewrites of a 200-byte value to compact the store several times.
+        let mut round = 0u8;
+        while round < 60 {
+            let mut budget = 0usize;
+            loop {
+                backend.flash_mock.borrow_mut().power_loss_after = Some(budget);
+                let result = backend.kv_put(b"tip", &[round; 200]);
+                backend.flash_mock.borrow_mut().power_loss_after = None;
+                if result.is_ok() {
+                    break;
+                }
+                let value = backend.kv_get(b"tip").unwrap_or_else(|_| unreachable!());
+                assert!(match value {
+                    Some(value) => round > 0 && value.as_bytes() == [round - 1; 200],
+                    None => round == 0,
+                });
+                budget += 1;
+            }
+            assert!(matches!(backend.kv_get(b"tip"), Ok(Some(value)) if value.as_bytes() == [round; 200]));
+            round += 1;
+        }
+        assert!(matches!(backend.kv_get(b"keep"), Ok(Some(value)) if value.as_bytes() == [9; 64]));
     }
 
     #[test]
-    fn control_plane_set_chain_configuration_is_set_once() {
-        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+    fn counters_survive_reload_init_and_import_until_decommission() {
+        let mut backend = Rp2040Backend::<TEST_FLASH_TWENTY_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_kv_store(4))
+            .and_then(|backend| backend.with_counters(2))
+            .unwrap_or_else(|_| unreachable!());
+        assert_eq!(backend.region.block_page_count(), 12);
         assert!(backend
-            .init([1u8; PRIVATE_KEY_SIZE], 1, [2u8; INIT_PARAMS_SIZE])
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
             .is_ok());
-        let cfg_block = block_from_marker(55);
-
-        assert!(backend.set_chain_configuration(&cfg_block).is_ok());
-        assert!(matches!(
-            backend.set_chain_configuration(&cfg_block),
-            Err(StorageError::ChainConfigurationAlreadySet)
-        ));
-
-        let loaded = backend.load_control_data();
-        assert!(loaded.is_ok());
-        let loaded = match loaded {
-            Ok(value) => value,
-            Err(_) => return,
-        };
-        assert!(loaded.chain_configuration.is_some());
+        assert_eq!(backend.superblock().counters, 2);
+        assert!(matches!(backend.counter(2), Err(StorageError::InvalidIndex)));
+
+        let mut boots = backend.counter(0).unwrap_or_else(|_| unreachable!());
+        assert!(matches!(boots.get(), Ok(0)));
+        assert!(matches!(boots.increment(), Ok(1)));
+        assert!(matches!(boots.increment(), Ok(2)));
+        assert!(matches!(backend.counter(1).and_then(|mut nonce| nonce.increment()), Ok(1)));
+        assert!(backend.kv_put(b"tip", &[5]).is_ok());
+
+        let image = backend.flash_image();
+        let mut reloaded = Rp2040Backend::<TEST_FLASH_TWENTY_BLOCK_PAGES>::from_flash_image(0, &image)
+            .and_then(|backend| backend.with_kv_store(4))
+            .and_then(|backend| backend.with_counters(2))
+            .unwrap_or_else(|_| unreachable!());
+        assert!(reloaded.load_control_data().is_ok());
+        assert!(matches!(reloaded.counter(0).and_then(|boots| boots.get()), Ok(2)));
+        assert!(matches!(reloaded.counter(1).and_then(|nonce| nonce.get()), Ok(1)));
+        assert!(matches!(reloaded.kv_get(b"tip"), Ok(Some(value)) if value.as_bytes() == [5]));
+
+        assert!(reloaded
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
+            .is_ok());
+        assert!(matches!(reloaded.counter(0).and_then(|boots| boots.get()), Ok(2)));
+        assert!(matches!(reloaded.counter(0).and_then(|mut boots| boots.increment()), Ok(3)));
+        assert!(matches!(reloaded.kv_get(b"tip"), Ok(None)));
+
+        let mut archive = [0u8; 1024];
+        let mut sink = crate::archive::SliceSink::new(&mut archive);
+        let options = ExportOptions { include_control_data: true, include_private_key: true };
+        assert!(reloaded.export_to(options, &mut sink).is_ok());
+        let len = sink.written().len();
+        assert!(reloaded.import_from(&mut crate::archive::SliceSource::new(&archive[..len])).is_ok());
+        assert!(matches!(reloaded.counter(0).and_then(|boots| boots.get()), Ok(3)));
+        assert!(matches!(reloaded.counter(1).and_then(|nonce| nonce.get()), Ok(1)));
+
+        assert!(reloaded.decommission(true).is_ok());
+        assert!(matches!(reloaded.counter(0).and_then(|boots| boots.get()), Ok(0)));
     }
 
     #[test]
-    fn control_plane_load_repairs_corrupted_replica() {
-        let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
+    fn counter_page_move_keeps_the_value_after_power_loss_at_every_step() {
+        let observer = CountingObserver::default();
+        let mut backend = Rp2040Backend::<TEST_FLASH_FOUR_BLOCK_PAGES>::new_for_tests(0)
+            .and_then(|backend| backend.with_counters(1))
+            .unwrap_or_else(|_| unreachable!())
+            .with_observer(&observer);
         assert!(backend
-            .init([1u8; PRIVATE_KEY_SIZE], 2, [3u8; INIT_PARAMS_SIZE])
+            .init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE])
             .is_ok());
-
-        // Corrupt first replica header byte.
-        let replica0_addr = backend.control_plane_page_address(0);
-        backend.flash_mock.borrow_mut().data[replica0_addr] ^= 0xFF;
-
-        assert!(backend.load_control_data().is_ok());
-
-        // Re-read replica page and check CRC-valid deserialization.
-        let mut repaired_page = [0u8; FLASH_PAGE_SIZE];
-        assert!(backend.read_page(replica0_addr, &mut repaired_page).is_ok());
-        let mut repaired_entry = [0u8; CONTROL_PLANE_ENTRY_SIZE];
-        repaired_entry.copy_from_slice(&repaired_page[..CONTROL_PLANE_ENTRY_SIZE]);
-        let repaired = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::deserialize_control_record(&repaired_entry);
-        assert!(repaired.is_ok());
+        assert!(matches!(backend.counter(0).and_then(|mut counter| counter.increment()), Ok(1)));
+        let erased = observer.pages_erased.get();
+        assert!(matches!(backend.counter(0).and_then(|mut counter| counter.increment()), Ok(2)));
+        assert_eq!(observer.pages_erased.get(), erased);
+
+        // Spend the rest of the first page without going through every increment.
+        let bitmap_start = backend.region.counter_page_address(0, 0) + FLASH_PAGE_SIZE
+            - (COUNTER_STEPS_PER_PAGE / 8) as usize;
+        let bitmap_end = backend.region.counter_page_address(0, 1);
+        backend.flash_mock.borrow_mut().data[bitmap_start..bitmap_end].fill(0);
+        let value = backend.counter(0).and_then(|counter| counter.get());
+        assert!(matches!(value, Ok(value) if value == COUNTER_STEPS_PER_PAGE));
+
+        let mut budget = 0usize;
+        loop {
+            backend.flash_mock.borrow_mut().power_loss_after = Some(budget);
+            let result = backend.counter(0).and_then(|mut counter| counter.increment());
+            backend.flash_mock.borrow_mut().power_loss_after = None;
+            if let Ok(value) = result {
+                assert_eq!(value, COUNTER_STEPS_PER_PAGE + 1);
+                break;
+            }
+            let value = backend.counter(0).and_then(|counter| counter.get());
+            assert!(matches!(value, Ok(value) if value == COUNTER_STEPS_PER_PAGE));
+            budget += 1;
+        }
+        let value = backend.counter(0).and_then(|counter| counter.get());
+        assert!(matches!(value, Ok(value) if value == COUNTER_STEPS_PER_PAGE + 1));
     }
 
     #[test]
     fn init_returns_error_on_misaligned_start_address() {
         let mut backend = Rp2040Backend::<TEST_FLASH_ONE_BLOCK_PAGE>::new_for_tests(0).unwrap_or_else(|_| unreachable!());
-        backend.data_storage_start_address = 1;
+        backend.region.start = 1;
         let init_result = backend.init([1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE]);
         assert!(matches!(init_result, Err(StorageError::InvalidConfiguration)));
     }
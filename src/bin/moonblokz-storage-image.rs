@@ -0,0 +1,278 @@
+/*! Host provisioning tool that builds ready-to-flash RP2040 storage images.
+
+The output matches the flash contents produced by `Rp2040Backend` after
+`init`, optional `set_chain_configuration` and optional `save_block` calls, so
+devices boot already initialized.
+
+Usage:
+`moonblokz-storage-image --start <addr> --private-key <hex> --node-id <id> [options]`
+
+Options:
+- `--flash-size <bytes>`: total flash size (default: 2 MiB).
+- `--init-params <hex>`: init params, zero-padded to `INIT_PARAMS_SIZE` bytes.
+- `--chain-config <file>`: raw chain-configuration block bytes.
+- `--block <index>=<file>`: raw block bytes pre-seeded at `index` (repeatable).
+- `--replicas <count>`: control-plane replica count (default: 3).
+- `--placement <consecutive|spread|start-and-end>`: control-plane replica placement (default: consecutive).
+- `--sequence-index <pages>`: sequence index pages (default: none).
+- `--kv-pages <pages>`: key-value store pages, even and at least 4 (default: none).
+- `--counters <count>`: monotonic counters (default: none).
+- `--slot-meta <pages>`: slot metadata pages (default: none).
+- `--key-secret <text>`: wrap the private key with a key derived from this secret
+  (feature `key-encryption`).
+- `--unique-id <hex>`: device flash unique id bound into the wrapping key
+  (feature `key-encryption`; with or without `--key-secret`).
+- `--bin <file>`: raw region output, to be programmed at `--start`.
+- `--uf2 <file>`: UF2 output with RP2040 family id.
+
+Numbers accept decimal or `0x`-prefixed hex.
+*/
+
+use moonblokz_chain_types::Block;
+use moonblokz_crypto::PRIVATE_KEY_SIZE;
+use moonblokz_storage::backend_rp2040::RP2040_DEFAULT_FLASH_SIZE;
+use moonblokz_storage::rp2040_image::{Rp2040ImageBuilder, UF2_BLOCK_SIZE, write_uf2};
+use moonblokz_storage::{ControlPlaneLayout, ControlPlanePlacement, INIT_PARAMS_SIZE, StorageIndex};
+#[cfg(feature = "key-encryption")]
+use moonblokz_storage::KeyProtection;
+use std::process::ExitCode;
+
+struct Options {
+    start: usize,
+    flash_size: usize,
+    private_key: [u8; PRIVATE_KEY_SIZE],
+    node_id: u32,
+    init_params: [u8; INIT_PARAMS_SIZE],
+    chain_config: Option<String>,
+    blocks: Vec<(StorageIndex, String)>,
+    layout: ControlPlaneLayout,
+    sequence_index_pages: usize,
+    kv_pages: usize,
+    counters: usize,
+    slot_meta_pages: usize,
+    key_secret: Option<String>,
+    unique_id: Option<Vec<u8>>,
+    bin_output: Option<String>,
+    uf2_output: Option<String>,
+}
+
+fn parse_number(value: &str) -> Result<usize, String> {
+    let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|_| format!("invalid number: {}", value))
+}
+
+fn parse_hex(value: &str, out: &mut [u8], exact: bool) -> Result<(), String> {
+    if value.len() % 2 != 0 || value.len() / 2 > out.len() || (exact && value.len() / 2 != out.len()) {
+        return Err(format!("hex value must encode {} bytes: {}", out.len(), value));
+    }
+    for (index, chunk) in value.as_bytes().chunks(2).enumerate() {
+        let text = std::str::from_utf8(chunk).map_err(|_| format!("invalid hex: {}", value))?;
+        out[index] = u8::from_str_radix(text, 16).map_err(|_| format!("invalid hex: {}", value))?;
+    }
+    Ok(())
+}
+
+fn parse_placement(value: &str) -> Result<ControlPlanePlacement, String> {
+    match value {
+        "consecutive" => Ok(ControlPlanePlacement::Consecutive),
+        "spread" => Ok(ControlPlanePlacement::Spread),
+        "start-and-end" => Ok(ControlPlanePlacement::StartAndEnd),
+        _ => Err(format!("unknown placement: {}", value)),
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut start = None;
+    let mut flash_size = RP2040_DEFAULT_FLASH_SIZE;
+    let mut private_key = None;
+    let mut node_id = None;
+    let mut init_params = [0u8; INIT_PARAMS_SIZE];
+    let mut chain_config = None;
+    let mut blocks = Vec::new();
+    let mut layout = ControlPlaneLayout::DEFAULT;
+    let mut sequence_index_pages = 0usize;
+    let mut kv_pages = 0usize;
+    let mut counters = 0usize;
+    let mut slot_meta_pages = 0usize;
+    let mut key_secret = None;
+    let mut unique_id = None;
+    let mut bin_output = None;
+    let mut uf2_output = None;
+
+    let mut index = 1usize;
+    while index < args.len() {
+        let flag = args[index].as_str();
+        let value = args
+            .get(index + 1)
+            .ok_or_else(|| format!("missing value for {}", flag))?;
+        match flag {
+            "--start" => start = Some(parse_number(value)?),
+            "--flash-size" => flash_size = parse_number(value)?,
+            "--private-key" => {
+                let mut key = [0u8; PRIVATE_KEY_SIZE];
+                parse_hex(value, &mut key, true)?;
+                private_key = Some(key);
+            }
+            "--node-id" => {
+                let id = parse_number(value)?;
+                node_id = Some(u32::try_from(id).map_err(|_| format!("node id out of range: {}", value))?);
+            }
+            "--init-params" => parse_hex(value, &mut init_params, false)?,
+            "--chain-config" => chain_config = Some(value.clone()),
+            "--block" => {
+                let (slot, path) = value
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected <index>=<file>: {}", value))?;
+                let slot = StorageIndex::try_from(parse_number(slot)?)
+                    .map_err(|_| format!("storage index out of range: {}", slot))?;
+                blocks.push((slot, path.to_string()));
+            }
+            "--replicas" => layout.replica_count = parse_number(value)?,
+            "--placement" => layout.placement = parse_placement(value)?,
+            "--sequence-index" => sequence_index_pages = parse_number(value)?,
+            "--kv-pages" => kv_pages = parse_number(value)?,
+            "--counters" => counters = parse_number(value)?,
+            "--slot-meta" => slot_meta_pages = parse_number(value)?,
+            "--key-secret" => key_secret = Some(value.clone()),
+            "--unique-id" => {
+                let mut id = vec![0u8; value.len() / 2];
+                parse_hex(value, &mut id, true)?;
+                unique_id = Some(id);
+            }
+            "--bin" => bin_output = Some(value.clone()),
+            "--uf2" => uf2_output = Some(value.clone()),
+            _ => return Err(format!("unknown option: {}", flag)),
+        }
+        index += 2;
+    }
+
+    if bin_output.is_none() && uf2_output.is_none() {
+        return Err("at least one of --bin or --uf2 is required".to_string());
+    }
+
+    Ok(Options {
+        start: start.ok_or("--start is required")?,
+        flash_size,
+        private_key: private_key.ok_or("--private-key is required")?,
+        node_id: node_id.ok_or("--node-id is required")?,
+        init_params,
+        chain_config,
+        blocks,
+        layout,
+        sequence_index_pages,
+        kv_pages,
+        counters,
+        slot_meta_pages,
+        key_secret,
+        unique_id,
+        bin_output,
+        uf2_output,
+    })
+}
+
+fn read_block(path: &str) -> Result<Block, String> {
+    let bytes = std::fs::read(path).map_err(|err| format!("failed to read {}: {}", path, err))?;
+    Block::from_bytes(&bytes).map_err(|_| format!("{} is not a valid block", path))
+}
+
+#[cfg(feature = "key-encryption")]
+fn wrap_private_key<'a>(
+    builder: Rp2040ImageBuilder<'a>,
+    options: &Options,
+) -> Result<Rp2040ImageBuilder<'a>, String> {
+    let secret = options.key_secret.as_deref().unwrap_or("");
+    let protection = KeyProtection::from_secret(secret.as_bytes(), options.unique_id.as_deref())
+        .map_err(|_| "key secret or unique id is empty or too long".to_string())?;
+    builder
+        .with_key_protection(protection)
+        .map_err(|_| "failed to wrap the private key".to_string())
+}
+
+#[cfg(not(feature = "key-encryption"))]
+fn wrap_private_key<'a>(
+    _builder: Rp2040ImageBuilder<'a>,
+    _options: &Options,
+) -> Result<Rp2040ImageBuilder<'a>, String> {
+    Err("--key-secret and --unique-id require the key-encryption feature".to_string())
+}
+
+fn run(options: Options) -> Result<(), String> {
+    if options.start >= options.flash_size {
+        return Err("--start must be below --flash-size".to_string());
+    }
+
+    let mut region = vec![0u8; options.flash_size - options.start];
+    let mut builder = Rp2040ImageBuilder::new(
+        &mut region,
+        options.start,
+        options.private_key,
+        options.node_id,
+        options.init_params,
+    )
+    .map_err(|_| "storage region must be page aligned and hold the control plane".to_string())?;
+    builder = builder
+        .with_control_plane_layout(options.layout)
+        .and_then(|builder| builder.with_sequence_index(options.sequence_index_pages))
+        .and_then(|builder| builder.with_kv_store(options.kv_pages))
+        .and_then(|builder| builder.with_counters(options.counters))
+        .and_then(|builder| builder.with_slot_meta(options.slot_meta_pages))
+        .map_err(|_| "layout options leave no valid block area in the storage region".to_string())?;
+    if options.key_secret.is_some() || options.unique_id.is_some() {
+        builder = wrap_private_key(builder, &options)?;
+    }
+
+    if let Some(path) = &options.chain_config {
+        let block = read_block(path)?;
+        builder
+            .set_chain_configuration(&block)
+            .map_err(|_| "failed to store chain configuration".to_string())?;
+    }
+
+    for (storage_index, path) in &options.blocks {
+        let block = read_block(path)?;
+        builder.save_block(*storage_index, &block).map_err(|_| {
+            format!(
+                "storage index {} is out of range (max {})",
+                storage_index,
+                builder.max_storage_slots()
+            )
+        })?;
+    }
+
+    if let Some(path) = &options.bin_output {
+        std::fs::write(path, builder.as_bytes()).map_err(|err| format!("failed to write {}: {}", path, err))?;
+    }
+
+    if let Some(path) = &options.uf2_output {
+        let mut uf2 = Vec::with_capacity(builder.as_bytes().len() * 2);
+        write_uf2(builder.as_bytes(), options.start, |block: &[u8; UF2_BLOCK_SIZE]| {
+            uf2.extend_from_slice(block);
+            Ok(())
+        })
+        .map_err(|_| "failed to encode UF2".to_string())?;
+        std::fs::write(path, &uf2).map_err(|err| format!("failed to write {}: {}", path, err))?;
+    }
+
+    println!(
+        "built {} byte storage image for flash offset {:#x} ({} slots)",
+        builder.as_bytes().len(),
+        options.start,
+        builder.max_storage_slots()
+    );
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().collect();
+    let result = parse_options(&args).and_then(run);
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,393 @@
+/*! Host inspection tool for raw RP2040 flash dumps.
+
+Decodes the `backend-rp2040` storage layout from a flash image (for example
+one saved with `picotool save --all`) and prints control-plane replica status
+and occupied block slots.
+
+Usage:
+- `moonblokz-storage-inspect <flash-dump.bin> <data_storage_start_address>`
+- `moonblokz-storage-inspect fsck [--repair --out <repaired.bin>] <flash-dump.bin> <data_storage_start_address>`
+
+The start address is a flash offset, given in decimal or `0x`-prefixed hex.
+The `fsck` command prints one JSON object per finding followed by a summary
+object; with `--repair` the repaired image is written to `--out`.
+*/
+
+use moonblokz_chain_types::{HASH_SIZE, MAX_BLOCK_SIZE, calculate_hash};
+use moonblokz_storage::backend_rp2040::{
+    BLOCKS_PER_PAGE, CONTROL_PLANE_ENTRY_SIZE, FLASH_PAGE_SIZE, RP2040_DEFAULT_FLASH_SIZE,
+    Rp2040Backend, Rp2040Region, SLOT_HASH_OFFSET, SLOT_SIZE_BYTES, SUPERBLOCK_OFFSET,
+    compiled_superblock,
+};
+use moonblokz_storage::counter::COUNTER_PAGES;
+use moonblokz_storage::superblock::{SUPERBLOCK_SIZE, Superblock};
+use moonblokz_storage::{
+    CONTROL_PLANE_VERSION, ControlPlaneLayout, FsckFinding, FsckIssue, FsckOptions, StorageError,
+};
+use std::process::ExitCode;
+
+type Layout = Rp2040Backend<RP2040_DEFAULT_FLASH_SIZE>;
+
+fn parse_address(value: &str) -> Option<usize> {
+    match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+fn error_name(err: &StorageError) -> &'static str {
+    match err {
+        StorageError::InvalidIndex => "invalid-index",
+        StorageError::BlockAbsent => "absent",
+        StorageError::IntegrityFailure => "integrity-failure",
+        StorageError::ControlPlaneUninitialized => "erased",
+        StorageError::ChainConfigurationAlreadySet => "chain-configuration-already-set",
+        StorageError::ControlPlaneCorrupted => "corrupted",
+        StorageError::ControlPlaneIncompatible => "incompatible",
+        StorageError::InvalidConfiguration => "invalid-configuration",
+        StorageError::KeyUnwrapFailed => "wrapped-key",
+        StorageError::LayoutMismatch { .. } => "layout-mismatch",
+        StorageError::RelocationInProgress => "relocation-in-progress",
+        StorageError::InitInProgress => "init-in-progress",
+        StorageError::SlotOccupied => "slot-occupied",
+        StorageError::Conflict { .. } => "conflict",
+        StorageError::KvInvalidEntry => "kv-invalid-entry",
+        StorageError::KvFull => "kv-full",
+        StorageError::BackendIo { .. } => "backend-io",
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn read_superblock(image: &[u8], page_address: usize) -> Result<Option<Superblock>, StorageError> {
+    let mut stored = [0u8; SUPERBLOCK_SIZE];
+    stored.copy_from_slice(&image[page_address + SUPERBLOCK_OFFSET..page_address + FLASH_PAGE_SIZE]);
+    Superblock::decode(&stored)
+}
+
+/// Control-plane layout recorded by replica 0, which always sits at the start
+/// of the region; images without a readable superblock use the default.
+fn stored_layout(image: &[u8], start: usize) -> ControlPlaneLayout {
+    match read_superblock(image, start) {
+        Ok(Some(superblock)) => superblock.control_plane_layout().unwrap_or_default(),
+        _ => ControlPlaneLayout::DEFAULT,
+    }
+}
+
+fn stored_sequence_index_pages(image: &[u8], start: usize) -> usize {
+    match read_superblock(image, start) {
+        Ok(Some(superblock)) => superblock.sequence_index_pages as usize,
+        _ => 0,
+    }
+}
+
+fn stored_kv_pages(image: &[u8], start: usize) -> usize {
+    match read_superblock(image, start) {
+        Ok(Some(superblock)) => superblock.kv_pages as usize,
+        _ => 0,
+    }
+}
+
+fn stored_counters(image: &[u8], start: usize) -> usize {
+    match read_superblock(image, start) {
+        Ok(Some(superblock)) => superblock.counters as usize,
+        _ => 0,
+    }
+}
+
+fn stored_slot_meta_pages(image: &[u8], start: usize) -> usize {
+    match read_superblock(image, start) {
+        Ok(Some(superblock)) => superblock.slot_meta_pages as usize,
+        _ => 0,
+    }
+}
+
+/// Region covering the whole image from `start`, with the layout options the
+/// stored superblock records; both inspect and fsck read the image through it.
+fn stored_region(image: &[u8], start: usize) -> Rp2040Region {
+    Rp2040Region::new("inspect", start, image.len() / FLASH_PAGE_SIZE * FLASH_PAGE_SIZE)
+        .with_control_plane_layout(stored_layout(image, start))
+        .with_sequence_index(stored_sequence_index_pages(image, start))
+        .with_kv_store(stored_kv_pages(image, start))
+        .with_counters(stored_counters(image, start))
+        .with_slot_meta(stored_slot_meta_pages(image, start))
+}
+
+fn superblock_status(image: &[u8], page_address: usize, region: &Rp2040Region) -> String {
+    match read_superblock(image, page_address) {
+        Ok(None) => "missing".to_string(),
+        Ok(Some(superblock)) => match superblock.check(&compiled_superblock(region)) {
+            Ok(()) => "ok".to_string(),
+            Err(StorageError::LayoutMismatch {
+                field,
+                stored,
+                expected,
+            }) => format!("mismatch({} stored={} expected={})", field.name(), stored, expected),
+            Err(err) => error_name(&err).to_string(),
+        },
+        Err(err) => error_name(&err).to_string(),
+    }
+}
+
+fn print_control_plane(image: &[u8], region: &Rp2040Region) {
+    let layout = region.control_plane;
+    let page_count = region.layout_page_count();
+    println!(
+        "control plane (version {} expected, {} replicas, {:?} placement):",
+        CONTROL_PLANE_VERSION, layout.replica_count, layout.placement
+    );
+    for replica_index in 0..layout.replica_count {
+        let page_address = region.start + layout.replica_unit(page_count, replica_index) * FLASH_PAGE_SIZE;
+        let mut entry = [0u8; CONTROL_PLANE_ENTRY_SIZE];
+        entry.copy_from_slice(&image[page_address..page_address + CONTROL_PLANE_ENTRY_SIZE]);
+
+        let header = Layout::control_record_header(&entry);
+        let status = match Layout::deserialize_control_record(&entry) {
+            Ok(record) => format!(
+                "valid node_id={} chain_configuration={}",
+                record.own_node_id,
+                if record.chain_configuration.is_some() { "set" } else { "unset" }
+            ),
+            Err(err) => error_name(&err).to_string(),
+        };
+        println!(
+            "  replica {} @ {:#08x}: version={} crc stored={:#010x} computed={:#010x} crc_ok={} status={} superblock={}",
+            replica_index,
+            page_address,
+            header.version,
+            header.stored_crc32,
+            header.computed_crc32,
+            header.stored_crc32 == header.computed_crc32,
+            status,
+            superblock_status(image, page_address, region)
+        );
+    }
+}
+
+fn print_slots(image: &[u8], region: &Rp2040Region) -> usize {
+    let layout = region.control_plane;
+    let region_pages = region.layout_page_count();
+    let page_count = region.block_page_count();
+    let mut mismatches = 0usize;
+
+    println!("block slots ({} pages, {} slots per page):", page_count, BLOCKS_PER_PAGE);
+    for page_index in 0..page_count {
+        let page_address = region.start + layout.data_unit(region_pages, page_index) * FLASH_PAGE_SIZE;
+        for slot_index in 0..BLOCKS_PER_PAGE {
+            let slot_address = page_address + slot_index * SLOT_SIZE_BYTES;
+            let slot = &image[slot_address..slot_address + SLOT_SIZE_BYTES];
+            let storage_index = page_index * BLOCKS_PER_PAGE + slot_index;
+
+            match Layout::decode_slot_block(slot) {
+                Err(StorageError::BlockAbsent) => {}
+                Ok(block) => {
+                    let header = block.header();
+                    println!(
+                        "  slot {} @ {:#08x}: version={} sequence={} creator={} payload_type={} hash={}",
+                        storage_index,
+                        slot_address,
+                        header.version,
+                        header.sequence,
+                        header.creator,
+                        header.payload_type,
+                        hex(&slot[SLOT_HASH_OFFSET..SLOT_HASH_OFFSET + HASH_SIZE])
+                    );
+                }
+                Err(err) => {
+                    mismatches += 1;
+                    let computed = calculate_hash(&slot[..MAX_BLOCK_SIZE]);
+                    let hash_ok = computed[..] == slot[SLOT_HASH_OFFSET..SLOT_HASH_OFFSET + HASH_SIZE];
+                    println!(
+                        "  slot {} @ {:#08x}: {} hash_ok={} stored={} computed={}",
+                        storage_index,
+                        slot_address,
+                        error_name(&err),
+                        hash_ok,
+                        hex(&slot[SLOT_HASH_OFFSET..SLOT_HASH_OFFSET + HASH_SIZE]),
+                        hex(&computed)
+                    );
+                }
+            }
+        }
+    }
+
+    mismatches
+}
+
+fn finding_json(finding: &FsckFinding) -> String {
+    let location = match finding.issue {
+        FsckIssue::ReplicaErased { replica_index }
+        | FsckIssue::ReplicaCorrupted { replica_index }
+        | FsckIssue::ReplicaIncompatible { replica_index }
+        | FsckIssue::ReplicaDisagreement { replica_index }
+        | FsckIssue::ReplicaPaddingAnomaly { replica_index }
+        | FsckIssue::SuperblockMissing { replica_index } => {
+            format!("\"replica_index\":{}", replica_index)
+        }
+        FsckIssue::SlotIntegrityFailure { storage_index }
+        | FsckIssue::SlotHalfWritten { storage_index }
+        | FsckIssue::SlotPaddingAnomaly { storage_index } => {
+            format!("\"storage_index\":{}", storage_index)
+        }
+        FsckIssue::PagePaddingAnomaly { page_address } => {
+            format!("\"page_address\":{}", page_address)
+        }
+    };
+    format!(
+        "{{\"issue\":\"{}\",{},\"repaired\":{}}}",
+        finding.issue.code(),
+        location,
+        finding.repaired
+    )
+}
+
+fn run_fsck<const FLASH_SIZE: usize>(
+    image: &[u8],
+    start: usize,
+    repair: bool,
+    out: Option<&str>,
+) -> Result<bool, String> {
+    let regions: &'static [Rp2040Region] = Box::leak(Box::new([stored_region(image, start)]));
+    let mut backend = Rp2040Backend::<FLASH_SIZE>::from_flash_image(start, image)
+        .and_then(|backend| backend.with_partitions(regions))
+        .map_err(|err| format!("cannot open image: {}", error_name(&err)))?;
+    let report = backend
+        .fsck(FsckOptions { repair }, &mut |finding| println!("{}", finding_json(finding)))
+        .map_err(|err| format!("fsck failed: {}", error_name(&err)))?;
+    println!(
+        "{{\"summary\":{{\"replicas_checked\":{},\"replicas_valid\":{},\"slots_checked\":{},\"slots_occupied\":{},\"issues_found\":{},\"issues_repaired\":{}}}}}",
+        report.replicas_checked,
+        report.replicas_valid,
+        report.slots_checked,
+        report.slots_occupied,
+        report.issues_found,
+        report.issues_repaired
+    );
+
+    if let Some(path) = out {
+        std::fs::write(path, &backend.flash_image()[..image.len()])
+            .map_err(|err| format!("failed to write {}: {}", path, err))?;
+    }
+    Ok(report.issues_found == report.issues_repaired)
+}
+
+fn fsck_main(args: &[String]) -> ExitCode {
+    let mut repair = false;
+    let mut out = None;
+    let mut positional = Vec::new();
+    let mut index = 0usize;
+    while index < args.len() {
+        match args[index].as_str() {
+            "--repair" => repair = true,
+            "--out" => {
+                index += 1;
+                out = args.get(index).cloned();
+            }
+            value => positional.push(value.to_string()),
+        }
+        index += 1;
+    }
+    if positional.len() != 2 || (repair && out.is_none()) {
+        eprintln!("usage: fsck [--repair --out <repaired.bin>] <flash-dump.bin> <data_storage_start_address>");
+        return ExitCode::from(2);
+    }
+
+    let (image, start) = match load_image(&positional[0], &positional[1]) {
+        Ok(value) => value,
+        Err(code) => return code,
+    };
+
+    let out = out.as_deref();
+    let result = match image.len() {
+        0..=0x20_0000 => run_fsck::<0x20_0000>(&image, start, repair, out),
+        0x20_0001..=0x40_0000 => run_fsck::<0x40_0000>(&image, start, repair, out),
+        0x40_0001..=0x80_0000 => run_fsck::<0x80_0000>(&image, start, repair, out),
+        0x80_0001..=0x100_0000 => run_fsck::<0x100_0000>(&image, start, repair, out),
+        _ => Err("images larger than 16 MiB are not supported".to_string()),
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn load_image(path: &str, address: &str) -> Result<(Vec<u8>, usize), ExitCode> {
+    let image = match std::fs::read(path) {
+        Ok(value) => value,
+        Err(err) => {
+            eprintln!("failed to read {}: {}", path, err);
+            return Err(ExitCode::from(2));
+        }
+    };
+    let start = match parse_address(address) {
+        Some(value) => value,
+        None => {
+            eprintln!("invalid data_storage_start_address: {}", address);
+            return Err(ExitCode::from(2));
+        }
+    };
+    if start % FLASH_PAGE_SIZE != 0 || start + FLASH_PAGE_SIZE > image.len() {
+        eprintln!(
+            "data_storage_start_address {:#x} must be page aligned and leave room for the control plane in a {} byte image",
+            start,
+            image.len()
+        );
+        return Err(ExitCode::from(2));
+    }
+    Ok((image, start))
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("fsck") {
+        return fsck_main(&args[2..]);
+    }
+    if args.len() != 3 {
+        eprintln!("usage: {} <flash-dump.bin> <data_storage_start_address>", args[0]);
+        eprintln!("       {} fsck [--repair --out <repaired.bin>] <flash-dump.bin> <data_storage_start_address>", args[0]);
+        return ExitCode::from(2);
+    }
+
+    let (image, start) = match load_image(&args[1], &args[2]) {
+        Ok(value) => value,
+        Err(code) => return code,
+    };
+
+    println!("image: {} bytes, storage starts at {:#x}", image.len(), start);
+    let region = stored_region(&image, start);
+    if region.block_page_count() == 0 {
+        eprintln!(
+            "invalid layout: {} page(s) left for {} control-plane replicas and block pages after the tail areas",
+            region.layout_page_count(),
+            region.control_plane.replica_count
+        );
+        return ExitCode::from(2);
+    }
+    print_control_plane(&image, &region);
+    if region.sequence_index_pages > 0 {
+        println!("sequence index: {} page(s) at the end of the region", region.sequence_index_pages);
+    }
+    if region.kv_pages > 0 {
+        println!("key-value store: {} page(s), two mirrored copies, before the sequence index", region.kv_pages);
+    }
+    if region.counters > 0 {
+        println!("counters: {} ({} pages each) before the key-value store", region.counters, COUNTER_PAGES);
+    }
+    if region.slot_meta_pages > 0 {
+        println!("slot metadata: {} page(s) before the counters", region.slot_meta_pages);
+    }
+    let mismatches = print_slots(&image, &region);
+
+    if mismatches > 0 {
+        println!("{} slot(s) failed verification", mismatches);
+        return ExitCode::from(1);
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,92 @@
+/*! Build-script helper for the RP2040 storage linker fragment.
+
+Enabled by the `build-script` feature, which links `std`. Add the crate as a
+build dependency with that feature and call `emit_linker_script` from
+`build.rs`; `memory.x` then only has to declare the `STORAGE` region and
+`INCLUDE moonblokz-storage.x`.
+
+The emitted script is `linker/moonblokz-storage.x` followed by a link-time
+check that `STORAGE` holds every control-plane replica of the configured
+layout plus at least one block page.
+*/
+
+use crate::control_plane::ControlPlaneLayout;
+use std::format;
+use std::io;
+use std::path::PathBuf;
+use std::println;
+use std::string::String;
+
+/// File name of the emitted fragment, as referenced by `INCLUDE` in `memory.x`.
+pub const LINKER_SCRIPT_NAME: &str = "moonblokz-storage.x";
+
+const LINKER_FRAGMENT: &str = include_str!("../linker/moonblokz-storage.x");
+const STORAGE_PAGE_SIZE: usize = 4096;
+
+/// Returns the linker fragment for a region using `layout`.
+///
+/// Parameters:
+/// - `layout`: control-plane layout the firmware configures on the backend.
+///
+/// Example:
+/// ```
+/// use moonblokz_storage::build_script::linker_script;
+/// use moonblokz_storage::control_plane::ControlPlaneLayout;
+///
+/// let script = linker_script(&ControlPlaneLayout::DEFAULT);
+/// assert!(script.contains("__storage_start"));
+/// ```
+pub fn linker_script(layout: &ControlPlaneLayout) -> String {
+    format!(
+        "{LINKER_FRAGMENT}\nASSERT(LENGTH(STORAGE) >= {} * {STORAGE_PAGE_SIZE}, \"moonblokz-storage: STORAGE must hold {} \
+         control-plane replicas and at least one block page\");\n",
+        layout.replica_count + 1,
+        layout.replica_count,
+    )
+}
+
+/// Writes the linker fragment for `layout` into `OUT_DIR` and adds `OUT_DIR`
+/// to the linker search path.
+///
+/// Call this from `build.rs` only; it reads `OUT_DIR` and prints cargo
+/// directives on stdout.
+///
+/// Errors:
+/// - `io::ErrorKind::NotFound`: `OUT_DIR` is not set (not running as a build script).
+/// - any error writing the file.
+///
+/// Parameters:
+/// - `layout`: control-plane layout the firmware configures on the backend.
+///
+/// Example:
+/// ```ignore
+/// // build.rs
+/// use moonblokz_storage::control_plane::ControlPlaneLayout;
+///
+/// fn main() {
+///     moonblokz_storage::build_script::emit_linker_script(&ControlPlaneLayout::DEFAULT)
+///         .expect("failed to emit moonblokz-storage.x");
+/// }
+/// ```
+pub fn emit_linker_script(layout: &ControlPlaneLayout) -> io::Result<()> {
+    let out_dir = std::env::var_os("OUT_DIR")
+        .map(PathBuf::from)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "OUT_DIR is not set"))?;
+    std::fs::write(out_dir.join(LINKER_SCRIPT_NAME), linker_script(layout))?;
+    println!("cargo:rustc-link-search={}", out_dir.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control_plane::ControlPlanePlacement;
+
+    #[test]
+    fn length_check_follows_replica_count() {
+        let script = linker_script(&ControlPlaneLayout::new(5, ControlPlanePlacement::Spread));
+        assert!(script.starts_with(LINKER_FRAGMENT));
+        assert!(script.contains("ASSERT(LENGTH(STORAGE) >= 6 * 4096"));
+        assert!(!LINKER_FRAGMENT.contains("LENGTH(STORAGE) >="));
+    }
+}
@@ -1,39 +1,81 @@
-/*! Backend conformance tests that validate shared storage trait semantics. */
+/*! Backend conformance tests that validate shared storage trait semantics.
+
+Every test runs once per control-plane layout in `REPLICA_COUNTS` x
+`PLACEMENTS`, so `load_control_data` is exercised for each supported count.
+*/
 
 #[cfg(test)]
 mod tests {
-    use crate::{MoonblokzStorage, StorageError, StorageTrait};
+    use crate::sequence_index::block_sequence;
+    use crate::{
+        ControlPlaneData, ControlPlaneLayout, ControlPlanePlacement, MoonblokzStorage, StorageError,
+        StorageIndex, StorageTrait, block_hash,
+    };
     use moonblokz_chain_types::{Block, MAX_BLOCK_SIZE};
     use moonblokz_crypto::PRIVATE_KEY_SIZE;
 
+    const REPLICA_COUNTS: [usize; 3] = [1, 3, 5];
+    const PLACEMENTS: [ControlPlanePlacement; 3] = [
+        ControlPlanePlacement::Consecutive,
+        ControlPlanePlacement::Spread,
+        ControlPlanePlacement::StartAndEnd,
+    ];
+
     #[cfg(feature = "backend-memory")]
-    const TEST_STORAGE_SIZE: usize = 8 * MAX_BLOCK_SIZE;
+    const TEST_STORAGE_SIZE: usize = 12 * MAX_BLOCK_SIZE;
     #[cfg(feature = "backend-rp2040")]
-    const TEST_STORAGE_SIZE: usize = (crate::CONTROL_PLANE_COUNT + 2) * 4096;
-    #[cfg(feature = "backend-rp2040")]
-    const TEST_INVALID_INDEX: u32 = ((TEST_STORAGE_SIZE / crate::backend_rp2040::FLASH_PAGE_SIZE)
-        * crate::backend_rp2040::BLOCKS_PER_PAGE) as u32;
+    const TEST_STORAGE_SIZE: usize = (5 + 2) * 4096;
     #[cfg(feature = "backend-memory")]
     const TEST_CONTROL_PLANE_ENTRY_SIZE: usize =
-        1 + 1 + PRIVATE_KEY_SIZE + 4 + 1 + crate::INIT_PARAMS_SIZE + 2 + MAX_BLOCK_SIZE + 4;
-    #[cfg(feature = "backend-memory")]
-    const TEST_CONTROL_PLANE_RESERVED_BYTES: usize =
-        crate::CONTROL_PLANE_COUNT * TEST_CONTROL_PLANE_ENTRY_SIZE;
+        1 + 1 + 1 + 12 + PRIVATE_KEY_SIZE + 16 + 4 + 1 + crate::INIT_PARAMS_SIZE + 2 + MAX_BLOCK_SIZE + 4;
+
     #[cfg(feature = "backend-memory")]
-    const TEST_INVALID_INDEX: u32 = if TEST_STORAGE_SIZE > TEST_CONTROL_PLANE_RESERVED_BYTES {
-        ((TEST_STORAGE_SIZE - TEST_CONTROL_PLANE_RESERVED_BYTES) / MAX_BLOCK_SIZE) as u32
-    } else {
-        0
-    };
+    fn invalid_index(replica_count: usize) -> u32 {
+        let reserved_bytes = replica_count * TEST_CONTROL_PLANE_ENTRY_SIZE;
+        ((TEST_STORAGE_SIZE - reserved_bytes) / MAX_BLOCK_SIZE) as u32
+    }
+
+    #[cfg(feature = "backend-rp2040")]
+    fn invalid_index(replica_count: usize) -> u32 {
+        let page_count = TEST_STORAGE_SIZE / crate::backend_rp2040::FLASH_PAGE_SIZE;
+        ((page_count - replica_count) * crate::backend_rp2040::BLOCKS_PER_PAGE) as u32
+    }
 
     #[cfg(feature = "backend-memory")]
-    fn new_backend() -> MoonblokzStorage<TEST_STORAGE_SIZE> {
+    fn new_backend(layout: ControlPlaneLayout) -> MoonblokzStorage<TEST_STORAGE_SIZE> {
         MoonblokzStorage::<TEST_STORAGE_SIZE>::new()
+            .with_control_plane_layout(layout)
+            .unwrap_or_else(|_| unreachable!())
     }
 
     #[cfg(feature = "backend-rp2040")]
-    fn new_backend() -> MoonblokzStorage<TEST_STORAGE_SIZE> {
-        MoonblokzStorage::<TEST_STORAGE_SIZE>::new_for_tests(0).unwrap_or_else(|_| unreachable!())
+    fn new_backend(layout: ControlPlaneLayout) -> MoonblokzStorage<TEST_STORAGE_SIZE> {
+        MoonblokzStorage::<TEST_STORAGE_SIZE>::new_for_tests(0)
+            .and_then(|backend| backend.with_control_plane_layout(layout))
+            .unwrap_or_else(|_| unreachable!())
+    }
+
+    /// Runs `check` against a freshly initialized backend for every layout,
+    /// passing the first invalid storage index of that layout.
+    fn for_each_layout(mut check: impl FnMut(&mut MoonblokzStorage<TEST_STORAGE_SIZE>, u32)) {
+        let mut count_index = 0usize;
+        while count_index < REPLICA_COUNTS.len() {
+            let mut placement_index = 0usize;
+            while placement_index < PLACEMENTS.len() {
+                let replica_count = REPLICA_COUNTS[count_index];
+                let mut storage = new_backend(ControlPlaneLayout::new(replica_count, PLACEMENTS[placement_index]));
+                assert!(storage
+                    .init(
+                        [1u8; PRIVATE_KEY_SIZE],
+                        1,
+                        [0u8; crate::INIT_PARAMS_SIZE],
+                    )
+                    .is_ok());
+                check(&mut storage, invalid_index(replica_count));
+                placement_index += 1;
+            }
+            count_index += 1;
+        }
     }
 
     fn block_from_marker(marker: u8) -> Block {
@@ -50,88 +92,160 @@ mod tests {
 
     #[test]
     fn conformance_save_read_round_trip_returns_exact_saved_block() {
-        let mut storage = new_backend();
-        assert!(storage
-            .init(
-                [1u8; PRIVATE_KEY_SIZE],
-                1,
-                [0u8; crate::INIT_PARAMS_SIZE],
-            )
-            .is_ok());
-        let block = block_from_marker(41);
-        assert!(storage.save_block(0, &block).is_ok());
-
-        let read_result = storage.read_block(0);
-        assert!(read_result.is_ok());
-        let read_block = match read_result {
-            Ok(value) => value,
-            Err(_) => return,
-        };
-        assert_eq!(read_block.as_bytes(), block.as_bytes());
+        for_each_layout(|storage, _| {
+            let block = block_from_marker(41);
+            assert!(storage.save_block(0, &block).is_ok());
+
+            let read_result = storage.read_block(0);
+            assert!(read_result.is_ok());
+            let read_block = match read_result {
+                Ok(value) => value,
+                Err(_) => return,
+            };
+            assert_eq!(read_block.as_bytes(), block.as_bytes());
+        });
     }
 
     #[test]
     fn conformance_empty_slot_reports_block_absent() {
-        let mut storage = new_backend();
-        assert!(storage
-            .init(
-                [1u8; PRIVATE_KEY_SIZE],
-                1,
-                [0u8; crate::INIT_PARAMS_SIZE],
-            )
-            .is_ok());
-        assert!(matches!(
-            storage.read_block(0),
-            Err(StorageError::BlockAbsent)
-        ));
+        for_each_layout(|storage, _| {
+            assert!(matches!(
+                storage.read_block(0),
+                Err(StorageError::BlockAbsent)
+            ));
+        });
     }
 
     #[test]
     fn conformance_invalid_index_reports_invalid_index_for_read_and_save() {
-        let mut storage = new_backend();
-        assert!(storage
-            .init(
-                [1u8; PRIVATE_KEY_SIZE],
-                1,
-                [0u8; crate::INIT_PARAMS_SIZE],
-            )
-            .is_ok());
-        let block = block_from_marker(42);
-
-        assert!(matches!(
-            storage.read_block(TEST_INVALID_INDEX),
-            Err(StorageError::InvalidIndex)
-        ));
-        assert!(matches!(
-            storage.save_block(TEST_INVALID_INDEX, &block),
-            Err(StorageError::InvalidIndex)
-        ));
+        for_each_layout(|storage, invalid_index| {
+            let block = block_from_marker(42);
+
+            assert!(matches!(
+                storage.read_block(invalid_index),
+                Err(StorageError::InvalidIndex)
+            ));
+            assert!(matches!(
+                storage.save_block(invalid_index, &block),
+                Err(StorageError::InvalidIndex)
+            ));
+        });
     }
 
     #[test]
     fn conformance_startup_scan_returns_typed_outcomes_for_mixed_slots() {
-        let mut storage = new_backend();
-        assert!(storage
-            .init(
-                [1u8; PRIVATE_KEY_SIZE],
-                1,
-                [0u8; crate::INIT_PARAMS_SIZE],
-            )
-            .is_ok());
-        let block_a = block_from_marker(43);
-        let block_b = block_from_marker(44);
-        assert!(storage.save_block(1, &block_a).is_ok());
-        assert!(storage.save_block(3, &block_b).is_ok());
-
-        assert!(matches!(
-            storage.read_block(0),
-            Err(StorageError::BlockAbsent)
-        ));
-        assert!(matches!(storage.read_block(1), Ok(_)));
-        assert!(matches!(
-            storage.read_block(2),
-            Err(StorageError::BlockAbsent)
-        ));
-        assert!(matches!(storage.read_block(3), Ok(_)));
+        for_each_layout(|storage, _| {
+            let block_a = block_from_marker(43);
+            let block_b = block_from_marker(44);
+            assert!(storage.save_block(1, &block_a).is_ok());
+            assert!(storage.save_block(3, &block_b).is_ok());
+
+            assert!(matches!(
+                storage.read_block(0),
+                Err(StorageError::BlockAbsent)
+            ));
+            assert!(matches!(storage.read_block(1), Ok(_)));
+            assert!(matches!(
+                storage.read_block(2),
+                Err(StorageError::BlockAbsent)
+            ));
+            assert!(matches!(storage.read_block(3), Ok(_)));
+        });
+    }
+
+    #[test]
+    fn conformance_control_data_round_trips_with_chain_configuration_set_once() {
+        for_each_layout(|storage, _| {
+            let block = block_from_marker(45);
+            assert!(storage.save_block(0, &block).is_ok());
+            assert!(storage.set_chain_configuration(&block).is_ok());
+            assert!(matches!(
+                storage.set_chain_configuration(&block),
+                Err(StorageError::ChainConfigurationAlreadySet)
+            ));
+
+            let loaded = match storage.load_control_data() {
+                Ok(value) => value,
+                Err(_) => unreachable!(),
+            };
+            assert_eq!(loaded.private_key, [1u8; PRIVATE_KEY_SIZE]);
+            assert_eq!(loaded.own_node_id, 1);
+            assert!(loaded.chain_configuration.is_some());
+            assert!(storage.read_block(0).is_ok());
+        });
+    }
+
+    /// Forwards only the required `StorageTrait` methods, so `find_by_hash`
+    /// falls back to the trait's default scan.
+    struct DefaultLookup<'a>(&'a mut MoonblokzStorage<TEST_STORAGE_SIZE>);
+
+    impl StorageTrait for DefaultLookup<'_> {
+        fn init(
+            &mut self,
+            private_key: [u8; PRIVATE_KEY_SIZE],
+            own_node_id: u32,
+            init_params: [u8; crate::INIT_PARAMS_SIZE],
+        ) -> Result<(), StorageError> {
+            self.0.init(private_key, own_node_id, init_params)
+        }
+
+        fn save_block(&mut self, storage_index: StorageIndex, block: &Block) -> Result<(), StorageError> {
+            self.0.save_block(storage_index, block)
+        }
+
+        fn read_block(&self, storage_index: StorageIndex) -> Result<Block, StorageError> {
+            self.0.read_block(storage_index)
+        }
+
+        fn set_chain_configuration(&mut self, block: &Block) -> Result<(), StorageError> {
+            self.0.set_chain_configuration(block)
+        }
+
+        fn load_control_data(&mut self) -> Result<ControlPlaneData, StorageError> {
+            self.0.load_control_data()
+        }
+    }
+
+    #[test]
+    fn conformance_default_find_by_hash_agrees_with_the_backend() {
+        for_each_layout(|storage, invalid_index| {
+            let block = block_from_marker(46);
+            let last_index = invalid_index - 1;
+            assert!(storage.save_block(last_index, &block).is_ok());
+            let hash = block_hash(&block);
+            let missing = block_hash(&block_from_marker(47));
+
+            assert!(matches!(storage.find_by_hash(&hash), Ok(Some(index)) if index == last_index));
+            assert!(matches!(storage.find_by_hash(&missing), Ok(None)));
+            let fallback = DefaultLookup(storage);
+            assert!(matches!(fallback.find_by_hash(&hash), Ok(Some(index)) if index == last_index));
+            assert!(matches!(fallback.find_by_hash(&missing), Ok(None)));
+        });
+    }
+
+    #[test]
+    fn conformance_sequence_lookups_and_health_return_results() {
+        for_each_layout(|storage, invalid_index| {
+            let block = block_from_marker(48);
+            let sequence = block_sequence(&block);
+            let last_index = invalid_index - 1;
+            assert!(matches!(storage.max_sequence(), Ok(None)));
+            assert!(storage.save_block(last_index, &block).is_ok());
+
+            assert!(matches!(storage.find_by_sequence(sequence), Ok(Some(index)) if index == last_index));
+            assert!(matches!(storage.find_by_sequence(sequence + 1), Ok(None)));
+            assert!(matches!(storage.max_sequence(), Ok(Some(tip)) if tip == sequence));
+            let mut seen = 0usize;
+            assert!(storage
+                .range_by_sequence(0..u32::MAX, &mut |found, index| {
+                    assert_eq!((found, index), (sequence, last_index));
+                    seen += 1;
+                })
+                .is_ok());
+            assert_eq!(seen, 1);
+
+            let health = storage.control_plane_health(true);
+            assert!(matches!(health, Ok(ref report) if report.is_healthy() && !report.repaired));
+        });
     }
 }
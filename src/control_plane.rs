@@ -0,0 +1,1009 @@
+/*! Shared control-plane entry codec and private-key protection.
+
+Every backend stores the same control-plane entry layout; only the replica
+placement differs. Version 2 entries (all integers little-endian):
+
+`version:u8, private_key_size:u8, key_protection:u8, key_nonce:[u8;12],
+private_key:[u8;PRIVATE_KEY_SIZE], key_tag:[u8;16], own_node_id:u32,
+init_params_size:u8, init_params:[u8;INIT_PARAMS_SIZE], max_block_size:u16,
+chain_config_block:[u8;MAX_BLOCK_SIZE], crc32:u32`
+
+With `key_protection == 0` the private key is stored in plaintext and the
+nonce and tag are zero. With `key_protection == 1` the private key is sealed
+with ChaCha20-Poly1305 under a wrapping key derived by `KeyProtection`; the
+entry's version byte and node id are authenticated as associated data. A
+backend configured with a wrapping key rejects plaintext entries of the
+current version, so an attacker cannot downgrade the key by writing one; only
+the version 1 migration and an explicit `set_key_protection` turn a plaintext
+key into a wrapped one.
+
+Older entry versions are decoded by the migrations registered in `MIGRATIONS`
+and rewritten in the current layout on the next load. Version 1 entries (no
+protection byte, nonce or tag) are the only retired layout so far.
+
+Encoded entries and decode temporaries carry key material and are returned as
+`Zeroizing` buffers so they are wiped when dropped.
+*/
+
+use crate::{
+    CONTROL_PLANE_COUNT, CONTROL_PLANE_VERSION, ControlPlaneData, INIT_PARAMS_SIZE, MAX_CONTROL_PLANE_COUNT,
+    StorageError,
+};
+use moonblokz_chain_types::{Block, HASH_SIZE, MAX_BLOCK_SIZE};
+use moonblokz_crypto::PRIVATE_KEY_SIZE;
+use zeroize::Zeroizing;
+
+#[cfg(feature = "key-encryption")]
+use chacha20poly1305::aead::{AeadInPlace, KeyInit};
+#[cfg(feature = "key-encryption")]
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce, Tag};
+#[cfg(feature = "key-encryption")]
+use moonblokz_chain_types::calculate_hash;
+#[cfg(feature = "key-encryption")]
+use zeroize::Zeroize;
+
+/// Wrapping key size in bytes.
+pub const WRAPPING_KEY_SIZE: usize = 32;
+/// Maximum caller-supplied secret size accepted by `KeyProtection::from_secret`.
+pub const KEY_SECRET_MAX_SIZE: usize = 64;
+/// Maximum device unique-id size accepted by `KeyProtection::from_secret`.
+pub const UNIQUE_ID_MAX_SIZE: usize = 16;
+
+const KEY_NONCE_SIZE: usize = 12;
+const KEY_TAG_SIZE: usize = 16;
+const KEY_PROTECTION_PLAINTEXT: u8 = 0;
+const KEY_PROTECTION_WRAPPED: u8 = 1;
+/// HKDF salt of the wrapping-key derivation.
+#[cfg(feature = "key-encryption")]
+const KEY_DERIVATION_SALT: &[u8] = b"moonblokz-storage key wrapping salt";
+/// HKDF info label of the wrapping-key derivation.
+#[cfg(feature = "key-encryption")]
+const KEY_DERIVATION_INFO: &[u8] = b"moonblokz-storage/control-plane/private-key-wrap/v2";
+#[cfg(feature = "key-encryption")]
+const HMAC_BLOCK_SIZE: usize = 64;
+/// Longest HMAC message: the length-prefixed secret and unique id.
+#[cfg(feature = "key-encryption")]
+const HMAC_MESSAGE_MAX_SIZE: usize = 2 + KEY_SECRET_MAX_SIZE + UNIQUE_ID_MAX_SIZE;
+
+pub(crate) const VERSION_OFFSET: usize = 0;
+pub(crate) const PRIVATE_KEY_SIZE_OFFSET: usize = VERSION_OFFSET + 1;
+const KEY_PROTECTION_OFFSET: usize = PRIVATE_KEY_SIZE_OFFSET + 1;
+const KEY_NONCE_OFFSET: usize = KEY_PROTECTION_OFFSET + 1;
+
+struct EntryLayout {
+    private_key: usize,
+    own_node_id: usize,
+    init_params_size: usize,
+    init_params: usize,
+    max_block_size: usize,
+    chain_config: usize,
+    crc32: usize,
+}
+
+impl EntryLayout {
+    const fn new(private_key: usize, key_tag_size: usize) -> Self {
+        let own_node_id = private_key + PRIVATE_KEY_SIZE + key_tag_size;
+        let init_params_size = own_node_id + 4;
+        let init_params = init_params_size + 1;
+        let max_block_size = init_params + INIT_PARAMS_SIZE;
+        let chain_config = max_block_size + 2;
+        Self {
+            private_key,
+            own_node_id,
+            init_params_size,
+            init_params,
+            max_block_size,
+            chain_config,
+            crc32: chain_config + MAX_BLOCK_SIZE,
+        }
+    }
+}
+
+const LAYOUT_V1: EntryLayout = EntryLayout::new(PRIVATE_KEY_SIZE_OFFSET + 1, 0);
+const LAYOUT_V2: EntryLayout = EntryLayout::new(KEY_NONCE_OFFSET + KEY_NONCE_SIZE, KEY_TAG_SIZE);
+
+#[cfg(feature = "key-encryption")]
+const KEY_TAG_OFFSET: usize = LAYOUT_V2.private_key + PRIVATE_KEY_SIZE;
+pub(crate) const CRC32_OFFSET: usize = LAYOUT_V2.crc32;
+/// Serialized control-plane entry size.
+pub const CONTROL_PLANE_ENTRY_SIZE: usize = CRC32_OFFSET + 4;
+
+const _: () = {
+    if HASH_SIZE < WRAPPING_KEY_SIZE || HASH_SIZE < KEY_NONCE_SIZE {
+        panic!("HASH_SIZE must cover the wrapping key and nonce sizes");
+    }
+};
+
+/// How the private key is stored in control-plane entries.
+///
+/// `Plaintext` is the default. `Wrapped` (feature `key-encryption`) seals the
+/// key with ChaCha20-Poly1305; entries written in one mode are only readable
+/// with the same wrapping key, and a backend configured with a wrapping key
+/// does not accept current-version plaintext entries.
+///
+/// The wrapping key is wiped when the value is dropped. The type is not `Copy`,
+/// so the key is only duplicated by an explicit `clone`.
+#[derive(Clone, Default)]
+pub enum KeyProtection {
+    /// Private key is stored unencrypted.
+    #[default]
+    Plaintext,
+    /// Private key is sealed under a derived wrapping key.
+    #[cfg(feature = "key-encryption")]
+    Wrapped {
+        /// Derived ChaCha20-Poly1305 key.
+        wrapping_key: [u8; WRAPPING_KEY_SIZE],
+    },
+}
+
+impl KeyProtection {
+    /// Derives a wrapping key from a caller secret and/or a device unique id.
+    ///
+    /// The key is HKDF-SHA256 (RFC 5869) with a fixed salt, the input keying
+    /// material `len(secret) || secret || len(unique_id) || unique_id` and the
+    /// info label `moonblokz-storage/control-plane/private-key-wrap/v2`. On
+    /// RP2040, pass the flash unique id from `Rp2040Backend::flash_unique_id`
+    /// to bind the key to the chip.
+    ///
+    /// Parameters:
+    /// - `secret`: caller-supplied secret, at most `KEY_SECRET_MAX_SIZE` bytes (may be empty).
+    /// - `unique_id`: optional device unique id, at most `UNIQUE_ID_MAX_SIZE` bytes.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::KeyProtection;
+    ///
+    /// let protection = KeyProtection::from_secret(b"provisioning secret", None)?;
+    /// ```
+    #[cfg(feature = "key-encryption")]
+    pub fn from_secret(secret: &[u8], unique_id: Option<&[u8]>) -> Result<Self, StorageError> {
+        let unique_id = unique_id.unwrap_or(&[]);
+        if (secret.is_empty() && unique_id.is_empty())
+            || secret.len() > KEY_SECRET_MAX_SIZE
+            || unique_id.len() > UNIQUE_ID_MAX_SIZE
+        {
+            return Err(StorageError::InvalidConfiguration);
+        }
+
+        let mut input = [0u8; HMAC_MESSAGE_MAX_SIZE];
+        let mut len = 0usize;
+        input[len] = secret.len() as u8;
+        len += 1;
+        input[len..len + secret.len()].copy_from_slice(secret);
+        len += secret.len();
+        input[len] = unique_id.len() as u8;
+        len += 1;
+        input[len..len + unique_id.len()].copy_from_slice(unique_id);
+        len += unique_id.len();
+
+        let mut pseudo_random_key = hmac_sha256(KEY_DERIVATION_SALT, &input[..len]);
+        input.zeroize();
+        // A single HKDF-Expand block covers the key: T(1) = HMAC(PRK, info || 0x01).
+        let mut info = [0u8; KEY_DERIVATION_INFO.len() + 1];
+        info[..KEY_DERIVATION_INFO.len()].copy_from_slice(KEY_DERIVATION_INFO);
+        info[KEY_DERIVATION_INFO.len()] = 1;
+        let mut digest = hmac_sha256(&pseudo_random_key, &info);
+        pseudo_random_key.zeroize();
+        let mut protection = KeyProtection::Wrapped {
+            wrapping_key: [0u8; WRAPPING_KEY_SIZE],
+        };
+        if let KeyProtection::Wrapped { wrapping_key } = &mut protection {
+            wrapping_key.copy_from_slice(&digest[..WRAPPING_KEY_SIZE]);
+        }
+        digest.zeroize();
+        Ok(protection)
+    }
+}
+
+/// HMAC-SHA256 (RFC 2104) over `calculate_hash`.
+///
+/// `key` is at most `HMAC_BLOCK_SIZE` and `message` at most
+/// `HMAC_MESSAGE_MAX_SIZE` bytes; the derivation never passes longer inputs.
+#[cfg(feature = "key-encryption")]
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; HASH_SIZE] {
+    let mut inner = [0u8; HMAC_BLOCK_SIZE + HMAC_MESSAGE_MAX_SIZE];
+    let mut outer = [0u8; HMAC_BLOCK_SIZE + HASH_SIZE];
+    inner[..key.len()].copy_from_slice(key);
+    outer[..key.len()].copy_from_slice(key);
+    let mut index = 0usize;
+    while index < HMAC_BLOCK_SIZE {
+        inner[index] ^= 0x36;
+        outer[index] ^= 0x5C;
+        index += 1;
+    }
+    inner[HMAC_BLOCK_SIZE..HMAC_BLOCK_SIZE + message.len()].copy_from_slice(message);
+    let mut inner_hash = calculate_hash(&inner[..HMAC_BLOCK_SIZE + message.len()]);
+    outer[HMAC_BLOCK_SIZE..].copy_from_slice(&inner_hash);
+    let mac = calculate_hash(&outer);
+    inner.zeroize();
+    outer.zeroize();
+    inner_hash.zeroize();
+    mac
+}
+
+impl Drop for KeyProtection {
+    fn drop(&mut self) {
+        #[cfg(feature = "key-encryption")]
+        if let KeyProtection::Wrapped { wrapping_key } = self {
+            wrapping_key.zeroize();
+        }
+    }
+}
+
+/// Where control-plane replicas sit inside a storage region.
+///
+/// Positions are counted in region units (flash pages on RP2040). Every
+/// placement keeps the first replica in the first unit, so its superblock is
+/// found without knowing the layout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ControlPlanePlacement {
+    /// Replicas occupy the first units of the region.
+    #[default]
+    Consecutive,
+    /// Replicas are spread evenly across the region.
+    Spread,
+    /// The first half of the replicas (rounded up) sits at the region start,
+    /// the rest at its end.
+    StartAndEnd,
+}
+
+impl ControlPlanePlacement {
+    pub(crate) fn to_u32(self) -> u32 {
+        match self {
+            ControlPlanePlacement::Consecutive => 0,
+            ControlPlanePlacement::Spread => 1,
+            ControlPlanePlacement::StartAndEnd => 2,
+        }
+    }
+
+    pub(crate) fn from_u32(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(ControlPlanePlacement::Consecutive),
+            1 => Some(ControlPlanePlacement::Spread),
+            2 => Some(ControlPlanePlacement::StartAndEnd),
+            _ => None,
+        }
+    }
+}
+
+/// Control-plane replica count and placement of a backend.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ControlPlaneLayout {
+    /// Number of replicas, `1..=MAX_CONTROL_PLANE_COUNT`.
+    pub replica_count: usize,
+    /// Replica positions inside the region.
+    pub placement: ControlPlanePlacement,
+}
+
+impl ControlPlaneLayout {
+    /// `CONTROL_PLANE_COUNT` consecutive replicas, the layout of regions
+    /// written before the layout became configurable.
+    pub const DEFAULT: Self = Self::new(CONTROL_PLANE_COUNT, ControlPlanePlacement::Consecutive);
+
+    /// Creates a layout descriptor.
+    ///
+    /// Parameters:
+    /// - `replica_count`: number of control-plane replicas.
+    /// - `placement`: replica positions inside the region.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::control_plane::{ControlPlaneLayout, ControlPlanePlacement};
+    ///
+    /// const LAYOUT: ControlPlaneLayout = ControlPlaneLayout::new(5, ControlPlanePlacement::Spread);
+    /// assert!(LAYOUT.validate().is_ok());
+    /// ```
+    pub const fn new(replica_count: usize, placement: ControlPlanePlacement) -> Self {
+        Self {
+            replica_count,
+            placement,
+        }
+    }
+
+    /// Checks the replica count.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: `replica_count` is zero or above `MAX_CONTROL_PLANE_COUNT`.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::control_plane::{ControlPlaneLayout, ControlPlanePlacement};
+    ///
+    /// assert!(ControlPlaneLayout::new(0, ControlPlanePlacement::Consecutive).validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), StorageError> {
+        if self.replica_count == 0 || self.replica_count > MAX_CONTROL_PLANE_COUNT {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        Ok(())
+    }
+
+    /// Returns the unit holding replica `replica_index` in a region of
+    /// `unit_count` units. Positions increase with the replica index.
+    ///
+    /// Parameters:
+    /// - `unit_count`: region size in units, at least `replica_count`.
+    /// - `replica_index`: replica number, below `replica_count`.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::control_plane::{ControlPlaneLayout, ControlPlanePlacement};
+    ///
+    /// let layout = ControlPlaneLayout::new(3, ControlPlanePlacement::StartAndEnd);
+    /// assert_eq!(layout.replica_unit(10, 1), 1);
+    /// assert_eq!(layout.replica_unit(10, 2), 9);
+    /// ```
+    pub fn replica_unit(&self, unit_count: usize, replica_index: usize) -> usize {
+        match self.placement {
+            ControlPlanePlacement::Consecutive => replica_index,
+            ControlPlanePlacement::Spread => replica_index * unit_count / self.replica_count,
+            ControlPlanePlacement::StartAndEnd => {
+                let front = self.replica_count.div_ceil(2);
+                if replica_index < front {
+                    replica_index
+                } else {
+                    unit_count - (self.replica_count - replica_index)
+                }
+            }
+        }
+    }
+
+    /// Returns the unit holding data unit `data_index`, counting only units
+    /// that hold no replica.
+    ///
+    /// Parameters:
+    /// - `unit_count`: region size in units, at least `replica_count`.
+    /// - `data_index`: data unit number, below `unit_count - replica_count`.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::control_plane::{ControlPlaneLayout, ControlPlanePlacement};
+    ///
+    /// let layout = ControlPlaneLayout::new(2, ControlPlanePlacement::Spread);
+    /// assert_eq!(layout.data_unit(10, 0), 1);
+    /// assert_eq!(layout.data_unit(10, 4), 6);
+    /// ```
+    pub fn data_unit(&self, unit_count: usize, data_index: usize) -> usize {
+        let mut unit = data_index;
+        let mut replica_index = 0usize;
+        while replica_index < self.replica_count {
+            if self.replica_unit(unit_count, replica_index) <= unit {
+                unit += 1;
+            }
+            replica_index += 1;
+        }
+        unit
+    }
+}
+
+impl Default for ControlPlaneLayout {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Decoded control-plane entry.
+pub(crate) struct DecodedEntry {
+    /// Decoded control data.
+    pub(crate) record: ControlPlaneData,
+    /// `true` when the entry should be rewritten in the current format
+    /// (legacy version).
+    pub(crate) needs_upgrade: bool,
+}
+
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    !crc32_update(0xFFFF_FFFF, bytes)
+}
+
+/// Folds `bytes` into a running CRC32 state; start from `0xFFFF_FFFF` and
+/// invert the final state, as `crc32` does.
+pub(crate) fn crc32_update(mut crc: u32, bytes: &[u8]) -> u32 {
+    let mut i = 0usize;
+    while i < bytes.len() {
+        crc ^= bytes[i] as u32;
+        let mut bit = 0usize;
+        while bit < 8 {
+            if (crc & 1) != 0 {
+                crc = (crc >> 1) ^ 0xEDB8_8320;
+            } else {
+                crc >>= 1;
+            }
+            bit += 1;
+        }
+        i += 1;
+    }
+    crc
+}
+
+/// Registered decoder for a retired control-plane entry version.
+///
+/// `decode_entry` routes entries whose version differs from
+/// `CONTROL_PLANE_VERSION` through `MIGRATIONS`. The CRC is checked at the
+/// migration's layout first; `decode` then validates the remaining fields and
+/// converts the entry into current `ControlPlaneData`. Loaders rewrite every
+/// replica in the current layout afterwards, so each migration runs once per
+/// device. When bumping `CONTROL_PLANE_VERSION`, freeze the previous layout and
+/// decoder here instead of changing them.
+struct Migration {
+    /// Entry version handled by this migration.
+    version: u8,
+    /// Field offsets of `version` entries.
+    layout: &'static EntryLayout,
+    /// Converts a CRC-checked `version` entry into current control data, using the
+    /// configured key protection for versions that stored a protected key.
+    decode: fn(&[u8; CONTROL_PLANE_ENTRY_SIZE], &EntryLayout, &KeyProtection) -> Result<ControlPlaneData, StorageError>,
+}
+
+/// Decoders for all supported older entry versions.
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    layout: &LAYOUT_V1,
+    decode: decode_plaintext_entry,
+}];
+
+fn migration_for(version: u8) -> Option<&'static Migration> {
+    let mut index = 0usize;
+    while index < MIGRATIONS.len() {
+        if MIGRATIONS[index].version == version {
+            return Some(&MIGRATIONS[index]);
+        }
+        index += 1;
+    }
+    None
+}
+
+fn layout_for_version(version: u8) -> &'static EntryLayout {
+    match migration_for(version) {
+        Some(migration) => migration.layout,
+        None => &LAYOUT_V2,
+    }
+}
+
+/// Returns `(version, stored_crc32, computed_crc32)` without validating the entry.
+pub(crate) fn entry_header(bytes: &[u8; CONTROL_PLANE_ENTRY_SIZE]) -> (u8, u32, u32) {
+    let layout = layout_for_version(bytes[VERSION_OFFSET]);
+    let mut crc_bytes = [0u8; 4];
+    crc_bytes.copy_from_slice(&bytes[layout.crc32..layout.crc32 + 4]);
+    (
+        bytes[VERSION_OFFSET],
+        u32::from_le_bytes(crc_bytes),
+        crc32(&bytes[..layout.crc32]),
+    )
+}
+
+#[cfg(feature = "key-encryption")]
+fn key_nonce(
+    wrapping_key: &[u8; WRAPPING_KEY_SIZE],
+    private_key: &[u8; PRIVATE_KEY_SIZE],
+    aad: &[u8; 5],
+) -> [u8; KEY_NONCE_SIZE] {
+    let mut input = [0u8; WRAPPING_KEY_SIZE + PRIVATE_KEY_SIZE + 5];
+    input[..WRAPPING_KEY_SIZE].copy_from_slice(wrapping_key);
+    input[WRAPPING_KEY_SIZE..WRAPPING_KEY_SIZE + PRIVATE_KEY_SIZE].copy_from_slice(private_key);
+    input[WRAPPING_KEY_SIZE + PRIVATE_KEY_SIZE..].copy_from_slice(aad);
+    let digest = calculate_hash(&input);
+    input.zeroize();
+    let mut nonce = [0u8; KEY_NONCE_SIZE];
+    nonce.copy_from_slice(&digest[..KEY_NONCE_SIZE]);
+    nonce
+}
+
+/// Associated data binding a wrapped key to its entry.
+///
+/// `version` is the version byte stored in the entry itself, so the AAD of an
+/// existing entry does not change when `CONTROL_PLANE_VERSION` is bumped.
+#[cfg(feature = "key-encryption")]
+fn key_aad(version: u8, own_node_id: u32) -> [u8; 5] {
+    let mut aad = [0u8; 5];
+    aad[0] = version;
+    aad[1..].copy_from_slice(&own_node_id.to_le_bytes());
+    aad
+}
+
+/// Encodes `record` as a current-version entry.
+///
+/// Parameters:
+/// - `record`: control data to encode.
+/// - `protection`: private-key protection mode.
+pub(crate) fn encode_entry(
+    record: &ControlPlaneData,
+    protection: &KeyProtection,
+) -> Result<Zeroizing<[u8; CONTROL_PLANE_ENTRY_SIZE]>, StorageError> {
+    let layout = &LAYOUT_V2;
+    let mut out = Zeroizing::new([0u8; CONTROL_PLANE_ENTRY_SIZE]);
+    out[VERSION_OFFSET] = CONTROL_PLANE_VERSION;
+    out[PRIVATE_KEY_SIZE_OFFSET] = PRIVATE_KEY_SIZE as u8;
+    out[layout.private_key..layout.private_key + PRIVATE_KEY_SIZE].copy_from_slice(&record.private_key);
+
+    match protection {
+        KeyProtection::Plaintext => out[KEY_PROTECTION_OFFSET] = KEY_PROTECTION_PLAINTEXT,
+        #[cfg(feature = "key-encryption")]
+        KeyProtection::Wrapped { wrapping_key } => {
+            let aad = key_aad(out[VERSION_OFFSET], record.own_node_id);
+            let nonce = key_nonce(wrapping_key, &record.private_key, &aad);
+            let cipher = ChaCha20Poly1305::new(Key::from_slice(wrapping_key));
+            let tag = cipher
+                .encrypt_in_place_detached(
+                    Nonce::from_slice(&nonce),
+                    &aad,
+                    &mut out[layout.private_key..layout.private_key + PRIVATE_KEY_SIZE],
+                )
+                .map_err(|_| StorageError::InvalidConfiguration)?;
+            out[KEY_PROTECTION_OFFSET] = KEY_PROTECTION_WRAPPED;
+            out[KEY_NONCE_OFFSET..KEY_NONCE_OFFSET + KEY_NONCE_SIZE].copy_from_slice(&nonce);
+            out[KEY_TAG_OFFSET..KEY_TAG_OFFSET + KEY_TAG_SIZE].copy_from_slice(tag.as_slice());
+        }
+    }
+
+    out[layout.own_node_id..layout.own_node_id + 4].copy_from_slice(&record.own_node_id.to_le_bytes());
+    out[layout.init_params_size] = INIT_PARAMS_SIZE as u8;
+    out[layout.init_params..layout.init_params + INIT_PARAMS_SIZE].copy_from_slice(&record.init_params);
+    out[layout.max_block_size..layout.max_block_size + 2]
+        .copy_from_slice(&(MAX_BLOCK_SIZE as u16).to_le_bytes());
+
+    if let Some(chain_configuration) = &record.chain_configuration {
+        let bytes = chain_configuration.as_bytes();
+        out[layout.chain_config..layout.chain_config + bytes.len()].copy_from_slice(bytes);
+    }
+
+    let crc = crc32(&out[..layout.crc32]);
+    out[layout.crc32..layout.crc32 + 4].copy_from_slice(&crc.to_le_bytes());
+    Ok(out)
+}
+
+/// Decodes and validates an entry of any supported version.
+///
+/// Entries of an older version are converted by their registered migration
+/// and flagged with `needs_upgrade`.
+///
+/// Errors:
+/// - `ControlPlaneUninitialized`: entry is all `0x00` or all `0xFF`.
+/// - `ControlPlaneCorrupted`: CRC or structural validation failed.
+/// - `ControlPlaneIncompatible`: unknown version or mismatched constants.
+/// - `KeyUnwrapFailed`: wrapped key cannot be opened with `protection`, or a
+///   current-version entry holds a plaintext key while `protection` is `Wrapped`.
+///
+/// Parameters:
+/// - `bytes`: raw entry bytes.
+/// - `protection`: configured private-key protection mode.
+pub(crate) fn decode_entry(
+    bytes: &[u8; CONTROL_PLANE_ENTRY_SIZE],
+    protection: &KeyProtection,
+) -> Result<DecodedEntry, StorageError> {
+    if bytes.iter().all(|v| *v == 0) || bytes.iter().all(|v| *v == 0xFF) {
+        return Err(StorageError::ControlPlaneUninitialized);
+    }
+
+    let version = bytes[VERSION_OFFSET];
+    check_crc(bytes, layout_for_version(version))?;
+    if version != CONTROL_PLANE_VERSION {
+        let migration = migration_for(version).ok_or(StorageError::ControlPlaneIncompatible)?;
+        return Ok(DecodedEntry {
+            record: (migration.decode)(bytes, migration.layout, protection)?,
+            needs_upgrade: true,
+        });
+    }
+
+    let layout = &LAYOUT_V2;
+    check_constants(bytes, layout)?;
+    let mut private_key = Zeroizing::new([0u8; PRIVATE_KEY_SIZE]);
+    private_key.copy_from_slice(&bytes[layout.private_key..layout.private_key + PRIVATE_KEY_SIZE]);
+    let key_protection = bytes[KEY_PROTECTION_OFFSET];
+    match (key_protection, protection) {
+        (KEY_PROTECTION_PLAINTEXT, KeyProtection::Plaintext) => {}
+        // A plaintext entry under a configured wrapping key is a downgrade.
+        #[cfg(feature = "key-encryption")]
+        (KEY_PROTECTION_PLAINTEXT, KeyProtection::Wrapped { .. }) => return Err(StorageError::KeyUnwrapFailed),
+        #[cfg(feature = "key-encryption")]
+        (KEY_PROTECTION_WRAPPED, KeyProtection::Wrapped { wrapping_key }) => {
+            let cipher = ChaCha20Poly1305::new(Key::from_slice(wrapping_key));
+            let unwrapped = cipher.decrypt_in_place_detached(
+                Nonce::from_slice(&bytes[KEY_NONCE_OFFSET..KEY_NONCE_OFFSET + KEY_NONCE_SIZE]),
+                &key_aad(version, read_own_node_id(bytes, layout)),
+                &mut private_key[..],
+                Tag::from_slice(&bytes[KEY_TAG_OFFSET..KEY_TAG_OFFSET + KEY_TAG_SIZE]),
+            );
+            if unwrapped.is_err() {
+                return Err(StorageError::KeyUnwrapFailed);
+            }
+        }
+        (KEY_PROTECTION_WRAPPED, _) => return Err(StorageError::KeyUnwrapFailed),
+        _ => return Err(StorageError::ControlPlaneIncompatible),
+    }
+
+    Ok(DecodedEntry {
+        record: decode_fields(bytes, layout, &private_key)?,
+        needs_upgrade: false,
+    })
+}
+
+/// Decodes an entry whose private key is stored in plaintext at `layout`.
+///
+/// Used by the version 1 migration; version 1 had no key protection, so the
+/// configured `protection` only applies when the entry is rewritten.
+fn decode_plaintext_entry(
+    bytes: &[u8; CONTROL_PLANE_ENTRY_SIZE],
+    layout: &EntryLayout,
+    _protection: &KeyProtection,
+) -> Result<ControlPlaneData, StorageError> {
+    check_constants(bytes, layout)?;
+    let mut private_key = Zeroizing::new([0u8; PRIVATE_KEY_SIZE]);
+    private_key.copy_from_slice(&bytes[layout.private_key..layout.private_key + PRIVATE_KEY_SIZE]);
+    decode_fields(bytes, layout, &private_key)
+}
+
+fn check_crc(bytes: &[u8; CONTROL_PLANE_ENTRY_SIZE], layout: &EntryLayout) -> Result<(), StorageError> {
+    let mut crc_bytes = [0u8; 4];
+    crc_bytes.copy_from_slice(&bytes[layout.crc32..layout.crc32 + 4]);
+    if u32::from_le_bytes(crc_bytes) != crc32(&bytes[..layout.crc32]) {
+        return Err(StorageError::ControlPlaneCorrupted);
+    }
+    Ok(())
+}
+
+fn check_constants(bytes: &[u8; CONTROL_PLANE_ENTRY_SIZE], layout: &EntryLayout) -> Result<(), StorageError> {
+    let mut max_block_size_bytes = [0u8; 2];
+    max_block_size_bytes.copy_from_slice(&bytes[layout.max_block_size..layout.max_block_size + 2]);
+    if bytes[PRIVATE_KEY_SIZE_OFFSET] as usize != PRIVATE_KEY_SIZE
+        || bytes[layout.init_params_size] as usize != INIT_PARAMS_SIZE
+        || u16::from_le_bytes(max_block_size_bytes) as usize != MAX_BLOCK_SIZE
+    {
+        return Err(StorageError::ControlPlaneIncompatible);
+    }
+    Ok(())
+}
+
+fn read_own_node_id(bytes: &[u8; CONTROL_PLANE_ENTRY_SIZE], layout: &EntryLayout) -> u32 {
+    let mut own_node_id_bytes = [0u8; 4];
+    own_node_id_bytes.copy_from_slice(&bytes[layout.own_node_id..layout.own_node_id + 4]);
+    u32::from_le_bytes(own_node_id_bytes)
+}
+
+fn decode_fields(
+    bytes: &[u8; CONTROL_PLANE_ENTRY_SIZE],
+    layout: &EntryLayout,
+    private_key: &[u8; PRIVATE_KEY_SIZE],
+) -> Result<ControlPlaneData, StorageError> {
+    let mut init_params = [0u8; INIT_PARAMS_SIZE];
+    init_params.copy_from_slice(&bytes[layout.init_params..layout.init_params + INIT_PARAMS_SIZE]);
+
+    let chain_configuration = if bytes[layout.chain_config] == 0 {
+        None
+    } else {
+        let mut value = [0u8; MAX_BLOCK_SIZE];
+        value.copy_from_slice(&bytes[layout.chain_config..layout.chain_config + MAX_BLOCK_SIZE]);
+        Some(Block::from_bytes(&value).map_err(|_| StorageError::ControlPlaneCorrupted)?)
+    };
+
+    Ok(ControlPlaneData {
+        private_key: *private_key,
+        own_node_id: read_own_node_id(bytes, layout),
+        init_params,
+        chain_configuration,
+    })
+}
+
+/// Frozen images of retired entry versions, used as test fixtures.
+///
+/// The bytes are checked in literally and must never change: they are what
+/// earlier releases wrote, so migrations are tested against real legacy images
+/// rather than against the crate's current encoder or CRC.
+#[cfg(test)]
+pub(crate) mod frozen {
+    use super::CONTROL_PLANE_ENTRY_SIZE;
+    use crate::INIT_PARAMS_SIZE;
+    use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    use moonblokz_crypto::PRIVATE_KEY_SIZE;
+
+    // The fixtures were captured with these constants.
+    const _: () = assert!(PRIVATE_KEY_SIZE == 32 && INIT_PARAMS_SIZE == 100 && MAX_BLOCK_SIZE == 2016);
+
+    /// Private-key byte repeated in `V1_ENTRY`.
+    pub(crate) const V1_PRIVATE_KEY_BYTE: u8 = 0x05;
+    /// Node id stored in `V1_ENTRY`.
+    pub(crate) const V1_OWN_NODE_ID: u32 = 99;
+    /// Init-params byte repeated in `V1_ENTRY`.
+    pub(crate) const V1_INIT_PARAMS_BYTE: u8 = 0x06;
+
+    /// Version 1 entry fields up to the chain configuration:
+    /// `version, private_key_size, private_key, own_node_id, init_params_size,
+    /// init_params, max_block_size`. The chain configuration that follows is
+    /// all zero (none).
+    const V1_ENTRY_FIELDS: [u8; 141] = [
+        0x01, 0x20, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05,
+        0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05, 0x05,
+        0x05, 0x05, 0x63, 0x00, 0x00, 0x00, 0x64, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06,
+        0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06,
+        0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06,
+        0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06,
+        0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06,
+        0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06,
+        0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0x06, 0xE0, 0x07,
+    ];
+    /// Offset and bytes of the version 1 CRC32 trailer.
+    const V1_ENTRY_CRC32_OFFSET: usize = 2157;
+    const V1_ENTRY_CRC32: [u8; 4] = [0x3D, 0xB0, 0xC9, 0xAF];
+
+    /// Version 1 entry without chain configuration, zero-padded to the current
+    /// entry size as a v1 replica region reads back.
+    pub(crate) fn v1_entry() -> [u8; CONTROL_PLANE_ENTRY_SIZE] {
+        let mut out = [0u8; CONTROL_PLANE_ENTRY_SIZE];
+        out[..V1_ENTRY_FIELDS.len()].copy_from_slice(&V1_ENTRY_FIELDS);
+        out[V1_ENTRY_CRC32_OFFSET..V1_ENTRY_CRC32_OFFSET + 4].copy_from_slice(&V1_ENTRY_CRC32);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_record() -> ControlPlaneData {
+        ControlPlaneData {
+            private_key: [5u8; PRIVATE_KEY_SIZE],
+            own_node_id: 99,
+            init_params: [6u8; INIT_PARAMS_SIZE],
+            chain_configuration: None,
+        }
+    }
+
+    #[test]
+    fn plaintext_entry_round_trips() {
+        let encoded = match encode_entry(&test_record(), &KeyProtection::Plaintext) {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        };
+        let decoded = match decode_entry(&encoded, &KeyProtection::Plaintext) {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        };
+        assert_eq!(decoded.record.private_key, [5u8; PRIVATE_KEY_SIZE]);
+        assert_eq!(decoded.record.own_node_id, 99);
+        assert!(!decoded.needs_upgrade);
+    }
+
+    struct FmtBuffer {
+        bytes: [u8; 1024],
+        len: usize,
+    }
+
+    impl core::fmt::Write for FmtBuffer {
+        fn write_str(&mut self, value: &str) -> core::fmt::Result {
+            let end = self.len + value.len();
+            if end > self.bytes.len() {
+                return Err(core::fmt::Error);
+            }
+            self.bytes[self.len..end].copy_from_slice(value.as_bytes());
+            self.len = end;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn control_plane_data_debug_redacts_private_key() {
+        use core::fmt::Write;
+
+        let mut buffer = FmtBuffer {
+            bytes: [0u8; 1024],
+            len: 0,
+        };
+        assert!(write!(buffer, "{:?}", test_record()).is_ok());
+        let output = match core::str::from_utf8(&buffer.bytes[..buffer.len]) {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        };
+        assert!(output.contains("private_key: \"<redacted>\""));
+        assert!(output.contains("own_node_id: 99"));
+        assert!(!output.contains("[5, 5"));
+    }
+
+    #[test]
+    fn frozen_v1_entry_decodes_and_requests_upgrade() {
+        let encoded = frozen::v1_entry();
+        let decoded = match decode_entry(&encoded, &KeyProtection::Plaintext) {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        };
+        assert_eq!(decoded.record.private_key, [frozen::V1_PRIVATE_KEY_BYTE; PRIVATE_KEY_SIZE]);
+        assert_eq!(decoded.record.own_node_id, frozen::V1_OWN_NODE_ID);
+        assert_eq!(decoded.record.init_params, [frozen::V1_INIT_PARAMS_BYTE; INIT_PARAMS_SIZE]);
+        assert!(decoded.record.chain_configuration.is_none());
+        assert!(decoded.needs_upgrade);
+    }
+
+    #[test]
+    fn frozen_v1_crc_matches_crate_crc32() {
+        let (version, stored_crc32, computed_crc32) = entry_header(&frozen::v1_entry());
+        assert_eq!(version, 1);
+        assert_eq!(stored_crc32, computed_crc32);
+    }
+
+    #[test]
+    fn frozen_v1_entry_with_corrupted_crc_is_rejected() {
+        let mut encoded = frozen::v1_entry();
+        encoded[PRIVATE_KEY_SIZE_OFFSET + 1] ^= 0xFF;
+        assert!(matches!(
+            decode_entry(&encoded, &KeyProtection::Plaintext),
+            Err(StorageError::ControlPlaneCorrupted)
+        ));
+    }
+
+    #[test]
+    fn unregistered_version_is_incompatible() {
+        let mut encoded = match encode_entry(&test_record(), &KeyProtection::Plaintext) {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        };
+        encoded[VERSION_OFFSET] = CONTROL_PLANE_VERSION.wrapping_add(1);
+        let crc = crc32(&encoded[..CRC32_OFFSET]);
+        encoded[CRC32_OFFSET..CRC32_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
+        assert!(matches!(
+            decode_entry(&encoded, &KeyProtection::Plaintext),
+            Err(StorageError::ControlPlaneIncompatible)
+        ));
+    }
+
+    #[test]
+    fn migrations_cover_distinct_older_versions() {
+        let mut index = 0usize;
+        while index < MIGRATIONS.len() {
+            assert!(MIGRATIONS[index].version < CONTROL_PLANE_VERSION);
+            assert!(MIGRATIONS[index].layout.crc32 + 4 <= CONTROL_PLANE_ENTRY_SIZE);
+            let mut other = index + 1;
+            while other < MIGRATIONS.len() {
+                assert_ne!(MIGRATIONS[index].version, MIGRATIONS[other].version);
+                other += 1;
+            }
+            index += 1;
+        }
+    }
+
+    #[cfg(feature = "key-encryption")]
+    #[test]
+    fn wrapped_entry_hides_key_and_round_trips() {
+        let protection = match KeyProtection::from_secret(b"secret", Some(&[1, 2, 3, 4, 5, 6, 7, 8])) {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        };
+        let encoded = match encode_entry(&test_record(), &protection) {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        };
+        assert_ne!(
+            &encoded[LAYOUT_V2.private_key..LAYOUT_V2.private_key + PRIVATE_KEY_SIZE],
+            &[5u8; PRIVATE_KEY_SIZE][..]
+        );
+
+        let decoded = match decode_entry(&encoded, &protection) {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        };
+        assert_eq!(decoded.record.private_key, [5u8; PRIVATE_KEY_SIZE]);
+        assert!(!decoded.needs_upgrade);
+    }
+
+    #[cfg(feature = "key-encryption")]
+    #[test]
+    fn wrapped_entry_rejects_wrong_secret_and_missing_key() {
+        let protection = KeyProtection::from_secret(b"secret", None).unwrap_or_else(|_| unreachable!());
+        let wrong = KeyProtection::from_secret(b"other", None).unwrap_or_else(|_| unreachable!());
+        let encoded = match encode_entry(&test_record(), &protection) {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        };
+
+        assert!(matches!(
+            decode_entry(&encoded, &wrong),
+            Err(StorageError::KeyUnwrapFailed)
+        ));
+        assert!(matches!(
+            decode_entry(&encoded, &KeyProtection::Plaintext),
+            Err(StorageError::KeyUnwrapFailed)
+        ));
+    }
+
+    #[cfg(feature = "key-encryption")]
+    #[test]
+    fn plaintext_entry_is_rejected_under_a_wrapping_key_unless_it_is_v1() {
+        let protection = KeyProtection::from_secret(b"secret", None).unwrap_or_else(|_| unreachable!());
+        let plaintext = encode_entry(&test_record(), &KeyProtection::Plaintext).unwrap_or_else(|_| unreachable!());
+        assert!(matches!(
+            decode_entry(&plaintext, &protection),
+            Err(StorageError::KeyUnwrapFailed)
+        ));
+
+        let decoded = decode_entry(&frozen::v1_entry(), &protection);
+        assert!(decoded.is_ok());
+        let decoded = decoded.unwrap_or_else(|_| unreachable!());
+        assert!(decoded.needs_upgrade);
+        let rewritten = encode_entry(&decoded.record, &protection).unwrap_or_else(|_| unreachable!());
+        assert!(matches!(
+            decode_entry(&rewritten, &protection),
+            Ok(value) if value.record.private_key == [frozen::V1_PRIVATE_KEY_BYTE; PRIVATE_KEY_SIZE]
+        ));
+    }
+
+    #[cfg(feature = "key-encryption")]
+    #[test]
+    fn hmac_matches_rfc_4231_and_derivation_is_domain_separated() {
+        let expected = [
+            0x5b, 0xdc, 0xc1, 0x46, 0xbf, 0x60, 0x75, 0x4e, 0x6a, 0x04, 0x24, 0x26, 0x08, 0x95, 0x75, 0xc7, 0x5a,
+            0x00, 0x3f, 0x08, 0x9d, 0x27, 0x39, 0x83, 0x9d, 0xec, 0x58, 0xb9, 0x64, 0xec, 0x38, 0x43,
+        ];
+        assert_eq!(hmac_sha256(b"Jefe", b"what do ya want for nothing?"), expected);
+
+        let derived = KeyProtection::from_secret(b"secret", None).unwrap_or_else(|_| unreachable!());
+        let bare = calculate_hash(b"\x06secret\x00");
+        assert!(matches!(derived, KeyProtection::Wrapped { wrapping_key } if wrapping_key[..] != bare[..]));
+    }
+
+    #[cfg(feature = "key-encryption")]
+    #[test]
+    fn from_secret_rejects_empty_and_oversized_inputs() {
+        assert!(matches!(
+            KeyProtection::from_secret(&[], None),
+            Err(StorageError::InvalidConfiguration)
+        ));
+        assert!(matches!(
+            KeyProtection::from_secret(&[0u8; KEY_SECRET_MAX_SIZE + 1], None),
+            Err(StorageError::InvalidConfiguration)
+        ));
+    }
+
+    #[test]
+    fn layout_places_replicas_and_data_units_without_overlap() {
+        const UNIT_COUNT: usize = 11;
+        let placements = [
+            ControlPlanePlacement::Consecutive,
+            ControlPlanePlacement::Spread,
+            ControlPlanePlacement::StartAndEnd,
+        ];
+        let mut placement_index = 0usize;
+        while placement_index < placements.len() {
+            let mut replica_count = 1usize;
+            while replica_count <= MAX_CONTROL_PLANE_COUNT {
+                let layout = ControlPlaneLayout::new(replica_count, placements[placement_index]);
+                let mut used = [false; UNIT_COUNT];
+                let mut replica_index = 0usize;
+                while replica_index < replica_count {
+                    let unit = layout.replica_unit(UNIT_COUNT, replica_index);
+                    assert!(!used[unit]);
+                    used[unit] = true;
+                    replica_index += 1;
+                }
+                assert_eq!(layout.replica_unit(UNIT_COUNT, 0), 0);
+
+                let mut data_index = 0usize;
+                while data_index < UNIT_COUNT - replica_count {
+                    let unit = layout.data_unit(UNIT_COUNT, data_index);
+                    assert!(!used[unit]);
+                    used[unit] = true;
+                    data_index += 1;
+                }
+                assert!(used.iter().all(|value| *value));
+                replica_count += 1;
+            }
+            placement_index += 1;
+        }
+    }
+
+    #[test]
+    fn layout_positions_match_placement() {
+        let spread = ControlPlaneLayout::new(3, ControlPlanePlacement::Spread);
+        assert_eq!(spread.replica_unit(12, 1), 4);
+        assert_eq!(spread.replica_unit(12, 2), 8);
+
+        let start_and_end = ControlPlaneLayout::new(5, ControlPlanePlacement::StartAndEnd);
+        assert_eq!(start_and_end.replica_unit(12, 2), 2);
+        assert_eq!(start_and_end.replica_unit(12, 3), 10);
+        assert_eq!(start_and_end.data_unit(12, 0), 3);
+
+        assert_eq!(ControlPlaneLayout::default(), ControlPlaneLayout::DEFAULT);
+        assert!(ControlPlaneLayout::new(MAX_CONTROL_PLANE_COUNT + 1, ControlPlanePlacement::Spread)
+            .validate()
+            .is_err());
+    }
+}
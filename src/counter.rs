@@ -0,0 +1,196 @@
+/*! Monotonic counters over dedicated page pairs, shared by all backends.
+
+`counter(id)` handles keep anti-replay nonces and boot counters. Each counter
+owns `COUNTER_PAGES` pages accessed through `KvPages`. The active page starts
+with a header holding the value the page starts from; the rest of the page is
+a bitmap and every increment clears one more bit, so it programs a single
+byte and needs no erase. The value is the header base plus the number of
+cleared bits. When the bitmap is used up, the counter moves to its other page
+with the current value as the new base, so a page is erased only once every
+`COUNTER_STEPS_PER_PAGE` increments.
+
+Crash safety:
+- A power loss while clearing a bit leaves either the old or the new value;
+  a partly programmed byte only clears bits, so the value never goes down.
+- A move programs the header of the new page after erasing it. Until the
+  header is intact the old, full page stays active, and afterwards its lower
+  generation hides it.
+
+Layout (all integers little-endian):
+
+- page header: `magic:[u8;4] = "MBCT", version:u8, reserved:[u8;3] = 0, generation:u32, base:u64, crc32:u32`
+- bitmap: the remaining bytes of the page, bits cleared from the lowest bit of
+  the first byte on.
+*/
+
+use crate::StorageError;
+use crate::control_plane::crc32;
+use crate::kv_store::{KV_PAGE_SIZE, KvPages};
+
+/// Pages owned by every counter.
+pub const COUNTER_PAGES: usize = 2;
+/// Counter page header magic bytes.
+pub const COUNTER_MAGIC: [u8; 4] = *b"MBCT";
+/// Counter page format version.
+pub const COUNTER_VERSION: u8 = 1;
+/// Increments one page absorbs before the counter moves to its other page.
+pub const COUNTER_STEPS_PER_PAGE: u64 = ((KV_PAGE_SIZE - PAGE_HEADER_SIZE) * 8) as u64;
+
+const PAGE_HEADER_SIZE: usize = 24;
+const BITMAP_CHUNK_SIZE: usize = 256;
+
+fn encode_page_header(generation: u32, base: u64) -> [u8; PAGE_HEADER_SIZE] {
+    let mut out = [0u8; PAGE_HEADER_SIZE];
+    out[..4].copy_from_slice(&COUNTER_MAGIC);
+    out[4] = COUNTER_VERSION;
+    out[8..12].copy_from_slice(&generation.to_le_bytes());
+    out[12..20].copy_from_slice(&base.to_le_bytes());
+    let crc = crc32(&out[..20]);
+    out[20..].copy_from_slice(&crc.to_le_bytes());
+    out
+}
+
+/// Returns generation and base of a valid page header of the current version.
+fn decode_page_header(bytes: &[u8]) -> Option<(u32, u64)> {
+    let mut crc = [0u8; 4];
+    crc.copy_from_slice(&bytes[20..PAGE_HEADER_SIZE]);
+    if bytes[..4] != COUNTER_MAGIC || bytes[4] != COUNTER_VERSION || u32::from_le_bytes(crc) != crc32(&bytes[..20]) {
+        return None;
+    }
+    let mut generation = [0u8; 4];
+    generation.copy_from_slice(&bytes[8..12]);
+    let mut base = [0u8; 8];
+    base.copy_from_slice(&bytes[12..20]);
+    Some((u32::from_le_bytes(generation), u64::from_le_bytes(base)))
+}
+
+/// State of the active page of a counter.
+struct ActivePage {
+    page: usize,
+    generation: u32,
+    value: u64,
+    /// Bitmap byte holding the next bit to clear, `None` when the page is used up.
+    next_byte: Option<usize>,
+}
+
+/// Returns the active page, `None` for a counter that was never incremented.
+fn active_page<P: KvPages + ?Sized>(pages: &P) -> Result<Option<ActivePage>, StorageError> {
+    let mut active: Option<(usize, u32, u64)> = None;
+    let mut page = 0usize;
+    while page < COUNTER_PAGES {
+        let mut header = [0u8; PAGE_HEADER_SIZE];
+        pages.read(page, 0, &mut header)?;
+        if let Some((generation, base)) = decode_page_header(&header) {
+            if active.is_none_or(|(_, current, _)| generation > current) {
+                active = Some((page, generation, base));
+            }
+        }
+        page += 1;
+    }
+    let Some((page, generation, base)) = active else {
+        return Ok(None);
+    };
+
+    // The bitmap is read in chunks to keep a page buffer off the stack.
+    let mut chunk = [0u8; BITMAP_CHUNK_SIZE];
+    let mut spent = 0u64;
+    let mut next_byte = None;
+    let mut offset = 0usize;
+    while offset < KV_PAGE_SIZE - PAGE_HEADER_SIZE {
+        let len = BITMAP_CHUNK_SIZE.min(KV_PAGE_SIZE - PAGE_HEADER_SIZE - offset);
+        pages.read(page, PAGE_HEADER_SIZE + offset, &mut chunk[..len])?;
+        spent += chunk[..len].iter().map(|byte| byte.count_zeros() as u64).sum::<u64>();
+        if next_byte.is_none() {
+            next_byte = chunk[..len].iter().position(|byte| *byte != 0).map(|position| offset + position);
+        }
+        offset += len;
+    }
+    Ok(Some(ActivePage {
+        page,
+        generation,
+        value: base + spent,
+        next_byte,
+    }))
+}
+
+pub(crate) fn get<P: KvPages + ?Sized>(pages: &P) -> Result<u64, StorageError> {
+    Ok(active_page(pages)?.map_or(0, |active| active.value))
+}
+
+/// Adds one to the counter and returns the new value.
+pub(crate) fn increment<P: KvPages + ?Sized>(pages: &mut P) -> Result<u64, StorageError> {
+    if pages.page_count() < COUNTER_PAGES {
+        return Err(StorageError::InvalidConfiguration);
+    }
+    let (page, byte, value) = match active_page(pages)? {
+        Some(ActivePage {
+            page,
+            value,
+            next_byte: Some(byte),
+            ..
+        }) => (page, byte, value),
+        active => {
+            let (next, generation, base) = match active {
+                Some(active) => ((active.page + 1) % COUNTER_PAGES, active.generation.wrapping_add(1), active.value),
+                None => (0, 1, 0),
+            };
+            pages.erase(next)?;
+            pages.program(next, 0, &encode_page_header(generation, base))?;
+            (next, 0, base)
+        }
+    };
+
+    let mut current = [0u8; 1];
+    pages.read(page, PAGE_HEADER_SIZE + byte, &mut current)?;
+    // Clears the lowest bit still set.
+    pages.program(page, PAGE_HEADER_SIZE + byte, &[current[0] & current[0].wrapping_sub(1)])?;
+    Ok(value + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWO_PAGES: usize = COUNTER_PAGES * KV_PAGE_SIZE;
+
+    #[test]
+    fn increments_clear_one_bit_each_and_move_to_the_other_page() {
+        let mut pages = [0u8; TWO_PAGES];
+        assert!(matches!(get(&pages[..]), Ok(0)));
+        assert!(matches!(increment(&mut pages[..]), Ok(1)));
+        assert!(matches!(increment(&mut pages[..]), Ok(2)));
+        assert_eq!(pages[PAGE_HEADER_SIZE], 0xFC);
+        assert_eq!(pages[PAGE_HEADER_SIZE + 1], 0xFF);
+
+        let mut expected = 2u64;
+        while expected < COUNTER_STEPS_PER_PAGE + 3 {
+            expected += 1;
+            assert!(matches!(increment(&mut pages[..]), Ok(value) if value == expected));
+        }
+        assert!(matches!(get(&pages[..]), Ok(value) if value == COUNTER_STEPS_PER_PAGE + 3));
+        assert!(matches!(active_page(&pages[..]), Ok(Some(active)) if active.page == 1 && active.generation == 2));
+    }
+
+    #[test]
+    fn torn_move_keeps_the_full_page_active() {
+        let mut pages = [0u8; TWO_PAGES];
+        assert!(increment(&mut pages[..]).is_ok());
+        pages[PAGE_HEADER_SIZE..KV_PAGE_SIZE].fill(0);
+        assert!(matches!(get(&pages[..]), Ok(value) if value == COUNTER_STEPS_PER_PAGE));
+
+        // The move erased the other page but lost power inside its header.
+        let header = encode_page_header(2, COUNTER_STEPS_PER_PAGE);
+        pages[KV_PAGE_SIZE..].fill(0xFF);
+        pages[KV_PAGE_SIZE..KV_PAGE_SIZE + 10].copy_from_slice(&header[..10]);
+        assert!(matches!(get(&pages[..]), Ok(value) if value == COUNTER_STEPS_PER_PAGE));
+
+        assert!(matches!(increment(&mut pages[..]), Ok(value) if value == COUNTER_STEPS_PER_PAGE + 1));
+        assert!(matches!(active_page(&pages[..]), Ok(Some(active)) if active.page == 1));
+    }
+
+    #[test]
+    fn single_page_is_rejected() {
+        let mut page = [0u8; KV_PAGE_SIZE];
+        assert!(matches!(increment(&mut page[..]), Err(StorageError::InvalidConfiguration)));
+    }
+}
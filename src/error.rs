@@ -1,5 +1,8 @@
 /*! Core error model for MoonBlokz storage public API contracts. */
 
+use crate::superblock::LayoutField;
+use moonblokz_chain_types::HASH_SIZE;
+
 /// Public storage error categories used by chain-level logic.
 pub enum StorageError {
     /// `storage_index` is outside valid storage bounds.
@@ -18,22 +21,61 @@ pub enum StorageError {
     ControlPlaneIncompatible,
     /// Backend configuration is invalid for required flash geometry constraints.
     InvalidConfiguration,
+    /// Wrapped private key could not be unwrapped (wrong secret, wrong device or tampered entry).
+    KeyUnwrapFailed,
+    /// Stored superblock geometry differs from the compiled geometry.
+    LayoutMismatch {
+        /// First differing geometry field.
+        field: LayoutField,
+        /// Value recorded on flash.
+        stored: u32,
+        /// Value expected by the running firmware.
+        expected: u32,
+    },
+    /// Storage region has a pending or finished relocation journal; finish it
+    /// with `relocate` before using the control plane.
+    RelocationInProgress,
+    /// Storage region has an unfinished `init`; finish it with `resume_init`
+    /// or run `init` again before using the control plane.
+    InitInProgress,
+    /// `save_block` targeted a write-once slot that already holds a block;
+    /// use `force_overwrite` to replace it deliberately.
+    SlotOccupied,
+    /// `replace_block` or `insert_block` found the slot holding something
+    /// other than expected; nothing was written.
+    Conflict {
+        /// `block_hash` of the block currently in the slot, `None` when it is empty.
+        current_hash: Option<[u8; HASH_SIZE]>,
+    },
+    /// `kv_put` or `kv_delete` got an empty key, or a key or value longer
+    /// than `KV_MAX_KEY_SIZE` / `KV_MAX_VALUE_SIZE`; nothing was written.
+    KvInvalidEntry,
+    /// `kv_put` found no room: the live entries do not fit in one page.
+    KvFull,
     /// Backend-level I/O failure while executing a storage operation.
     ///
     /// Canonical `code` mapping:
     /// Runtime codes:
     /// - `1`: memory backend save path received oversized block bytes.
     /// - `2`: memory backend read path failed to parse stored slot bytes.
+    /// - `3`: memory backend decommission verification found non-zero bytes.
     /// - `210`: RP2040 flash page read failed.
     /// - `211`: RP2040 flash page erase failed.
     /// - `212`: RP2040 flash page write failed.
     /// - `213`: RP2040 save path reached an unreachable backend branch.
+    /// - `214`: RP2040 flash unique id read failed.
+    /// - `215`: RP2040 decommission verification found a non-erased byte.
     /// - `220`: RP2040 flash page read failed during retrieve path.
+    /// - `300`: archive `SliceSink` buffer is full.
+    /// - `301`: archive `SliceSource` is exhausted (truncated archive).
+    /// - `302`: archive magic or format version mismatch.
+    /// - `303`: archive control data or block record is malformed.
     ///
     /// Test-only codes (RP2040 mock flash):
     /// - `230`: mock flash read out of bounds.
     /// - `231`: mock flash erase range invalid/out of bounds.
     /// - `232`: mock flash write out of bounds.
+    /// - `233`: mock flash simulated power loss.
     BackendIo {
         /// Backend-local error code.
         code: u16,
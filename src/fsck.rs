@@ -0,0 +1,156 @@
+/*! Offline consistency check (`fsck`) types shared by all backends.
+
+Backends scan every control-plane replica and block slot, report each anomaly
+through a caller callback as an `FsckFinding`, and summarize the scan in an
+`FsckReport`. With `FsckOptions::repair` set, backends rebuild invalid replicas
+from the primary replica and clear broken slots back to the empty state.
+*/
+
+use crate::StorageIndex;
+
+/// Options controlling an `fsck` run.
+#[derive(Clone, Copy, Default)]
+pub struct FsckOptions {
+    /// Rewrites invalid replicas and clears corrupt slots when `true`.
+    pub repair: bool,
+}
+
+/// Single anomaly detected by an `fsck` run.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FsckIssue {
+    /// Replica is erased while another replica holds valid control data.
+    ReplicaErased {
+        /// Zero-based replica index.
+        replica_index: usize,
+    },
+    /// Replica failed CRC or structural validation.
+    ReplicaCorrupted {
+        /// Zero-based replica index.
+        replica_index: usize,
+    },
+    /// Replica holds control data written with incompatible constants.
+    ReplicaIncompatible {
+        /// Zero-based replica index.
+        replica_index: usize,
+    },
+    /// Replica is valid but differs from the primary replica.
+    ReplicaDisagreement {
+        /// Zero-based replica index.
+        replica_index: usize,
+    },
+    /// Replica page has non-zero bytes after the control-plane entry.
+    ReplicaPaddingAnomaly {
+        /// Zero-based replica index.
+        replica_index: usize,
+    },
+    /// Valid replica page carries no layout superblock (written by an older release).
+    SuperblockMissing {
+        /// Zero-based replica index.
+        replica_index: usize,
+    },
+    /// Slot content does not match its stored hash or does not parse as a block.
+    SlotIntegrityFailure {
+        /// Slot index.
+        storage_index: StorageIndex,
+    },
+    /// Slot was only partially programmed (hash area still erased).
+    SlotHalfWritten {
+        /// Slot index.
+        storage_index: StorageIndex,
+    },
+    /// Empty slot holds stray non-empty bytes.
+    SlotPaddingAnomaly {
+        /// Slot index.
+        storage_index: StorageIndex,
+    },
+    /// Unused bytes after the last slot of a block page are not erased.
+    PagePaddingAnomaly {
+        /// Flash address of the block page.
+        page_address: usize,
+    },
+}
+
+impl FsckIssue {
+    /// Returns a stable machine-readable issue code.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::FsckIssue;
+    ///
+    /// let issue = FsckIssue::SlotHalfWritten { storage_index: 3 };
+    /// assert_eq!(issue.code(), "slot-half-written");
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match self {
+            FsckIssue::ReplicaErased { .. } => "replica-erased",
+            FsckIssue::ReplicaCorrupted { .. } => "replica-corrupted",
+            FsckIssue::ReplicaIncompatible { .. } => "replica-incompatible",
+            FsckIssue::ReplicaDisagreement { .. } => "replica-disagreement",
+            FsckIssue::ReplicaPaddingAnomaly { .. } => "replica-padding-anomaly",
+            FsckIssue::SuperblockMissing { .. } => "superblock-missing",
+            FsckIssue::SlotIntegrityFailure { .. } => "slot-integrity-failure",
+            FsckIssue::SlotHalfWritten { .. } => "slot-half-written",
+            FsckIssue::SlotPaddingAnomaly { .. } => "slot-padding-anomaly",
+            FsckIssue::PagePaddingAnomaly { .. } => "page-padding-anomaly",
+        }
+    }
+}
+
+/// Anomaly reported to the `fsck` callback together with its repair outcome.
+#[derive(Clone, Copy)]
+pub struct FsckFinding {
+    /// Detected anomaly.
+    pub issue: FsckIssue,
+    /// `true` when repair mode rewrote the affected replica, slot or page.
+    pub repaired: bool,
+}
+
+/// Summary of an `fsck` run.
+#[derive(Clone, Copy, Default)]
+pub struct FsckReport {
+    /// Number of control-plane replicas scanned.
+    pub replicas_checked: u32,
+    /// Number of replicas that decoded successfully.
+    pub replicas_valid: u32,
+    /// Number of block slots scanned.
+    pub slots_checked: u32,
+    /// Number of slots holding a verified block.
+    pub slots_occupied: u32,
+    /// Number of anomalies found.
+    pub issues_found: u32,
+    /// Number of anomalies repaired.
+    pub issues_repaired: u32,
+}
+
+impl FsckReport {
+    /// Returns `true` when the scan found no anomalies.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::FsckReport;
+    ///
+    /// assert!(FsckReport::default().is_clean());
+    /// ```
+    pub fn is_clean(&self) -> bool {
+        self.issues_found == 0
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        issue: FsckIssue,
+        repaired: bool,
+        on_finding: &mut dyn FnMut(&FsckFinding),
+    ) {
+        self.issues_found += 1;
+        if repaired {
+            self.issues_repaired += 1;
+        }
+        on_finding(&FsckFinding { issue, repaired });
+    }
+}
@@ -0,0 +1,146 @@
+/*! Control-plane replica health report shared by all backends.
+
+`load_control_data` silently rebuilds replicas that fail to decode from the
+first valid one. `control_plane_health` runs the same selection but reports
+the status of every replica, which one served as the primary and whether a
+repair write happened, so a failing flash sector shows up before the last
+replica goes. Its repair also rewrites replicas that decode but differ from
+the primary, which `load_control_data` leaves alone.
+*/
+
+use crate::MAX_CONTROL_PLANE_COUNT;
+use crate::StorageError;
+use crate::control_plane::CONTROL_PLANE_ENTRY_SIZE;
+use zeroize::Zeroizing;
+
+/// Status of one control-plane replica.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplicaStatus {
+    /// Replica decodes and matches the primary replica.
+    Valid,
+    /// Replica failed CRC or structural validation.
+    CrcMismatch,
+    /// Replica holds control data written with incompatible constants.
+    Incompatible,
+    /// Replica is erased.
+    Erased,
+    /// Replica is intact but its wrapped private key does not open with the
+    /// configured key protection.
+    KeyUnwrapFailed,
+    /// Replica decodes but its bytes differ from the primary replica.
+    DiffersFromPrimary,
+}
+
+impl ReplicaStatus {
+    /// Returns a stable machine-readable status code.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::ReplicaStatus;
+    ///
+    /// assert_eq!(ReplicaStatus::CrcMismatch.code(), "crc-mismatch");
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match self {
+            ReplicaStatus::Valid => "valid",
+            ReplicaStatus::CrcMismatch => "crc-mismatch",
+            ReplicaStatus::Incompatible => "incompatible",
+            ReplicaStatus::Erased => "erased",
+            ReplicaStatus::KeyUnwrapFailed => "key-unwrap-failed",
+            ReplicaStatus::DiffersFromPrimary => "differs-from-primary",
+        }
+    }
+}
+
+/// Per-replica health of the control plane.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ControlPlaneHealth {
+    /// Status per replica; only the first `replica_count` entries are used.
+    pub replicas: [ReplicaStatus; MAX_CONTROL_PLANE_COUNT],
+    /// Number of configured replicas.
+    pub replica_count: usize,
+    /// Replica `load_control_data` reads from, or `None` when no replica decodes.
+    pub primary_replica: Option<usize>,
+    /// `true` when at least one replica was rewritten from the primary.
+    pub repaired: bool,
+}
+
+impl ControlPlaneHealth {
+    /// Returns the statuses of the configured replicas.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_crypto::PRIVATE_KEY_SIZE;
+    /// use moonblokz_storage::{INIT_PARAMS_SIZE, MemoryBackend, ReplicaStatus, StorageTrait};
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// assert!(backend.init([1u8; PRIVATE_KEY_SIZE], 7, [0u8; INIT_PARAMS_SIZE]).is_ok());
+    /// let health = backend.control_plane_health(true).unwrap();
+    /// assert!(health.statuses().iter().all(|status| *status == ReplicaStatus::Valid));
+    /// ```
+    pub fn statuses(&self) -> &[ReplicaStatus] {
+        &self.replicas[..self.replica_count]
+    }
+
+    /// Returns `true` when every configured replica is valid.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_chain_types::MAX_BLOCK_SIZE;
+    /// use moonblokz_storage::MemoryBackend;
+    ///
+    /// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new();
+    /// assert!(!backend.control_plane_health(true).unwrap().is_healthy());
+    /// ```
+    pub fn is_healthy(&self) -> bool {
+        self.statuses().iter().all(|status| *status == ReplicaStatus::Valid)
+    }
+
+    pub(crate) fn new(replica_count: usize) -> Self {
+        Self {
+            replicas: [ReplicaStatus::Erased; MAX_CONTROL_PLANE_COUNT],
+            replica_count,
+            primary_replica: None,
+            repaired: false,
+        }
+    }
+
+    /// Classifies replica `replica_index` from its raw entry and decode result.
+    ///
+    /// The first replica that decodes becomes the primary, exactly as in
+    /// `load_control_data`; later decodable replicas are compared to it byte
+    /// for byte.
+    pub(crate) fn classify(
+        &mut self,
+        replica_index: usize,
+        entry: Zeroizing<[u8; CONTROL_PLANE_ENTRY_SIZE]>,
+        decoded: Result<(), StorageError>,
+        primary: &mut Option<Zeroizing<[u8; CONTROL_PLANE_ENTRY_SIZE]>>,
+    ) {
+        self.replicas[replica_index] = match decoded {
+            Ok(()) => match primary {
+                None => {
+                    self.primary_replica = Some(replica_index);
+                    *primary = Some(entry);
+                    ReplicaStatus::Valid
+                }
+                Some(primary_entry) if **primary_entry != *entry => ReplicaStatus::DiffersFromPrimary,
+                Some(_) => ReplicaStatus::Valid,
+            },
+            Err(StorageError::ControlPlaneUninitialized) => ReplicaStatus::Erased,
+            Err(StorageError::ControlPlaneIncompatible) => ReplicaStatus::Incompatible,
+            Err(StorageError::KeyUnwrapFailed) => ReplicaStatus::KeyUnwrapFailed,
+            Err(_) => ReplicaStatus::CrcMismatch,
+        };
+    }
+}
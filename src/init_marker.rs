@@ -0,0 +1,155 @@
+/*! Marker for an RP2040 `init` that has not finished erasing its region.
+
+`Rp2040Backend::init` first replaces every control-plane replica with an init
+marker, then erases the block pages in chunks of `INIT_ERASE_CHUNK_PAGES` and
+finally writes the control-plane entry the marker carries. While any replica
+holds a marker the region reports `StorageError::InitInProgress`, so a power
+loss mid-erase is visible instead of leaving a half-erased region behind.
+
+With two or more replicas, progress checkpoints are written round-robin to
+one replica at a time with an increasing sequence number; the newest readable
+marker is the resume point. Erasing is idempotent, so resuming from an older
+checkpoint only repeats some erases.
+
+Layout (all integers little-endian):
+
+`magic:[u8;4] = "MBIM", version:u8, sequence:u32, region_start:u32,
+region_end:u32, pages_erased:u32, pages_total:u32,
+entry:[u8; CONTROL_PLANE_ENTRY_SIZE], crc32:u32`
+*/
+
+use crate::StorageError;
+use crate::control_plane::CONTROL_PLANE_ENTRY_SIZE;
+use crate::record_header::{self, HEADER_SIZE};
+use zeroize::Zeroizing;
+
+/// Init marker magic bytes.
+pub const INIT_MARKER_MAGIC: [u8; 4] = *b"MBIM";
+/// Init marker format version.
+pub const INIT_MARKER_VERSION: u8 = 1;
+/// Serialized init marker size in bytes.
+pub const INIT_MARKER_SIZE: usize = ENTRY_OFFSET + CONTROL_PLANE_ENTRY_SIZE + 4;
+/// Block pages erased between two progress reports and checkpoints.
+pub const INIT_ERASE_CHUNK_PAGES: usize = 16;
+
+const FIELDS_OFFSET: usize = HEADER_SIZE;
+const ENTRY_OFFSET: usize = FIELDS_OFFSET + 5 * 4;
+const CRC32_OFFSET: usize = ENTRY_OFFSET + CONTROL_PLANE_ENTRY_SIZE;
+
+/// Erase progress of an `init`, reported after every chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InitProgress {
+    /// Block pages erased so far.
+    pub pages_erased: usize,
+    /// Block pages in the region.
+    pub pages_total: usize,
+}
+
+/// Decoded init marker.
+pub(crate) struct InitMarker {
+    pub(crate) sequence: u32,
+    pub(crate) region_start: u32,
+    pub(crate) region_end: u32,
+    /// Block pages `0..pages_erased` are known to be erased.
+    pub(crate) pages_erased: u32,
+    pub(crate) pages_total: u32,
+    pub(crate) entry: Zeroizing<[u8; CONTROL_PLANE_ENTRY_SIZE]>,
+}
+
+impl InitMarker {
+    pub(crate) fn progress(&self) -> InitProgress {
+        InitProgress {
+            pages_erased: self.pages_erased as usize,
+            pages_total: self.pages_total as usize,
+        }
+    }
+
+    pub(crate) fn encode(&self) -> Zeroizing<[u8; INIT_MARKER_SIZE]> {
+        let mut out = Zeroizing::new([0u8; INIT_MARKER_SIZE]);
+        record_header::write_header(&mut out[..], &INIT_MARKER_MAGIC, INIT_MARKER_VERSION);
+        record_header::write_fields(
+            &mut out[..],
+            FIELDS_OFFSET,
+            &[
+                self.sequence,
+                self.region_start,
+                self.region_end,
+                self.pages_erased,
+                self.pages_total,
+            ],
+        );
+        out[ENTRY_OFFSET..CRC32_OFFSET].copy_from_slice(&self.entry[..]);
+        record_header::seal(&mut out[..]);
+        out
+    }
+
+    /// Returns `Ok(None)` when `bytes` hold no valid marker (wrong magic or
+    /// CRC), for example a regular control-plane replica.
+    pub(crate) fn decode(bytes: &[u8; INIT_MARKER_SIZE]) -> Result<Option<Self>, StorageError> {
+        if !record_header::check(bytes, &INIT_MARKER_MAGIC, INIT_MARKER_VERSION)? {
+            return Ok(None);
+        }
+
+        let mut values = [0u32; 5];
+        record_header::read_fields(bytes, FIELDS_OFFSET, &mut values);
+        let mut entry = Zeroizing::new([0u8; CONTROL_PLANE_ENTRY_SIZE]);
+        entry.copy_from_slice(&bytes[ENTRY_OFFSET..CRC32_OFFSET]);
+
+        Ok(Some(Self {
+            sequence: values[0],
+            region_start: values[1],
+            region_end: values[2],
+            pages_erased: values[3],
+            pages_total: values[4],
+            entry,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_marker() -> InitMarker {
+        InitMarker {
+            sequence: 3,
+            region_start: 0x18_0000,
+            region_end: 0x20_0000,
+            pages_erased: 32,
+            pages_total: 125,
+            entry: Zeroizing::new([0x5A; CONTROL_PLANE_ENTRY_SIZE]),
+        }
+    }
+
+    #[test]
+    fn marker_round_trips() {
+        let encoded = test_marker().encode();
+        let decoded = match InitMarker::decode(&encoded) {
+            Ok(Some(value)) => value,
+            _ => panic!("marker should decode"),
+        };
+        assert_eq!(decoded.sequence, 3);
+        assert_eq!(decoded.region_start, 0x18_0000);
+        assert_eq!(decoded.region_end, 0x20_0000);
+        assert_eq!(
+            decoded.progress(),
+            InitProgress {
+                pages_erased: 32,
+                pages_total: 125,
+            }
+        );
+        assert_eq!(*decoded.entry, [0x5A; CONTROL_PLANE_ENTRY_SIZE]);
+    }
+
+    #[test]
+    fn damaged_or_foreign_page_is_not_a_marker() {
+        let mut encoded = test_marker().encode();
+        encoded[ENTRY_OFFSET] ^= 0x01;
+        assert!(matches!(InitMarker::decode(&encoded), Ok(None)));
+        assert!(matches!(InitMarker::decode(&[0xFF; INIT_MARKER_SIZE]), Ok(None)));
+
+        let mut newer = test_marker().encode();
+        newer[4] = INIT_MARKER_VERSION + 1;
+        assert!(matches!(InitMarker::decode(&newer), Err(StorageError::ControlPlaneIncompatible)));
+    }
+}
@@ -0,0 +1,586 @@
+/*! Crash-safe key-value store over dedicated pages, shared by all backends.
+
+`kv_get`, `kv_put` and `kv_delete` keep small firmware values (peer tables,
+the last known tip, counters, settings) next to the control plane. The store
+is a log over `KvPages`: one logical page is active and records are appended
+to it. A record supersedes every earlier record for its key, and a delete
+appends a tombstone. When the active page has no room left, the live records
+are compacted into the next logical page, which becomes active once its
+header is programmed.
+
+Every logical page is mirrored: the first half of the pages holds one copy,
+the second half the other, so a store needs an even number of pages and at
+least four. Appends and compactions write the first copy, then the second.
+
+Crash safety:
+- Page headers and records carry a CRC32. Like control-plane replicas, the
+  page with a valid header and the highest generation wins.
+- A power loss while appending leaves a damaged record that ends the log; the
+  next write compacts, dropping it, so the previous value of the key is kept.
+- Compaction programs the header of the new page last. Until then the old
+  page stays active, and afterwards its lower generation hides it.
+- Like control-plane replicas, the copies are selected by CRC: a header or
+  record that decays after it was written is taken from the other copy,
+  provided that copy carries the same generation. When the copies disagree,
+  the next write compacts into a fresh pair, rewriting both.
+
+Layout (all integers little-endian):
+
+- page header: `magic:[u8;4] = "MBKV", version:u8, reserved:[u8;3] = 0, generation:u32, crc32:u32`
+- record: `kind:u8 (1 put, 2 delete), key_len:u8, value_len:u16, crc32:u32, key, value`;
+  the CRC covers the first four bytes, the key and the value.
+*/
+
+use crate::StorageError;
+use crate::control_plane::crc32;
+
+/// Size of one key-value page in bytes (the RP2040 flash erase page).
+pub const KV_PAGE_SIZE: usize = 4096;
+/// Maximum key length in bytes.
+pub const KV_MAX_KEY_SIZE: usize = 32;
+/// Maximum value length in bytes.
+pub const KV_MAX_VALUE_SIZE: usize = 256;
+/// Key-value page header magic bytes.
+pub const KV_MAGIC: [u8; 4] = *b"MBKV";
+/// Key-value page format version.
+pub const KV_VERSION: u8 = 1;
+
+const PAGE_HEADER_SIZE: usize = 16;
+const RECORD_HEADER_SIZE: usize = 8;
+const MAX_RECORD_SIZE: usize = RECORD_HEADER_SIZE + KV_MAX_KEY_SIZE + KV_MAX_VALUE_SIZE;
+const KIND_PUT: u8 = 1;
+const KIND_DELETE: u8 = 2;
+const KIND_ERASED: u8 = 0xFF;
+
+/// Value returned by `kv_get`.
+#[derive(Clone, Copy)]
+pub struct KvValue {
+    len: usize,
+    bytes: [u8; KV_MAX_VALUE_SIZE],
+}
+
+impl KvValue {
+    /// Returns the stored value bytes.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// if let Some(tip) = backend.kv_get(b"tip")? {
+    ///     restore_tip(tip.as_bytes());
+    /// }
+    /// ```
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+/// Erase-unit storage behind a key-value log or a `counter`.
+pub(crate) trait KvPages {
+    /// Number of pages of `KV_PAGE_SIZE` bytes.
+    fn page_count(&self) -> usize;
+
+    fn read(&self, page: usize, offset: usize, out: &mut [u8]) -> Result<(), StorageError>;
+
+    /// Programs erased bytes of `page`.
+    fn program(&mut self, page: usize, offset: usize, bytes: &[u8]) -> Result<(), StorageError>;
+
+    /// Sets every byte of `page` to `0xFF`.
+    fn erase(&mut self, page: usize) -> Result<(), StorageError>;
+}
+
+/// RAM pages for the memory backend.
+impl KvPages for [u8] {
+    fn page_count(&self) -> usize {
+        self.len() / KV_PAGE_SIZE
+    }
+
+    fn read(&self, page: usize, offset: usize, out: &mut [u8]) -> Result<(), StorageError> {
+        let start = page * KV_PAGE_SIZE + offset;
+        out.copy_from_slice(&self[start..start + out.len()]);
+        Ok(())
+    }
+
+    fn program(&mut self, page: usize, offset: usize, bytes: &[u8]) -> Result<(), StorageError> {
+        let start = page * KV_PAGE_SIZE + offset;
+        // Like NOR flash, programming only clears bits.
+        for (stored, byte) in self[start..start + bytes.len()].iter_mut().zip(bytes) {
+            *stored &= *byte;
+        }
+        Ok(())
+    }
+
+    fn erase(&mut self, page: usize) -> Result<(), StorageError> {
+        self[page * KV_PAGE_SIZE..(page + 1) * KV_PAGE_SIZE].fill(0xFF);
+        Ok(())
+    }
+}
+
+/// Content of the log at one record offset.
+enum KvRecord {
+    /// End of the log: erased, damaged or past the page end.
+    End,
+    Entry {
+        kind: u8,
+        key: (usize, usize),
+        value: (usize, usize),
+        len: usize,
+    },
+}
+
+fn encode_page_header(generation: u32) -> [u8; PAGE_HEADER_SIZE] {
+    let mut out = [0u8; PAGE_HEADER_SIZE];
+    out[..4].copy_from_slice(&KV_MAGIC);
+    out[4] = KV_VERSION;
+    out[8..12].copy_from_slice(&generation.to_le_bytes());
+    let crc = crc32(&out[..12]);
+    out[12..].copy_from_slice(&crc.to_le_bytes());
+    out
+}
+
+/// Returns the generation of a valid page header of the current version.
+fn decode_page_header(bytes: &[u8; PAGE_HEADER_SIZE]) -> Option<u32> {
+    let mut values = [0u8; 4];
+    values.copy_from_slice(&bytes[12..]);
+    if bytes[..4] != KV_MAGIC || bytes[4] != KV_VERSION || u32::from_le_bytes(values) != crc32(&bytes[..12]) {
+        return None;
+    }
+    values.copy_from_slice(&bytes[8..12]);
+    Some(u32::from_le_bytes(values))
+}
+
+fn record_crc(page: &[u8], offset: usize, len: usize) -> u32 {
+    let mut hashed = [0u8; MAX_RECORD_SIZE];
+    let body = len - RECORD_HEADER_SIZE;
+    hashed[..4].copy_from_slice(&page[offset..offset + 4]);
+    hashed[4..4 + body].copy_from_slice(&page[offset + RECORD_HEADER_SIZE..offset + len]);
+    crc32(&hashed[..4 + body])
+}
+
+/// Decodes the record at `offset`; `page` may end before the page does.
+fn decode_record(page: &[u8], offset: usize) -> KvRecord {
+    if offset + RECORD_HEADER_SIZE > page.len() || page[offset] == KIND_ERASED {
+        return KvRecord::End;
+    }
+    let kind = page[offset];
+    let key_len = page[offset + 1] as usize;
+    let value_len = u16::from_le_bytes([page[offset + 2], page[offset + 3]]) as usize;
+    let len = RECORD_HEADER_SIZE + key_len + value_len;
+    if (kind != KIND_PUT && kind != KIND_DELETE)
+        || key_len == 0
+        || key_len > KV_MAX_KEY_SIZE
+        || value_len > KV_MAX_VALUE_SIZE
+        || offset + len > page.len()
+    {
+        return KvRecord::End;
+    }
+
+    let mut crc = [0u8; 4];
+    crc.copy_from_slice(&page[offset + 4..offset + RECORD_HEADER_SIZE]);
+    if u32::from_le_bytes(crc) != record_crc(page, offset, len) {
+        return KvRecord::End;
+    }
+    let key_start = offset + RECORD_HEADER_SIZE;
+    KvRecord::Entry {
+        kind,
+        key: (key_start, key_start + key_len),
+        value: (key_start + key_len, key_start + key_len + value_len),
+        len,
+    }
+}
+
+/// Writes a record at `offset` of `page`, which must have room for it.
+fn encode_record(page: &mut [u8], offset: usize, kind: u8, key: &[u8], value: &[u8]) -> usize {
+    let len = RECORD_HEADER_SIZE + key.len() + value.len();
+    page[offset] = kind;
+    page[offset + 1] = key.len() as u8;
+    page[offset + 2..offset + 4].copy_from_slice(&(value.len() as u16).to_le_bytes());
+    let key_start = offset + RECORD_HEADER_SIZE;
+    page[key_start..key_start + key.len()].copy_from_slice(key);
+    page[key_start + key.len()..offset + len].copy_from_slice(value);
+    let crc = record_crc(page, offset, len);
+    page[offset + 4..offset + RECORD_HEADER_SIZE].copy_from_slice(&crc.to_le_bytes());
+    len
+}
+
+/// Physical page holding `copy` (0 or 1) of logical page `logical`.
+fn copy_page<P: KvPages + ?Sized>(pages: &P, logical: usize, copy: usize) -> usize {
+    logical + copy * (pages.page_count() / 2)
+}
+
+/// Returns the generation recorded in the header of `copy` of `logical`.
+fn copy_generation<P: KvPages + ?Sized>(pages: &P, logical: usize, copy: usize) -> Result<Option<u32>, StorageError> {
+    let mut header = [0u8; PAGE_HEADER_SIZE];
+    pages.read(copy_page(pages, logical, copy), 0, &mut header)?;
+    Ok(decode_page_header(&header))
+}
+
+/// Returns the active logical page and its generation, `None` for an
+/// unformatted store. Either copy's header counts.
+fn active_page<P: KvPages + ?Sized>(pages: &P) -> Result<Option<(usize, u32)>, StorageError> {
+    let mut active: Option<(usize, u32)> = None;
+    let mut logical = 0usize;
+    while logical < pages.page_count() / 2 {
+        let mut copy = 0usize;
+        while copy < 2 {
+            if let Some(generation) = copy_generation(pages, logical, copy)? {
+                if active.is_none_or(|(_, current)| generation > current) {
+                    active = Some((logical, generation));
+                }
+            }
+            copy += 1;
+        }
+        logical += 1;
+    }
+    Ok(active)
+}
+
+/// Reads logical page `logical` into `merged`, taking each record from the
+/// copy with the newer header and, where that record fails its CRC, from the
+/// other copy if it carries the same generation. Returns the end of the
+/// merged log and whether both copies hold exactly that log followed by
+/// erased bytes.
+fn read_mirrored<P: KvPages + ?Sized>(
+    pages: &P,
+    logical: usize,
+    merged: &mut [u8; KV_PAGE_SIZE],
+) -> Result<(usize, bool), StorageError> {
+    let generations = [copy_generation(pages, logical, 0)?, copy_generation(pages, logical, 1)?];
+    let (first, second) = if generations[1] > generations[0] { (1, 0) } else { (0, 1) };
+    let mirrored = generations[first].is_some() && generations[second] == generations[first];
+    pages.read(copy_page(pages, logical, first), 0, merged)?;
+    let mirror = copy_page(pages, logical, second);
+
+    let mut other = [0u8; MAX_RECORD_SIZE];
+    let mut agree = mirrored;
+    let mut offset = PAGE_HEADER_SIZE;
+    loop {
+        let available = if mirrored { MAX_RECORD_SIZE.min(KV_PAGE_SIZE - offset) } else { 0 };
+        pages.read(mirror, offset, &mut other[..available])?;
+        let len = match (decode_record(&merged[..], offset), decode_record(&other[..available], 0)) {
+            (KvRecord::Entry { len, .. }, _) => len,
+            (KvRecord::End, KvRecord::Entry { len, .. }) => {
+                merged[offset..offset + len].copy_from_slice(&other[..len]);
+                agree = false;
+                len
+            }
+            (KvRecord::End, KvRecord::End) => break,
+        };
+        agree &= mirrored && merged[offset..offset + len] == other[..len];
+        offset += len;
+    }
+
+    agree &= merged[offset..].iter().all(|byte| *byte == 0xFF);
+    let mut tail = offset;
+    while agree && tail < KV_PAGE_SIZE {
+        let chunk = MAX_RECORD_SIZE.min(KV_PAGE_SIZE - tail);
+        pages.read(mirror, tail, &mut other[..chunk])?;
+        agree &= other[..chunk].iter().all(|byte| *byte == 0xFF);
+        tail += chunk;
+    }
+    Ok((offset, agree))
+}
+
+/// Calls `visit` with every intact record and returns the end of the log.
+fn for_each_record(page: &[u8; KV_PAGE_SIZE], visit: &mut dyn FnMut(usize, u8, &[u8], &[u8])) -> usize {
+    let mut offset = PAGE_HEADER_SIZE;
+    loop {
+        match decode_record(page, offset) {
+            KvRecord::End => return offset,
+            KvRecord::Entry { kind, key, value, len } => {
+                visit(offset, kind, &page[key.0..key.1], &page[value.0..value.1]);
+                offset += len;
+            }
+        }
+    }
+}
+
+/// Returns whether a store of `pages` pages is valid: 0 (disabled), or an
+/// even count of at least 4, two copies of at least two logical pages.
+pub(crate) fn valid_page_count(pages: usize) -> bool {
+    pages == 0 || (pages >= 4 && pages % 2 == 0)
+}
+
+fn validate(key: &[u8], value: &[u8]) -> Result<(), StorageError> {
+    if key.is_empty() || key.len() > KV_MAX_KEY_SIZE || value.len() > KV_MAX_VALUE_SIZE {
+        return Err(StorageError::KvInvalidEntry);
+    }
+    Ok(())
+}
+
+pub(crate) fn get<P: KvPages + ?Sized>(pages: &P, key: &[u8]) -> Result<Option<KvValue>, StorageError> {
+    let Some((active, _)) = active_page(pages)? else {
+        return Ok(None);
+    };
+    let mut page = [0u8; KV_PAGE_SIZE];
+    read_mirrored(pages, active, &mut page)?;
+
+    let mut found: Option<KvValue> = None;
+    for_each_record(&page, &mut |_, kind, record_key, value| {
+        if record_key != key {
+            return;
+        }
+        found = if kind == KIND_PUT {
+            let mut bytes = [0u8; KV_MAX_VALUE_SIZE];
+            bytes[..value.len()].copy_from_slice(value);
+            Some(KvValue {
+                len: value.len(),
+                bytes,
+            })
+        } else {
+            None
+        };
+    });
+    Ok(found)
+}
+
+pub(crate) fn put<P: KvPages + ?Sized>(pages: &mut P, key: &[u8], value: &[u8]) -> Result<(), StorageError> {
+    validate(key, value)?;
+    append(pages, KIND_PUT, key, value)
+}
+
+pub(crate) fn delete<P: KvPages + ?Sized>(pages: &mut P, key: &[u8]) -> Result<(), StorageError> {
+    validate(key, &[])?;
+    if get(pages, key)?.is_none() {
+        return Ok(());
+    }
+    append(pages, KIND_DELETE, key, &[])
+}
+
+fn append<P: KvPages + ?Sized>(pages: &mut P, kind: u8, key: &[u8], value: &[u8]) -> Result<(), StorageError> {
+    if pages.page_count() == 0 || !valid_page_count(pages.page_count()) {
+        return Err(StorageError::InvalidConfiguration);
+    }
+    let mut old = [0xFFu8; KV_PAGE_SIZE];
+    let (next, generation) = match active_page(pages)? {
+        Some((active, generation)) => {
+            let (end, clean) = read_mirrored(pages, active, &mut old)?;
+            let len = RECORD_HEADER_SIZE + key.len() + value.len();
+            if clean && end + len <= KV_PAGE_SIZE {
+                let mut record = [0xFFu8; MAX_RECORD_SIZE];
+                encode_record(&mut record, 0, kind, key, value);
+                pages.program(copy_page(pages, active, 0), end, &record[..len])?;
+                return pages.program(copy_page(pages, active, 1), end, &record[..len]);
+            }
+            ((active + 1) % (pages.page_count() / 2), generation.wrapping_add(1))
+        }
+        None => (0, 1),
+    };
+    compact(pages, &old, next, generation, kind, key, value)
+}
+
+/// Calls `visit` with the key and value of every live put in `old` that the
+/// record for `key` does not replace.
+fn for_each_live(old: &[u8; KV_PAGE_SIZE], key: &[u8], visit: &mut dyn FnMut(&[u8], &[u8])) {
+    for_each_record(old, &mut |offset, record_kind, record_key, record_value| {
+        if record_kind != KIND_PUT || record_key == key {
+            return;
+        }
+        let mut superseded = false;
+        for_each_record(old, &mut |later, _, later_key, _| {
+            superseded |= later > offset && later_key == record_key;
+        });
+        if !superseded {
+            visit(record_key, record_value);
+        }
+    });
+}
+
+/// Writes the live records of `old` plus the new record to both copies of
+/// logical page `next` and makes it active. Records are programmed one at a
+/// time, so no second page buffer is needed.
+fn compact<P: KvPages + ?Sized>(
+    pages: &mut P,
+    old: &[u8; KV_PAGE_SIZE],
+    next: usize,
+    generation: u32,
+    kind: u8,
+    key: &[u8],
+    value: &[u8],
+) -> Result<(), StorageError> {
+    let mut end = PAGE_HEADER_SIZE;
+    for_each_live(old, key, &mut |record_key, record_value| {
+        end += RECORD_HEADER_SIZE + record_key.len() + record_value.len();
+    });
+    // A fresh page needs no tombstone.
+    if kind == KIND_PUT {
+        end += RECORD_HEADER_SIZE + key.len() + value.len();
+    }
+    if end > KV_PAGE_SIZE {
+        return Err(StorageError::KvFull);
+    }
+
+    let mut record = [0xFFu8; MAX_RECORD_SIZE];
+    let mut copy = 0usize;
+    while copy < 2 {
+        let target = copy_page(pages, next, copy);
+        pages.erase(target)?;
+        let mut offset = PAGE_HEADER_SIZE;
+        let mut result = Ok(());
+        for_each_live(old, key, &mut |record_key, record_value| {
+            if result.is_ok() {
+                let len = encode_record(&mut record, 0, KIND_PUT, record_key, record_value);
+                result = pages.program(target, offset, &record[..len]);
+                offset += len;
+            }
+        });
+        result?;
+        if kind == KIND_PUT {
+            let len = encode_record(&mut record, 0, kind, key, value);
+            pages.program(target, offset, &record[..len])?;
+        }
+        pages.program(target, 0, &encode_page_header(generation))?;
+        copy += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FOUR_PAGES: usize = 4 * KV_PAGE_SIZE;
+
+    #[test]
+    fn ram_pages_only_clear_bits_until_erased() {
+        let mut pages = [0u8; FOUR_PAGES];
+        assert!(pages[..].erase(1).is_ok());
+        assert!(pages[..].program(1, 0, &[0xF0]).is_ok());
+        assert!(pages[..].program(1, 0, &[0x3C]).is_ok());
+        assert!(pages[..].program(0, 0, &[0xFF]).is_ok());
+        assert_eq!(pages[KV_PAGE_SIZE], 0x30);
+        assert_eq!(pages[0], 0);
+    }
+
+    #[test]
+    fn put_get_delete_round_trip_and_survive_compaction() {
+        let mut pages = [0u8; FOUR_PAGES];
+        assert!(matches!(get(&pages[..], b"tip"), Ok(None)));
+        assert!(delete(&mut pages[..], b"tip").is_ok());
+
+        assert!(put(&mut pages[..], b"tip", &[1, 2, 3]).is_ok());
+        assert!(put(&mut pages[..], b"boots", &[7]).is_ok());
+        assert!(matches!(get(&pages[..], b"tip"), Ok(Some(value)) if value.as_bytes() == [1, 2, 3]));
+
+        // Every put of a full value eventually forces a compaction.
+        let mut round = 0u8;
+        while round < 40 {
+            assert!(put(&mut pages[..], b"tip", &[round; KV_MAX_VALUE_SIZE]).is_ok());
+            round += 1;
+        }
+        assert!(matches!(get(&pages[..], b"tip"), Ok(Some(value)) if value.as_bytes() == [39; KV_MAX_VALUE_SIZE]));
+        assert!(matches!(get(&pages[..], b"boots"), Ok(Some(value)) if value.as_bytes() == [7]));
+        assert!(matches!(active_page(&pages[..]), Ok(Some((_, generation))) if generation > 1));
+
+        assert!(delete(&mut pages[..], b"boots").is_ok());
+        assert!(matches!(get(&pages[..], b"boots"), Ok(None)));
+        assert!(matches!(get(&pages[..], b"tip"), Ok(Some(_))));
+    }
+
+    #[test]
+    fn damaged_tail_keeps_the_previous_value_and_is_dropped_by_the_next_write() {
+        let mut pages = [0u8; FOUR_PAGES];
+        assert!(put(&mut pages[..], b"peer", &[1; 8]).is_ok());
+        let (active, _) = match active_page(&pages[..]) {
+            Ok(Some(value)) => value,
+            _ => unreachable!(),
+        };
+
+        // A torn append: the record header is programmed, the value is not.
+        let mut page = [0u8; KV_PAGE_SIZE];
+        assert!(pages[..].read(active, 0, &mut page).is_ok());
+        let end = for_each_record(&page, &mut |_, _, _, _| {});
+        let mut torn = [0xFFu8; KV_PAGE_SIZE];
+        encode_record(&mut torn, 0, KIND_PUT, b"peer", &[2; 8]);
+        assert!(pages[..].program(active, end, &torn[..RECORD_HEADER_SIZE + 4]).is_ok());
+
+        assert!(matches!(get(&pages[..], b"peer"), Ok(Some(value)) if value.as_bytes() == [1; 8]));
+        assert!(put(&mut pages[..], b"other", &[3]).is_ok());
+        assert!(matches!(active_page(&pages[..]), Ok(Some((page, 2))) if page != active));
+        assert!(matches!(get(&pages[..], b"peer"), Ok(Some(value)) if value.as_bytes() == [1; 8]));
+    }
+
+    #[test]
+    fn decayed_records_and_headers_are_read_from_the_mirror_and_rewritten_by_the_next_write() {
+        let mut pages = [0u8; FOUR_PAGES];
+        assert!(put(&mut pages[..], b"peer", &[1; 8]).is_ok());
+        assert!(put(&mut pages[..], b"tip", &[2; 4]).is_ok());
+        let (active, _) = match active_page(&pages[..]) {
+            Ok(Some(value)) => value,
+            _ => unreachable!(),
+        };
+
+        // Bits lost after the writes: first a record, then the header of the
+        // first copy.
+        let first = copy_page(&pages[..], active, 0) * KV_PAGE_SIZE;
+        pages[first + PAGE_HEADER_SIZE + RECORD_HEADER_SIZE] ^= 0x01;
+        assert!(matches!(get(&pages[..], b"peer"), Ok(Some(value)) if value.as_bytes() == [1; 8]));
+        assert!(matches!(get(&pages[..], b"tip"), Ok(Some(value)) if value.as_bytes() == [2; 4]));
+        pages[first] ^= 0x01;
+        assert!(matches!(active_page(&pages[..]), Ok(Some((page, 1))) if page == active));
+        assert!(matches!(get(&pages[..], b"peer"), Ok(Some(value)) if value.as_bytes() == [1; 8]));
+
+        // Disagreeing copies make the next write compact into a fresh pair.
+        assert!(put(&mut pages[..], b"boots", &[3]).is_ok());
+        assert!(matches!(active_page(&pages[..]), Ok(Some((page, 2))) if page != active));
+        let mut merged = [0u8; KV_PAGE_SIZE];
+        let (next, _) = match active_page(&pages[..]) {
+            Ok(Some(value)) => value,
+            _ => unreachable!(),
+        };
+        assert!(matches!(read_mirrored(&pages[..], next, &mut merged), Ok((_, true))));
+        assert!(matches!(get(&pages[..], b"peer"), Ok(Some(value)) if value.as_bytes() == [1; 8]));
+        assert!(matches!(get(&pages[..], b"tip"), Ok(Some(value)) if value.as_bytes() == [2; 4]));
+    }
+
+    #[test]
+    fn stale_mirror_records_are_ignored_after_a_torn_compaction() {
+        let mut pages = [0u8; FOUR_PAGES];
+        assert!(put(&mut pages[..], b"peer", &[1; 8]).is_ok());
+        // Only the first copy of page 1 is written before power is lost; the
+        // second still holds an older generation.
+        assert!(pages[..].erase(copy_page(&pages[..], 1, 1)).is_ok());
+        assert!(pages[..].program(copy_page(&pages[..], 1, 1), 0, &encode_page_header(0)).is_ok());
+        let mut stale = [0xFFu8; MAX_RECORD_SIZE];
+        let len = encode_record(&mut stale, 0, KIND_PUT, b"peer", &[9; 8]);
+        assert!(pages[..].program(copy_page(&pages[..], 1, 1), PAGE_HEADER_SIZE, &stale[..len]).is_ok());
+        let mut fresh = [0xFFu8; KV_PAGE_SIZE];
+        let end = PAGE_HEADER_SIZE + encode_record(&mut fresh, PAGE_HEADER_SIZE, KIND_PUT, b"peer", &[5; 8]);
+        let target = copy_page(&pages[..], 1, 0);
+        assert!(pages[..].erase(target).is_ok());
+        assert!(pages[..].program(target, PAGE_HEADER_SIZE, &fresh[PAGE_HEADER_SIZE..end]).is_ok());
+        assert!(pages[..].program(target, 0, &encode_page_header(2)).is_ok());
+
+        assert!(matches!(get(&pages[..], b"peer"), Ok(Some(value)) if value.as_bytes() == [5; 8]));
+        // The damaged fresh record must not be replaced by the stale one.
+        pages[target * KV_PAGE_SIZE + PAGE_HEADER_SIZE + RECORD_HEADER_SIZE] ^= 0x01;
+        assert!(matches!(get(&pages[..], b"peer"), Ok(None)));
+    }
+
+    #[test]
+    fn oversized_entries_and_too_few_or_odd_pages_are_rejected() {
+        let mut pages = [0u8; FOUR_PAGES];
+        assert!(matches!(put(&mut pages[..], &[], &[1]), Err(StorageError::KvInvalidEntry)));
+        assert!(matches!(
+            put(&mut pages[..], &[1; KV_MAX_KEY_SIZE + 1], &[1]),
+            Err(StorageError::KvInvalidEntry)
+        ));
+        assert!(matches!(
+            put(&mut pages[..], b"key", &[1; KV_MAX_VALUE_SIZE + 1]),
+            Err(StorageError::KvInvalidEntry)
+        ));
+        assert!(matches!(delete(&mut pages[..], &[]), Err(StorageError::KvInvalidEntry)));
+
+        let mut two = [0u8; 2 * KV_PAGE_SIZE];
+        assert!(matches!(
+            put(&mut two[..], b"key", &[1]),
+            Err(StorageError::InvalidConfiguration)
+        ));
+        let mut five = [0u8; 5 * KV_PAGE_SIZE];
+        assert!(matches!(
+            put(&mut five[..], b"key", &[1]),
+            Err(StorageError::InvalidConfiguration)
+        ));
+    }
+}
@@ -6,15 +6,41 @@ public API contracts are added incrementally by stories.
 
 #![no_std]
 
+#[cfg(any(feature = "build-script", all(feature = "cli", not(test))))]
+extern crate std;
+
 #[cfg(test)]
 mod conformance;
+pub mod archive;
+#[cfg(feature = "build-script")]
+pub mod build_script;
+pub mod control_plane;
+pub mod counter;
 pub mod error;
+pub mod fsck;
+pub mod health;
+pub mod kv_store;
+pub mod merkle;
+pub mod observer;
+mod record_header;
+pub mod sequence_index;
+pub mod superblock;
 pub mod types;
 
 #[cfg(feature = "backend-memory")]
 pub mod backend_memory;
 #[cfg(feature = "backend-rp2040")]
 pub mod backend_rp2040;
+#[cfg(feature = "backend-rp2040")]
+pub mod init_marker;
+#[cfg(feature = "backend-rp2040")]
+pub mod relocation;
+#[cfg(feature = "backend-rp2040")]
+pub mod rp2040_image;
+#[cfg(feature = "backend-rp2040")]
+pub mod sequence_log;
+#[cfg(feature = "backend-rp2040")]
+pub mod slot_meta;
 
 #[cfg(not(any(feature = "backend-memory", feature = "backend-rp2040")))]
 compile_error!("Exactly one backend feature must be enabled: backend-memory or backend-rp2040.");
@@ -22,8 +48,9 @@ compile_error!("Exactly one backend feature must be enabled: backend-memory or b
 #[cfg(all(feature = "backend-memory", feature = "backend-rp2040"))]
 compile_error!("Exactly one backend feature must be enabled: backend-memory or backend-rp2040.");
 
-use moonblokz_chain_types::Block;
+use moonblokz_chain_types::{Block, HASH_SIZE, MAX_BLOCK_SIZE, calculate_hash};
 use moonblokz_crypto::PRIVATE_KEY_SIZE;
+use zeroize::Zeroize;
 
 #[cfg(feature = "backend-memory")]
 pub use backend_memory::MemoryBackend;
@@ -57,17 +84,62 @@ pub type MoonblokzStorage<const STORAGE_SIZE: usize> = MemoryBackend<STORAGE_SIZ
 /// let _use_storage = storage;
 /// ```
 pub type MoonblokzStorage<const STORAGE_SIZE: usize> = Rp2040Backend<STORAGE_SIZE>;
+pub use control_plane::{ControlPlaneLayout, ControlPlanePlacement, KeyProtection};
 pub use error::StorageError;
-pub use types::StorageIndex;
+pub use fsck::{FsckFinding, FsckIssue, FsckOptions, FsckReport};
+pub use health::{ControlPlaneHealth, ReplicaStatus};
+pub use kv_store::KvValue;
+#[cfg(feature = "defmt")]
+pub use observer::DefmtObserver;
+#[cfg(feature = "log")]
+pub use observer::LogObserver;
+pub use observer::{NoopObserver, StorageObserver};
+pub use superblock::{LayoutField, Superblock};
+pub use types::{StorageIndex, WriteOnceSlots};
+#[cfg(feature = "slot-meta")]
+pub use types::SLOT_META_SIZE;
 
 /// Initialization parameter byte size.
 pub const INIT_PARAMS_SIZE: usize = 100;
-/// Number of replicated control-plane entries.
+/// Default number of replicated control-plane entries.
 pub const CONTROL_PLANE_COUNT: usize = 3;
+/// Largest replica count accepted by `ControlPlaneLayout::validate`.
+pub const MAX_CONTROL_PLANE_COUNT: usize = 8;
 /// Storage-library control-plane schema version.
-pub const CONTROL_PLANE_VERSION: u8 = 1;
+pub const CONTROL_PLANE_VERSION: u8 = 2;
+
+/// Returns the hash `find_by_hash` matches a stored block against.
+///
+/// The hash covers the serialized block zero-padded to `MAX_BLOCK_SIZE`, the
+/// same bytes the RP2040 backend hashes into every slot. It is a storage
+/// hash and not necessarily the hash chain logic identifies the block by.
+///
+/// Parameters:
+/// - `block`: block to hash.
+///
+/// Example:
+/// ```
+/// use moonblokz_chain_types::{Block, MAX_BLOCK_SIZE};
+/// use moonblokz_storage::block_hash;
+///
+/// let mut bytes = [0u8; MAX_BLOCK_SIZE];
+/// bytes[0] = 1;
+/// let block = Block::from_bytes(&bytes).unwrap();
+/// assert_eq!(block_hash(&block), block_hash(&block));
+/// ```
+pub fn block_hash(block: &Block) -> [u8; HASH_SIZE] {
+    let mut padded = [0u8; MAX_BLOCK_SIZE];
+    let block_bytes = block.serialized_bytes();
+    let len = block_bytes.len().min(MAX_BLOCK_SIZE);
+    padded[..len].copy_from_slice(&block_bytes[..len]);
+    calculate_hash(&padded)
+}
 
 /// Canonical control-plane data returned by `load_control_data`.
+///
+/// The private key is wiped when the value is dropped and is redacted from
+/// `Debug` output. Copying `private_key` out of the struct creates a copy the
+/// caller is responsible for wiping.
 pub struct ControlPlaneData {
     /// Persisted private key.
     pub private_key: [u8; PRIVATE_KEY_SIZE],
@@ -79,6 +151,23 @@ pub struct ControlPlaneData {
     pub chain_configuration: Option<Block>,
 }
 
+impl Drop for ControlPlaneData {
+    fn drop(&mut self) {
+        self.private_key.zeroize();
+    }
+}
+
+impl core::fmt::Debug for ControlPlaneData {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ControlPlaneData")
+            .field("private_key", &"<redacted>")
+            .field("own_node_id", &self.own_node_id)
+            .field("init_params", &self.init_params)
+            .field("chain_configuration", &self.chain_configuration.is_some())
+            .finish()
+    }
+}
+
 /// Synchronous, `no_std` storage API contract for MoonBlokz chain logic.
 pub trait StorageTrait {
     /// Initializes backend storage state.
@@ -231,4 +320,41 @@ pub trait StorageTrait {
     /// Parameters:
     /// - none.
     fn load_control_data(&mut self) -> Result<ControlPlaneData, StorageError>;
+
+    /// Returns the lowest storage index holding an intact block whose
+    /// `block_hash` is `hash`, or `None` when no slot matches.
+    ///
+    /// The key is `block_hash`: SHA-256 over the serialized block zero-padded
+    /// to `MAX_BLOCK_SIZE`, the hash the RP2040 backend stores with every
+    /// slot. It equals the hash chain logic identifies a block by only if
+    /// the chain hashes the same bytes.
+    ///
+    /// The default implementation reads slots with `read_block` from index 0
+    /// up to the first `InvalidIndex`, skipping empty and damaged slots. Both
+    /// backends override it with a scan that avoids parsing every block.
+    ///
+    /// Errors:
+    /// - `BackendIo` when a storage read fails.
+    ///
+    /// Parameters:
+    /// - `hash`: hash as returned by `block_hash`.
+    fn find_by_hash(&self, hash: &[u8; HASH_SIZE]) -> Result<Option<StorageIndex>, StorageError> {
+        let mut storage_index: StorageIndex = 0;
+        loop {
+            match self.read_block(storage_index) {
+                Ok(block) => {
+                    if block_hash(&block) == *hash {
+                        return Ok(Some(storage_index));
+                    }
+                }
+                Err(StorageError::BlockAbsent | StorageError::IntegrityFailure) => {}
+                Err(StorageError::InvalidIndex) => return Ok(None),
+                Err(err) => return Err(err),
+            }
+            storage_index = match storage_index.checked_add(1) {
+                Some(value) => value,
+                None => return Ok(None),
+            };
+        }
+    }
 }
@@ -0,0 +1,882 @@
+/*! Merkle tree over block slots for comparing stored chains.
+
+The slot hash of slot `i` is the `block_hash` of the block it holds, which is
+the hash the RP2040 backend stores next to every slot, or `EMPTY_LEAF` for an
+empty or damaged slot. The tree is shaped like RFC 6962: a leaf hashes
+`0x00 || slot_hash`, a range of more than one leaf splits after the largest
+power of two below its length, and an inner node hashes
+`0x01 || left || right`, so a leaf can never be passed off as an inner node.
+
+Two nodes with the same slot count have equal roots exactly when their slots
+hold the same blocks. When the roots differ, the peers compare `subtree_hash`
+of both halves returned by `split` and descend into the differing ones, which
+finds a differing slot in a logarithmic number of rounds. `proof` proves a
+single slot against a root.
+
+Inner nodes are kept in in-order layout: the node splitting between leaves `i`
+and `i + 1` is stored at index `i`. A `MerkleTree<LEAVES>` therefore holds
+`2 * LEAVES` hashes, and `set_leaf` rehashes only the path to the root.
+
+The capacity is a compile-time constant, so a backend does not own a tree;
+`MerkleObserver` keeps one in step with it instead. Installed with
+`with_observer`, it receives the slot events every backend write, `init`,
+`decommission`, `fsck` repair and RP2040 `relocate` emits, and marks a slot
+empty when a read finds it damaged. A backend's `build_merkle_tree` loads it
+once at boot. RP2040 partitions other than the backend's own region emit no
+slot events; load their trees with `Rp2040Partition::build_merkle_tree`.
+*/
+
+use crate::{StorageError, StorageIndex, StorageObserver, block_hash};
+use core::cell::RefCell;
+use core::ops::Range;
+use moonblokz_chain_types::{Block, HASH_SIZE, calculate_hash};
+
+/// Slot hash of an empty or damaged slot, and the root of a tree without leaves.
+pub const EMPTY_LEAF: [u8; HASH_SIZE] = [0u8; HASH_SIZE];
+/// Longest proof: `StorageIndex` is a `u32`, so a tree is at most 32 levels deep.
+pub const MAX_PROOF_LEN: usize = 32;
+
+const LEAF_PREFIX: u8 = 0x00;
+const INNER_NODE_PREFIX: u8 = 0x01;
+
+/// Splits a tree node range into its two children, or returns `None` for a
+/// single leaf or an empty range.
+///
+/// Parameters:
+/// - `range`: leaf range of a node, starting with `0..leaf_count`.
+///
+/// Example:
+/// ```
+/// use moonblokz_storage::merkle::split;
+///
+/// assert_eq!(split(0..6), Some((0..4, 4..6)));
+/// assert_eq!(split(4..6), Some((4..5, 5..6)));
+/// assert_eq!(split(5..6), None);
+/// ```
+pub fn split(range: Range<StorageIndex>) -> Option<(Range<StorageIndex>, Range<StorageIndex>)> {
+    let len = range.end.checked_sub(range.start)?;
+    if len < 2 {
+        return None;
+    }
+    // Largest power of two below `len`.
+    let middle = range.start + (1 << (31 - (len - 1).leading_zeros()));
+    Some((range.start..middle, middle..range.end))
+}
+
+/// Returns the leaf node hash of a slot hash, `0x00 || slot_hash` hashed.
+///
+/// Parameters:
+/// - `slot_hash`: `block_hash` of the stored block, or `EMPTY_LEAF`.
+///
+/// Example:
+/// ```
+/// use moonblokz_storage::merkle::{EMPTY_LEAF, MerkleTree, leaf_hash};
+///
+/// let mut tree = MerkleTree::<1>::new();
+/// assert!(tree.load(1, &mut |_| Ok(EMPTY_LEAF)).is_ok());
+/// assert_eq!(tree.root(), leaf_hash(&EMPTY_LEAF));
+/// ```
+pub fn leaf_hash(slot_hash: &[u8; HASH_SIZE]) -> [u8; HASH_SIZE] {
+    let mut bytes = [0u8; 1 + HASH_SIZE];
+    bytes[0] = LEAF_PREFIX;
+    bytes[1..].copy_from_slice(slot_hash);
+    calculate_hash(&bytes)
+}
+
+fn hash_inner(left: &[u8; HASH_SIZE], right: &[u8; HASH_SIZE]) -> [u8; HASH_SIZE] {
+    let mut bytes = [0u8; 1 + 2 * HASH_SIZE];
+    bytes[0] = INNER_NODE_PREFIX;
+    bytes[1..1 + HASH_SIZE].copy_from_slice(left);
+    bytes[1 + HASH_SIZE..].copy_from_slice(right);
+    calculate_hash(&bytes)
+}
+
+/// Merkle tree over up to `LEAVES` slots.
+///
+/// `set_leaf` and `record_block` rehash one path in `O(log n)`; wrap the tree
+/// in a `MerkleObserver` to have a backend call them on every write.
+///
+/// Example:
+/// ```
+/// use moonblokz_storage::merkle::{EMPTY_LEAF, MerkleTree};
+///
+/// let mut tree = MerkleTree::<8>::new();
+/// assert!(tree.load(5, &mut |_| Ok(EMPTY_LEAF)).is_ok());
+/// let empty_root = tree.root();
+/// assert!(tree.set_leaf(3, [7u8; 32]).is_ok());
+/// assert_ne!(tree.root(), empty_root);
+/// ```
+pub struct MerkleTree<const LEAVES: usize> {
+    leaf_count: StorageIndex,
+    leaves: [[u8; HASH_SIZE]; LEAVES],
+    inner: [[u8; HASH_SIZE]; LEAVES],
+}
+
+impl<const LEAVES: usize> Default for MerkleTree<LEAVES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const LEAVES: usize> MerkleTree<LEAVES> {
+    /// Creates a tree without leaves; `load` or a backend's
+    /// `build_merkle_tree` sizes it.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::merkle::{EMPTY_LEAF, MerkleTree};
+    ///
+    /// let tree = MerkleTree::<4>::new();
+    /// assert_eq!(tree.leaf_count(), 0);
+    /// assert_eq!(tree.root(), EMPTY_LEAF);
+    /// ```
+    pub const fn new() -> Self {
+        Self {
+            leaf_count: 0,
+            leaves: [EMPTY_LEAF; LEAVES],
+            inner: [EMPTY_LEAF; LEAVES],
+        }
+    }
+
+    /// Returns the number of leaves, i.e. the slot count the tree was loaded with.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::merkle::MerkleTree;
+    ///
+    /// assert_eq!(MerkleTree::<4>::new().leaf_count(), 0);
+    /// ```
+    pub fn leaf_count(&self) -> StorageIndex {
+        self.leaf_count
+    }
+
+    /// Replaces all leaves and rehashes the tree once.
+    ///
+    /// A failed load leaves a tree without leaves.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: `leaf_count` exceeds `LEAVES`.
+    /// - any error returned by `leaf_at`.
+    ///
+    /// Parameters:
+    /// - `leaf_count`: number of leaves.
+    /// - `leaf_at`: returns the slot hash of a slot; called once per slot in ascending order.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::merkle::MerkleTree;
+    ///
+    /// let mut tree = MerkleTree::<4>::new();
+    /// assert!(tree.load(3, &mut |index| Ok([index as u8; 32])).is_ok());
+    /// assert!(tree.load(5, &mut |index| Ok([index as u8; 32])).is_err());
+    /// ```
+    pub fn load(
+        &mut self,
+        leaf_count: StorageIndex,
+        leaf_at: &mut dyn FnMut(StorageIndex) -> Result<[u8; HASH_SIZE], StorageError>,
+    ) -> Result<(), StorageError> {
+        if leaf_count as usize > LEAVES {
+            return Err(StorageError::InvalidConfiguration);
+        }
+
+        self.leaf_count = 0;
+        let mut index: StorageIndex = 0;
+        while index < leaf_count {
+            self.leaves[index as usize] = leaf_at(index)?;
+            index += 1;
+        }
+        self.leaf_count = leaf_count;
+        if leaf_count > 0 {
+            self.rehash(0..leaf_count);
+        }
+        Ok(())
+    }
+
+    /// Returns the slot hash of slot `index`.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `index` is not below `leaf_count`.
+    ///
+    /// Parameters:
+    /// - `index`: slot index.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::merkle::{EMPTY_LEAF, MerkleTree};
+    ///
+    /// let mut tree = MerkleTree::<4>::new();
+    /// assert!(tree.load(2, &mut |_| Ok(EMPTY_LEAF)).is_ok());
+    /// assert!(matches!(tree.leaf(1), Ok(leaf) if leaf == EMPTY_LEAF));
+    /// ```
+    pub fn leaf(&self, index: StorageIndex) -> Result<[u8; HASH_SIZE], StorageError> {
+        if index >= self.leaf_count {
+            return Err(StorageError::InvalidIndex);
+        }
+        Ok(self.leaves[index as usize])
+    }
+
+    /// Replaces the slot hash of slot `index` and rehashes its path to the root.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `index` is not below `leaf_count`.
+    ///
+    /// Parameters:
+    /// - `index`: slot index.
+    /// - `leaf`: new slot hash, `block_hash` of the stored block or `EMPTY_LEAF`.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::merkle::{EMPTY_LEAF, MerkleTree};
+    ///
+    /// let mut tree = MerkleTree::<4>::new();
+    /// assert!(tree.load(4, &mut |_| Ok(EMPTY_LEAF)).is_ok());
+    /// assert!(tree.set_leaf(2, [1u8; 32]).is_ok());
+    /// assert!(tree.set_leaf(4, [1u8; 32]).is_err());
+    /// ```
+    pub fn set_leaf(&mut self, index: StorageIndex, leaf: [u8; HASH_SIZE]) -> Result<(), StorageError> {
+        if index >= self.leaf_count {
+            return Err(StorageError::InvalidIndex);
+        }
+        self.leaves[index as usize] = leaf;
+
+        // Collect the inner nodes above the leaf top-down, then rehash them bottom-up.
+        let mut path: [(StorageIndex, StorageIndex); MAX_PROOF_LEN] = [(0, 0); MAX_PROOF_LEN];
+        let mut depth = 0usize;
+        let mut range = 0..self.leaf_count;
+        while let Some((left, right)) = split(range.clone()) {
+            path[depth] = (range.start, range.end);
+            depth += 1;
+            range = if index < left.end { left } else { right };
+        }
+        while depth > 0 {
+            depth -= 1;
+            if let Some((left, right)) = split(path[depth].0..path[depth].1) {
+                self.inner[(left.end - 1) as usize] = hash_inner(&self.node(left), &self.node(right));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the slot hash of slot `index` to `block_hash(block)`.
+    ///
+    /// `MerkleObserver` does this for every block a backend writes.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `index` is not below `leaf_count`.
+    ///
+    /// Parameters:
+    /// - `index`: storage index the block was saved to.
+    /// - `block`: saved block.
+    ///
+    /// Example:
+    /// ```ignore
+    /// backend.save_block(4, &block)?;
+    /// tree.record_block(4, &block)?;
+    /// ```
+    pub fn record_block(&mut self, index: StorageIndex, block: &Block) -> Result<(), StorageError> {
+        self.set_leaf(index, block_hash(block))
+    }
+
+    /// Returns the root hash, or `EMPTY_LEAF` for a tree without leaves.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::merkle::{MerkleTree, leaf_hash};
+    ///
+    /// let mut tree = MerkleTree::<4>::new();
+    /// assert!(tree.load(1, &mut |_| Ok([3u8; 32])).is_ok());
+    /// assert_eq!(tree.root(), leaf_hash(&[3u8; 32]));
+    /// ```
+    pub fn root(&self) -> [u8; HASH_SIZE] {
+        if self.leaf_count == 0 {
+            return EMPTY_LEAF;
+        }
+        self.node(0..self.leaf_count)
+    }
+
+    /// Returns the hash of the leaves in `range`, hashed as a tree of its own.
+    ///
+    /// Ranges produced by `split`, starting from `0..leaf_count`, are tree
+    /// nodes and answered from the stored hashes; any other range is hashed
+    /// from its leaves.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `range` is empty or extends past `leaf_count`.
+    ///
+    /// Parameters:
+    /// - `range`: slot indices to cover.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::merkle::{MerkleTree, split};
+    ///
+    /// let mut tree = MerkleTree::<8>::new();
+    /// assert!(tree.load(6, &mut |index| Ok([index as u8; 32])).is_ok());
+    /// let (left, right) = split(0..6).unwrap();
+    /// assert!(tree.subtree_hash(left).is_ok());
+    /// assert!(tree.subtree_hash(right).is_ok());
+    /// assert!(tree.subtree_hash(6..7).is_err());
+    /// ```
+    pub fn subtree_hash(&self, range: Range<StorageIndex>) -> Result<[u8; HASH_SIZE], StorageError> {
+        if range.start >= range.end || range.end > self.leaf_count {
+            return Err(StorageError::InvalidIndex);
+        }
+        Ok(self.range_hash(range))
+    }
+
+    /// Returns the sibling hashes proving the slot hash of slot `index` against `root`.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `index` is not below `leaf_count`.
+    ///
+    /// Parameters:
+    /// - `index`: slot index.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::merkle::MerkleTree;
+    ///
+    /// let mut tree = MerkleTree::<8>::new();
+    /// assert!(tree.load(5, &mut |index| Ok([index as u8; 32])).is_ok());
+    /// let proof = tree.proof(3).unwrap();
+    /// assert!(proof.verify(&[3u8; 32], &tree.root()));
+    /// assert!(!proof.verify(&[4u8; 32], &tree.root()));
+    /// ```
+    pub fn proof(&self, index: StorageIndex) -> Result<MerkleProof, StorageError> {
+        if index >= self.leaf_count {
+            return Err(StorageError::InvalidIndex);
+        }
+
+        let mut top_down = [EMPTY_LEAF; MAX_PROOF_LEN];
+        let mut depth = 0usize;
+        let mut range = 0..self.leaf_count;
+        while let Some((left, right)) = split(range.clone()) {
+            if index < left.end {
+                top_down[depth] = self.node(right);
+                range = left;
+            } else {
+                top_down[depth] = self.node(left);
+                range = right;
+            }
+            depth += 1;
+        }
+
+        let mut proof = MerkleProof {
+            index,
+            leaf_count: self.leaf_count,
+            siblings: [EMPTY_LEAF; MAX_PROOF_LEN],
+            len: depth,
+        };
+        let mut level = 0usize;
+        while level < depth {
+            proof.siblings[level] = top_down[depth - 1 - level];
+            level += 1;
+        }
+        Ok(proof)
+    }
+
+    /// Hash of a tree node; `range` must be a node.
+    fn node(&self, range: Range<StorageIndex>) -> [u8; HASH_SIZE] {
+        match split(range.clone()) {
+            Some((left, _)) => self.inner[(left.end - 1) as usize],
+            None => leaf_hash(&self.leaves[range.start as usize]),
+        }
+    }
+
+    fn rehash(&mut self, range: Range<StorageIndex>) -> [u8; HASH_SIZE] {
+        match split(range.clone()) {
+            Some((left, right)) => {
+                let inner_index = (left.end - 1) as usize;
+                let left_hash = self.rehash(left);
+                let right_hash = self.rehash(right);
+                self.inner[inner_index] = hash_inner(&left_hash, &right_hash);
+                self.inner[inner_index]
+            }
+            None => leaf_hash(&self.leaves[range.start as usize]),
+        }
+    }
+
+    fn is_node(&self, range: &Range<StorageIndex>) -> bool {
+        let mut node = 0..self.leaf_count;
+        loop {
+            if node == *range {
+                return true;
+            }
+            match split(node) {
+                Some((left, _)) if range.end <= left.end => node = left,
+                Some((_, right)) if range.start >= right.start => node = right,
+                _ => return false,
+            }
+        }
+    }
+
+    fn range_hash(&self, range: Range<StorageIndex>) -> [u8; HASH_SIZE] {
+        if self.is_node(&range) {
+            return self.node(range);
+        }
+        match split(range.clone()) {
+            Some((left, right)) => hash_inner(&self.range_hash(left), &self.range_hash(right)),
+            None => leaf_hash(&self.leaves[range.start as usize]),
+        }
+    }
+}
+
+/// Observer keeping a `MerkleTree` in step with the backend it is installed on.
+///
+/// Load it once with `load` and the backend's `build_merkle_tree`; after that
+/// every slot event updates the tree. A reset to more slots than `LEAVES`
+/// leaves a tree without leaves, and events arriving while `load` runs are
+/// dropped.
+///
+/// Example:
+/// ```
+/// use moonblokz_chain_types::{Block, MAX_BLOCK_SIZE};
+/// use moonblokz_storage::merkle::{MerkleObserver, MerkleTree};
+/// use moonblokz_storage::{MemoryBackend, StorageTrait};
+///
+/// let merkle = MerkleObserver::<16>::new();
+/// let mut backend = MemoryBackend::<{ 4 * MAX_BLOCK_SIZE + 8000 }>::new().with_observer(&merkle);
+/// assert!(merkle.load(|tree| backend.build_merkle_tree(tree)).is_ok());
+/// let mut bytes = [0u8; MAX_BLOCK_SIZE];
+/// bytes[0] = 1;
+/// let block = Block::from_bytes(&bytes).unwrap();
+/// assert!(backend.save_block(1, &block).is_ok());
+///
+/// let mut rebuilt = MerkleTree::<16>::new();
+/// assert!(backend.build_merkle_tree(&mut rebuilt).is_ok());
+/// assert_eq!(merkle.root(), rebuilt.root());
+/// ```
+pub struct MerkleObserver<const LEAVES: usize> {
+    tree: RefCell<MerkleTree<LEAVES>>,
+}
+
+impl<const LEAVES: usize> Default for MerkleObserver<LEAVES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const LEAVES: usize> MerkleObserver<LEAVES> {
+    /// Creates an observer holding a tree without leaves.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::merkle::{EMPTY_LEAF, MerkleObserver};
+    ///
+    /// assert_eq!(MerkleObserver::<4>::new().root(), EMPTY_LEAF);
+    /// ```
+    pub const fn new() -> Self {
+        Self {
+            tree: RefCell::new(MerkleTree::new()),
+        }
+    }
+
+    /// Reloads the tree, typically with a backend's `build_merkle_tree`.
+    ///
+    /// Errors:
+    /// - any error returned by `build`.
+    ///
+    /// Parameters:
+    /// - `build`: fills the tree from the stored slots.
+    ///
+    /// Example:
+    /// ```ignore
+    /// merkle.load(|tree| backend.build_merkle_tree(tree))?;
+    /// ```
+    pub fn load(
+        &self,
+        build: impl FnOnce(&mut MerkleTree<LEAVES>) -> Result<(), StorageError>,
+    ) -> Result<(), StorageError> {
+        build(&mut self.tree.borrow_mut())
+    }
+
+    /// Returns the number of leaves of the tree.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::merkle::MerkleObserver;
+    ///
+    /// assert_eq!(MerkleObserver::<4>::new().leaf_count(), 0);
+    /// ```
+    pub fn leaf_count(&self) -> StorageIndex {
+        self.tree.borrow().leaf_count()
+    }
+
+    /// Returns the root hash; see `MerkleTree::root`.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let root = merkle.root();
+    /// ```
+    pub fn root(&self) -> [u8; HASH_SIZE] {
+        self.tree.borrow().root()
+    }
+
+    /// Returns the hash of the leaves in `range`; see `MerkleTree::subtree_hash`.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `range` is empty or extends past `leaf_count`.
+    ///
+    /// Parameters:
+    /// - `range`: slot indices to cover.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let hash = merkle.subtree_hash(0..128)?;
+    /// ```
+    pub fn subtree_hash(&self, range: Range<StorageIndex>) -> Result<[u8; HASH_SIZE], StorageError> {
+        self.tree.borrow().subtree_hash(range)
+    }
+
+    /// Returns the proof of slot `index`; see `MerkleTree::proof`.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `index` is not below `leaf_count`.
+    ///
+    /// Parameters:
+    /// - `index`: slot index.
+    ///
+    /// Example:
+    /// ```ignore
+    /// let proof = merkle.proof(4)?;
+    /// ```
+    pub fn proof(&self, index: StorageIndex) -> Result<MerkleProof, StorageError> {
+        self.tree.borrow().proof(index)
+    }
+}
+
+impl<const LEAVES: usize> StorageObserver for MerkleObserver<LEAVES> {
+    fn on_slot_changed(&self, storage_index: StorageIndex, block_hash: Option<&[u8; HASH_SIZE]>) {
+        if let Ok(mut tree) = self.tree.try_borrow_mut() {
+            let _ = tree.set_leaf(storage_index, block_hash.copied().unwrap_or(EMPTY_LEAF));
+        }
+    }
+
+    fn on_slots_reset(&self, slot_count: StorageIndex) {
+        if let Ok(mut tree) = self.tree.try_borrow_mut() {
+            let _ = tree.load(slot_count, &mut |_| Ok(EMPTY_LEAF));
+        }
+    }
+}
+
+/// Sibling hashes proving one leaf against a root, ordered from the leaf up.
+///
+/// Example:
+/// ```
+/// use moonblokz_storage::merkle::{MerkleProof, MerkleTree};
+///
+/// let mut tree = MerkleTree::<4>::new();
+/// assert!(tree.load(3, &mut |index| Ok([index as u8; 32])).is_ok());
+/// let sent = tree.proof(2).unwrap();
+/// let received = MerkleProof::from_parts(sent.index(), sent.leaf_count(), sent.siblings()).unwrap();
+/// assert!(received.verify(&[2u8; 32], &tree.root()));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MerkleProof {
+    index: StorageIndex,
+    leaf_count: StorageIndex,
+    siblings: [[u8; HASH_SIZE]; MAX_PROOF_LEN],
+    len: usize,
+}
+
+impl MerkleProof {
+    /// Rebuilds a proof received from a peer.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: more than `MAX_PROOF_LEN` siblings.
+    ///
+    /// Parameters:
+    /// - `index`: proven slot index.
+    /// - `leaf_count`: leaf count of the tree the proof was taken from.
+    /// - `siblings`: sibling hashes from the leaf up.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::merkle::MerkleProof;
+    ///
+    /// assert!(MerkleProof::from_parts(0, 1, &[]).is_ok());
+    /// assert!(MerkleProof::from_parts(0, 1, &[[0u8; 32]; 33]).is_err());
+    /// ```
+    pub fn from_parts(
+        index: StorageIndex,
+        leaf_count: StorageIndex,
+        siblings: &[[u8; HASH_SIZE]],
+    ) -> Result<Self, StorageError> {
+        if siblings.len() > MAX_PROOF_LEN {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        let mut proof = Self {
+            index,
+            leaf_count,
+            siblings: [EMPTY_LEAF; MAX_PROOF_LEN],
+            len: siblings.len(),
+        };
+        proof.siblings[..siblings.len()].copy_from_slice(siblings);
+        Ok(proof)
+    }
+
+    /// Returns the proven slot index.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::merkle::MerkleProof;
+    ///
+    /// let proof = MerkleProof::from_parts(2, 5, &[[0u8; 32]; 3]).unwrap();
+    /// assert_eq!(proof.index(), 2);
+    /// ```
+    pub fn index(&self) -> StorageIndex {
+        self.index
+    }
+
+    /// Returns the leaf count of the tree the proof was taken from.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::merkle::MerkleProof;
+    ///
+    /// let proof = MerkleProof::from_parts(2, 5, &[[0u8; 32]; 3]).unwrap();
+    /// assert_eq!(proof.leaf_count(), 5);
+    /// ```
+    pub fn leaf_count(&self) -> StorageIndex {
+        self.leaf_count
+    }
+
+    /// Returns the sibling hashes, from the leaf up.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::merkle::MerkleProof;
+    ///
+    /// let proof = MerkleProof::from_parts(2, 5, &[[0u8; 32]; 3]).unwrap();
+    /// assert_eq!(proof.siblings().len(), 3);
+    /// ```
+    pub fn siblings(&self) -> &[[u8; HASH_SIZE]] {
+        &self.siblings[..self.len]
+    }
+
+    /// Returns the root implied by `leaf`, or `None` when the proof does not
+    /// fit the shape of a tree with `leaf_count` leaves.
+    ///
+    /// Parameters:
+    /// - `leaf`: claimed slot hash of the proven slot.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::merkle::MerkleTree;
+    ///
+    /// let mut tree = MerkleTree::<4>::new();
+    /// assert!(tree.load(4, &mut |index| Ok([index as u8; 32])).is_ok());
+    /// assert_eq!(tree.proof(1).unwrap().root_for(&[1u8; 32]), Some(tree.root()));
+    /// ```
+    pub fn root_for(&self, leaf: &[u8; HASH_SIZE]) -> Option<[u8; HASH_SIZE]> {
+        if self.index >= self.leaf_count {
+            return None;
+        }
+
+        // `true` where the sibling is the right child, top-down.
+        let mut sibling_on_right = [false; MAX_PROOF_LEN];
+        let mut depth = 0usize;
+        let mut range = 0..self.leaf_count;
+        while let Some((left, right)) = split(range.clone()) {
+            sibling_on_right[depth] = self.index < left.end;
+            range = if self.index < left.end { left } else { right };
+            depth += 1;
+        }
+        if depth != self.len {
+            return None;
+        }
+
+        let mut hash = leaf_hash(leaf);
+        let mut level = 0usize;
+        while level < depth {
+            let sibling = &self.siblings[level];
+            hash = if sibling_on_right[depth - 1 - level] {
+                hash_inner(&hash, sibling)
+            } else {
+                hash_inner(sibling, &hash)
+            };
+            level += 1;
+        }
+        Some(hash)
+    }
+
+    /// Returns `true` when `leaf` and the siblings hash to `root`.
+    ///
+    /// Parameters:
+    /// - `leaf`: claimed slot hash of the proven slot.
+    /// - `root`: trusted root.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::merkle::MerkleTree;
+    ///
+    /// let mut tree = MerkleTree::<4>::new();
+    /// assert!(tree.load(2, &mut |index| Ok([index as u8; 32])).is_ok());
+    /// assert!(tree.proof(0).unwrap().verify(&[0u8; 32], &tree.root()));
+    /// ```
+    pub fn verify(&self, leaf: &[u8; HASH_SIZE], root: &[u8; HASH_SIZE]) -> bool {
+        self.root_for(leaf).is_some_and(|value| value == *root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf_of(index: StorageIndex) -> [u8; HASH_SIZE] {
+        let mut leaf = [0u8; HASH_SIZE];
+        leaf[..4].copy_from_slice(&(index + 1).to_le_bytes());
+        leaf
+    }
+
+    /// Straightforward RFC 6962 style hash of `leaves`.
+    fn reference_hash(leaves: &[[u8; HASH_SIZE]]) -> [u8; HASH_SIZE] {
+        match split(0..leaves.len() as StorageIndex) {
+            Some((left, _)) => hash_inner(
+                &reference_hash(&leaves[..left.end as usize]),
+                &reference_hash(&leaves[left.end as usize..]),
+            ),
+            None => leaf_hash(&leaves[0]),
+        }
+    }
+
+    #[test]
+    fn incremental_updates_match_a_full_rehash() {
+        let mut leaf_count: StorageIndex = 1;
+        while leaf_count <= 13 {
+            let mut tree = MerkleTree::<16>::new();
+            assert!(tree.load(leaf_count, &mut |_| Ok(EMPTY_LEAF)).is_ok());
+            let mut leaves = [EMPTY_LEAF; 16];
+            let mut index: StorageIndex = 0;
+            while index < leaf_count {
+                leaves[index as usize] = leaf_of(index);
+                assert!(tree.set_leaf(index, leaf_of(index)).is_ok());
+                assert_eq!(tree.root(), reference_hash(&leaves[..leaf_count as usize]));
+                index += 1;
+            }
+
+            let mut loaded = MerkleTree::<16>::new();
+            assert!(loaded.load(leaf_count, &mut |index| Ok(leaf_of(index))).is_ok());
+            assert_eq!(loaded.root(), tree.root());
+            leaf_count += 1;
+        }
+    }
+
+    #[test]
+    fn proofs_verify_every_leaf_and_reject_others() {
+        let mut tree = MerkleTree::<16>::new();
+        assert!(tree.load(11, &mut |index| Ok(leaf_of(index))).is_ok());
+        let root = tree.root();
+        let mut index: StorageIndex = 0;
+        while index < 11 {
+            let proof = tree.proof(index).unwrap_or_else(|_| unreachable!());
+            assert!(proof.verify(&leaf_of(index), &root));
+            assert!(!proof.verify(&leaf_of(index + 1), &root));
+            let moved = MerkleProof::from_parts((index + 1) % 11, 11, proof.siblings())
+                .unwrap_or_else(|_| unreachable!());
+            assert!(!moved.verify(&leaf_of(index), &root));
+            index += 1;
+        }
+        assert!(matches!(tree.proof(11), Err(StorageError::InvalidIndex)));
+    }
+
+    #[test]
+    fn inner_nodes_cannot_pose_as_leaves() {
+        let mut tree = MerkleTree::<4>::new();
+        assert!(tree.load(4, &mut |index| Ok(leaf_of(index))).is_ok());
+        let left = tree.subtree_hash(0..2).unwrap_or_else(|_| unreachable!());
+        let right = tree.subtree_hash(2..4).unwrap_or_else(|_| unreachable!());
+
+        let forged = MerkleProof::from_parts(0, 2, &[right]).unwrap_or_else(|_| unreachable!());
+        assert!(!forged.verify(&left, &tree.root()));
+        let mut shortened = MerkleTree::<2>::new();
+        assert!(shortened.load(2, &mut |index| Ok(if index == 0 { left } else { right })).is_ok());
+        assert_ne!(shortened.root(), tree.root());
+    }
+
+    #[test]
+    fn observer_follows_slot_events() {
+        let merkle = MerkleObserver::<8>::new();
+        merkle.on_slots_reset(5);
+        let mut tree = MerkleTree::<8>::new();
+        assert!(tree.load(5, &mut |_| Ok(EMPTY_LEAF)).is_ok());
+        assert_eq!(merkle.root(), tree.root());
+
+        merkle.on_slot_changed(3, Some(&leaf_of(3)));
+        assert!(tree.set_leaf(3, leaf_of(3)).is_ok());
+        assert_eq!(merkle.root(), tree.root());
+        merkle.on_slot_changed(3, None);
+        merkle.on_slot_changed(7, Some(&leaf_of(7)));
+        assert!(tree.set_leaf(3, EMPTY_LEAF).is_ok());
+        assert_eq!(merkle.root(), tree.root());
+
+        assert!(merkle.load(|loaded| loaded.load(5, &mut |index| Ok(leaf_of(index)))).is_ok());
+        assert!(matches!(merkle.proof(2), Ok(proof) if proof.verify(&leaf_of(2), &merkle.root())));
+        merkle.on_slots_reset(9);
+        assert_eq!(merkle.leaf_count(), 0);
+        assert_eq!(merkle.root(), EMPTY_LEAF);
+    }
+
+    #[test]
+    fn descending_through_differing_subtrees_finds_the_changed_slot() {
+        let mut ours = MerkleTree::<16>::new();
+        let mut theirs = MerkleTree::<16>::new();
+        assert!(ours.load(13, &mut |index| Ok(leaf_of(index))).is_ok());
+        assert!(theirs.load(13, &mut |index| Ok(leaf_of(index))).is_ok());
+        assert!(theirs.set_leaf(9, EMPTY_LEAF).is_ok());
+
+        let mut range = 0..13;
+        let mut rounds = 0usize;
+        while let Some((left, right)) = split(range.clone()) {
+            let same_left = ours.subtree_hash(left.clone()).ok() == theirs.subtree_hash(left.clone()).ok();
+            range = if same_left { right } else { left };
+            rounds += 1;
+        }
+        assert_eq!(range, 9..10);
+        assert!(rounds <= 4);
+    }
+
+    #[test]
+    fn subtree_hash_covers_arbitrary_ranges() {
+        let mut tree = MerkleTree::<16>::new();
+        assert!(tree.load(10, &mut |index| Ok(leaf_of(index))).is_ok());
+        let mut leaves = [EMPTY_LEAF; 10];
+        let mut index = 0usize;
+        while index < leaves.len() {
+            leaves[index] = leaf_of(index as StorageIndex);
+            index += 1;
+        }
+        assert!(matches!(tree.subtree_hash(0..10), Ok(hash) if hash == tree.root()));
+        assert!(matches!(tree.subtree_hash(3..9), Ok(hash) if hash == reference_hash(&leaves[3..9])));
+        assert!(matches!(tree.subtree_hash(4..4), Err(StorageError::InvalidIndex)));
+        assert!(matches!(tree.subtree_hash(8..11), Err(StorageError::InvalidIndex)));
+        assert!(matches!(tree.load(17, &mut |_| Ok(EMPTY_LEAF)), Err(StorageError::InvalidConfiguration)));
+    }
+}
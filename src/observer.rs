@@ -0,0 +1,303 @@
+/*! Storage observer hooks for tracing backend activity.
+
+Backends call a `StorageObserver` on key storage events. The default observer
+is `NoopObserver`, so instrumentation costs nothing unless a caller opts in.
+Optional adapters forward events to `defmt` (feature `defmt`) and `log`
+(feature `log`).
+*/
+
+use crate::StorageIndex;
+use moonblokz_chain_types::HASH_SIZE;
+
+/// Event hooks invoked by storage backends.
+///
+/// Every method has a no-op default implementation, so observers only
+/// override the events they care about.
+///
+/// Example:
+/// ```
+/// use core::cell::Cell;
+/// use moonblokz_storage::{StorageIndex, StorageObserver};
+///
+/// struct WriteCounter {
+///     writes: Cell<u32>,
+/// }
+///
+/// impl StorageObserver for WriteCounter {
+///     fn on_slot_written(&self, _storage_index: StorageIndex) {
+///         self.writes.set(self.writes.get() + 1);
+///     }
+/// }
+///
+/// let counter = WriteCounter { writes: Cell::new(0) };
+/// counter.on_slot_written(3);
+/// assert_eq!(counter.writes.get(), 1);
+/// ```
+pub trait StorageObserver {
+    /// Called when a backend starts `init`, or when `resume_init` picks up an
+    /// interrupted one; each is paired with `on_init_finished` on success.
+    ///
+    /// Parameters:
+    /// - none.
+    fn on_init_started(&self) {}
+
+    /// Called when a backend finished `init` successfully.
+    ///
+    /// Parameters:
+    /// - none.
+    fn on_init_finished(&self) {}
+
+    /// Called after a block was persisted to a slot.
+    ///
+    /// Parameters:
+    /// - `storage_index`: slot index that was written.
+    fn on_slot_written(&self, _storage_index: StorageIndex) {}
+
+    /// Called when the content of a slot changed: after a block was written,
+    /// or when a slot was found damaged or cleared by `fsck`.
+    ///
+    /// RP2040 partitions other than the backend's own region do not report it.
+    ///
+    /// Parameters:
+    /// - `storage_index`: slot index that changed.
+    /// - `block_hash`: `block_hash` of the stored block, `None` for an empty
+    ///   or damaged slot.
+    fn on_slot_changed(&self, _storage_index: StorageIndex, _block_hash: Option<&[u8; HASH_SIZE]>) {}
+
+    /// Called when every slot became empty, by `init`, `resume_init` and
+    /// `decommission`, or when the slot count changed, by RP2040 `relocate`;
+    /// `relocate` then reports every occupied slot with `on_slot_changed`.
+    ///
+    /// RP2040 partitions other than the backend's own region do not report it.
+    ///
+    /// Parameters:
+    /// - `slot_count`: number of block slots.
+    fn on_slots_reset(&self, _slot_count: StorageIndex) {}
+
+    /// Called after a block was read and verified from a slot.
+    ///
+    /// Parameters:
+    /// - `storage_index`: slot index that was read.
+    fn on_slot_read(&self, _storage_index: StorageIndex) {}
+
+    /// Called when a slot failed integrity verification.
+    ///
+    /// Parameters:
+    /// - `storage_index`: slot index that failed verification.
+    fn on_integrity_failure(&self, _storage_index: StorageIndex) {}
+
+    /// Called after an invalid control-plane replica was rewritten.
+    ///
+    /// Parameters:
+    /// - `replica_index`: zero-based control-plane replica index.
+    fn on_replica_repaired(&self, _replica_index: usize) {}
+
+    /// Called after a flash page was erased.
+    ///
+    /// Parameters:
+    /// - `page_address`: flash address of the erased page.
+    fn on_page_erased(&self, _page_address: usize) {}
+}
+
+impl<T: StorageObserver + ?Sized> StorageObserver for &T {
+    fn on_init_started(&self) {
+        (**self).on_init_started();
+    }
+
+    fn on_init_finished(&self) {
+        (**self).on_init_finished();
+    }
+
+    fn on_slot_written(&self, storage_index: StorageIndex) {
+        (**self).on_slot_written(storage_index);
+    }
+
+    fn on_slot_changed(&self, storage_index: StorageIndex, block_hash: Option<&[u8; HASH_SIZE]>) {
+        (**self).on_slot_changed(storage_index, block_hash);
+    }
+
+    fn on_slots_reset(&self, slot_count: StorageIndex) {
+        (**self).on_slots_reset(slot_count);
+    }
+
+    fn on_slot_read(&self, storage_index: StorageIndex) {
+        (**self).on_slot_read(storage_index);
+    }
+
+    fn on_integrity_failure(&self, storage_index: StorageIndex) {
+        (**self).on_integrity_failure(storage_index);
+    }
+
+    fn on_replica_repaired(&self, replica_index: usize) {
+        (**self).on_replica_repaired(replica_index);
+    }
+
+    fn on_page_erased(&self, page_address: usize) {
+        (**self).on_page_erased(page_address);
+    }
+}
+
+/// Forwards every event to both observers, first to `.0`.
+///
+/// Example:
+/// ```
+/// use moonblokz_storage::merkle::MerkleObserver;
+/// use moonblokz_storage::{NoopObserver, StorageObserver};
+///
+/// let merkle = MerkleObserver::<4>::new();
+/// (NoopObserver, &merkle).on_slots_reset(2);
+/// assert_eq!(merkle.leaf_count(), 2);
+/// ```
+impl<A: StorageObserver, B: StorageObserver> StorageObserver for (A, B) {
+    fn on_init_started(&self) {
+        self.0.on_init_started();
+        self.1.on_init_started();
+    }
+
+    fn on_init_finished(&self) {
+        self.0.on_init_finished();
+        self.1.on_init_finished();
+    }
+
+    fn on_slot_written(&self, storage_index: StorageIndex) {
+        self.0.on_slot_written(storage_index);
+        self.1.on_slot_written(storage_index);
+    }
+
+    fn on_slot_changed(&self, storage_index: StorageIndex, block_hash: Option<&[u8; HASH_SIZE]>) {
+        self.0.on_slot_changed(storage_index, block_hash);
+        self.1.on_slot_changed(storage_index, block_hash);
+    }
+
+    fn on_slots_reset(&self, slot_count: StorageIndex) {
+        self.0.on_slots_reset(slot_count);
+        self.1.on_slots_reset(slot_count);
+    }
+
+    fn on_slot_read(&self, storage_index: StorageIndex) {
+        self.0.on_slot_read(storage_index);
+        self.1.on_slot_read(storage_index);
+    }
+
+    fn on_integrity_failure(&self, storage_index: StorageIndex) {
+        self.0.on_integrity_failure(storage_index);
+        self.1.on_integrity_failure(storage_index);
+    }
+
+    fn on_replica_repaired(&self, replica_index: usize) {
+        self.0.on_replica_repaired(replica_index);
+        self.1.on_replica_repaired(replica_index);
+    }
+
+    fn on_page_erased(&self, page_address: usize) {
+        self.0.on_page_erased(page_address);
+        self.1.on_page_erased(page_address);
+    }
+}
+
+/// Observer that ignores every event.
+///
+/// Example:
+/// ```
+/// use moonblokz_storage::{NoopObserver, StorageObserver};
+///
+/// NoopObserver.on_slot_written(0);
+/// ```
+#[derive(Clone, Copy, Default)]
+pub struct NoopObserver;
+
+impl StorageObserver for NoopObserver {}
+
+/// Observer adapter that emits events through `defmt`.
+///
+/// Example:
+/// ```ignore
+/// use moonblokz_storage::{DefmtObserver, StorageObserver};
+///
+/// DefmtObserver.on_slot_written(0);
+/// ```
+#[cfg(feature = "defmt")]
+#[derive(Clone, Copy, Default)]
+pub struct DefmtObserver;
+
+#[cfg(feature = "defmt")]
+impl StorageObserver for DefmtObserver {
+    fn on_init_started(&self) {
+        defmt::info!("moonblokz-storage: init started");
+    }
+
+    fn on_init_finished(&self) {
+        defmt::info!("moonblokz-storage: init finished");
+    }
+
+    fn on_slot_written(&self, storage_index: StorageIndex) {
+        defmt::debug!("moonblokz-storage: slot written index={=u32}", storage_index);
+    }
+
+    fn on_slots_reset(&self, slot_count: StorageIndex) {
+        defmt::debug!("moonblokz-storage: slots reset count={=u32}", slot_count);
+    }
+
+    fn on_slot_read(&self, storage_index: StorageIndex) {
+        defmt::trace!("moonblokz-storage: slot read index={=u32}", storage_index);
+    }
+
+    fn on_integrity_failure(&self, storage_index: StorageIndex) {
+        defmt::warn!("moonblokz-storage: integrity failure index={=u32}", storage_index);
+    }
+
+    fn on_replica_repaired(&self, replica_index: usize) {
+        defmt::warn!("moonblokz-storage: control-plane replica repaired replica={=usize}", replica_index);
+    }
+
+    fn on_page_erased(&self, page_address: usize) {
+        defmt::trace!("moonblokz-storage: page erased address={=usize:#x}", page_address);
+    }
+}
+
+/// Observer adapter that emits events through the `log` facade.
+///
+/// Example:
+/// ```ignore
+/// use moonblokz_storage::{LogObserver, StorageObserver};
+///
+/// LogObserver.on_slot_written(0);
+/// ```
+#[cfg(feature = "log")]
+#[derive(Clone, Copy, Default)]
+pub struct LogObserver;
+
+#[cfg(feature = "log")]
+impl StorageObserver for LogObserver {
+    fn on_init_started(&self) {
+        log::info!("moonblokz-storage: init started");
+    }
+
+    fn on_init_finished(&self) {
+        log::info!("moonblokz-storage: init finished");
+    }
+
+    fn on_slot_written(&self, storage_index: StorageIndex) {
+        log::debug!("moonblokz-storage: slot written index={}", storage_index);
+    }
+
+    fn on_slots_reset(&self, slot_count: StorageIndex) {
+        log::debug!("moonblokz-storage: slots reset count={}", slot_count);
+    }
+
+    fn on_slot_read(&self, storage_index: StorageIndex) {
+        log::trace!("moonblokz-storage: slot read index={}", storage_index);
+    }
+
+    fn on_integrity_failure(&self, storage_index: StorageIndex) {
+        log::warn!("moonblokz-storage: integrity failure index={}", storage_index);
+    }
+
+    fn on_replica_repaired(&self, replica_index: usize) {
+        log::warn!("moonblokz-storage: control-plane replica repaired replica={}", replica_index);
+    }
+
+    fn on_page_erased(&self, page_address: usize) {
+        log::trace!("moonblokz-storage: page erased address={:#x}", page_address);
+    }
+}
@@ -0,0 +1,110 @@
+/*! Shared codec for framed records stored next to control-plane replicas.
+
+The superblock, the init marker and the relocation journal use the same
+framing (all integers little-endian):
+
+`magic:[u8;4], version:u8, record-specific bytes, crc32:u32`
+
+The CRC covers everything before it and is always the last four bytes of the
+record. Record-specific bytes are mostly `u32` fields, read and written with
+`write_fields` / `read_fields`.
+*/
+
+use crate::StorageError;
+use crate::control_plane::crc32;
+
+/// Offset of the first record-specific byte, after magic and version.
+pub(crate) const HEADER_SIZE: usize = 5;
+
+/// Writes magic and version at the start of `out`.
+pub(crate) fn write_header(out: &mut [u8], magic: &[u8; 4], version: u8) {
+    out[..4].copy_from_slice(magic);
+    out[4] = version;
+}
+
+/// Writes `fields` as consecutive `u32` values starting at `offset`.
+pub(crate) fn write_fields(out: &mut [u8], offset: usize, fields: &[u32]) {
+    let mut index = 0usize;
+    while index < fields.len() {
+        let field_offset = offset + index * 4;
+        out[field_offset..field_offset + 4].copy_from_slice(&fields[index].to_le_bytes());
+        index += 1;
+    }
+}
+
+/// Writes the CRC32 of everything before the last four bytes into them.
+pub(crate) fn seal(out: &mut [u8]) {
+    let crc32_offset = out.len() - 4;
+    let crc = crc32(&out[..crc32_offset]);
+    out[crc32_offset..].copy_from_slice(&crc.to_le_bytes());
+}
+
+/// Checks the framing of a stored record.
+///
+/// Returns `Ok(false)` when the magic is absent or the CRC does not match
+/// (an erased, foreign or torn record) and `Ok(true)` for an intact record.
+///
+/// Errors:
+/// - `ControlPlaneIncompatible`: the magic is present but the version is not
+///   `version`. Checked before the CRC, so a newer, differently sized format
+///   is refused rather than mistaken for a missing record and overwritten.
+pub(crate) fn check(bytes: &[u8], magic: &[u8; 4], version: u8) -> Result<bool, StorageError> {
+    if bytes[..4] != *magic {
+        return Ok(false);
+    }
+    if bytes[4] != version {
+        return Err(StorageError::ControlPlaneIncompatible);
+    }
+    let crc32_offset = bytes.len() - 4;
+    let mut crc_bytes = [0u8; 4];
+    crc_bytes.copy_from_slice(&bytes[crc32_offset..]);
+    Ok(u32::from_le_bytes(crc_bytes) == crc32(&bytes[..crc32_offset]))
+}
+
+/// Reads `values.len()` consecutive `u32` values starting at `offset`.
+pub(crate) fn read_fields(bytes: &[u8], offset: usize, values: &mut [u32]) {
+    let mut index = 0usize;
+    while index < values.len() {
+        let field_offset = offset + index * 4;
+        let mut value = [0u8; 4];
+        value.copy_from_slice(&bytes[field_offset..field_offset + 4]);
+        values[index] = u32::from_le_bytes(value);
+        index += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAGIC: [u8; 4] = *b"TEST";
+
+    fn sealed() -> [u8; 17] {
+        let mut out = [0u8; 17];
+        write_header(&mut out, &MAGIC, 2);
+        write_fields(&mut out, HEADER_SIZE, &[7, 0x0102_0304]);
+        seal(&mut out);
+        out
+    }
+
+    #[test]
+    fn record_round_trips() {
+        let bytes = sealed();
+        assert!(matches!(check(&bytes, &MAGIC, 2), Ok(true)));
+        let mut values = [0u32; 2];
+        read_fields(&bytes, HEADER_SIZE, &mut values);
+        assert_eq!(values, [7, 0x0102_0304]);
+    }
+
+    #[test]
+    fn torn_foreign_and_unknown_version_records_are_told_apart() {
+        let mut torn = sealed();
+        torn[HEADER_SIZE] ^= 0x01;
+        assert!(matches!(check(&torn, &MAGIC, 2), Ok(false)));
+        assert!(matches!(check(&[0xFF; 17], &MAGIC, 2), Ok(false)));
+
+        let mut newer = sealed();
+        newer[4] = 3;
+        assert!(matches!(check(&newer, &MAGIC, 2), Err(StorageError::ControlPlaneIncompatible)));
+    }
+}
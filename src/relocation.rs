@@ -0,0 +1,189 @@
+/*! Journal for moving the RP2040 storage region to a new start address.
+
+`Rp2040Backend::relocate` copies block pages to their new location in
+descending order and records its progress in a journal that replaces the
+first two control-plane replicas of the old region. The journal alternates
+between those two pages with an increasing sequence number, so an interrupted
+journal update always leaves the previous journal readable. The journal also
+carries a copy of the control-plane entry, which is written to the new region
+once all block pages are in place.
+
+Layout (all integers little-endian):
+
+`magic:[u8;4] = "MBRJ", version:u8, phase:u8, sequence:u32, old_start:u32,
+old_flash_size:u32, new_start:u32, new_flash_size:u32, remaining_pages:u32,
+entry:[u8; CONTROL_PLANE_ENTRY_SIZE], crc32:u32`
+*/
+
+use crate::StorageError;
+use crate::control_plane::CONTROL_PLANE_ENTRY_SIZE;
+use crate::record_header::{self, HEADER_SIZE};
+use zeroize::Zeroizing;
+
+/// Relocation journal magic bytes.
+pub const RELOCATION_JOURNAL_MAGIC: [u8; 4] = *b"MBRJ";
+/// Relocation journal format version.
+pub const RELOCATION_JOURNAL_VERSION: u8 = 1;
+/// Serialized relocation journal size in bytes.
+pub const RELOCATION_JOURNAL_SIZE: usize = ENTRY_OFFSET + CONTROL_PLANE_ENTRY_SIZE + 4;
+
+const PHASE_OFFSET: usize = HEADER_SIZE;
+const FIELDS_OFFSET: usize = PHASE_OFFSET + 1;
+const ENTRY_OFFSET: usize = FIELDS_OFFSET + 6 * 4;
+const CRC32_OFFSET: usize = ENTRY_OFFSET + CONTROL_PLANE_ENTRY_SIZE;
+
+/// Destination of a relocation recorded in the journal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RelocationTarget {
+    /// First flash address of the new storage region.
+    pub data_storage_start_address: usize,
+    /// Flash size the new region is laid out for.
+    pub flash_size: usize,
+}
+
+/// Relocation step recorded in the journal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RelocationPhase {
+    /// Block pages are being copied, highest page first.
+    Blocks,
+    /// All block pages are copied; control-plane replicas are being written.
+    ControlPlane,
+    /// The new region is complete; the journal only forwards to it.
+    Done,
+}
+
+impl RelocationPhase {
+    fn to_byte(self) -> u8 {
+        match self {
+            RelocationPhase::Blocks => 0,
+            RelocationPhase::ControlPlane => 1,
+            RelocationPhase::Done => 2,
+        }
+    }
+
+    fn from_byte(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(RelocationPhase::Blocks),
+            1 => Some(RelocationPhase::ControlPlane),
+            2 => Some(RelocationPhase::Done),
+            _ => None,
+        }
+    }
+}
+
+/// Decoded relocation journal.
+pub(crate) struct RelocationJournal {
+    pub(crate) phase: RelocationPhase,
+    pub(crate) sequence: u32,
+    pub(crate) old_start: u32,
+    pub(crate) old_flash_size: u32,
+    pub(crate) new_start: u32,
+    pub(crate) new_flash_size: u32,
+    /// Block pages not yet known to be copied; page `remaining_pages - 1` is next.
+    pub(crate) remaining_pages: u32,
+    pub(crate) entry: Zeroizing<[u8; CONTROL_PLANE_ENTRY_SIZE]>,
+}
+
+impl RelocationJournal {
+    pub(crate) fn target(&self) -> RelocationTarget {
+        RelocationTarget {
+            data_storage_start_address: self.new_start as usize,
+            flash_size: self.new_flash_size as usize,
+        }
+    }
+
+    pub(crate) fn encode(&self) -> Zeroizing<[u8; RELOCATION_JOURNAL_SIZE]> {
+        let mut out = Zeroizing::new([0u8; RELOCATION_JOURNAL_SIZE]);
+        record_header::write_header(&mut out[..], &RELOCATION_JOURNAL_MAGIC, RELOCATION_JOURNAL_VERSION);
+        out[PHASE_OFFSET] = self.phase.to_byte();
+        record_header::write_fields(
+            &mut out[..],
+            FIELDS_OFFSET,
+            &[
+                self.sequence,
+                self.old_start,
+                self.old_flash_size,
+                self.new_start,
+                self.new_flash_size,
+                self.remaining_pages,
+            ],
+        );
+        out[ENTRY_OFFSET..CRC32_OFFSET].copy_from_slice(&self.entry[..]);
+        record_header::seal(&mut out[..]);
+        out
+    }
+
+    /// Returns `Ok(None)` when `bytes` hold no valid journal (wrong magic,
+    /// CRC or phase), for example a regular control-plane replica.
+    pub(crate) fn decode(bytes: &[u8; RELOCATION_JOURNAL_SIZE]) -> Result<Option<Self>, StorageError> {
+        if !record_header::check(bytes, &RELOCATION_JOURNAL_MAGIC, RELOCATION_JOURNAL_VERSION)? {
+            return Ok(None);
+        }
+        let phase = match RelocationPhase::from_byte(bytes[PHASE_OFFSET]) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        let mut values = [0u32; 6];
+        record_header::read_fields(bytes, FIELDS_OFFSET, &mut values);
+        let mut entry = Zeroizing::new([0u8; CONTROL_PLANE_ENTRY_SIZE]);
+        entry.copy_from_slice(&bytes[ENTRY_OFFSET..CRC32_OFFSET]);
+
+        Ok(Some(Self {
+            phase,
+            sequence: values[0],
+            old_start: values[1],
+            old_flash_size: values[2],
+            new_start: values[3],
+            new_flash_size: values[4],
+            remaining_pages: values[5],
+            entry,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_journal() -> RelocationJournal {
+        RelocationJournal {
+            phase: RelocationPhase::Blocks,
+            sequence: 7,
+            old_start: 0x18_0000,
+            old_flash_size: 0x20_0000,
+            new_start: 0x1A_0000,
+            new_flash_size: 0x20_0000,
+            remaining_pages: 12,
+            entry: Zeroizing::new([0x5A; CONTROL_PLANE_ENTRY_SIZE]),
+        }
+    }
+
+    #[test]
+    fn journal_round_trips() {
+        let encoded = test_journal().encode();
+        let decoded = match RelocationJournal::decode(&encoded) {
+            Ok(Some(value)) => value,
+            _ => panic!("journal should decode"),
+        };
+        assert_eq!(decoded.phase, RelocationPhase::Blocks);
+        assert_eq!(decoded.sequence, 7);
+        assert_eq!(decoded.remaining_pages, 12);
+        assert_eq!(*decoded.entry, [0x5A; CONTROL_PLANE_ENTRY_SIZE]);
+        assert_eq!(
+            decoded.target(),
+            RelocationTarget {
+                data_storage_start_address: 0x1A_0000,
+                flash_size: 0x20_0000,
+            }
+        );
+    }
+
+    #[test]
+    fn damaged_or_foreign_page_is_not_a_journal() {
+        let mut encoded = test_journal().encode();
+        encoded[ENTRY_OFFSET] ^= 0x01;
+        assert!(matches!(RelocationJournal::decode(&encoded), Ok(None)));
+        assert!(matches!(RelocationJournal::decode(&[0xFF; RELOCATION_JOURNAL_SIZE]), Ok(None)));
+    }
+}
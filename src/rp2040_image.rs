@@ -0,0 +1,635 @@
+/*! Offline RP2040 storage image builder for factory provisioning.
+
+Builds the exact flash contents `Rp2040Backend` would produce after `init`,
+`set_chain_configuration` and `save_block`, so a device flashed with the image
+boots already initialized. Images can be emitted as raw region bytes or as UF2.
+*/
+
+use crate::backend_rp2040::{
+    FLASH_PAGE_SIZE, Rp2040Backend, Rp2040Region, SUPERBLOCK_OFFSET, compiled_superblock,
+    map_storage_index,
+};
+use crate::control_plane::{self, ControlPlaneLayout, KeyProtection};
+use crate::sequence_index::block_sequence;
+use crate::sequence_log::{self, LogRecord, SEQUENCE_RECORD_SIZE};
+use crate::{ControlPlaneData, INIT_PARAMS_SIZE, StorageError, StorageIndex, block_hash};
+use moonblokz_chain_types::Block;
+use moonblokz_crypto::PRIVATE_KEY_SIZE;
+
+/// RP2040 XIP flash base address used for UF2 target addresses.
+pub const RP2040_XIP_BASE: usize = 0x1000_0000;
+/// UF2 block size in bytes.
+pub const UF2_BLOCK_SIZE: usize = 512;
+/// Payload bytes carried by one UF2 block.
+pub const UF2_PAYLOAD_SIZE: usize = 256;
+/// UF2 family id for RP2040.
+pub const UF2_FAMILY_ID_RP2040: u32 = 0xE48B_FF56;
+
+const UF2_MAGIC_START0: u32 = 0x0A32_4655;
+const UF2_MAGIC_START1: u32 = 0x9E5D_5157;
+const UF2_MAGIC_END: u32 = 0x0AB1_6F30;
+const UF2_FLAG_FAMILY_ID_PRESENT: u32 = 0x0000_2000;
+const UF2_DATA_OFFSET: usize = 32;
+
+type Codec = Rp2040Backend;
+
+/// Builder that writes an RP2040 storage region image into a caller buffer.
+///
+/// The buffer covers the storage region from `data_storage_start_address` to
+/// the end of flash, matching the region owned by `Rp2040Backend`.
+///
+/// Example:
+/// ```ignore
+/// use moonblokz_storage::rp2040_image::Rp2040ImageBuilder;
+///
+/// let mut region = [0u8; 5 * 4096];
+/// let builder = Rp2040ImageBuilder::new(&mut region, 0x1F_B000, [1u8; 32], 7, [0u8; 100])?;
+/// let _slots = builder.max_storage_slots();
+/// ```
+pub struct Rp2040ImageBuilder<'a> {
+    region: &'a mut [u8],
+    storage: Rp2040Region,
+    record: ControlPlaneData,
+    key_protection: KeyProtection,
+}
+
+impl<'a> Rp2040ImageBuilder<'a> {
+    /// Creates an initialized image, equivalent to `Rp2040Backend::init`.
+    ///
+    /// Parameters:
+    /// - `region`: output buffer for the storage region (`flash_size - data_storage_start_address` bytes).
+    /// - `data_storage_start_address`: flash offset where the region will be programmed.
+    /// - `private_key`: node private key bytes.
+    /// - `own_node_id`: local node identifier.
+    /// - `init_params`: free-form control-plane initialization bytes.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::rp2040_image::Rp2040ImageBuilder;
+    ///
+    /// let mut region = [0u8; 5 * 4096];
+    /// let _builder = Rp2040ImageBuilder::new(&mut region, 0, [1u8; 32], 7, [0u8; 100])?;
+    /// ```
+    pub fn new(
+        region: &'a mut [u8],
+        data_storage_start_address: usize,
+        private_key: [u8; PRIVATE_KEY_SIZE],
+        own_node_id: u32,
+        init_params: [u8; INIT_PARAMS_SIZE],
+    ) -> Result<Self, StorageError> {
+        let storage = Rp2040Region::new(
+            "image",
+            data_storage_start_address,
+            data_storage_start_address + region.len(),
+        );
+        if data_storage_start_address % FLASH_PAGE_SIZE != 0
+            || region.len() % FLASH_PAGE_SIZE != 0
+            || storage.block_page_count() == 0
+        {
+            return Err(StorageError::InvalidConfiguration);
+        }
+
+        let mut builder = Self {
+            region,
+            storage,
+            record: ControlPlaneData {
+                private_key,
+                own_node_id,
+                init_params,
+                chain_configuration: None,
+            },
+            key_protection: KeyProtection::Plaintext,
+        };
+        builder.region.fill(0xFF);
+        builder.write_control_plane()?;
+        Ok(builder)
+    }
+
+    /// Lays the image out with a non-default control-plane layout, matching a
+    /// backend configured with `Rp2040Backend::with_control_plane_layout`.
+    ///
+    /// Call it before `save_block`: the region is rewritten and previously
+    /// saved slots are erased.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: invalid replica count, or no block page left.
+    ///
+    /// Parameters:
+    /// - `layout`: replica count and placement.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::rp2040_image::Rp2040ImageBuilder;
+    /// use moonblokz_storage::{ControlPlaneLayout, ControlPlanePlacement};
+    ///
+    /// let mut region = [0u8; 8 * 4096];
+    /// let builder = Rp2040ImageBuilder::new(&mut region, 0x1F_8000, [1u8; 32], 7, [0u8; 100])?
+    ///     .with_control_plane_layout(ControlPlaneLayout::new(5, ControlPlanePlacement::Spread))?;
+    /// assert_eq!(builder.max_storage_slots(), 6);
+    /// ```
+    pub fn with_control_plane_layout(mut self, layout: ControlPlaneLayout) -> Result<Self, StorageError> {
+        let storage = self.storage.with_control_plane_layout(layout);
+        if !storage.has_valid_layout() {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        self.storage = storage;
+        self.region.fill(0xFF);
+        self.write_control_plane()?;
+        Ok(self)
+    }
+
+    /// Reserves sequence index pages, matching a backend configured with
+    /// `Rp2040Backend::with_sequence_index`.
+    ///
+    /// The image then carries a built index with one record per
+    /// `save_block`. Call it before `save_block`: the region is rewritten and
+    /// previously saved slots are erased.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: no block page left, or the pages cannot hold
+    ///   a record for every slot plus one.
+    ///
+    /// Parameters:
+    /// - `pages`: sequence index pages at the end of the region.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::rp2040_image::Rp2040ImageBuilder;
+    ///
+    /// let mut region = [0u8; 8 * 4096];
+    /// let builder = Rp2040ImageBuilder::new(&mut region, 0x1F_8000, [1u8; 32], 7, [0u8; 100])?
+    ///     .with_sequence_index(1)?;
+    /// assert_eq!(builder.max_storage_slots(), 8);
+    /// ```
+    pub fn with_sequence_index(mut self, pages: usize) -> Result<Self, StorageError> {
+        let storage = self.storage.with_sequence_index(pages);
+        if !storage.has_valid_layout() {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        self.storage = storage;
+        self.region.fill(0xFF);
+        self.write_control_plane()?;
+        Ok(self)
+    }
+
+    /// Reserves `pages` pages before any sequence index for the key-value
+    /// store, matching `Rp2040Backend::with_kv_store`.
+    ///
+    /// The pages are left erased, so the device starts with an empty store.
+    /// Call it before `save_block`: the region is rewritten and previously
+    /// saved slots are erased.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: `pages` is odd or 2, or no block page left.
+    ///
+    /// Parameters:
+    /// - `pages`: key-value pages.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::rp2040_image::Rp2040ImageBuilder;
+    ///
+    /// let mut region = [0u8; 10 * 4096];
+    /// let builder = Rp2040ImageBuilder::new(&mut region, 0x1F_6000, [1u8; 32], 7, [0u8; 100])?
+    ///     .with_kv_store(4)?;
+    /// assert_eq!(builder.max_storage_slots(), 6);
+    /// ```
+    pub fn with_kv_store(mut self, pages: usize) -> Result<Self, StorageError> {
+        let storage = self.storage.with_kv_store(pages);
+        if !storage.has_valid_layout() {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        self.storage = storage;
+        self.region.fill(0xFF);
+        self.write_control_plane()?;
+        Ok(self)
+    }
+
+    /// Reserves `count` counters before the key-value store, matching
+    /// `Rp2040Backend::with_counters`.
+    ///
+    /// The pages are left erased, so every counter starts at 0. Call it
+    /// before `save_block`: the region is rewritten and previously saved
+    /// slots are erased.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: no block page left.
+    ///
+    /// Parameters:
+    /// - `count`: number of counters.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::rp2040_image::Rp2040ImageBuilder;
+    ///
+    /// let mut region = [0u8; 8 * 4096];
+    /// let builder = Rp2040ImageBuilder::new(&mut region, 0x1F_8000, [1u8; 32], 7, [0u8; 100])?
+    ///     .with_counters(1)?;
+    /// assert_eq!(builder.max_storage_slots(), 6);
+    /// ```
+    pub fn with_counters(mut self, count: usize) -> Result<Self, StorageError> {
+        let storage = self.storage.with_counters(count);
+        if !storage.has_valid_layout() {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        self.storage = storage;
+        self.region.fill(0xFF);
+        self.write_control_plane()?;
+        Ok(self)
+    }
+
+    /// Reserves `pages` pages before the counters for slot metadata, matching
+    /// `Rp2040Backend::with_slot_meta`.
+    ///
+    /// The pages are left erased, so no slot starts with metadata. Call it
+    /// before `save_block`: the region is rewritten and previously saved
+    /// slots are erased.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: no block page left, or the pages cannot hold
+    ///   an area for every slot.
+    ///
+    /// Parameters:
+    /// - `pages`: slot metadata pages.
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::rp2040_image::Rp2040ImageBuilder;
+    ///
+    /// let mut region = [0u8; 8 * 4096];
+    /// let builder = Rp2040ImageBuilder::new(&mut region, 0x1F_8000, [1u8; 32], 7, [0u8; 100])?
+    ///     .with_slot_meta(1)?;
+    /// assert_eq!(builder.max_storage_slots(), 8);
+    /// ```
+    pub fn with_slot_meta(mut self, pages: usize) -> Result<Self, StorageError> {
+        let storage = self.storage.with_slot_meta(pages);
+        if !storage.has_valid_layout() {
+            return Err(StorageError::InvalidConfiguration);
+        }
+        self.storage = storage;
+        self.region.fill(0xFF);
+        self.write_control_plane()?;
+        Ok(self)
+    }
+
+    /// Stores the private key wrapped, matching a backend configured with
+    /// `Rp2040Backend::with_key_protection`.
+    ///
+    /// Only the control-plane pages are rewritten, so saved slots are kept.
+    ///
+    /// Errors:
+    /// - `InvalidConfiguration`: the private key could not be sealed.
+    ///
+    /// Parameters:
+    /// - `protection`: key protection mode (see `KeyProtection::from_secret`).
+    ///
+    /// Example:
+    /// ```ignore
+    /// use moonblokz_storage::KeyProtection;
+    /// use moonblokz_storage::rp2040_image::Rp2040ImageBuilder;
+    ///
+    /// let mut region = [0u8; 5 * 4096];
+    /// let protection = KeyProtection::from_secret(b"fleet secret", None)?;
+    /// let _builder = Rp2040ImageBuilder::new(&mut region, 0x1F_B000, [1u8; 32], 7, [0u8; 100])?
+    ///     .with_key_protection(protection)?;
+    /// ```
+    pub fn with_key_protection(mut self, protection: KeyProtection) -> Result<Self, StorageError> {
+        self.key_protection = protection;
+        self.write_control_plane()?;
+        Ok(self)
+    }
+
+    /// Returns the number of block slots available in the image.
+    ///
+    /// Parameters:
+    /// - none.
+    pub fn max_storage_slots(&self) -> StorageIndex {
+        self.storage.max_storage_slots()
+    }
+
+    /// Returns the flash offset where the image must be programmed.
+    ///
+    /// Parameters:
+    /// - none.
+    pub fn data_storage_start_address(&self) -> usize {
+        self.storage.start
+    }
+
+    /// Persists the chain-configuration block, equivalent to `set_chain_configuration`.
+    ///
+    /// Parameters:
+    /// - `block`: chain-configuration block to persist.
+    pub fn set_chain_configuration(&mut self, block: &Block) -> Result<(), StorageError> {
+        if self.record.chain_configuration.is_some() {
+            return Err(StorageError::ChainConfigurationAlreadySet);
+        }
+
+        self.record.chain_configuration = Some(
+            Block::from_bytes(block.as_bytes()).map_err(|_| StorageError::BackendIo { code: 213 })?,
+        );
+        self.write_control_plane()
+    }
+
+    /// Pre-seeds a block slot, equivalent to `save_block`.
+    ///
+    /// Errors:
+    /// - `InvalidIndex`: `storage_index` is outside the image.
+    /// - `InvalidConfiguration`: the sequence index is full; unlike the
+    ///   backend, the builder does not rebuild it.
+    ///
+    /// Parameters:
+    /// - `storage_index`: destination slot index.
+    /// - `block`: block to persist.
+    pub fn save_block(&mut self, storage_index: StorageIndex, block: &Block) -> Result<(), StorageError> {
+        if storage_index >= self.max_storage_slots() {
+            return Err(StorageError::InvalidIndex);
+        }
+
+        self.append_sequence_record(storage_index, block_sequence(block), sequence_log::hash_tag(&block_hash(block)))?;
+        let mapping = map_storage_index(storage_index);
+        let page_start = self.storage.block_page_address(mapping.page_index as usize) - self.storage.start;
+        let page = &mut self.region[page_start..page_start + FLASH_PAGE_SIZE];
+        Codec::encode_block_to_slot(page, &mapping, block)
+    }
+
+    /// Returns the finished raw region image.
+    ///
+    /// Parameters:
+    /// - none.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.region
+    }
+
+    fn append_sequence_record(
+        &mut self,
+        storage_index: StorageIndex,
+        sequence: u32,
+        hash_tag: u32,
+    ) -> Result<(), StorageError> {
+        if self.storage.sequence_index_pages == 0 {
+            return Ok(());
+        }
+        let mut position = 1usize;
+        while position <= sequence_log::capacity(self.storage.sequence_index_pages) {
+            let start = self.storage.sequence_log_address(position) - self.storage.start;
+            let record = &mut self.region[start..start + SEQUENCE_RECORD_SIZE];
+            if matches!(sequence_log::decode_record(record), LogRecord::Erased) {
+                record.copy_from_slice(&sequence_log::encode_record(storage_index, sequence, hash_tag));
+                return Ok(());
+            }
+            position += 1;
+        }
+        Err(StorageError::InvalidConfiguration)
+    }
+
+    fn write_control_plane(&mut self) -> Result<(), StorageError> {
+        let encoded = control_plane::encode_entry(&self.record, &self.key_protection)?;
+        let superblock = compiled_superblock(&self.storage).encode();
+        let mut replica_index = 0usize;
+        while replica_index < self.storage.control_plane.replica_count {
+            let page_start = self.storage.control_plane_page_address(replica_index) - self.storage.start;
+            let page = &mut self.region[page_start..page_start + FLASH_PAGE_SIZE];
+            page.fill(0);
+            page[..encoded.len()].copy_from_slice(&encoded[..]);
+            page[SUPERBLOCK_OFFSET..].copy_from_slice(&superblock);
+            replica_index += 1;
+        }
+
+        // Written with the control plane, so a rewritten region keeps its index built.
+        if self.storage.sequence_index_pages > 0 {
+            let header_start = self.storage.sequence_log_address(0) - self.storage.start;
+            self.region[header_start..header_start + SEQUENCE_RECORD_SIZE]
+                .copy_from_slice(&sequence_log::encode_header());
+        }
+        Ok(())
+    }
+}
+
+/// Encodes a raw flash image as RP2040 UF2 blocks.
+///
+/// Every 256-byte chunk is emitted, including erased ones, so flashing the UF2
+/// overwrites any stale data in the storage region.
+///
+/// Parameters:
+/// - `image`: raw bytes to program; length must be a multiple of 256.
+/// - `flash_offset`: flash offset of the first image byte.
+/// - `emit`: callback receiving each encoded UF2 block in order.
+///
+/// Example:
+/// ```ignore
+/// use moonblokz_storage::rp2040_image::{UF2_BLOCK_SIZE, write_uf2};
+///
+/// let image = [0xFFu8; 4096];
+/// let mut blocks = 0;
+/// write_uf2(&image, 0x18_0000, |_block: &[u8; UF2_BLOCK_SIZE]| {
+///     blocks += 1;
+///     Ok(())
+/// })?;
+/// assert_eq!(blocks, 16);
+/// ```
+pub fn write_uf2<F>(image: &[u8], flash_offset: usize, mut emit: F) -> Result<(), StorageError>
+where
+    F: FnMut(&[u8; UF2_BLOCK_SIZE]) -> Result<(), StorageError>,
+{
+    if image.len() % UF2_PAYLOAD_SIZE != 0 {
+        return Err(StorageError::InvalidConfiguration);
+    }
+
+    let block_count = (image.len() / UF2_PAYLOAD_SIZE) as u32;
+    let mut block = [0u8; UF2_BLOCK_SIZE];
+    let mut block_index = 0u32;
+    while block_index < block_count {
+        let chunk_start = block_index as usize * UF2_PAYLOAD_SIZE;
+        let target_address = (RP2040_XIP_BASE + flash_offset + chunk_start) as u32;
+
+        block.fill(0);
+        block[0..4].copy_from_slice(&UF2_MAGIC_START0.to_le_bytes());
+        block[4..8].copy_from_slice(&UF2_MAGIC_START1.to_le_bytes());
+        block[8..12].copy_from_slice(&UF2_FLAG_FAMILY_ID_PRESENT.to_le_bytes());
+        block[12..16].copy_from_slice(&target_address.to_le_bytes());
+        block[16..20].copy_from_slice(&(UF2_PAYLOAD_SIZE as u32).to_le_bytes());
+        block[20..24].copy_from_slice(&block_index.to_le_bytes());
+        block[24..28].copy_from_slice(&block_count.to_le_bytes());
+        block[28..32].copy_from_slice(&UF2_FAMILY_ID_RP2040.to_le_bytes());
+        block[UF2_DATA_OFFSET..UF2_DATA_OFFSET + UF2_PAYLOAD_SIZE]
+            .copy_from_slice(&image[chunk_start..chunk_start + UF2_PAYLOAD_SIZE]);
+        block[UF2_BLOCK_SIZE - 4..].copy_from_slice(&UF2_MAGIC_END.to_le_bytes());
+
+        emit(&block)?;
+        block_index += 1;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend_rp2040::BLOCKS_PER_PAGE_INDEX;
+    use crate::{CONTROL_PLANE_COUNT, ControlPlanePlacement, StorageTrait};
+    use moonblokz_chain_types::MAX_BLOCK_SIZE;
+
+    const TEST_FLASH_SIZE: usize = (CONTROL_PLANE_COUNT + 3) * FLASH_PAGE_SIZE;
+    const TEST_START_ADDRESS: usize = FLASH_PAGE_SIZE;
+    const TEST_REGION_SIZE: usize = TEST_FLASH_SIZE - TEST_START_ADDRESS;
+
+    fn block_from_marker(marker: u8) -> Block {
+        let mut bytes = [0u8; MAX_BLOCK_SIZE];
+        bytes[0] = 1;
+        bytes[1] = marker;
+        let parse_result = Block::from_bytes(&bytes);
+        assert!(parse_result.is_ok());
+        match parse_result {
+            Ok(value) => value,
+            Err(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn image_matches_backend_flash_byte_for_byte() {
+        let private_key = [5u8; PRIVATE_KEY_SIZE];
+        let init_params = [6u8; INIT_PARAMS_SIZE];
+        let config = block_from_marker(1);
+        let block_a = block_from_marker(2);
+        let block_b = block_from_marker(3);
+
+        let mut backend = Rp2040Backend::<TEST_FLASH_SIZE>::new_for_tests(TEST_START_ADDRESS)
+            .unwrap_or_else(|_| unreachable!());
+        assert!(backend.init(private_key, 11, init_params).is_ok());
+        assert!(backend.set_chain_configuration(&config).is_ok());
+        assert!(backend.save_block(0, &block_a).is_ok());
+        assert!(backend.save_block(3, &block_b).is_ok());
+
+        let mut region = [0u8; TEST_REGION_SIZE];
+        let builder = Rp2040ImageBuilder::new(&mut region, TEST_START_ADDRESS, private_key, 11, init_params);
+        assert!(builder.is_ok());
+        let mut builder = match builder {
+            Ok(value) => value,
+            Err(_) => return,
+        };
+        assert!(builder.set_chain_configuration(&config).is_ok());
+        assert!(builder.save_block(0, &block_a).is_ok());
+        assert!(builder.save_block(3, &block_b).is_ok());
+
+        let flash = backend.flash_image();
+        assert_eq!(builder.as_bytes(), &flash[TEST_START_ADDRESS..]);
+    }
+
+    #[test]
+    fn image_with_control_plane_layout_matches_backend_flash() {
+        let layout = ControlPlaneLayout::new(3, ControlPlanePlacement::Spread);
+        let block = block_from_marker(5);
+
+        let mut backend = Rp2040Backend::<TEST_FLASH_SIZE>::new_for_tests(TEST_START_ADDRESS)
+            .and_then(|backend| backend.with_control_plane_layout(layout))
+            .unwrap_or_else(|_| unreachable!());
+        assert!(backend.init([5u8; PRIVATE_KEY_SIZE], 11, [6u8; INIT_PARAMS_SIZE]).is_ok());
+        assert!(backend.save_block(3, &block).is_ok());
+
+        let mut region = [0u8; TEST_REGION_SIZE];
+        let mut builder =
+            Rp2040ImageBuilder::new(&mut region, TEST_START_ADDRESS, [5u8; PRIVATE_KEY_SIZE], 11, [6u8; INIT_PARAMS_SIZE])
+                .and_then(|builder| builder.with_control_plane_layout(layout))
+                .unwrap_or_else(|_| unreachable!());
+        assert_eq!(builder.max_storage_slots(), 2 * BLOCKS_PER_PAGE_INDEX);
+        assert!(builder.save_block(3, &block).is_ok());
+
+        let flash = backend.flash_image();
+        assert_eq!(builder.as_bytes(), &flash[TEST_START_ADDRESS..]);
+    }
+
+    #[test]
+    fn image_with_sequence_index_matches_backend_flash() {
+        let block_a = block_from_marker(2);
+        let block_b = block_from_marker(3);
+
+        let mut backend = Rp2040Backend::<TEST_FLASH_SIZE>::new_for_tests(TEST_START_ADDRESS)
+            .and_then(|backend| backend.with_sequence_index(1))
+            .unwrap_or_else(|_| unreachable!());
+        assert!(backend.init([5u8; PRIVATE_KEY_SIZE], 11, [6u8; INIT_PARAMS_SIZE]).is_ok());
+        assert!(backend.save_block(0, &block_a).is_ok());
+        assert!(backend.save_block(3, &block_b).is_ok());
+
+        let mut region = [0u8; TEST_REGION_SIZE];
+        let mut builder =
+            Rp2040ImageBuilder::new(&mut region, TEST_START_ADDRESS, [5u8; PRIVATE_KEY_SIZE], 11, [6u8; INIT_PARAMS_SIZE])
+                .and_then(|builder| builder.with_sequence_index(1))
+                .unwrap_or_else(|_| unreachable!());
+        assert!(builder.save_block(0, &block_a).is_ok());
+        assert!(builder.save_block(3, &block_b).is_ok());
+
+        let flash = backend.flash_image();
+        assert_eq!(builder.as_bytes(), &flash[TEST_START_ADDRESS..]);
+        assert!(matches!(backend.find_by_sequence(3), Ok(Some(3))));
+    }
+
+    #[cfg(feature = "key-encryption")]
+    #[test]
+    fn image_with_key_protection_matches_backend_flash() {
+        let protection =
+            KeyProtection::from_secret(b"fleet secret", Some(&[7u8; 8])).unwrap_or_else(|_| unreachable!());
+        let block = block_from_marker(2);
+
+        let mut backend = Rp2040Backend::<TEST_FLASH_SIZE>::new_for_tests(TEST_START_ADDRESS)
+            .unwrap_or_else(|_| unreachable!())
+            .with_key_protection(protection.clone());
+        assert!(backend.init([5u8; PRIVATE_KEY_SIZE], 11, [6u8; INIT_PARAMS_SIZE]).is_ok());
+        assert!(backend.save_block(1, &block).is_ok());
+
+        let mut region = [0u8; TEST_REGION_SIZE];
+        let mut builder =
+            Rp2040ImageBuilder::new(&mut region, TEST_START_ADDRESS, [5u8; PRIVATE_KEY_SIZE], 11, [6u8; INIT_PARAMS_SIZE])
+                .unwrap_or_else(|_| unreachable!());
+        assert!(builder.save_block(1, &block).is_ok());
+        let builder = builder.with_key_protection(protection).unwrap_or_else(|_| unreachable!());
+
+        let flash = backend.flash_image();
+        assert_eq!(builder.as_bytes(), &flash[TEST_START_ADDRESS..]);
+        let control_data = backend.load_control_data().unwrap_or_else(|_| unreachable!());
+        assert_eq!(control_data.private_key, [5u8; PRIVATE_KEY_SIZE]);
+    }
+
+    #[test]
+    fn image_builder_rejects_out_of_range_index_and_bad_geometry() {
+        let mut region = [0u8; TEST_REGION_SIZE];
+        let builder = Rp2040ImageBuilder::new(&mut region, TEST_START_ADDRESS, [1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE]);
+        assert!(builder.is_ok());
+        let mut builder = match builder {
+            Ok(value) => value,
+            Err(_) => return,
+        };
+        let block = block_from_marker(4);
+        let invalid_index = builder.max_storage_slots();
+        assert!(matches!(
+            builder.save_block(invalid_index, &block),
+            Err(StorageError::InvalidIndex)
+        ));
+
+        let mut misaligned = [0u8; TEST_REGION_SIZE];
+        assert!(matches!(
+            Rp2040ImageBuilder::new(&mut misaligned, 1, [1u8; PRIVATE_KEY_SIZE], 1, [0u8; INIT_PARAMS_SIZE]),
+            Err(StorageError::InvalidConfiguration)
+        ));
+    }
+
+    #[test]
+    fn uf2_blocks_cover_image_with_rp2040_addresses() {
+        let mut image = [0xFFu8; 2 * UF2_PAYLOAD_SIZE];
+        image[UF2_PAYLOAD_SIZE] = 0xAB;
+        let mut seen = 0u32;
+        let result = write_uf2(&image, 0x18_0000, |block: &[u8; UF2_BLOCK_SIZE]| {
+            let mut address = [0u8; 4];
+            address.copy_from_slice(&block[12..16]);
+            assert_eq!(
+                u32::from_le_bytes(address) as usize,
+                RP2040_XIP_BASE + 0x18_0000 + seen as usize * UF2_PAYLOAD_SIZE
+            );
+            let expected_first = if seen == 1 { 0xAB } else { 0xFF };
+            assert_eq!(block[UF2_DATA_OFFSET], expected_first);
+            seen += 1;
+            Ok(())
+        });
+        assert!(result.is_ok());
+        assert_eq!(seen, 2);
+    }
+}
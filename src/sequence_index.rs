@@ -0,0 +1,192 @@
+/*! Block lookup by `BlockHeader::sequence`, shared by all backends.
+
+`find_by_sequence`, `max_sequence` and `range_by_sequence` run over candidate
+`(storage_index, sequence)` pairs that may be stale or duplicated, and confirm
+every hit against the slot itself before reporting it. The memory backend
+offers its occupied slots as candidates. The RP2040 backend offers the records
+of its on-flash sequence log (`sequence_log`) when one is built, and the
+sequence bytes of every slot otherwise.
+
+When several slots hold the same sequence number, the lowest storage index is
+reported.
+*/
+
+use crate::{StorageError, StorageIndex};
+use core::ops::Range;
+use moonblokz_chain_types::Block;
+
+/// Offset of `sequence:u32` in a serialized block header, right after `version:u8`.
+pub const SEQUENCE_OFFSET: usize = 1;
+
+/// Reads the little-endian header sequence number from raw slot bytes without
+/// parsing the block; used to pick candidates, which are verified afterwards.
+pub(crate) fn sequence_from_bytes(bytes: &[u8]) -> u32 {
+    let mut value = [0u8; 4];
+    value.copy_from_slice(&bytes[SEQUENCE_OFFSET..SEQUENCE_OFFSET + 4]);
+    u32::from_le_bytes(value)
+}
+
+pub(crate) fn block_sequence(block: &Block) -> u32 {
+    block.header().sequence
+}
+
+/// Candidate pairs and slot verification of one backend region.
+pub(crate) trait SequenceSource {
+    /// Calls `visit` with every candidate pair until it returns `false`.
+    fn for_each_candidate(
+        &self,
+        visit: &mut dyn FnMut(StorageIndex, u32) -> bool,
+    ) -> Result<(), StorageError>;
+
+    /// Returns `true` when slot `storage_index` holds an intact block with `sequence`.
+    fn holds(&self, storage_index: StorageIndex, sequence: u32) -> Result<bool, StorageError>;
+}
+
+pub(crate) fn find_by_sequence(
+    source: &dyn SequenceSource,
+    sequence: u32,
+) -> Result<Option<StorageIndex>, StorageError> {
+    let mut found: Option<StorageIndex> = None;
+    let mut failure: Option<StorageError> = None;
+    source.for_each_candidate(&mut |storage_index, candidate| {
+        if candidate != sequence || found.is_some_and(|index| index <= storage_index) {
+            return true;
+        }
+        match source.holds(storage_index, candidate) {
+            Ok(true) => found = Some(storage_index),
+            Ok(false) => {}
+            Err(error) => {
+                failure = Some(error);
+                return false;
+            }
+        }
+        true
+    })?;
+
+    match failure {
+        Some(error) => Err(error),
+        None => Ok(found),
+    }
+}
+
+pub(crate) fn max_sequence(source: &dyn SequenceSource) -> Result<Option<u32>, StorageError> {
+    // Stale candidates above the tip are discarded one sequence number at a time.
+    let mut below: Option<u32> = None;
+    loop {
+        let mut highest: Option<u32> = None;
+        source.for_each_candidate(&mut |_, sequence| {
+            if below.is_none_or(|bound| sequence < bound) && highest.is_none_or(|value| sequence > value) {
+                highest = Some(sequence);
+            }
+            true
+        })?;
+
+        let sequence = match highest {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        if find_by_sequence(source, sequence)?.is_some() {
+            return Ok(Some(sequence));
+        }
+        below = Some(sequence);
+    }
+}
+
+pub(crate) fn range_by_sequence(
+    source: &dyn SequenceSource,
+    range: Range<u32>,
+    on_block: &mut dyn FnMut(u32, StorageIndex),
+) -> Result<(), StorageError> {
+    let mut lower = range.start;
+    while lower < range.end {
+        let mut lowest: Option<u32> = None;
+        source.for_each_candidate(&mut |_, sequence| {
+            if sequence >= lower && sequence < range.end && lowest.is_none_or(|value| sequence < value) {
+                lowest = Some(sequence);
+            }
+            true
+        })?;
+
+        let sequence = match lowest {
+            Some(value) => value,
+            None => break,
+        };
+        if let Some(storage_index) = find_by_sequence(source, sequence)? {
+            on_block(sequence, storage_index);
+        }
+        // `sequence < range.end`, so this cannot overflow.
+        lower = sequence + 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Candidates `(storage_index, sequence)`; slots hold the listed sequence.
+    struct TableSource {
+        candidates: &'static [(StorageIndex, u32)],
+        slots: &'static [(StorageIndex, u32)],
+    }
+
+    impl SequenceSource for TableSource {
+        fn for_each_candidate(
+            &self,
+            visit: &mut dyn FnMut(StorageIndex, u32) -> bool,
+        ) -> Result<(), StorageError> {
+            let mut index = 0usize;
+            while index < self.candidates.len() && visit(self.candidates[index].0, self.candidates[index].1) {
+                index += 1;
+            }
+            Ok(())
+        }
+
+        fn holds(&self, storage_index: StorageIndex, sequence: u32) -> Result<bool, StorageError> {
+            Ok(self.slots.iter().any(|slot| *slot == (storage_index, sequence)))
+        }
+    }
+
+    // Slot 4 was overwritten (9 -> 3) and the write of sequence 12 to slot 0 was lost.
+    const SOURCE: TableSource = TableSource {
+        candidates: &[(4, 9), (2, 5), (7, 5), (1, 7), (4, 3), (0, 12), (5, 5)],
+        slots: &[(4, 3), (2, 5), (7, 5), (5, 5), (1, 7)],
+    };
+
+    #[test]
+    fn stale_candidates_are_rejected() {
+        assert!(matches!(find_by_sequence(&SOURCE, 5), Ok(Some(2))));
+        assert!(matches!(find_by_sequence(&SOURCE, 9), Ok(None)));
+        assert!(matches!(find_by_sequence(&SOURCE, 12), Ok(None)));
+        assert!(matches!(max_sequence(&SOURCE), Ok(Some(7))));
+    }
+
+    #[test]
+    fn range_reports_each_sequence_once_in_ascending_order() {
+        let mut seen: [(u32, StorageIndex); 8] = [(0, 0); 8];
+        let mut count = 0usize;
+        assert!(range_by_sequence(&SOURCE, 0..u32::MAX, &mut |sequence, storage_index| {
+            seen[count] = (sequence, storage_index);
+            count += 1;
+        })
+        .is_ok());
+        assert_eq!(&seen[..count], &[(3, 4), (5, 2), (7, 1)]);
+
+        count = 0;
+        assert!(range_by_sequence(&SOURCE, 4..7, &mut |sequence, storage_index| {
+            seen[count] = (sequence, storage_index);
+            count += 1;
+        })
+        .is_ok());
+        assert_eq!(&seen[..count], &[(5, 2)]);
+    }
+
+    #[test]
+    fn empty_source_has_no_tip() {
+        let empty = TableSource {
+            candidates: &[],
+            slots: &[],
+        };
+        assert!(matches!(max_sequence(&empty), Ok(None)));
+    }
+}
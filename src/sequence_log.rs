@@ -0,0 +1,148 @@
+/*! On-flash sequence log behind the RP2040 sequence and hash index.
+
+A region configured with `Rp2040Region::with_sequence_index(pages)` reserves
+its last `pages` pages for an append-only log of
+`(storage_index, sequence, hash_tag)` records. Record position 0 holds a
+header marking the log as built; positions are filled in order, so the erased
+records form a suffix of the log.
+
+`hash_tag` is the first four bytes of the slot's `block_hash`, little-endian.
+`find_by_hash` uses it to pick candidate slots, so a built log serves hash
+lookups without reading every block page; a tag match is only a candidate
+and is confirmed against the hash stored in the slot.
+
+Crash safety:
+- `save_block` appends the record of the new block before the slot is written,
+  so every intact slot is covered by a record while the log is built. Records
+  left stale by an overwrite or a lost slot write are rejected when the slot
+  is read back (`sequence_index`).
+- A power loss while appending leaves a damaged record that fails its CRC and
+  is skipped; the next append takes the following position.
+- A rebuild erases the log, appends one record per intact slot and programs
+  the header last. Until the header is present the log is ignored and lookups
+  scan the slots instead.
+
+A full log is rebuilt from a slot scan by the `save_block` that finds it full.
+
+Layout (all integers little-endian):
+
+- header: `magic:[u8;4] = "MBSQ", version:u8, reserved:[u8;7] = 0, crc32:u32`
+- record: `storage_index:u32, sequence:u32, hash_tag:u32, crc32:u32`
+*/
+
+use crate::StorageIndex;
+use crate::backend_rp2040::FLASH_PAGE_SIZE;
+use crate::control_plane::crc32;
+use moonblokz_chain_types::HASH_SIZE;
+
+/// Sequence log header magic bytes.
+pub const SEQUENCE_LOG_MAGIC: [u8; 4] = *b"MBSQ";
+/// Sequence log format version.
+pub const SEQUENCE_LOG_VERSION: u8 = 1;
+/// Size of the header and of every record in bytes.
+pub const SEQUENCE_RECORD_SIZE: usize = 16;
+/// Records per sequence index page; records never cross pages.
+pub const SEQUENCE_RECORDS_PER_PAGE: usize = FLASH_PAGE_SIZE / SEQUENCE_RECORD_SIZE;
+
+const CRC_OFFSET: usize = SEQUENCE_RECORD_SIZE - 4;
+
+/// Content of one record position.
+pub(crate) enum LogRecord {
+    Erased,
+    Damaged,
+    Entry {
+        storage_index: StorageIndex,
+        sequence: u32,
+        hash_tag: u32,
+    },
+}
+
+/// Records a log of `pages` pages can hold next to its header.
+pub(crate) const fn capacity(pages: usize) -> usize {
+    (pages * SEQUENCE_RECORDS_PER_PAGE).saturating_sub(1)
+}
+
+/// Returns the tag a record keeps of `hash`.
+pub(crate) fn hash_tag(hash: &[u8; HASH_SIZE]) -> u32 {
+    u32::from_le_bytes([hash[0], hash[1], hash[2], hash[3]])
+}
+
+pub(crate) fn encode_header() -> [u8; SEQUENCE_RECORD_SIZE] {
+    let mut out = [0u8; SEQUENCE_RECORD_SIZE];
+    out[..4].copy_from_slice(&SEQUENCE_LOG_MAGIC);
+    out[4] = SEQUENCE_LOG_VERSION;
+    let crc = crc32(&out[..CRC_OFFSET]);
+    out[CRC_OFFSET..].copy_from_slice(&crc.to_le_bytes());
+    out
+}
+
+/// Returns `true` for a header of the current version; a log written in any
+/// other format is treated as not built and rebuilt.
+pub(crate) fn is_header(bytes: &[u8; SEQUENCE_RECORD_SIZE]) -> bool {
+    *bytes == encode_header()
+}
+
+pub(crate) fn encode_record(storage_index: StorageIndex, sequence: u32, hash_tag: u32) -> [u8; SEQUENCE_RECORD_SIZE] {
+    let mut out = [0u8; SEQUENCE_RECORD_SIZE];
+    out[..4].copy_from_slice(&storage_index.to_le_bytes());
+    out[4..8].copy_from_slice(&sequence.to_le_bytes());
+    out[8..12].copy_from_slice(&hash_tag.to_le_bytes());
+    let crc = crc32(&out[..CRC_OFFSET]);
+    out[CRC_OFFSET..].copy_from_slice(&crc.to_le_bytes());
+    out
+}
+
+pub(crate) fn decode_record(bytes: &[u8]) -> LogRecord {
+    if bytes.iter().all(|byte| *byte == 0xFF) {
+        return LogRecord::Erased;
+    }
+
+    let mut values = [0u32; 4];
+    let mut index = 0usize;
+    while index < values.len() {
+        let mut value = [0u8; 4];
+        value.copy_from_slice(&bytes[index * 4..index * 4 + 4]);
+        values[index] = u32::from_le_bytes(value);
+        index += 1;
+    }
+    if values[3] != crc32(&bytes[..CRC_OFFSET]) {
+        return LogRecord::Damaged;
+    }
+    LogRecord::Entry {
+        storage_index: values[0],
+        sequence: values[1],
+        hash_tag: values[2],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_round_trips() {
+        let encoded = encode_record(513, 0xDEAD_BEEF, 0x0403_0201);
+        assert!(matches!(
+            decode_record(&encoded),
+            LogRecord::Entry {
+                storage_index: 513,
+                sequence: 0xDEAD_BEEF,
+                hash_tag: 0x0403_0201,
+            }
+        ));
+        let mut hash = [0u8; HASH_SIZE];
+        hash[..4].copy_from_slice(&[1, 2, 3, 4]);
+        assert_eq!(hash_tag(&hash), 0x0403_0201);
+    }
+
+    #[test]
+    fn erased_and_partially_programmed_records_are_told_apart() {
+        assert!(matches!(decode_record(&[0xFF; SEQUENCE_RECORD_SIZE]), LogRecord::Erased));
+
+        let mut partial = [0xFF; SEQUENCE_RECORD_SIZE];
+        partial[..6].copy_from_slice(&encode_record(7, 8, 9)[..6]);
+        assert!(matches!(decode_record(&partial), LogRecord::Damaged));
+        assert!(is_header(&encode_header()));
+        assert!(!is_header(&partial));
+    }
+}
@@ -0,0 +1,167 @@
+/*! On-flash per-slot metadata pages of the RP2040 backend.
+
+A region configured with `Rp2040Region::with_slot_meta(pages)` reserves
+`pages` pages before its counters for metadata. Every slot owns one
+`SLOT_META_AREA_SIZE` byte area there (slot `n` at page
+`n / SLOT_META_AREAS_PER_PAGE`), holding up to `SLOT_META_GENERATIONS`
+records. Positions are filled in order and the last intact record is
+current, so an update only programs erased bytes and needs no erase. The
+block pages are untouched, so `BLOCKS_PER_PAGE` is the same with and without
+the pages.
+
+The pages are part of the layout whether or not the `slot-meta` feature is
+enabled, so images and tools lay out the same region as the firmware; only
+`read_slot_meta` and `write_slot_meta` need the feature. Block writes always
+clear the metadata of a slot that has some.
+
+Crash safety:
+- A power loss while programming a record leaves a damaged record that fails
+  its CRC and is skipped; the previous record stays current and the next
+  update takes the following position.
+- A block write programs a `cleared` record before the slot is erased, so a
+  new block never inherits the metadata of the block it replaces; a power
+  loss in between only drops the old block's metadata. When every position is
+  used the area is programmed to zero instead, which damages every record.
+- When every position is used, a metadata update rewrites the page with the
+  new record at position 0. A power loss during that rewrite loses the
+  metadata of every slot in the page; their blocks are not affected.
+
+Layout (all integers little-endian):
+
+- record: `kind:u8, meta:[u8; SLOT_META_SIZE], crc32:u32`, kind 1 holds
+  metadata and kind 2 clears it (`meta` all zero).
+*/
+
+use crate::backend_rp2040::FLASH_PAGE_SIZE;
+use crate::control_plane::crc32;
+use crate::types::SLOT_META_SIZE;
+
+/// Record kind holding a metadata value.
+pub const SLOT_META_KIND_VALUE: u8 = 1;
+/// Record kind clearing the metadata of a slot.
+pub const SLOT_META_KIND_CLEARED: u8 = 2;
+/// Size of one metadata record in bytes.
+pub const SLOT_META_RECORD_SIZE: usize = 1 + SLOT_META_SIZE + 4;
+/// Metadata records per slot before an update rewrites the page.
+pub const SLOT_META_GENERATIONS: usize = 4;
+/// Size of the metadata area of one slot in bytes.
+pub const SLOT_META_AREA_SIZE: usize = SLOT_META_GENERATIONS * SLOT_META_RECORD_SIZE;
+/// Slot metadata areas per metadata page; areas never cross pages.
+pub const SLOT_META_AREAS_PER_PAGE: usize = FLASH_PAGE_SIZE / SLOT_META_AREA_SIZE;
+
+const CRC_OFFSET: usize = 1 + SLOT_META_SIZE;
+
+/// Content of one record position.
+pub(crate) enum MetaRecord {
+    Erased,
+    Damaged,
+    Entry(Option<[u8; SLOT_META_SIZE]>),
+}
+
+/// Slots `pages` metadata pages can hold areas for.
+pub(crate) const fn capacity(pages: usize) -> usize {
+    pages * SLOT_META_AREAS_PER_PAGE
+}
+
+/// Encodes a record setting `meta`, or clearing the metadata for `None`.
+pub(crate) fn encode_record(meta: Option<&[u8; SLOT_META_SIZE]>) -> [u8; SLOT_META_RECORD_SIZE] {
+    let mut out = [0u8; SLOT_META_RECORD_SIZE];
+    match meta {
+        Some(meta) => {
+            out[0] = SLOT_META_KIND_VALUE;
+            out[1..CRC_OFFSET].copy_from_slice(meta);
+        }
+        None => out[0] = SLOT_META_KIND_CLEARED,
+    }
+    let crc = crc32(&out[..CRC_OFFSET]);
+    out[CRC_OFFSET..].copy_from_slice(&crc.to_le_bytes());
+    out
+}
+
+pub(crate) fn decode_record(bytes: &[u8]) -> MetaRecord {
+    if bytes.iter().all(|byte| *byte == 0xFF) {
+        return MetaRecord::Erased;
+    }
+
+    let mut crc = [0u8; 4];
+    crc.copy_from_slice(&bytes[CRC_OFFSET..SLOT_META_RECORD_SIZE]);
+    if u32::from_le_bytes(crc) != crc32(&bytes[..CRC_OFFSET]) {
+        return MetaRecord::Damaged;
+    }
+    match bytes[0] {
+        SLOT_META_KIND_VALUE => {
+            let mut meta = [0u8; SLOT_META_SIZE];
+            meta.copy_from_slice(&bytes[1..CRC_OFFSET]);
+            MetaRecord::Entry(Some(meta))
+        }
+        SLOT_META_KIND_CLEARED => MetaRecord::Entry(None),
+        _ => MetaRecord::Damaged,
+    }
+}
+
+/// Returns the current metadata of a metadata area, `None` when it has none.
+pub(crate) fn latest(area: &[u8]) -> Option<[u8; SLOT_META_SIZE]> {
+    let mut current = None;
+    let mut position = 0usize;
+    while position < area.len() / SLOT_META_RECORD_SIZE {
+        let start = position * SLOT_META_RECORD_SIZE;
+        match decode_record(&area[start..start + SLOT_META_RECORD_SIZE]) {
+            MetaRecord::Erased => break,
+            MetaRecord::Damaged => {}
+            MetaRecord::Entry(meta) => current = meta,
+        }
+        position += 1;
+    }
+    current
+}
+
+/// Returns the first erased record position of a metadata area, `None` when
+/// every position is used.
+pub(crate) fn next_free(area: &[u8]) -> Option<usize> {
+    let mut position = 0usize;
+    while position < area.len() / SLOT_META_RECORD_SIZE {
+        let start = position * SLOT_META_RECORD_SIZE;
+        if matches!(decode_record(&area[start..start + SLOT_META_RECORD_SIZE]), MetaRecord::Erased) {
+            return Some(position);
+        }
+        position += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_round_trips() {
+        let meta = [0xA5; SLOT_META_SIZE];
+        assert!(matches!(decode_record(&encode_record(Some(&meta))), MetaRecord::Entry(Some(value)) if value == meta));
+        assert!(matches!(decode_record(&encode_record(None)), MetaRecord::Entry(None)));
+        assert!(matches!(decode_record(&[0xFF; SLOT_META_RECORD_SIZE]), MetaRecord::Erased));
+        assert!(matches!(decode_record(&[0x00; SLOT_META_RECORD_SIZE]), MetaRecord::Damaged));
+    }
+
+    #[test]
+    fn latest_skips_damaged_records_and_follows_cleared_ones() {
+        let mut area = [0xFF; SLOT_META_AREA_SIZE];
+        assert!(latest(&area).is_none());
+        assert_eq!(next_free(&area), Some(0));
+
+        area[..SLOT_META_RECORD_SIZE].copy_from_slice(&encode_record(Some(&[1; SLOT_META_SIZE])));
+        let partial = encode_record(Some(&[2; SLOT_META_SIZE]));
+        area[SLOT_META_RECORD_SIZE..SLOT_META_RECORD_SIZE + 6].copy_from_slice(&partial[..6]);
+        assert!(matches!(latest(&area), Some(meta) if meta == [1; SLOT_META_SIZE]));
+        assert_eq!(next_free(&area), Some(2));
+
+        area[2 * SLOT_META_RECORD_SIZE..3 * SLOT_META_RECORD_SIZE].copy_from_slice(&encode_record(None));
+        assert!(latest(&area).is_none());
+        area[3 * SLOT_META_RECORD_SIZE..].copy_from_slice(&encode_record(Some(&[3; SLOT_META_SIZE])));
+        assert!(matches!(latest(&area), Some(meta) if meta == [3; SLOT_META_SIZE]));
+        assert_eq!(next_free(&area), None);
+
+        area.fill(0);
+        assert!(latest(&area).is_none());
+        assert_eq!(next_free(&area), None);
+    }
+}
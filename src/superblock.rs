@@ -0,0 +1,392 @@
+/*! On-flash layout descriptor (superblock).
+
+Flash-backed backends store a superblock next to every control-plane replica,
+recording the geometry the region was written with. Firmware built with a
+different `MAX_BLOCK_SIZE`, start address, region end or control-plane
+layout then fails with a precise `StorageError::LayoutMismatch` instead of
+misreading slots.
+
+Layout (all integers little-endian):
+
+`magic:[u8;4] = "MBSB", version:u8, flash_page_size:u32, slot_size_bytes:u32,
+blocks_per_page:u32, max_block_size:u32, hash_size:u32,
+data_storage_start_address:u32, flash_size:u32, control_plane_replicas:u32,
+control_plane_placement:u32, sequence_index_pages:u32, kv_pages:u32,
+counters:u32, slot_meta_pages:u32, crc32:u32`
+
+A superblock carrying the magic but a version this firmware does not know is
+refused with `ControlPlaneIncompatible` rather than treated as missing, so an
+older firmware never overwrites a newer layout. Future versions must keep
+`magic` and `version` at the start of the `SUPERBLOCK_SIZE` window.
+*/
+
+use crate::StorageError;
+use crate::control_plane::{ControlPlaneLayout, ControlPlanePlacement};
+use crate::record_header::{self, HEADER_SIZE};
+
+/// Superblock magic bytes.
+pub const SUPERBLOCK_MAGIC: [u8; 4] = *b"MBSB";
+/// Superblock format version.
+pub const SUPERBLOCK_VERSION: u8 = 1;
+/// Serialized superblock size in bytes.
+pub const SUPERBLOCK_SIZE: usize = HEADER_SIZE + FIELD_COUNT * 4 + 4;
+
+const FIELD_COUNT: usize = 13;
+const FIELDS_OFFSET: usize = HEADER_SIZE;
+
+/// Geometry field recorded in the superblock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutField {
+    /// Flash erase page size.
+    FlashPageSize,
+    /// Bytes per block slot including hash metadata.
+    SlotSizeBytes,
+    /// Block slots per flash page.
+    BlocksPerPage,
+    /// Maximum block size compiled into `moonblokz-chain-types`.
+    MaxBlockSize,
+    /// Block hash size.
+    HashSize,
+    /// First flash address of the storage region.
+    DataStorageStartAddress,
+    /// End of the storage region (the flash size unless partitioned).
+    FlashSize,
+    /// Number of control-plane replicas.
+    ControlPlaneReplicas,
+    /// Control-plane replica placement.
+    ControlPlanePlacement,
+    /// Pages reserved for the sequence index at the end of the region.
+    SequenceIndexPages,
+    /// Pages reserved for the key-value store before the sequence index.
+    KvPages,
+    /// Monotonic counters, two pages each, before the key-value store.
+    Counters,
+    /// Pages reserved for slot metadata before the counters.
+    SlotMetaPages,
+}
+
+impl LayoutField {
+    /// Returns a stable kebab-case name for logs and tooling.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::superblock::LayoutField;
+    ///
+    /// assert_eq!(LayoutField::MaxBlockSize.name(), "max-block-size");
+    /// ```
+    pub fn name(&self) -> &'static str {
+        match self {
+            LayoutField::FlashPageSize => "flash-page-size",
+            LayoutField::SlotSizeBytes => "slot-size-bytes",
+            LayoutField::BlocksPerPage => "blocks-per-page",
+            LayoutField::MaxBlockSize => "max-block-size",
+            LayoutField::HashSize => "hash-size",
+            LayoutField::DataStorageStartAddress => "data-storage-start-address",
+            LayoutField::FlashSize => "flash-size",
+            LayoutField::ControlPlaneReplicas => "control-plane-replicas",
+            LayoutField::ControlPlanePlacement => "control-plane-placement",
+            LayoutField::SequenceIndexPages => "sequence-index-pages",
+            LayoutField::KvPages => "kv-pages",
+            LayoutField::Counters => "counters",
+            LayoutField::SlotMetaPages => "slot-meta-pages",
+        }
+    }
+}
+
+/// Storage geometry persisted in the superblock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Superblock {
+    /// Flash erase page size in bytes.
+    pub flash_page_size: u32,
+    /// Bytes per block slot including hash metadata.
+    pub slot_size_bytes: u32,
+    /// Block slots per flash page.
+    pub blocks_per_page: u32,
+    /// Maximum block size in bytes.
+    pub max_block_size: u32,
+    /// Block hash size in bytes.
+    pub hash_size: u32,
+    /// First flash address of the storage region.
+    pub data_storage_start_address: u32,
+    /// End of the storage region in bytes; the flash size unless partitioned.
+    pub flash_size: u32,
+    /// Number of control-plane replicas.
+    pub control_plane_replicas: u32,
+    /// Control-plane replica placement code (0 consecutive, 1 spread, 2 start and end).
+    pub control_plane_placement: u32,
+    /// Pages reserved for the sequence index at the end of the region (0 when disabled).
+    pub sequence_index_pages: u32,
+    /// Pages reserved for the key-value store (0 when disabled).
+    pub kv_pages: u32,
+    /// Monotonic counters, two pages each (0 when disabled).
+    pub counters: u32,
+    /// Pages reserved for slot metadata (0 when disabled).
+    pub slot_meta_pages: u32,
+}
+
+impl Superblock {
+    fn fields(&self) -> [(LayoutField, u32); FIELD_COUNT] {
+        [
+            (LayoutField::FlashPageSize, self.flash_page_size),
+            (LayoutField::SlotSizeBytes, self.slot_size_bytes),
+            (LayoutField::BlocksPerPage, self.blocks_per_page),
+            (LayoutField::MaxBlockSize, self.max_block_size),
+            (LayoutField::HashSize, self.hash_size),
+            (LayoutField::DataStorageStartAddress, self.data_storage_start_address),
+            (LayoutField::FlashSize, self.flash_size),
+            (LayoutField::ControlPlaneReplicas, self.control_plane_replicas),
+            (LayoutField::ControlPlanePlacement, self.control_plane_placement),
+            (LayoutField::SequenceIndexPages, self.sequence_index_pages),
+            (LayoutField::KvPages, self.kv_pages),
+            (LayoutField::Counters, self.counters),
+            (LayoutField::SlotMetaPages, self.slot_meta_pages),
+        ]
+    }
+
+    /// Returns the recorded control-plane layout, or `None` for an unknown
+    /// placement code or replica count.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::ControlPlaneLayout;
+    /// use moonblokz_storage::superblock::Superblock;
+    ///
+    /// let superblock = Superblock {
+    ///     flash_page_size: 4096,
+    ///     slot_size_bytes: 2048,
+    ///     blocks_per_page: 2,
+    ///     max_block_size: 2016,
+    ///     hash_size: 32,
+    ///     data_storage_start_address: 0x18_0000,
+    ///     flash_size: 0x20_0000,
+    ///     control_plane_replicas: 3,
+    ///     control_plane_placement: 0,
+    ///     sequence_index_pages: 0,
+    ///     kv_pages: 0,
+    ///     counters: 0,
+    ///     slot_meta_pages: 0,
+    /// };
+    /// assert_eq!(superblock.control_plane_layout(), Some(ControlPlaneLayout::DEFAULT));
+    /// ```
+    pub fn control_plane_layout(&self) -> Option<ControlPlaneLayout> {
+        let placement = ControlPlanePlacement::from_u32(self.control_plane_placement)?;
+        let layout = ControlPlaneLayout::new(self.control_plane_replicas as usize, placement);
+        layout.validate().ok()?;
+        Some(layout)
+    }
+
+    /// Serializes the superblock.
+    ///
+    /// Parameters:
+    /// - none.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::superblock::{SUPERBLOCK_MAGIC, Superblock};
+    ///
+    /// let superblock = Superblock {
+    ///     flash_page_size: 4096,
+    ///     slot_size_bytes: 2048,
+    ///     blocks_per_page: 2,
+    ///     max_block_size: 2016,
+    ///     hash_size: 32,
+    ///     data_storage_start_address: 0x18_0000,
+    ///     flash_size: 0x20_0000,
+    ///     control_plane_replicas: 3,
+    ///     control_plane_placement: 0,
+    ///     sequence_index_pages: 0,
+    ///     kv_pages: 0,
+    ///     counters: 0,
+    ///     slot_meta_pages: 0,
+    /// };
+    /// assert_eq!(superblock.encode()[..4], SUPERBLOCK_MAGIC);
+    /// ```
+    pub fn encode(&self) -> [u8; SUPERBLOCK_SIZE] {
+        let mut out = [0u8; SUPERBLOCK_SIZE];
+        record_header::write_header(&mut out, &SUPERBLOCK_MAGIC, SUPERBLOCK_VERSION);
+        let fields = self.fields();
+        let mut values = [0u32; FIELD_COUNT];
+        let mut index = 0usize;
+        while index < fields.len() {
+            values[index] = fields[index].1;
+            index += 1;
+        }
+        record_header::write_fields(&mut out, FIELDS_OFFSET, &values);
+        record_header::seal(&mut out);
+        out
+    }
+
+    /// Parses a stored superblock.
+    ///
+    /// Returns `Ok(None)` when no valid superblock is present (wrong magic or
+    /// CRC), for example in regions written before superblocks existed or in
+    /// a torn write.
+    ///
+    /// Errors:
+    /// - `ControlPlaneIncompatible`: magic is present but the version is not `SUPERBLOCK_VERSION`.
+    ///
+    /// Parameters:
+    /// - `bytes`: raw superblock bytes.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::superblock::{SUPERBLOCK_SIZE, Superblock};
+    ///
+    /// assert!(matches!(Superblock::decode(&[0xFF; SUPERBLOCK_SIZE]), Ok(None)));
+    /// ```
+    pub fn decode(bytes: &[u8; SUPERBLOCK_SIZE]) -> Result<Option<Self>, StorageError> {
+        if !record_header::check(bytes, &SUPERBLOCK_MAGIC, SUPERBLOCK_VERSION)? {
+            return Ok(None);
+        }
+
+        let mut values = [0u32; FIELD_COUNT];
+        record_header::read_fields(bytes, FIELDS_OFFSET, &mut values);
+        Ok(Some(Self {
+            flash_page_size: values[0],
+            slot_size_bytes: values[1],
+            blocks_per_page: values[2],
+            max_block_size: values[3],
+            hash_size: values[4],
+            data_storage_start_address: values[5],
+            flash_size: values[6],
+            control_plane_replicas: values[7],
+            control_plane_placement: values[8],
+            sequence_index_pages: values[9],
+            kv_pages: values[10],
+            counters: values[11],
+            slot_meta_pages: values[12],
+        }))
+    }
+
+    /// Compares a stored superblock with the compiled geometry.
+    ///
+    /// Errors:
+    /// - `LayoutMismatch`: first differing field, with stored and expected values.
+    ///
+    /// Parameters:
+    /// - `expected`: geometry of the running firmware.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::StorageError;
+    /// use moonblokz_storage::superblock::{LayoutField, Superblock};
+    ///
+    /// let stored = Superblock {
+    ///     flash_page_size: 4096,
+    ///     slot_size_bytes: 2048,
+    ///     blocks_per_page: 2,
+    ///     max_block_size: 2016,
+    ///     hash_size: 32,
+    ///     data_storage_start_address: 0x18_0000,
+    ///     flash_size: 0x20_0000,
+    ///     control_plane_replicas: 3,
+    ///     control_plane_placement: 0,
+    ///     sequence_index_pages: 0,
+    ///     kv_pages: 0,
+    ///     counters: 0,
+    ///     slot_meta_pages: 0,
+    /// };
+    /// let expected = Superblock { flash_size: 0x40_0000, ..stored };
+    /// assert!(matches!(
+    ///     stored.check(&expected),
+    ///     Err(StorageError::LayoutMismatch { field: LayoutField::FlashSize, .. })
+    /// ));
+    /// ```
+    pub fn check(&self, expected: &Superblock) -> Result<(), StorageError> {
+        let stored = self.fields();
+        let expected = expected.fields();
+        let mut index = 0usize;
+        while index < stored.len() {
+            if stored[index].1 != expected[index].1 {
+                return Err(StorageError::LayoutMismatch {
+                    field: stored[index].0,
+                    stored: stored[index].1,
+                    expected: expected[index].1,
+                });
+            }
+            index += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_superblock() -> Superblock {
+        Superblock {
+            flash_page_size: 4096,
+            slot_size_bytes: 2048,
+            blocks_per_page: 2,
+            max_block_size: 2016,
+            hash_size: 32,
+            data_storage_start_address: 0x18_0000,
+            flash_size: 0x20_0000,
+            control_plane_replicas: 3,
+            control_plane_placement: 0,
+            sequence_index_pages: 0,
+            kv_pages: 0,
+            counters: 0,
+            slot_meta_pages: 0,
+        }
+    }
+
+    #[test]
+    fn superblock_round_trips() {
+        let encoded = test_superblock().encode();
+        assert!(matches!(Superblock::decode(&encoded), Ok(Some(value)) if value == test_superblock()));
+    }
+
+    #[test]
+    fn damaged_or_missing_superblock_decodes_as_absent() {
+        let mut encoded = test_superblock().encode();
+        encoded[FIELDS_OFFSET] ^= 0x01;
+        assert!(matches!(Superblock::decode(&encoded), Ok(None)));
+        assert!(matches!(Superblock::decode(&[0u8; SUPERBLOCK_SIZE]), Ok(None)));
+    }
+
+    #[test]
+    fn unknown_superblock_version_is_incompatible() {
+        let mut encoded = test_superblock().encode();
+        encoded[4] = SUPERBLOCK_VERSION + 1;
+        assert!(matches!(
+            Superblock::decode(&encoded),
+            Err(StorageError::ControlPlaneIncompatible)
+        ));
+
+        // A differently sized future format does not match this window's CRC;
+        // the version byte alone must still refuse it.
+        encoded[FIELDS_OFFSET] ^= 0x01;
+        assert!(matches!(
+            Superblock::decode(&encoded),
+            Err(StorageError::ControlPlaneIncompatible)
+        ));
+    }
+
+    #[test]
+    fn check_reports_first_mismatching_field() {
+        let stored = test_superblock();
+        assert!(stored.check(&stored).is_ok());
+
+        let expected = Superblock {
+            max_block_size: 1000,
+            data_storage_start_address: 0,
+            ..stored
+        };
+        assert!(matches!(
+            stored.check(&expected),
+            Err(StorageError::LayoutMismatch {
+                field: LayoutField::MaxBlockSize,
+                stored: 2016,
+                expected: 1000,
+            })
+        ));
+    }
+}
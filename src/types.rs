@@ -1,4 +1,111 @@
 /*! Public API types for MoonBlokz storage contract boundaries. */
 
+use crate::StorageError;
+use moonblokz_chain_types::HASH_SIZE;
+
 /// Canonical index type for storage placement.
 pub type StorageIndex = u32;
+
+/// Size in bytes of the metadata value `write_slot_meta` attaches to a slot
+/// (feature `slot-meta`). Its contents are defined by chain logic.
+pub const SLOT_META_SIZE: usize = 16;
+
+/// Storage indices protected against overwrites by `save_block`.
+///
+/// Saving a block to a protected slot that already holds one fails with
+/// `StorageError::SlotOccupied` instead of replacing it; `force_overwrite`
+/// bypasses the check. The policy is runtime configuration and is not
+/// recorded on flash.
+///
+/// Example:
+/// ```
+/// use moonblokz_storage::WriteOnceSlots;
+///
+/// let finalized = WriteOnceSlots::range(0, 1000);
+/// assert!(finalized.contains(999));
+/// assert!(!finalized.contains(1000));
+/// assert!(!WriteOnceSlots::NONE.contains(0));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WriteOnceSlots {
+    /// First protected storage index.
+    pub start: StorageIndex,
+    /// Storage index one past the protected range.
+    pub end: StorageIndex,
+}
+
+impl WriteOnceSlots {
+    /// No slot is protected; `save_block` overwrites freely.
+    pub const NONE: Self = Self::range(0, 0);
+    /// Every slot is protected.
+    pub const ALL: Self = Self::range(0, StorageIndex::MAX);
+
+    /// Protects the storage indices `start..end`.
+    ///
+    /// Parameters:
+    /// - `start`: first protected storage index.
+    /// - `end`: storage index one past the protected range.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::WriteOnceSlots;
+    ///
+    /// const FINALIZED: WriteOnceSlots = WriteOnceSlots::range(0, 64);
+    /// assert!(FINALIZED.contains(0));
+    /// ```
+    pub const fn range(start: StorageIndex, end: StorageIndex) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns `true` when `storage_index` is protected.
+    ///
+    /// Parameters:
+    /// - `storage_index`: slot index to check.
+    ///
+    /// Example:
+    /// ```
+    /// use moonblokz_storage::WriteOnceSlots;
+    ///
+    /// assert!(WriteOnceSlots::ALL.contains(7));
+    /// ```
+    pub const fn contains(&self, storage_index: StorageIndex) -> bool {
+        self.start <= storage_index && storage_index < self.end
+    }
+}
+
+impl Default for WriteOnceSlots {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+/// Precondition of a slot write, checked against what the slot holds right
+/// before it is rewritten.
+#[derive(Clone, Copy)]
+pub(crate) enum WriteCondition {
+    /// Overwrite whatever the slot holds.
+    Any,
+    /// The slot must be empty (`insert_block`).
+    Empty,
+    /// The slot must hold a block with this hash (`replace_block`).
+    Holds([u8; HASH_SIZE]),
+}
+
+impl WriteCondition {
+    /// Checks the condition against `current`, the hash of the block in the
+    /// slot (`None` when empty), then the write-once protection of the slot.
+    pub(crate) fn check(&self, current: Option<[u8; HASH_SIZE]>, write_once: bool) -> Result<(), StorageError> {
+        let holds = match self {
+            WriteCondition::Any => true,
+            WriteCondition::Empty => current.is_none(),
+            WriteCondition::Holds(expected) => current == Some(*expected),
+        };
+        if !holds {
+            return Err(StorageError::Conflict { current_hash: current });
+        }
+        if write_once && current.is_some() {
+            return Err(StorageError::SlotOccupied);
+        }
+        Ok(())
+    }
+}